@@ -11,7 +11,7 @@ use tessellate::{gpu, TessResult};
 use tessellate::gpu::options::GpuOptions;
 
 fn main() -> TessResult<()> {
-    let cube = Cube::create(Vertex::default(), 3.0, FaceType::Quad, Color::default());
+    let cube = Cube::create(Vertex::default(), 3.0, FaceType::Triangle, Color::default());
     let pyramid = Pyramid::create(Vertex::default(), 5.0, 5.0, Color::default());
 
     let mut diff = pyramid.difference(cube.clone())?;
@@ -2,15 +2,19 @@ use std::io;
 
 pub use tobj::{load_obj, LoadError};
 
-use crate::mesh::MeshError;
+use crate::mesh::material::{Color, RgbaColor};
+use crate::mesh::{Mesh, MeshError};
 
+pub mod gltf;
 pub mod obj;
 pub mod ply;
 pub mod stl;
+pub mod vtk;
 
 #[derive(Debug)]
 pub enum FileError {
     ImportObjError(LoadError),
+    GltfError(::gltf::Error),
     FileError(io::Error),
     MeshError(MeshError),
     Custom(String),
@@ -23,6 +27,12 @@ impl From<LoadError> for FileError {
     }
 }
 
+impl From<::gltf::Error> for FileError {
+    fn from(e: ::gltf::Error) -> Self {
+        FileError::GltfError(e)
+    }
+}
+
 impl From<io::Error> for FileError {
     fn from(e: io::Error) -> Self {
         FileError::FileError(e)
@@ -34,3 +44,44 @@ impl From<MeshError> for FileError {
         FileError::MeshError(e)
     }
 }
+
+/// The colors of the vertices if the mesh is colored per vertex.
+pub(crate) fn vertex_colors(mesh: &Mesh) -> Result<Option<Vec<RgbaColor>>, FileError> {
+    match mesh.attributes().color() {
+        Color::Vertex(colors) if colors.len() == mesh.vertices().len() => Ok(Some(colors.clone())),
+        Color::Vertex(colors) => Err(FileError::Custom(format!(
+            "The number of vertex colors {} does not match the number of vertices {}",
+            colors.len(),
+            mesh.vertices().len()
+        ))),
+        Color::Func(f) => Ok(Some(
+            mesh.vertices()
+                .iter()
+                .enumerate()
+                .map(|(i, v)| f(v, i))
+                .collect(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// The colors of the faces if the mesh is colored per face.
+pub(crate) fn face_colors(mesh: &Mesh) -> Result<Option<Vec<RgbaColor>>, FileError> {
+    match mesh.attributes().color() {
+        Color::Face(colors) if colors.len() == mesh.faces().len() => Ok(Some(colors.clone())),
+        Color::Face(colors) => Err(FileError::Custom(format!(
+            "The number of face colors {} does not match the number of faces {}",
+            colors.len(),
+            mesh.faces().len()
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// The file in the temp dir named after the test and the process,
+/// so the concurrent test runs do not overwrite each other's files.
+#[cfg(test)]
+pub(crate) fn tmp(name: &str) -> String {
+    let name = format!("tessellate_{}_{}", std::process::id(), name);
+    std::env::temp_dir().join(name).to_str().unwrap().to_string()
+}
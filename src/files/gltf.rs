@@ -0,0 +1,636 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use ::gltf::json;
+use ::gltf::mesh::Mode;
+use glam::{Mat4, Vec3};
+use json::validation::Checked::Valid;
+use json::validation::USize64;
+use log::info;
+
+use crate::files::{face_colors, vertex_colors, FileError};
+use crate::mesh::attributes::{Attributes, MeshType};
+use crate::mesh::material::{Color, Material, RgbaColor};
+use crate::mesh::parts::edge::Edge;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::transform::Transform;
+use crate::mesh::Mesh;
+
+/// The size of the vertices of the imported clouds of points.
+const CLOUD_VERTEX_SIZE: usize = 2;
+
+/// Imports a glTF (`.gltf`) or a binary glTF (`.glb`) file and converts it into a vector of `Mesh` objects.
+///
+/// Every primitive of the meshes referenced by the nodes of the scene becomes a separate `Mesh`
+/// with the transformations of the nodes applied to the vertices.
+/// The triangles become the polygons, the points become a cloud and the lines become a lines mesh.
+///
+/// The base color of the PBR material goes to the diffuse (and ambient) part of `Material`
+/// since the shader multiplies the material by the color of the vertices.
+/// The vertex colors (`COLOR_0`) become `Color::Vertex`, otherwise the mesh is white
+/// with the opacity of the base color.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to the glTF or GLB file.
+///
+/// # Errors
+///
+/// This function will return a `FileError` if the file or the buffers it refers to cannot be loaded
+/// or a primitive has no positions or refers to the absent vertices.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::files::gltf::import_gltf;
+/// let meshes = import_gltf("path/to/your.glb").unwrap();
+/// ```
+pub fn import_gltf(path: &str) -> Result<Vec<Mesh>, FileError> {
+    let (document, buffers, _images) = ::gltf::import(path)?;
+    let mut meshes = vec![];
+
+    let scenes: Vec<_> = match document.default_scene() {
+        Some(scene) => vec![scene],
+        None => document.scenes().collect(),
+    };
+    if scenes.is_empty() {
+        // the file is a library of meshes without a scene
+        for mesh in document.meshes() {
+            import_mesh(&mesh, Mat4::IDENTITY, &buffers, &mut meshes)?;
+        }
+    }
+
+    let mut stack: Vec<_> = scenes
+        .iter()
+        .flat_map(|s| s.nodes())
+        .map(|n| (n, Mat4::IDENTITY))
+        .collect();
+    while let Some((node, parent)) = stack.pop() {
+        let matrix = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            info!("Importing a mesh: {:?}", mesh.name());
+            import_mesh(&mesh, matrix, &buffers, &mut meshes)?;
+        }
+        stack.extend(node.children().map(|c| (c, matrix)));
+    }
+
+    Ok(meshes)
+}
+
+fn import_mesh(
+    mesh: &::gltf::Mesh,
+    matrix: Mat4,
+    buffers: &[::gltf::buffer::Data],
+    meshes: &mut Vec<Mesh>,
+) -> Result<(), FileError> {
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+        let vertices: Vec<Vertex> = reader
+            .read_positions()
+            .ok_or(FileError::Custom(format!(
+                "The primitive {} of the mesh {} has no positions",
+                primitive.index(),
+                mesh.index()
+            )))?
+            .map(Vertex::from)
+            .collect();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+        if let Some(wrong) = indices.iter().find(|i| **i >= vertices.len()) {
+            return Err(FileError::Custom(format!(
+                "The primitive {} of the mesh {} refers to the vertex {} but there are only {} vertices",
+                primitive.index(),
+                mesh.index(),
+                wrong,
+                vertices.len()
+            )));
+        }
+
+        let pbr = primitive.material().pbr_metallic_roughness();
+        let [r, g, b, a] = pbr.base_color_factor();
+        let material = if primitive.material().index().is_some() {
+            to_material(Vec3::new(r, g, b), pbr.roughness_factor())
+        } else {
+            Material::default()
+        };
+        let color = match reader.read_colors(0) {
+            Some(colors) => Color::Vertex(
+                colors
+                    .into_rgba_f32()
+                    .map(|[r, g, b, alpha]| to_rgba(r, g, b, alpha * a))
+                    .collect(),
+            ),
+            None => Color::Mesh(to_rgba(1.0, 1.0, 1.0, a)),
+        };
+        let attributes = Attributes::default().with_material(material);
+
+        let mut imported = match primitive.mode() {
+            Mode::Points => {
+                let mut mesh = Mesh::cloud(vertices, CLOUD_VERTEX_SIZE, attributes);
+                mesh.attributes_mut().set_color(color);
+                mesh
+            }
+            Mode::Lines | Mode::LineStrip | Mode::LineLoop => {
+                let pairs: Vec<(usize, usize)> = match primitive.mode() {
+                    Mode::Lines => indices.chunks_exact(2).map(|l| (l[0], l[1])).collect(),
+                    Mode::LineStrip => indices.windows(2).map(|l| (l[0], l[1])).collect(),
+                    _ => indices
+                        .iter()
+                        .zip(indices.iter().cycle().skip(1))
+                        .map(|(a, b)| (*a, *b))
+                        .collect(),
+                };
+                let edges = pairs
+                    .into_iter()
+                    .map(|(a, b)| Edge::new(vertices[a], vertices[b]))
+                    .collect();
+                // the vertices are merged in the lines mesh, so the vertex colors can not be kept
+                let color = match color {
+                    Color::Vertex(_) => Color::Mesh(to_rgba(1.0, 1.0, 1.0, a)),
+                    c => c,
+                };
+                let mut attributes = attributes;
+                attributes.set_color(color);
+                Mesh::lines(edges, attributes)?
+            }
+            Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan => {
+                let faces: Vec<Face> = match primitive.mode() {
+                    Mode::Triangles => indices
+                        .chunks_exact(3)
+                        .map(|t| Face::new3(t[0], t[1], t[2]))
+                        .collect(),
+                    // every odd triangle of the strip is flipped to keep the winding order
+                    Mode::TriangleStrip => indices
+                        .windows(3)
+                        .enumerate()
+                        .map(|(i, t)| {
+                            if i % 2 == 0 {
+                                Face::new3(t[0], t[1], t[2])
+                            } else {
+                                Face::new3(t[1], t[0], t[2])
+                            }
+                        })
+                        .collect(),
+                    _ => indices
+                        .windows(2)
+                        .skip(1)
+                        .map(|t| Face::new3(indices[0], t[0], t[1]))
+                        .collect(),
+                };
+                let mut attributes = attributes;
+                attributes.set_color(color);
+                attributes.set_mesh_type(MeshType::Polygons);
+                Mesh::from_vertices(vertices, faces, attributes)
+            }
+        };
+        imported.transform(matrix)?;
+        meshes.push(imported);
+    }
+    Ok(())
+}
+
+/// Phong material approximating the metallic-roughness one.
+/// The shininess is derived from the roughness as `2 / roughness^2 - 2`.
+fn to_material(base: Vec3, roughness: f32) -> Material {
+    let default = Material::default();
+    let roughness = roughness.max(0.01);
+    Material::new(
+        default.ambient() * base,
+        base,
+        default.specular() * (1.0 - roughness),
+        2.0 / (roughness * roughness) - 2.0,
+    )
+}
+
+fn to_rgba(r: f32, g: f32, b: f32, a: f32) -> RgbaColor {
+    let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    RgbaColor::new(c(r), c(g), c(b), c(a))
+}
+
+/// The data of a mesh prepared for glTF.
+struct Primitive {
+    positions: Vec<[f32; 3]>,
+    colors: Option<Vec<[f32; 4]>>,
+    indices: Vec<u32>,
+    mode: json::mesh::Mode,
+}
+
+impl Primitive {
+    fn new(mesh: &Mesh) -> Result<Self, FileError> {
+        let positions = |vs: &[Vertex]| vs.iter().map(|v| [v.x, v.y, v.z]).collect::<Vec<_>>();
+        let rgba = |c: &RgbaColor| -> [f32; 4] { c.clone().into() };
+        let mode = match mesh.attributes().mesh_type() {
+            MeshType::Polygons => json::mesh::Mode::Triangles,
+            MeshType::Lines => json::mesh::Mode::Lines,
+            MeshType::Cloud(_) => json::mesh::Mode::Points,
+        };
+
+        let indices: Vec<usize> = match mode {
+            json::mesh::Mode::Triangles => mesh
+                .faces()
+                .iter()
+                .flat_map(|f| f.triangulate())
+                .flat_map(|f| f.flatten())
+                .collect(),
+            json::mesh::Mode::Lines => mesh.edges().iter().flat_map(|e| [e.0, e.1]).collect(),
+            _ => (0..mesh.vertices().len()).collect(),
+        };
+
+        // glTF has no colors per face or line, so the vertices are duplicated for every element
+        let per_element = match (mode, mesh.attributes().color()) {
+            (json::mesh::Mode::Triangles, _) => face_colors(mesh)?.map(|colors| {
+                mesh.faces()
+                    .iter()
+                    .zip(colors)
+                    .flat_map(|(f, c)| vec![c; f.triangulate().len() * 3])
+                    .collect::<Vec<_>>()
+            }),
+            (json::mesh::Mode::Lines, Color::Line(colors)) if colors.len() == mesh.edges().len() => {
+                Some(colors.iter().flat_map(|c| [c.clone(), c.clone()]).collect())
+            }
+            _ => None,
+        };
+
+        let mut primitive = match per_element {
+            Some(colors) => Primitive {
+                positions: indices
+                    .iter()
+                    .map(|i| mesh.get(*i).map(|v| [v.x, v.y, v.z]))
+                    .collect::<Result<_, _>>()?,
+                colors: Some(colors.iter().map(rgba).collect()),
+                indices: (0..indices.len() as u32).collect(),
+                mode,
+            },
+            None => Primitive {
+                positions: positions(mesh.vertices()),
+                colors: vertex_colors(mesh)?.map(|cs| cs.iter().map(rgba).collect()),
+                indices: indices.iter().map(|i| *i as u32).collect(),
+                mode,
+            },
+        };
+        if let Some(wrong) = primitive
+            .indices
+            .iter()
+            .find(|i| **i as usize >= primitive.positions.len())
+        {
+            return Err(FileError::Custom(format!(
+                "The mesh refers to the vertex {} but there are only {} vertices",
+                wrong,
+                primitive.positions.len()
+            )));
+        }
+        if mode == json::mesh::Mode::Points {
+            primitive.indices.clear();
+        }
+        Ok(primitive)
+    }
+}
+
+/// Exports the meshes into a single binary glTF (`.glb`) file.
+/// Usually these are the meshes passed to `visualize`.
+///
+/// Every mesh becomes a node of the scene with a mesh of a single primitive.
+/// The polygons are written as triangles, the lines as lines and the clouds as points.
+/// The colors of the vertices (`Color::Vertex`, `Color::Func`) are written as `COLOR_0`,
+/// the colors of the faces and the lines are written as `COLOR_0` of the duplicated vertices
+/// and the color of the mesh (`Color::Mesh`) goes to the base color of the material.
+/// The base color is multiplied by the diffuse part of `Material`.
+///
+/// # Arguments
+///
+/// * `meshes` - The meshes to export.
+/// * `path` - A string slice that holds the path to the GLB file.
+///
+/// # Errors
+///
+/// This function will return a `FileError` if the file cannot be written
+/// or the colors do not match the vertices or faces of a mesh.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::files::gltf::export_glb;
+/// use tessellate::mesh::shape::pyramid::Pyramid;
+/// use tessellate::mesh::HasMesh;
+/// let pyramid = Pyramid::default();
+/// export_glb(&[pyramid.mesh().clone()], "path/to/your.glb").unwrap();
+/// ```
+pub fn export_glb(meshes: &[Mesh], path: &str) -> Result<(), FileError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_glb(meshes, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the meshes as a binary glTF into the writer (see `export_glb`).
+pub fn write_glb<W: Write>(meshes: &[Mesh], writer: W) -> Result<(), FileError> {
+    let mut root = json::Root::default();
+    let mut bin: Vec<u8> = vec![];
+    let buffer = json::Index::<json::Buffer>::new(0);
+    let mut nodes = vec![];
+
+    for (idx, mesh) in meshes.iter().enumerate() {
+        let primitive = Primitive::new(mesh)?;
+        if primitive.positions.is_empty() {
+            continue;
+        }
+        let (min, max) = bounds(&primitive.positions);
+        let positions = push_accessor(
+            &mut root,
+            &mut bin,
+            buffer,
+            bytemuck::cast_slice(&primitive.positions),
+            primitive.positions.len(),
+            json::accessor::ComponentType::F32,
+            json::accessor::Type::Vec3,
+            Some(json::buffer::Target::ArrayBuffer),
+            Some((min, max)),
+        );
+        let mut attributes = BTreeMap::new();
+        attributes.insert(Valid(json::mesh::Semantic::Positions), positions);
+        if let Some(colors) = primitive.colors.as_ref() {
+            let colors = push_accessor(
+                &mut root,
+                &mut bin,
+                buffer,
+                bytemuck::cast_slice(colors),
+                colors.len(),
+                json::accessor::ComponentType::F32,
+                json::accessor::Type::Vec4,
+                Some(json::buffer::Target::ArrayBuffer),
+                None,
+            );
+            attributes.insert(Valid(json::mesh::Semantic::Colors(0)), colors);
+        }
+        let indices = if primitive.indices.is_empty() {
+            None
+        } else {
+            Some(push_accessor(
+                &mut root,
+                &mut bin,
+                buffer,
+                bytemuck::cast_slice(&primitive.indices),
+                primitive.indices.len(),
+                json::accessor::ComponentType::U32,
+                json::accessor::Type::Scalar,
+                Some(json::buffer::Target::ElementArrayBuffer),
+                None,
+            ))
+        };
+
+        let material = root.push(to_gltf_material(mesh, format!("mesh_{}", idx)));
+        let gltf_mesh = root.push(json::Mesh {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: Some(format!("mesh_{}", idx)),
+            primitives: vec![json::mesh::Primitive {
+                attributes,
+                extensions: Default::default(),
+                extras: Default::default(),
+                indices,
+                material: Some(material),
+                mode: Valid(primitive.mode),
+                targets: None,
+            }],
+            weights: None,
+        });
+        nodes.push(root.push(json::Node {
+            mesh: Some(gltf_mesh),
+            name: Some(format!("mesh_{}", idx)),
+            ..Default::default()
+        }));
+    }
+
+    if !bin.is_empty() {
+        root.push(json::Buffer {
+            byte_length: USize64::from(bin.len()),
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            uri: None,
+        });
+    }
+    let scene = root.push(json::Scene {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        nodes,
+    });
+    root.scene = Some(scene);
+
+    let json = json::serialize::to_vec(&root)
+        .map_err(|e| FileError::Custom(format!("Failed to serialize glTF: {}", e)))?;
+    let glb = ::gltf::binary::Glb {
+        header: ::gltf::binary::Header {
+            magic: *b"glTF",
+            version: 2,
+            // the length is calculated by the writer
+            length: 0,
+        },
+        json: Cow::Owned(json),
+        bin: if bin.is_empty() { None } else { Some(Cow::Owned(bin)) },
+    };
+    glb.to_writer(writer)?;
+    Ok(())
+}
+
+/// Appends the data to the binary buffer and creates the view and the accessor for it.
+#[allow(clippy::too_many_arguments)]
+fn push_accessor(
+    root: &mut json::Root,
+    bin: &mut Vec<u8>,
+    buffer: json::Index<json::Buffer>,
+    data: &[u8],
+    count: usize,
+    component_type: json::accessor::ComponentType,
+    type_: json::accessor::Type,
+    target: Option<json::buffer::Target>,
+    bounds: Option<([f32; 3], [f32; 3])>,
+) -> json::Index<json::Accessor> {
+    let offset = bin.len();
+    bin.extend_from_slice(data);
+    // the views should be aligned to 4 bytes
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+    let view = root.push(json::buffer::View {
+        buffer,
+        byte_length: USize64::from(data.len()),
+        byte_offset: Some(USize64::from(offset)),
+        byte_stride: None,
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        target: target.map(Valid),
+    });
+    root.push(json::Accessor {
+        buffer_view: Some(view),
+        byte_offset: Some(USize64(0)),
+        count: USize64::from(count),
+        component_type: Valid(json::accessor::GenericComponentType(component_type)),
+        extensions: Default::default(),
+        extras: Default::default(),
+        type_: Valid(type_),
+        min: bounds.map(|(min, _)| json::Value::from(min.to_vec())),
+        max: bounds.map(|(_, max)| json::Value::from(max.to_vec())),
+        name: None,
+        normalized: false,
+        sparse: None,
+    })
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    (min, max)
+}
+
+/// The metallic-roughness material with the base color taken
+/// from the diffuse part of `Material` and the color of the mesh.
+/// The roughness is the inverse of the shininess conversion in `to_material`.
+fn to_gltf_material(mesh: &Mesh, name: String) -> json::Material {
+    let material = mesh.attributes().material();
+    let color: [f32; 4] = match mesh.attributes().color() {
+        Color::Mesh(c) => c.clone().into(),
+        _ => [1.0, 1.0, 1.0, 1.0],
+    };
+    let diffuse = material.diffuse();
+    let roughness = (2.0 / (material.shininess().max(0.0) + 2.0)).sqrt();
+    json::Material {
+        name: Some(name),
+        alpha_mode: if color[3] < 1.0 {
+            Valid(json::material::AlphaMode::Blend)
+        } else {
+            Valid(json::material::AlphaMode::Opaque)
+        },
+        double_sided: true,
+        pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+            base_color_factor: json::material::PbrBaseColorFactor([
+                diffuse.x * color[0],
+                diffuse.y * color[1],
+                diffuse.z * color[2],
+                color[3],
+            ]),
+            metallic_factor: json::material::StrengthFactor(0.0),
+            roughness_factor: json::material::StrengthFactor(roughness),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::tmp;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn export_import() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::Mesh(RgbaColor::RED));
+        let cloud = Mesh::cloud(
+            vec![Vertex::new(0.0, 0.0, 0.0), Vertex::new(1.0, 2.0, 3.0)],
+            CLOUD_VERTEX_SIZE,
+            Color::Vertex(vec![RgbaColor::BLUE, RgbaColor::GREEN]).into(),
+        );
+        let path = tmp("export_import.glb");
+        export_glb(&[cube.mesh().clone(), cloud], &path).unwrap();
+
+        let meshes = import_gltf(&path).unwrap();
+        assert_eq!(meshes.len(), 2);
+        let (imported_cloud, imported_cube) = if meshes[0].faces().is_empty() {
+            (&meshes[0], &meshes[1])
+        } else {
+            (&meshes[1], &meshes[0])
+        };
+
+        assert_eq!(imported_cube.vertices(), cube.vertices());
+        assert_eq!(imported_cube.faces(), cube.faces());
+        let diffuse = imported_cube.attributes().material().diffuse();
+        assert!((diffuse - Material::default().diffuse() * Vec3::X).length() < 1e-2);
+
+        assert_eq!(imported_cloud.vertices().len(), 2);
+        assert!(matches!(imported_cloud.attributes().mesh_type(), MeshType::Cloud(_)));
+        match imported_cloud.attributes().color() {
+            Color::Vertex(colors) => assert_eq!(colors, &vec![RgbaColor::BLUE, RgbaColor::GREEN]),
+            _ => panic!("expected vertex colors"),
+        }
+    }
+
+    #[test]
+    fn export_face_colors() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        let mut colored = cube.mesh().clone();
+        let colors = vec![RgbaColor::RED; colored.faces().len()];
+        colored.attributes_mut().set_color(Color::Face(colors));
+        let path = tmp("export_face_colors.glb");
+        export_glb(&[colored], &path).unwrap();
+
+        let meshes = import_gltf(&path).unwrap();
+        assert_eq!(meshes.len(), 1);
+        // two triangles per quad with the own vertices
+        assert_eq!(meshes[0].faces().len(), 12);
+        assert_eq!(meshes[0].vertices().len(), 36);
+        match meshes[0].attributes().color() {
+            Color::Vertex(colors) => assert!(colors.iter().all(|c| c == &RgbaColor::RED)),
+            _ => panic!("expected vertex colors"),
+        }
+    }
+
+    #[test]
+    fn import_node_transform() {
+        let gltf = r#"{
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [
+                {"translation": [10.0, 0.0, 0.0], "children": [1]},
+                {"scale": [2.0, 2.0, 2.0], "mesh": 0}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [0.0, 1.0, 0.0, 1.0]}}],
+            "accessors": [{
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+            }],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+            }]
+        }"#;
+        let path = tmp("import_node_transform.gltf");
+        std::fs::write(&path, gltf).unwrap();
+
+        let meshes = import_gltf(&path).unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(
+            meshes[0].vertices(),
+            &vec![
+                Vertex::new(10.0, 0.0, 0.0),
+                Vertex::new(12.0, 0.0, 0.0),
+                Vertex::new(10.0, 2.0, 0.0)
+            ]
+        );
+        assert_eq!(meshes[0].faces(), &vec![Face::new3(0, 1, 2)]);
+        assert_eq!(meshes[0].attributes().material().diffuse(), Vec3::Y);
+    }
+
+    #[test]
+    fn import_missing_file() {
+        assert!(import_gltf(&tmp("import_missing_file.glb")).is_err());
+    }
+}
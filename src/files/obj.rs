@@ -1,9 +1,12 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 use tobj::load_obj;
 use log::info;
-use crate::files::FileError;
+use crate::files::{face_colors, vertex_colors, FileError};
 use crate::mesh::{Mesh, MeshError};
 use crate::mesh::attributes::Attributes;
-use crate::mesh::material::Color;
+use crate::mesh::material::{Color, RgbaColor};
 use crate::mesh::parts::face::Face;
 use crate::mesh::parts::vertex::Vertex;
 
@@ -103,4 +106,175 @@ pub fn import_obj(path: &str, options: &tobj::LoadOptions) -> Result<Mesh, FileE
         .into_iter()
         .next()
         .ok_or(FileError::MeshError(MeshError::Custom("No mesh found".to_string())))?)
-}
\ No newline at end of file
+}
+/// Exports `Mesh` objects into an OBJ file along with an MTL file describing their materials.
+/// The MTL file is placed next to the OBJ file and has the same name with the `mtl` extension.
+///
+/// Every mesh becomes a separate object with its own material built from the `Material`
+/// and the color of the mesh. The vertex colors (`Color::Vertex` and `Color::Func`)
+/// are written after the coordinates of the vertices (`v x y z r g b`),
+/// the faces colored by `Color::Face` get a material for every distinct color.
+///
+/// # Arguments
+///
+/// * `meshes` - The meshes to export.
+/// * `path` - A string slice that holds the path to the OBJ file.
+///
+/// # Errors
+///
+/// This function will return a `FileError` if the files cannot be written
+/// or the colors do not match the vertices or faces of a mesh.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::files::obj::export_objs;
+/// use tessellate::mesh::shape::pyramid::Pyramid;
+/// use tessellate::mesh::HasMesh;
+/// let pyramid = Pyramid::default();
+/// export_objs(&[pyramid.mesh().clone()], "path/to/your.obj").unwrap();
+/// ```
+pub fn export_objs(meshes: &[Mesh], path: &str) -> Result<(), FileError> {
+    let path = Path::new(path);
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or(FileError::Custom(format!("Invalid path {:?}", path)))?;
+
+    let mut obj = BufWriter::new(File::create(path)?);
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+    writeln!(obj, "# Created by tessellate")?;
+    writeln!(obj, "mtllib {}", mtl_name)?;
+    writeln!(mtl, "# Created by tessellate")?;
+
+    let mut offset = 1;
+    for (idx, mesh) in meshes.iter().enumerate() {
+        let name = format!("mesh_{}", idx);
+        write_obj(mesh, &name, offset, &mut obj, &mut mtl)?;
+        offset += mesh.vertices().len();
+    }
+    obj.flush()?;
+    mtl.flush()?;
+    Ok(())
+}
+
+/// Exports a single `Mesh` into an OBJ file along with an MTL file.
+/// See `export_objs` for details.
+pub fn export_obj(mesh: &Mesh, path: &str) -> Result<(), FileError> {
+    export_objs(std::slice::from_ref(mesh), path)
+}
+
+/// Writes the mesh as the object `name` whose vertices start from `offset`
+/// and its materials.
+fn write_obj<O: Write, M: Write>(
+    mesh: &Mesh,
+    name: &str,
+    offset: usize,
+    obj: &mut O,
+    mtl: &mut M,
+) -> Result<(), FileError> {
+    let v_colors = vertex_colors(mesh)?;
+    let f_colors = face_colors(mesh)?;
+    let material = mesh.attributes().material();
+    let base = match mesh.attributes().color() {
+        Color::Mesh(c) => c.clone(),
+        _ => RgbaColor::WHITE,
+    };
+
+    let mut write_material = |mtl_name: &str, color: &RgbaColor| -> Result<(), FileError> {
+        let [r, g, b, a]: [f32; 4] = color.clone().into();
+        let rgb = glam::Vec3::new(r, g, b);
+        let ka = material.ambient() * rgb;
+        let kd = material.diffuse() * rgb;
+        let ks = material.specular();
+        writeln!(mtl, "newmtl {}", mtl_name)?;
+        writeln!(mtl, "Ka {} {} {}", ka.x, ka.y, ka.z)?;
+        writeln!(mtl, "Kd {} {} {}", kd.x, kd.y, kd.z)?;
+        writeln!(mtl, "Ks {} {} {}", ks.x, ks.y, ks.z)?;
+        writeln!(mtl, "Ns {}", material.shininess())?;
+        writeln!(mtl, "d {}", a)?;
+        writeln!(mtl, "illum 2")?;
+        Ok(())
+    };
+
+    writeln!(obj, "o {}", name)?;
+    for (i, v) in mesh.vertices().iter().enumerate() {
+        match v_colors.as_ref().map(|cs| &cs[i]) {
+            Some(c) => {
+                let [r, g, b, _]: [f32; 4] = c.clone().into();
+                writeln!(obj, "v {} {} {} {} {} {}", v.x, v.y, v.z, r, g, b)?
+            }
+            None => writeln!(obj, "v {} {} {}", v.x, v.y, v.z)?,
+        }
+    }
+
+    let write_face = |obj: &mut O, face: &Face| -> Result<(), FileError> {
+        write!(obj, "f")?;
+        for idx in face.flatten() {
+            write!(obj, " {}", idx + offset)?;
+        }
+        writeln!(obj)?;
+        Ok(())
+    };
+
+    match f_colors {
+        Some(colors) => {
+            let mut palette: Vec<[u8; 4]> = vec![];
+            let mut current = None;
+            for (face, RgbaColor(color)) in mesh.faces().iter().zip(colors.iter()) {
+                let k = match palette.iter().position(|c| c == color) {
+                    Some(k) => k,
+                    None => {
+                        palette.push(*color);
+                        write_material(&format!("{}_{}", name, palette.len() - 1), &RgbaColor(*color))?;
+                        palette.len() - 1
+                    }
+                };
+                if current != Some(k) {
+                    writeln!(obj, "usemtl {}_{}", name, k)?;
+                    current = Some(k);
+                }
+                write_face(obj, face)?;
+            }
+        }
+        None => {
+            write_material(name, &base)?;
+            writeln!(obj, "usemtl {}", name)?;
+            for face in mesh.faces() {
+                write_face(obj, face)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::tmp;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn export_import() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        let mut colored = cube.mesh().clone();
+        let colors = (0..colored.faces().len())
+            .map(|i| if i % 2 == 0 { RgbaColor::RED } else { RgbaColor::BLUE })
+            .collect();
+        colored.attributes_mut().set_color(Color::Face(colors));
+
+        let path = tmp("export_import.obj");
+        export_objs(&[cube.mesh().clone(), colored], &path).unwrap();
+
+        let (models, materials) = load_obj(&path, &tobj::LoadOptions::default()).unwrap();
+        assert_eq!(materials.unwrap().len(), 3);
+        assert!(models.len() >= 2);
+
+        let meshes = import_objs(&path, &tobj::GPU_LOAD_OPTIONS).unwrap();
+        let faces: usize = meshes.iter().map(|m| m.faces().len()).sum();
+        assert_eq!(faces, 2 * 12);
+    }
+}
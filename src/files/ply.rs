@@ -1,15 +1,29 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
 use ply_rs::parser::Parser;
-use ply_rs::ply::{Property, PropertyAccess};
+use ply_rs::ply::{DefaultElement, Property};
 
-use crate::files::FileError;
-use crate::mesh::attributes::Attributes;
-use crate::mesh::material::Color;
+use crate::files::{face_colors, vertex_colors, FileError};
+use crate::mesh::attributes::{Attributes, MeshType};
+use crate::mesh::fields::{FieldData, NORMALS};
+use crate::mesh::material::{Color, RgbaColor};
 use crate::mesh::Mesh;
 use crate::mesh::parts::face::Face;
 use crate::mesh::parts::vertex::Vertex;
 
 /// Imports a PLY file and converts it into a `Mesh` object.
 ///
+/// Supported are the ascii and binary encodings, any numeric type of the coordinates
+/// and any integer type of the face indexes (`vertex_indices` or `vertex_index`).
+/// The `red`, `green`, `blue` and optional `alpha` properties of the vertices or the faces
+/// become `Color::Vertex` or `Color::Face`, the integer colors are expected in `0..=255`,
+/// the float ones in `0..=1`.
+/// The normals (`nx`, `ny`, `nz`) are stored in the vector field `normals`
+/// and the rest of the scalar properties of the vertices in the fields with the same names
+/// (see `Mesh::fields`), the integer properties become the integer fields.
+/// The file without faces is imported as a cloud of points.
+///
 /// # Arguments
 ///
 /// * `path` - A string slice that holds the path to the PLY file.
@@ -23,90 +37,422 @@ use crate::mesh::parts::vertex::Vertex;
 /// This function will return an error if the file cannot be opened, read, or if the PLY data cannot be parsed correctly.
 pub fn import_ply(path: &str) -> Result<Mesh, FileError> {
     let mut f = std::io::BufReader::new(std::fs::File::open(path)?);
-    let vertex_parser =  Parser::<PlyVertex>::new();
-    let face_parser =  Parser::<PlyFace>::new();
-    let header = vertex_parser.read_header(&mut f)?;
+    let parser = Parser::<DefaultElement>::new();
+    let header = parser.read_header(&mut f)?;
 
     let mut vertex_list = Vec::new();
     let mut face_list = Vec::new();
     for (_ignore_key, element) in &header.elements {
-
+        // the unknown elements should be read anyway to get to the next ones
+        let payload = parser.read_payload_for_element(&mut f, element, &header)?;
         match element.name.as_ref() {
-            "vertex" => {vertex_list = vertex_parser.read_payload_for_element(&mut f, &element, &header).unwrap();},
-            "face" => {face_list = face_parser.read_payload_for_element(&mut f, &element, &header).unwrap();},
-            _ => {},
+            "vertex" => vertex_list = payload,
+            "face" => face_list = payload,
+            _ => {}
         }
     }
 
-    let mut vertices = Vec::new();
-    let mut faces = Vec::new();
-    for ply_vertex in vertex_list {
-        match ply_vertex {
-            PlyVertex::Vertex(x, y, z) => vertices.push(Vertex::new(x, y, z)),
-            e => return Err(FileError::Custom(format!("Failed to parse vertex {:?}",e))),
+    let mut vertices = Vec::with_capacity(vertex_list.len());
+    let mut vertex_colors = Vec::with_capacity(vertex_list.len());
+    let mut normals = Vec::with_capacity(vertex_list.len());
+    let mut fields: Vec<(String, FieldData)> = vec![];
+    for (idx, element) in vertex_list.iter().enumerate() {
+        let coord = |name: &str| {
+            scalar(element, name).ok_or(FileError::Custom(format!(
+                "The vertex {} has no numeric property {}",
+                idx, name
+            )))
+        };
+        vertices.push(Vertex::new(coord("x")?, coord("y")?, coord("z")?));
+        vertex_colors.push(color(element));
+        if let (Some(x), Some(y), Some(z)) = (
+            scalar(element, "nx"),
+            scalar(element, "ny"),
+            scalar(element, "nz"),
+        ) {
+            normals.push([x, y, z]);
+        }
+        for (name, prop) in element.iter() {
+            if RESERVED.contains(&name.as_str()) {
+                continue;
+            }
+            let value = match (as_i64(prop), as_f32(prop)) {
+                (Some(v), _) => FieldData::Integer(vec![v]),
+                (None, Some(v)) => FieldData::Scalar(vec![v]),
+                _ => continue,
+            };
+            match (fields.iter_mut().find(|(n, _)| n == name), value) {
+                (Some((_, FieldData::Integer(values))), FieldData::Integer(v)) => values.extend(v),
+                (Some((_, FieldData::Scalar(values))), FieldData::Scalar(v)) => values.extend(v),
+                (None, value) if idx == 0 => fields.push((name.clone(), value)),
+                _ => {
+                    return Err(FileError::Custom(format!(
+                        "The property {} of the vertex {} differs from the first vertex",
+                        name, idx
+                    )))
+                }
+            }
         }
     }
 
-    for ply_face in face_list {
-        match ply_face {
-            PlyFace::Face(v) => faces.extend(Face::new(v)?),
-            e => return Err(FileError::Custom(format!("Failed to parse face {:?}",e))),
+    let mut faces = Vec::with_capacity(face_list.len());
+    let mut face_colors = Vec::with_capacity(face_list.len());
+    for (idx, element) in face_list.iter().enumerate() {
+        let indices = element
+            .get("vertex_indices")
+            .or(element.get("vertex_index"))
+            .and_then(as_indices)
+            .ok_or(FileError::Custom(format!(
+                "The face {} has no integer list vertex_indices",
+                idx
+            )))?;
+        if let Some(wrong) = indices.iter().find(|i| **i >= vertices.len()) {
+            return Err(FileError::Custom(format!(
+                "The face {} refers to the vertex {} but there are only {} vertices",
+                idx,
+                wrong,
+                vertices.len()
+            )));
         }
+        let polygon = Face::new(indices)?;
+        // the polygons are split into triangles, every one gets the color of the polygon
+        face_colors.extend(polygon.iter().map(|_| color(element)));
+        faces.extend(polygon);
+    }
+
+    let color = if !vertex_colors.is_empty() && vertex_colors.iter().all(Option::is_some) {
+        Color::Vertex(vertex_colors.into_iter().flatten().collect())
+    } else if !face_colors.is_empty() && face_colors.iter().all(Option::is_some) {
+        Color::Face(face_colors.into_iter().flatten().collect())
+    } else {
+        Color::default()
+    };
+
+    let mut mesh = if faces.is_empty() {
+        Mesh::cloud(vertices, CLOUD_VERTEX_SIZE, color.into())
+    } else {
+        let mut attributes: Attributes = color.into();
+        attributes.set_mesh_type(MeshType::Polygons);
+        Mesh::from_vertices(vertices, faces, attributes)
+    };
+
+    if normals.len() == mesh.vertices().len() && !normals.is_empty() {
+        mesh.add_vertex_field(NORMALS, FieldData::Vector(normals))?;
+    }
+    for (name, data) in fields {
+        mesh.add_vertex_field(name, data)?;
+    }
+    Ok(mesh)
+}
+
+/// The size of the vertices of the imported clouds of points.
+const CLOUD_VERTEX_SIZE: usize = 2;
+
+/// The properties of the vertices that are not turned into the fields.
+const RESERVED: [&str; 10] = [
+    "x", "y", "z", "nx", "ny", "nz", "red", "green", "blue", "alpha",
+];
+
+fn as_f32(prop: &Property) -> Option<f32> {
+    match *prop {
+        Property::Char(v) => Some(v as f32),
+        Property::UChar(v) => Some(v as f32),
+        Property::Short(v) => Some(v as f32),
+        Property::UShort(v) => Some(v as f32),
+        Property::Int(v) => Some(v as f32),
+        Property::UInt(v) => Some(v as f32),
+        Property::Float(v) => Some(v),
+        Property::Double(v) => Some(v as f32),
+        _ => None,
+    }
+}
+
+fn as_i64(prop: &Property) -> Option<i64> {
+    match *prop {
+        Property::Char(v) => Some(v as i64),
+        Property::UChar(v) => Some(v as i64),
+        Property::Short(v) => Some(v as i64),
+        Property::UShort(v) => Some(v as i64),
+        Property::Int(v) => Some(v as i64),
+        Property::UInt(v) => Some(v as i64),
+        _ => None,
+    }
+}
+
+fn scalar(element: &DefaultElement, name: &str) -> Option<f32> {
+    element.get(name).and_then(as_f32)
+}
 
+fn as_indices(prop: &Property) -> Option<Vec<usize>> {
+    fn convert<T: Copy + TryInto<usize>>(values: &[T]) -> Option<Vec<usize>> {
+        values.iter().map(|v| (*v).try_into().ok()).collect()
     }
+    match prop {
+        Property::ListChar(v) => convert(v),
+        Property::ListUChar(v) => convert(v),
+        Property::ListShort(v) => convert(v),
+        Property::ListUShort(v) => convert(v),
+        Property::ListInt(v) => convert(v),
+        Property::ListUInt(v) => convert(v),
+        _ => None,
+    }
+}
 
-    Ok(Mesh::from_vertices(vertices, faces, Attributes::default()))
+/// The color of an element if it has the `red`, `green` and `blue` properties.
+fn color(element: &DefaultElement) -> Option<RgbaColor> {
+    let channel = |name: &str| -> Option<u8> {
+        match element.get(name)? {
+            Property::Float(v) => Some((v.clamp(0.0, 1.0) * 255.0).round() as u8),
+            Property::Double(v) => Some((v.clamp(0.0, 1.0) * 255.0).round() as u8),
+            p => as_f32(p).map(|v| v.clamp(0.0, 255.0) as u8),
+        }
+    };
+    Some(RgbaColor::new(
+        channel("red")?,
+        channel("green")?,
+        channel("blue")?,
+        channel("alpha").unwrap_or(255),
+    ))
 }
 
-#[derive(Debug)]
-enum PlyVertex {
-    New,
-    Vertex(f32, f32, f32),
-    Failed(String),
+/// The encoding of a PLY file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlyFormat {
+    Ascii,
+    #[default]
+    BinaryLittleEndian,
 }
 
-#[derive(Debug)]
-enum PlyFace {
-    New,
-    Face(Vec<i32>),
-    Failed(String),
+/// Exports a `Mesh` into a PLY file.
+/// The vertex and face colors (`Color::Vertex`, `Color::Func` and `Color::Face`)
+/// are written as the `red`, `green`, `blue` and `alpha` properties.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to export.
+/// * `path` - A string slice that holds the path to the PLY file.
+/// * `format` - The encoding of the file.
+///
+/// # Returns
+///
+/// * `Result<(), FileError>` - Returns `()` on success, or a `FileError` on failure.
+pub fn export_ply(mesh: &Mesh, path: &str, format: PlyFormat) -> Result<(), FileError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_ply(mesh, &mut file, format)?;
+    file.flush()?;
+    Ok(())
 }
 
-impl PropertyAccess for PlyFace {
-    fn new() -> Self {
-        PlyFace::New
-    }
-    fn set_property(&mut self, key: String, property: Property) {
-        match (key.as_ref(), property) {
-            ("vertex_indices", Property::ListInt(v)) => match self {
-                PlyFace::New => *self = PlyFace::Face(v),
-                _ => *self = PlyFace::Failed("Unexpected key/value combination".to_string()),
-            },
-            (k, _) => {}//*self = PlyFace::Failed(format!("Unexpected key: {}", k)),
+/// Writes a `Mesh` in the PLY format into the given writer.
+/// See `export_ply` for details.
+///
+/// The payload is written by hand since the binary writer of `ply_rs`
+/// puts the number of elements instead of the length of the list.
+pub fn write_ply<W: Write>(mesh: &Mesh, out: &mut W, format: PlyFormat) -> Result<(), FileError> {
+    let v_colors = vertex_colors(mesh)?;
+    let f_colors = face_colors(mesh)?;
+    let color_props = "property uchar red\nproperty uchar green\nproperty uchar blue\nproperty uchar alpha\n";
+
+    writeln!(out, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(out, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(out, "format binary_little_endian 1.0")?,
+    }
+    writeln!(out, "comment Created by tessellate")?;
+    writeln!(out, "element vertex {}", mesh.vertices().len())?;
+    write!(out, "property float x\nproperty float y\nproperty float z\n")?;
+    if v_colors.is_some() {
+        write!(out, "{}", color_props)?;
+    }
+    writeln!(out, "element face {}", mesh.faces().len())?;
+    writeln!(out, "property list uchar int vertex_indices")?;
+    if f_colors.is_some() {
+        write!(out, "{}", color_props)?;
+    }
+    writeln!(out, "end_header")?;
+
+    for (i, v) in mesh.vertices().iter().enumerate() {
+        let color = v_colors.as_ref().map(|cs| &cs[i]);
+        match format {
+            PlyFormat::Ascii => {
+                write!(out, "{} {} {}", v.x, v.y, v.z)?;
+                if let Some(RgbaColor([r, g, b, a])) = color {
+                    write!(out, " {} {} {} {}", r, g, b, a)?;
+                }
+                writeln!(out)?;
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for c in [v.x, v.y, v.z] {
+                    out.write_all(&c.to_le_bytes())?;
+                }
+                if let Some(RgbaColor(rgba)) = color {
+                    out.write_all(rgba)?;
+                }
+            }
         }
     }
+
+    for (i, f) in mesh.faces().iter().enumerate() {
+        let indices = f.flatten();
+        let color = f_colors.as_ref().map(|cs| &cs[i]);
+        match format {
+            PlyFormat::Ascii => {
+                write!(out, "{}", indices.len())?;
+                for idx in indices {
+                    write!(out, " {}", idx)?;
+                }
+                if let Some(RgbaColor([r, g, b, a])) = color {
+                    write!(out, " {} {} {} {}", r, g, b, a)?;
+                }
+                writeln!(out)?;
+            }
+            PlyFormat::BinaryLittleEndian => {
+                out.write_all(&[indices.len() as u8])?;
+                for idx in indices {
+                    out.write_all(&(idx as i32).to_le_bytes())?;
+                }
+                if let Some(RgbaColor(rgba)) = color {
+                    out.write_all(rgba)?;
+                }
+            }
+        }
+    }
+    Ok(())
 }
-impl PropertyAccess for PlyVertex {
-    fn new() -> Self {
-        PlyVertex::New
-    }
-    fn set_property(&mut self, key: String, property: Property) {
-        match (key.as_ref(), property) {
-            ("x", Property::Float(v)) => match self {
-                PlyVertex::New => *self = PlyVertex::Vertex(v, 0.0, 0.0),
-                PlyVertex::Vertex(_, y, z) => *self = PlyVertex::Vertex(v, *y, *z),
-                _ => *self = PlyVertex::Failed("Unexpected key/value combination".to_string()),
-            },
-            ("y", Property::Float(v)) => match self {
-                PlyVertex::Vertex(x, _, z) => *self = PlyVertex::Vertex(*x, v, *z),
-                _ => *self = PlyVertex::Failed("Unexpected key/value combination".to_string()),
-            },
-            ("z", Property::Float(v)) => match self {
-                PlyVertex::Vertex(x, y, _) => *self = PlyVertex::Vertex(*x, *y, v),
-                _ => *self = PlyVertex::Failed("Unexpected key/value combination".to_string()),
-            },
-
-            (k, _) => {} // *self = PlyVertex::Failed(format!("Unexpected key: {}", k)),
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::tmp;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn export_import() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        let mut colored = cube.mesh().clone();
+        let colors = vec![RgbaColor::RED; colored.faces().len()];
+        colored.attributes_mut().set_color(Color::Face(colors));
+        for (format, name) in [(PlyFormat::BinaryLittleEndian, "bin"), (PlyFormat::Ascii, "ascii")] {
+            let path = tmp(&format!("export_import_{}.ply", name));
+            export_ply(&colored, &path, format).unwrap();
+            let mesh = import_ply(&path).unwrap();
+            assert_eq!(mesh.faces(), cube.faces());
+            assert_eq!(mesh.vertices(), cube.vertices());
         }
     }
+
+    fn write_tmp(name: &str, content: &str) -> String {
+        let path = tmp(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn export_import_face_colors() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default());
+        let mut colored = cube.mesh().clone();
+        let colors = vec![RgbaColor::RED; colored.faces().len()];
+        colored.attributes_mut().set_color(Color::Face(colors.clone()));
+        let path = tmp("export_import_face_colors.ply");
+        export_ply(&colored, &path, PlyFormat::BinaryLittleEndian).unwrap();
+        let mesh = import_ply(&path).unwrap();
+        match mesh.attributes().color() {
+            Color::Face(imported) => assert_eq!(imported, &colors),
+            _ => panic!("expected face colors"),
+        }
+    }
+
+    #[test]
+    fn import_properties() {
+        let path = write_tmp(
+            "import_properties.ply",
+            "ply\nformat ascii 1.0\n\
+             element vertex 4\n\
+             property double x\nproperty double y\nproperty double z\n\
+             property float nx\nproperty float ny\nproperty float nz\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\n\
+             property float temperature\n\
+             property int label\n\
+             element face 1\n\
+             property list uchar uint vertex_indices\n\
+             end_header\n\
+             0 0 0 0 0 1 255 0 0 10 1\n\
+             1 0 0 0 0 1 0 255 0 20 1\n\
+             1 1 0 0 0 1 0 0 255 30 2\n\
+             0 1 0 0 0 1 255 255 255 40 2\n\
+             4 0 1 2 3\n",
+        );
+        let mesh = import_ply(&path).unwrap();
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.vertices()[2], Vertex::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.faces().len(), 1);
+        match mesh.attributes().color() {
+            Color::Vertex(colors) => assert_eq!(
+                colors,
+                &vec![
+                    RgbaColor::new(255, 0, 0, 255),
+                    RgbaColor::new(0, 255, 0, 255),
+                    RgbaColor::new(0, 0, 255, 255),
+                    RgbaColor::new(255, 255, 255, 255),
+                ]
+            ),
+            _ => panic!("expected vertex colors"),
+        }
+        assert_eq!(
+            mesh.fields().vertex(NORMALS),
+            Some(&FieldData::Vector(vec![[0.0, 0.0, 1.0]; 4]))
+        );
+        assert_eq!(
+            mesh.fields().vertex("temperature"),
+            Some(&FieldData::Scalar(vec![10.0, 20.0, 30.0, 40.0]))
+        );
+        assert_eq!(mesh.fields().vertex("label"), Some(&FieldData::Integer(vec![1, 1, 2, 2])));
+    }
+
+    #[test]
+    fn import_cloud() {
+        let path = write_tmp(
+            "import_cloud.ply",
+            "ply\nformat ascii 1.0\n\
+             element vertex 3\n\
+             property float x\nproperty float y\nproperty float z\n\
+             end_header\n\
+             0 0 0\n1 0 0\n0 1 0\n",
+        );
+        let mesh = import_ply(&path).unwrap();
+        assert_eq!(mesh.vertices().len(), 3);
+        assert!(matches!(mesh.attributes().mesh_type(), MeshType::Cloud(_)));
+    }
+
+    #[test]
+    fn import_malformed() {
+        let missing_coord = write_tmp(
+            "import_malformed_coord.ply",
+            "ply\nformat ascii 1.0\n\
+             element vertex 1\n\
+             property float x\nproperty float y\n\
+             end_header\n\
+             0 0\n",
+        );
+        assert!(import_ply(&missing_coord).is_err());
+
+        let wrong_index = write_tmp(
+            "import_malformed_index.ply",
+            "ply\nformat ascii 1.0\n\
+             element vertex 3\n\
+             property float x\nproperty float y\nproperty float z\n\
+             element face 1\n\
+             property list uchar int vertex_indices\n\
+             end_header\n\
+             0 0 0\n1 0 0\n0 1 0\n\
+             3 0 1 7\n",
+        );
+        assert!(import_ply(&wrong_index).is_err());
+
+        let broken_header = write_tmp("import_malformed_header.ply", "ply\nformat nonsense\n");
+        assert!(import_ply(&broken_header).is_err());
+    }
 }
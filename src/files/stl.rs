@@ -1,12 +1,22 @@
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
 use stl_io::IndexedTriangle;
 
 use crate::files::FileError;
 use crate::mesh::attributes::Attributes;
 use crate::mesh::Mesh;
+use crate::mesh::normals::calculate_normal;
 use crate::mesh::parts::face::Face;
 use crate::mesh::parts::vertex::Vertex;
 
+/// The encoding of an STL file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StlFormat {
+    Ascii,
+    #[default]
+    Binary,
+}
+
 /// Imports an STL file and converts it into a `Mesh`.
 ///
 /// # Arguments
@@ -39,3 +49,83 @@ pub fn import_stl(path: &str) -> Result<Mesh, FileError> {
 
     Ok(Mesh::from_vertices(vertices, faces, Attributes::default()))
 }
+
+/// Exports a `Mesh` into an STL file.
+/// The faces are triangulated, the colors are not written since STL does not support them.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to export.
+/// * `path` - A string slice that holds the path to the STL file.
+/// * `format` - The encoding of the file.
+///
+/// # Returns
+///
+/// * `Result<(), FileError>` - Returns `()` on success, or a `FileError` on failure.
+pub fn export_stl(mesh: &Mesh, path: &str, format: StlFormat) -> Result<(), FileError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_stl(mesh, &mut file, format)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes a `Mesh` in the STL format into the given writer.
+/// See `export_stl` for details.
+pub fn write_stl<W: Write>(mesh: &Mesh, out: &mut W, format: StlFormat) -> Result<(), FileError> {
+    let mut triangles = vec![];
+    for face in mesh.faces().iter().flat_map(|f| f.triangulate()) {
+        let vertices = face
+            .flatten()
+            .into_iter()
+            .map(|i| mesh.get(i).cloned())
+            .collect::<Result<Vec<_>, _>>()?;
+        let n = calculate_normal(&vertices);
+        let [a, b, c] = [vertices[0], vertices[1], vertices[2]].map(|v| [v.x, v.y, v.z]);
+        triangles.push(stl_io::Triangle {
+            normal: stl_io::Normal::new([n.x, n.y, n.z]),
+            vertices: [a, b, c].map(stl_io::Vertex::new),
+        });
+    }
+
+    match format {
+        StlFormat::Binary => stl_io::write_stl(out, triangles.iter())?,
+        StlFormat::Ascii => {
+            writeln!(out, "solid mesh")?;
+            for t in triangles.iter() {
+                let n = &t.normal;
+                writeln!(out, "  facet normal {} {} {}", n[0], n[1], n[2])?;
+                writeln!(out, "    outer loop")?;
+                for v in t.vertices.iter() {
+                    writeln!(out, "      vertex {} {} {}", v[0], v[1], v[2])?;
+                }
+                writeln!(out, "    endloop")?;
+                writeln!(out, "  endfacet")?;
+            }
+            writeln!(out, "endsolid mesh")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::tmp;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+
+    #[test]
+    fn export_import() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        for (format, name) in [(StlFormat::Binary, "bin"), (StlFormat::Ascii, "ascii")] {
+            let path = tmp(&format!("export_import_{}.stl", name));
+            export_stl(&cube, &path, format).unwrap();
+            let mesh = import_stl(&path).unwrap();
+            assert_eq!(mesh.faces().len(), 12);
+            assert_eq!(mesh.vertices().len(), 8);
+            assert_eq!(mesh.aabb().min(), cube.aabb().min());
+            assert_eq!(mesh.aabb().max(), cube.aabb().max());
+        }
+    }
+}
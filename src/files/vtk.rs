@@ -0,0 +1,609 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use log::warn;
+
+use crate::files::{face_colors, vertex_colors, FileError};
+use crate::mesh::attributes::{Attributes, MeshType};
+use crate::mesh::fields::FieldData;
+use crate::mesh::material::{Color, RgbaColor};
+use crate::mesh::parts::edge::MeshEdge;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::Mesh;
+
+mod legacy;
+mod xml;
+
+/// The size of the vertices of the imported clouds of points.
+const CLOUD_VERTEX_SIZE: usize = 2;
+
+/// The name of the data arrays keeping the colors of the points or the cells.
+const COLORS: &str = "colors";
+
+// The cell types of VTK, see `vtkCellType.h`.
+const VTK_VERTEX: u8 = 1;
+const VTK_POLY_VERTEX: u8 = 2;
+const VTK_LINE: u8 = 3;
+const VTK_POLY_LINE: u8 = 4;
+const VTK_TRIANGLE: u8 = 5;
+const VTK_TRIANGLE_STRIP: u8 = 6;
+const VTK_POLYGON: u8 = 7;
+const VTK_PIXEL: u8 = 8;
+const VTK_QUAD: u8 = 9;
+
+/// The flavour of the written VTK file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VtkFormat {
+    /// The legacy ascii `.vtk` file with the `POLYDATA` dataset.
+    #[default]
+    Legacy,
+    /// The XML `.vtu` file.
+    UnstructuredGrid,
+    /// The XML `.vtp` file.
+    PolyData,
+}
+
+/// Imports a VTK file and converts it into a `Mesh` object.
+///
+/// The files with the `vtu` and `vtp` extensions are read as the XML `UnstructuredGrid` and `PolyData`
+/// (the ascii, the binary and the appended arrays are supported, the latter can be compressed with zlib),
+/// the rest are read as the legacy (ascii or binary) `POLYDATA` and `UNSTRUCTURED_GRID`.
+///
+/// The triangles, the quads and the polygons become the faces of the mesh.
+/// The file without them becomes a lines mesh if it has the lines or a cloud of points otherwise.
+/// The point data goes to the vertex fields and the cell data goes to the face fields (see `Mesh::fields`).
+/// The arrays with one component become the scalar fields (or the integer ones for the integer arrays),
+/// the ones with three components become the vector fields
+/// and the rest are split into the scalar fields per component (`name_0`, `name_1`, ...).
+/// The `COLOR_SCALARS` of the legacy files and the arrays named `colors` of the XML files
+/// become `Color::Vertex` or `Color::Face`.
+///
+/// # Arguments
+///
+/// * `path` - A string slice that holds the path to the VTK file.
+///
+/// # Errors
+///
+/// This function will return a `FileError` if the file cannot be read,
+/// it is malformed or it has the cells of the unsupported (3D) types.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::files::vtk::import_vtk;
+/// let mesh = import_vtk("path/to/your.vtu").unwrap();
+/// ```
+pub fn import_vtk(path: &str) -> Result<Mesh, FileError> {
+    let data = std::fs::read(path)?;
+    let dataset = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("vtu") | Some("vtp") => xml::read(&data)?,
+        _ => legacy::read(&data)?,
+    };
+    dataset.into_mesh()
+}
+
+/// Exports the mesh into a VTK file that can be opened in ParaView.
+///
+/// The faces are written as the triangles and the quads, the lines mesh as the lines
+/// and the cloud of points as the vertices.
+/// The vertex fields are written as the point data and the face fields as the cell data.
+/// The vertex colors (`Color::Vertex`, `Color::Func`) and the face colors (`Color::Face`)
+/// are written as the arrays named `colors`.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh to export.
+/// * `path` - A string slice that holds the path to the VTK file.
+/// * `format` - The flavour of the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::files::vtk::{export_vtk, VtkFormat};
+/// use tessellate::mesh::shape::pyramid::Pyramid;
+/// use tessellate::mesh::HasMesh;
+/// let pyramid = Pyramid::default();
+/// export_vtk(pyramid.mesh(), "path/to/your.vtp", VtkFormat::PolyData).unwrap();
+/// ```
+pub fn export_vtk(mesh: &Mesh, path: &str, format: VtkFormat) -> Result<(), FileError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_vtk(mesh, &mut writer, format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the mesh in the VTK format into the writer (see `export_vtk`).
+pub fn write_vtk<W: Write>(mesh: &Mesh, writer: &mut W, format: VtkFormat) -> Result<(), FileError> {
+    let dataset = Dataset::from_mesh(mesh)?;
+    match format {
+        VtkFormat::Legacy => legacy::write(&dataset, writer),
+        VtkFormat::UnstructuredGrid => xml::write(&dataset, writer, false),
+        VtkFormat::PolyData => xml::write(&dataset, writer, true),
+    }
+}
+
+/// A cell of a dataset with the type from `vtkCellType.h`.
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    kind: u8,
+    points: Vec<usize>,
+}
+
+/// A data array of the point or the cell data.
+#[derive(Debug, Clone, PartialEq)]
+struct DataArray {
+    name: String,
+    components: usize,
+    values: Vec<f64>,
+    /// The values were stored as integers (the colors are in `0..=255` then).
+    integer: bool,
+    colors: bool,
+}
+
+impl DataArray {
+    fn new(name: &str, components: usize, values: Vec<f64>, integer: bool) -> Self {
+        DataArray {
+            name: name.to_string(),
+            components: components.max(1),
+            values,
+            integer,
+            colors: false,
+        }
+    }
+
+    fn tuples(&self) -> usize {
+        self.values.len() / self.components
+    }
+
+    fn rgba(&self) -> Vec<RgbaColor> {
+        let channel = |v: f64| {
+            if self.integer {
+                v.clamp(0.0, 255.0) as u8
+            } else {
+                (v.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        };
+        self.values
+            .chunks(self.components)
+            .map(|c| {
+                RgbaColor::new(
+                    channel(c[0]),
+                    c.get(1).map_or(0, |v| channel(*v)),
+                    c.get(2).map_or(0, |v| channel(*v)),
+                    c.get(3).map_or(255, |v| channel(*v)),
+                )
+            })
+            .collect()
+    }
+
+    /// Converts the array into the fields, the tuples are picked by the indexes.
+    fn fields(&self, indexes: &[usize]) -> Vec<(String, FieldData)> {
+        let c = self.components;
+        match c {
+            1 if self.integer => vec![(
+                self.name.clone(),
+                FieldData::Integer(indexes.iter().map(|i| self.values[*i] as i64).collect()),
+            )],
+            1 => vec![(
+                self.name.clone(),
+                FieldData::Scalar(indexes.iter().map(|i| self.values[*i] as f32).collect()),
+            )],
+            3 => vec![(
+                self.name.clone(),
+                FieldData::Vector(
+                    indexes
+                        .iter()
+                        .map(|i| {
+                            let v = &self.values[i * 3..i * 3 + 3];
+                            [v[0] as f32, v[1] as f32, v[2] as f32]
+                        })
+                        .collect(),
+                ),
+            )],
+            _ => (0..c)
+                .map(|k| {
+                    (
+                        format!("{}_{}", self.name, k),
+                        FieldData::Scalar(indexes.iter().map(|i| self.values[i * c + k] as f32).collect()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn from_field(name: &str, data: &FieldData) -> Self {
+        match data {
+            FieldData::Scalar(v) => DataArray::new(name, 1, v.iter().map(|v| *v as f64).collect(), false),
+            FieldData::Integer(v) => DataArray::new(name, 1, v.iter().map(|v| *v as f64).collect(), true),
+            FieldData::Vector(v) => DataArray::new(
+                name,
+                3,
+                v.iter().flat_map(|v| v.map(|c| c as f64)).collect(),
+                false,
+            ),
+        }
+    }
+
+    fn from_colors(colors: &[RgbaColor]) -> Self {
+        let mut array = DataArray::new(
+            COLORS,
+            4,
+            colors.iter().flat_map(|c| c.0.map(|v| v as f64)).collect(),
+            true,
+        );
+        array.colors = true;
+        array
+    }
+}
+
+/// The intermediate representation of the VTK files.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Dataset {
+    points: Vec<Vertex>,
+    cells: Vec<Cell>,
+    point_data: Vec<DataArray>,
+    cell_data: Vec<DataArray>,
+}
+
+impl Dataset {
+    fn into_mesh(self) -> Result<Mesh, FileError> {
+        let points_len = self.points.len();
+        if let Some(cell) = self.cells.iter().find(|c| c.points.iter().any(|p| *p >= points_len)) {
+            return Err(FileError::Custom(format!(
+                "The cell {:?} refers to the absent point, there are only {} points",
+                cell.points, points_len
+            )));
+        }
+        for array in self.point_data.iter() {
+            if array.tuples() != points_len {
+                return Err(FileError::Custom(format!(
+                    "The point data {} has {} values but there are {} points",
+                    array.name,
+                    array.tuples(),
+                    points_len
+                )));
+            }
+        }
+        for array in self.cell_data.iter() {
+            if array.tuples() != self.cells.len() {
+                return Err(FileError::Custom(format!(
+                    "The cell data {} has {} values but there are {} cells",
+                    array.name,
+                    array.tuples(),
+                    self.cells.len()
+                )));
+            }
+        }
+
+        // the faces with the indexes of the cells they come from
+        let mut faces = vec![];
+        let mut origins = vec![];
+        let mut edges = vec![];
+        for (idx, cell) in self.cells.iter().enumerate() {
+            let p = &cell.points;
+            let cell_faces = match cell.kind {
+                VTK_VERTEX | VTK_POLY_VERTEX => vec![],
+                VTK_LINE | VTK_POLY_LINE => {
+                    edges.extend(p.windows(2).map(|e| MeshEdge::new(e[0], e[1])));
+                    vec![]
+                }
+                VTK_TRIANGLE | VTK_QUAD | VTK_POLYGON => Face::new(p.clone())?,
+                VTK_PIXEL if p.len() == 4 => vec![Face::new4(p[0], p[1], p[3], p[2])],
+                VTK_TRIANGLE_STRIP => p
+                    .windows(3)
+                    .enumerate()
+                    .map(|(i, t)| {
+                        if i % 2 == 0 {
+                            Face::new3(t[0], t[1], t[2])
+                        } else {
+                            Face::new3(t[1], t[0], t[2])
+                        }
+                    })
+                    .collect(),
+                kind => {
+                    return Err(FileError::Custom(format!(
+                        "The cell type {} is not supported",
+                        kind
+                    )))
+                }
+            };
+            origins.extend(cell_faces.iter().map(|_| idx));
+            faces.extend(cell_faces);
+        }
+
+        let vertex_color = self.point_data.iter().find(|a| a.colors).map(|a| a.rgba());
+        let vertices: Vec<usize> = (0..points_len).collect();
+        let mut mesh = if !faces.is_empty() {
+            let face_color = self
+                .cell_data
+                .iter()
+                .find(|a| a.colors)
+                .map(|a| origins.iter().map(|i| a.rgba()[*i].clone()).collect());
+            let color = match (vertex_color, face_color) {
+                (Some(colors), _) => Color::Vertex(colors),
+                (None, Some(colors)) => Color::Face(colors),
+                _ => Color::default(),
+            };
+            let mut mesh = Mesh::from_vertices(self.points, faces, Attributes::new(MeshType::Polygons));
+            mesh.attributes_mut().set_color(color);
+            for array in self.cell_data.iter().filter(|a| !a.colors) {
+                for (name, data) in array.fields(&origins) {
+                    mesh.add_face_field(name, data)?;
+                }
+            }
+            mesh
+        } else {
+            if !self.cell_data.is_empty() {
+                warn!("The cell data of the lines and the vertices is not imported");
+            }
+            let color = vertex_color.map(Color::Vertex).unwrap_or_default();
+            if !edges.is_empty() {
+                Mesh::from_edges(self.points, edges, color.into())?
+            } else {
+                Mesh::cloud(self.points, CLOUD_VERTEX_SIZE, color.into())
+            }
+        };
+        for array in self.point_data.iter().filter(|a| !a.colors) {
+            for (name, data) in array.fields(&vertices) {
+                mesh.add_vertex_field(name, data)?;
+            }
+        }
+        Ok(mesh)
+    }
+
+    fn from_mesh(mesh: &Mesh) -> Result<Self, FileError> {
+        let cells: Vec<Cell> = match mesh.attributes().mesh_type() {
+            MeshType::Polygons => mesh
+                .faces()
+                .iter()
+                .map(|f| Cell {
+                    kind: match f {
+                        Face::Triangle(..) => VTK_TRIANGLE,
+                        Face::Quad(..) => VTK_QUAD,
+                    },
+                    points: f.flatten(),
+                })
+                .collect(),
+            MeshType::Lines => mesh
+                .edges()
+                .iter()
+                .map(|MeshEdge(a, b)| Cell {
+                    kind: VTK_LINE,
+                    points: vec![*a, *b],
+                })
+                .collect(),
+            MeshType::Cloud(_) => (0..mesh.vertices().len())
+                .map(|i| Cell {
+                    kind: VTK_VERTEX,
+                    points: vec![i],
+                })
+                .collect(),
+        };
+
+        let mut point_data: Vec<DataArray> = mesh
+            .fields()
+            .vertex_fields()
+            .map(|(name, data)| DataArray::from_field(name, data))
+            .collect();
+        if let Some(colors) = vertex_colors(mesh)? {
+            point_data.push(DataArray::from_colors(&colors));
+        }
+
+        let mut cell_data = vec![];
+        if mesh.is_polygons() {
+            cell_data.extend(
+                mesh.fields()
+                    .face_fields()
+                    .map(|(name, data)| DataArray::from_field(name, data)),
+            );
+            if let Some(colors) = face_colors(mesh)? {
+                cell_data.push(DataArray::from_colors(&colors));
+            }
+        }
+
+        Ok(Dataset {
+            points: mesh.vertices().clone(),
+            cells,
+            point_data,
+            cell_data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::tmp;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::HasMesh;
+
+    fn cube_with_fields() -> Mesh {
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        let mut mesh = cube.mesh().clone();
+        let temperature = (0..mesh.vertices().len()).map(|i| i as f32).collect();
+        mesh.add_vertex_field("temperature", FieldData::Scalar(temperature)).unwrap();
+        let stress = (0..mesh.faces().len()).map(|i| [i as f32, 0.0, 1.0]).collect();
+        mesh.add_face_field("stress", FieldData::Vector(stress)).unwrap();
+        let ids = (0..mesh.faces().len() as i64).map(|i| i * 10).collect();
+        mesh.add_face_field("id", FieldData::Integer(ids)).unwrap();
+        let colors = (0..mesh.faces().len()).map(|i| RgbaColor::new(i as u8, 0, 0, 255)).collect();
+        mesh.attributes_mut().set_color(Color::Face(colors));
+        mesh
+    }
+
+    #[test]
+    fn export_import() {
+        let mesh = cube_with_fields();
+        for (format, ext) in [
+            (VtkFormat::Legacy, "vtk"),
+            (VtkFormat::UnstructuredGrid, "vtu"),
+            (VtkFormat::PolyData, "vtp"),
+        ] {
+            let path = tmp(&format!("export_import.{}", ext));
+            export_vtk(&mesh, &path, format).unwrap();
+            let imported = import_vtk(&path).unwrap();
+            assert_eq!(imported.vertices(), mesh.vertices(), "{}", ext);
+            assert_eq!(imported.faces(), mesh.faces(), "{}", ext);
+            assert_eq!(imported.fields(), mesh.fields(), "{}", ext);
+            match (imported.attributes().color(), mesh.attributes().color()) {
+                (Color::Face(lhs), Color::Face(rhs)) => assert_eq!(lhs, rhs, "{}", ext),
+                _ => panic!("expected face colors in {}", ext),
+            }
+        }
+    }
+
+    #[test]
+    fn export_import_lines() {
+        let lines = Mesh::from_edges(
+            vec![Vertex::new(0.0, 0.0, 0.0), Vertex::new(1.0, 0.0, 0.0), Vertex::new(1.0, 1.0, 0.0)],
+            vec![MeshEdge::new(0, 1), MeshEdge::new(1, 2)],
+            Attributes::default(),
+        )
+        .unwrap();
+        let path = tmp("export_import_lines.vtp");
+        export_vtk(&lines, &path, VtkFormat::PolyData).unwrap();
+        let imported = import_vtk(&path).unwrap();
+        assert!(imported.is_lines());
+        assert_eq!(imported.edges(), lines.edges());
+    }
+
+    #[test]
+    fn import_legacy_unstructured_grid() {
+        let path = tmp("import_legacy_unstructured_grid.vtk");
+        std::fs::write(
+            &path,
+            "# vtk DataFile Version 3.0\n\
+             grid\n\
+             ASCII\n\
+             DATASET UNSTRUCTURED_GRID\n\
+             POINTS 5 double\n\
+             0 0 0  1 0 0  1 1 0  0 1 0\n\
+             2 0 0\n\
+             CELLS 3 11\n\
+             4 0 1 2 3\n\
+             3 1 4 2\n\
+             1 4\n\
+             CELL_TYPES 3\n\
+             9 5 1\n\
+             POINT_DATA 5\n\
+             SCALARS pressure float\n\
+             LOOKUP_TABLE default\n\
+             1 2 3 4 5\n\
+             CELL_DATA 3\n\
+             FIELD FieldData 1\n\
+             id 1 3 int\n\
+             7 8 9\n",
+        )
+        .unwrap();
+
+        let mesh = import_vtk(&path).unwrap();
+        assert_eq!(mesh.vertices().len(), 5);
+        assert_eq!(mesh.faces(), &vec![Face::new4(0, 1, 2, 3), Face::new3(1, 4, 2)]);
+        assert_eq!(
+            mesh.fields().vertex("pressure"),
+            Some(&FieldData::Scalar(vec![1.0, 2.0, 3.0, 4.0, 5.0]))
+        );
+        assert_eq!(mesh.fields().face("id"), Some(&FieldData::Integer(vec![7, 8])));
+    }
+
+    #[test]
+    fn import_legacy_binary() {
+        let mut data = b"# vtk DataFile Version 2.0\nbinary\nBINARY\nDATASET POLYDATA\nPOINTS 3 float\n".to_vec();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(b"\nPOLYGONS 1 4\n");
+        for v in [3i32, 0, 1, 2] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(b"\nPOINT_DATA 3\nVECTORS velocity double\n");
+        for v in [1.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0] {
+            data.extend(v.to_be_bytes());
+        }
+        data.extend(b"\n");
+        let path = tmp("import_legacy_binary.vtk");
+        std::fs::write(&path, data).unwrap();
+
+        let mesh = import_vtk(&path).unwrap();
+        assert_eq!(mesh.vertices()[1], Vertex::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.faces(), &vec![Face::new3(0, 1, 2)]);
+        assert_eq!(
+            mesh.fields().vertex("velocity"),
+            Some(&FieldData::Vector(vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]))
+        );
+    }
+
+    #[test]
+    fn import_appended_compressed() {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+
+        // the appended raw blocks compressed with zlib as ParaView writes them by default
+        let block = |data: Vec<u8>| {
+            let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+            encoder.write_all(&data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let mut res = vec![];
+            for v in [1, data.len(), data.len(), compressed.len()] {
+                res.extend((v as u32).to_le_bytes());
+            }
+            res.extend(compressed);
+            res
+        };
+        let points = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let points = block(points.iter().flat_map(|v| v.to_le_bytes()).collect());
+        let connectivity = block([0i32, 1, 2].iter().flat_map(|v| v.to_le_bytes()).collect());
+        let offsets = block(3i32.to_le_bytes().to_vec());
+        let types = block(vec![VTK_TRIANGLE]);
+        let o1 = points.len();
+        let o2 = o1 + connectivity.len();
+        let o3 = o2 + offsets.len();
+
+        let mut data = format!(
+            r#"<?xml version="1.0"?>
+<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian" header_type="UInt32" compressor="vtkZLibDataCompressor">
+  <UnstructuredGrid>
+    <Piece NumberOfPoints="3" NumberOfCells="1">
+      <Points><DataArray type="Float32" NumberOfComponents="3" format="appended" offset="0"/></Points>
+      <Cells>
+        <DataArray type="Int32" Name="connectivity" format="appended" offset="{}"/>
+        <DataArray type="Int32" Name="offsets" format="appended" offset="{}"/>
+        <DataArray type="UInt8" Name="types" format="appended" offset="{}"/>
+      </Cells>
+    </Piece>
+  </UnstructuredGrid>
+  <AppendedData encoding="raw">
+   _"#,
+            o1, o2, o3
+        )
+        .into_bytes();
+        for b in [points, connectivity, offsets, types] {
+            data.extend(b);
+        }
+        data.extend(b"\n  </AppendedData>\n</VTKFile>\n");
+        let path = tmp("import_appended_compressed.vtu");
+        std::fs::write(&path, data).unwrap();
+
+        let mesh = import_vtk(&path).unwrap();
+        assert_eq!(mesh.vertices()[2], Vertex::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.faces(), &vec![Face::new3(0, 1, 2)]);
+    }
+
+    #[test]
+    fn import_malformed() {
+        let path = tmp("import_malformed.vtk");
+        std::fs::write(
+            &path,
+            "# vtk DataFile Version 3.0\nbroken\nASCII\nDATASET POLYDATA\nPOINTS 2 float\n0 0 0 1 0 0\nPOLYGONS 1 4\n3 0 1 5\n",
+        )
+        .unwrap();
+        assert!(import_vtk(&path).is_err());
+
+        let path = tmp("import_malformed.vtu");
+        std::fs::write(&path, "<VTKFile type=\"UnstructuredGrid\"><Piece>").unwrap();
+        assert!(import_vtk(&path).is_err());
+    }
+}
@@ -0,0 +1,487 @@
+//! The legacy `.vtk` files, see "VTK File Formats" in the VTK User's Guide.
+use std::io::Write;
+
+use crate::files::vtk::{
+    Cell, DataArray, Dataset, VTK_LINE, VTK_POLYGON, VTK_POLY_LINE, VTK_POLY_VERTEX,
+    VTK_TRIANGLE_STRIP, VTK_VERTEX,
+};
+use crate::files::FileError;
+use crate::mesh::parts::vertex::Vertex;
+
+fn err<T>(msg: String) -> Result<T, FileError> {
+    Err(FileError::Custom(msg))
+}
+
+/// Reads the keywords line by line and the data either as the ascii tokens
+/// or as the big-endian binary values.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    binary: bool,
+}
+
+impl<'a> Reader<'a> {
+    /// The next line, the empty lines are skipped.
+    fn line(&mut self) -> Option<String> {
+        while self.pos < self.data.len() {
+            let rest = &self.data[self.pos..];
+            let end = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+            self.pos += (end + 1).min(rest.len());
+            let line = String::from_utf8_lossy(&rest[..end]).trim().to_string();
+            if !line.is_empty() {
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    fn peek_line(&mut self) -> Option<String> {
+        let pos = self.pos;
+        let line = self.line();
+        self.pos = pos;
+        line
+    }
+
+    fn token(&mut self) -> Option<&'a str> {
+        let data = self.data;
+        while self.pos < data.len() && data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < data.len() && !data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&data[start..self.pos])
+            .ok()
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Reads `n` values of the type, the integers are marked with the flag.
+    fn values(&mut self, n: usize, tp: &str) -> Result<(Vec<f64>, bool), FileError> {
+        let (size, integer) = type_size(tp)?;
+        if !self.binary {
+            let values = (0..n)
+                .map(|_| match self.token() {
+                    Some(t) => t
+                        .parse::<f64>()
+                        .or_else(|e| match t.to_ascii_lowercase().as_str() {
+                            "nan" => Ok(f64::NAN),
+                            "inf" | "infinity" => Ok(f64::INFINITY),
+                            "-inf" | "-infinity" => Ok(f64::NEG_INFINITY),
+                            _ => Err(e),
+                        })
+                        .or_else(|_| err(format!("The value {} is not a number", t))),
+                    None => err(format!("Expected {} values but the file ends", n)),
+                })
+                .collect::<Result<_, _>>()?;
+            return Ok((values, integer));
+        }
+
+        let len = n * size;
+        if self.pos + len > self.data.len() {
+            return err(format!("Expected {} bytes but the file ends", len));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        let values = bytes
+            .chunks(size)
+            .map(|b| decode_be(b, tp))
+            .collect::<Result<_, _>>()?;
+        Ok((values, integer))
+    }
+
+    fn indexes(&mut self, n: usize, tp: &str) -> Result<Vec<usize>, FileError> {
+        let (values, _) = self.values(n, tp)?;
+        values
+            .into_iter()
+            .map(|v| {
+                if v >= 0.0 && v.fract() == 0.0 {
+                    Ok(v as usize)
+                } else {
+                    err(format!("The value {} is not an index", v))
+                }
+            })
+            .collect()
+    }
+}
+
+/// The size in bytes and whether it is an integer type.
+fn type_size(tp: &str) -> Result<(usize, bool), FileError> {
+    match tp.to_ascii_lowercase().as_str() {
+        "bit" => err("The bit arrays are not supported".to_string()),
+        "char" | "unsigned_char" => Ok((1, true)),
+        "short" | "unsigned_short" => Ok((2, true)),
+        "int" | "unsigned_int" | "vtkidtype" => Ok((4, true)),
+        "long" | "unsigned_long" | "vtktypeint64" | "vtktypeuint64" => Ok((8, true)),
+        "float" => Ok((4, false)),
+        "double" => Ok((8, false)),
+        _ => err(format!("The data type {} is not supported", tp)),
+    }
+}
+
+fn decode_be(b: &[u8], tp: &str) -> Result<f64, FileError> {
+    Ok(match tp.to_ascii_lowercase().as_str() {
+        "char" => b[0] as i8 as f64,
+        "unsigned_char" => b[0] as f64,
+        "short" => i16::from_be_bytes([b[0], b[1]]) as f64,
+        "unsigned_short" => u16::from_be_bytes([b[0], b[1]]) as f64,
+        "int" | "vtkidtype" => i32::from_be_bytes(b.try_into().unwrap()) as f64,
+        "unsigned_int" => u32::from_be_bytes(b.try_into().unwrap()) as f64,
+        "long" | "vtktypeint64" => i64::from_be_bytes(b.try_into().unwrap()) as f64,
+        "unsigned_long" | "vtktypeuint64" => u64::from_be_bytes(b.try_into().unwrap()) as f64,
+        "float" => f32::from_be_bytes(b.try_into().unwrap()) as f64,
+        "double" => f64::from_be_bytes(b.try_into().unwrap()),
+        _ => return err(format!("The data type {} is not supported", tp)),
+    })
+}
+
+fn number(token: Option<&str>, what: &str) -> Result<usize, FileError> {
+    token
+        .and_then(|t| t.parse().ok())
+        .ok_or(FileError::Custom(format!("Expected the {}", what)))
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Dataset, FileError> {
+    let mut reader = Reader {
+        data,
+        pos: 0,
+        binary: false,
+    };
+    match reader.line() {
+        Some(l) if l.starts_with("# vtk DataFile") => {}
+        _ => return err("The file has no legacy VTK header".to_string()),
+    }
+    let _title = reader.line();
+    reader.binary = match reader.line().map(|l| l.to_ascii_uppercase()).as_deref() {
+        Some("ASCII") => false,
+        Some("BINARY") => true,
+        other => return err(format!("Unknown encoding {:?}", other)),
+    };
+
+    let mut dataset = Dataset::default();
+    // the cells of the poly data go in the order of the sections regardless of the order in the file
+    let mut poly_cells: [Vec<Cell>; 4] = Default::default();
+    let mut cell_types: Option<Vec<u8>> = None;
+    // the point data or the cell data with the number of tuples
+    let mut attributes: Option<(bool, usize)> = None;
+
+    while let Some(line) = reader.line() {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default().to_ascii_uppercase();
+        match keyword.as_str() {
+            "DATASET" => match words.next().map(|w| w.to_ascii_uppercase()).as_deref() {
+                Some("POLYDATA") | Some("UNSTRUCTURED_GRID") => {}
+                other => return err(format!("The dataset {:?} is not supported", other)),
+            },
+            "POINTS" => {
+                let n = number(words.next(), "number of points")?;
+                let tp = words.next().unwrap_or("float");
+                let (values, _) = reader.values(n * 3, tp)?;
+                dataset.points = values
+                    .chunks(3)
+                    .map(|c| Vertex::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                    .collect();
+            }
+            "VERTICES" | "LINES" | "POLYGONS" | "TRIANGLE_STRIPS" | "CELLS" => {
+                let n = number(words.next(), "number of cells")?;
+                let size = number(words.next(), "size of the cells")?;
+                let connectivity = read_cells(&mut reader, n, size)?;
+                let (section, kind) = match keyword.as_str() {
+                    "VERTICES" => (0, VTK_POLY_VERTEX),
+                    "LINES" => (1, VTK_POLY_LINE),
+                    "TRIANGLE_STRIPS" => (2, VTK_TRIANGLE_STRIP),
+                    "POLYGONS" => (3, VTK_POLYGON),
+                    // the types come in CELL_TYPES
+                    _ => (4, 0),
+                };
+                let cells = connectivity.into_iter().map(|points| Cell { kind, points });
+                if section < 4 {
+                    poly_cells[section].extend(cells);
+                } else {
+                    dataset.cells.extend(cells);
+                }
+            }
+            "CELL_TYPES" => {
+                let n = number(words.next(), "number of cell types")?;
+                let types = reader.indexes(n, "int")?;
+                cell_types = Some(types.into_iter().map(|t| t as u8).collect());
+            }
+            "POINT_DATA" | "CELL_DATA" => {
+                attributes = Some((keyword == "POINT_DATA", number(words.next(), "number of values")?));
+            }
+            "METADATA" => {
+                // the information keys till the empty line are not needed
+                while let Some(l) = reader.peek_line() {
+                    if l.starts_with("INFORMATION") || l.starts_with("NAME") || l.starts_with("DATA") {
+                        reader.line();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let (is_point, n) = attributes.ok_or(FileError::Custom(format!(
+                    "Unexpected keyword {}",
+                    keyword
+                )))?;
+                let arrays = read_attribute(&mut reader, &keyword, &mut words, n)?;
+                if is_point {
+                    dataset.point_data.extend(arrays);
+                } else {
+                    dataset.cell_data.extend(arrays);
+                }
+            }
+        }
+    }
+
+    if let Some(types) = cell_types {
+        if types.len() != dataset.cells.len() {
+            return err(format!(
+                "There are {} cell types for {} cells",
+                types.len(),
+                dataset.cells.len()
+            ));
+        }
+        for (cell, kind) in dataset.cells.iter_mut().zip(types) {
+            cell.kind = kind;
+        }
+    } else if !dataset.cells.is_empty() {
+        return err("The cells have no CELL_TYPES".to_string());
+    }
+    for cells in poly_cells {
+        dataset.cells.extend(cells.into_iter().map(|mut c| {
+            // the single vertices and the lines are the special cases of the poly types
+            c.kind = match (c.kind, c.points.len()) {
+                (VTK_POLY_VERTEX, 1) => VTK_VERTEX,
+                (VTK_POLY_LINE, 2) => VTK_LINE,
+                (kind, _) => kind,
+            };
+            c
+        }));
+    }
+    Ok(dataset)
+}
+
+/// Reads the cells either in the classic layout (`n p0 p1 ...` per cell)
+/// or in the layout of the version 5 (`OFFSETS` and `CONNECTIVITY`).
+fn read_cells(reader: &mut Reader, n: usize, size: usize) -> Result<Vec<Vec<usize>>, FileError> {
+    let next = reader.peek_line().unwrap_or_default().to_ascii_uppercase();
+    if next.starts_with("OFFSETS") {
+        let tp = reader.line().unwrap_or_default();
+        let tp = tp.split_whitespace().nth(1).unwrap_or("vtktypeint64").to_string();
+        let offsets = reader.indexes(n, &tp)?;
+        let conn_line = reader.line().unwrap_or_default();
+        if !conn_line.to_ascii_uppercase().starts_with("CONNECTIVITY") {
+            return err("Expected CONNECTIVITY after OFFSETS".to_string());
+        }
+        let tp = conn_line.split_whitespace().nth(1).unwrap_or("vtktypeint64").to_string();
+        let connectivity = reader.indexes(size, &tp)?;
+        return offsets
+            .windows(2)
+            .map(|w| {
+                connectivity
+                    .get(w[0]..w[1])
+                    .map(|c| c.to_vec())
+                    .ok_or(FileError::Custom("The offsets do not match the connectivity".to_string()))
+            })
+            .collect();
+    }
+
+    let values = reader.indexes(size, "int")?;
+    let mut cells = Vec::with_capacity(n);
+    let mut i = 0;
+    while cells.len() < n {
+        let len = *values
+            .get(i)
+            .ok_or(FileError::Custom("The cells are shorter than declared".to_string()))?;
+        let points = values
+            .get(i + 1..i + 1 + len)
+            .ok_or(FileError::Custom("The cells are shorter than declared".to_string()))?;
+        cells.push(points.to_vec());
+        i += len + 1;
+    }
+    Ok(cells)
+}
+
+fn read_attribute<'a>(
+    reader: &mut Reader,
+    keyword: &str,
+    words: &mut impl Iterator<Item = &'a str>,
+    n: usize,
+) -> Result<Vec<DataArray>, FileError> {
+    let mut name = || {
+        words
+            .next()
+            .map(|w| w.replace("%20", " "))
+            .ok_or(FileError::Custom(format!("The {} has no name", keyword)))
+    };
+    match keyword {
+        "SCALARS" => {
+            let name = name()?;
+            let tp = words.next().unwrap_or("float").to_string();
+            let components = words.next().and_then(|c| c.parse().ok()).unwrap_or(1);
+            if reader
+                .peek_line()
+                .is_some_and(|l| l.to_ascii_uppercase().starts_with("LOOKUP_TABLE"))
+            {
+                reader.line();
+            }
+            let (values, integer) = reader.values(n * components, &tp)?;
+            Ok(vec![DataArray::new(&name, components, values, integer)])
+        }
+        "VECTORS" | "NORMALS" => {
+            let name = name()?;
+            let tp = words.next().unwrap_or("float").to_string();
+            let (values, integer) = reader.values(n * 3, &tp)?;
+            Ok(vec![DataArray::new(&name, 3, values, integer)])
+        }
+        "TENSORS" => {
+            let name = name()?;
+            let tp = words.next().unwrap_or("float").to_string();
+            let (values, integer) = reader.values(n * 9, &tp)?;
+            Ok(vec![DataArray::new(&name, 9, values, integer)])
+        }
+        "TEXTURE_COORDINATES" => {
+            let name = name()?;
+            let dim = number(words.next(), "dimension of the texture coordinates")?;
+            let tp = words.next().unwrap_or("float").to_string();
+            let (values, integer) = reader.values(n * dim, &tp)?;
+            Ok(vec![DataArray::new(&name, dim, values, integer)])
+        }
+        "COLOR_SCALARS" => {
+            let name = name()?;
+            let components = number(words.next(), "number of the color components")?;
+            // the binary colors are unsigned chars, the ascii ones are floats in 0..1
+            let tp = if reader.binary { "unsigned_char" } else { "float" };
+            let (values, integer) = reader.values(n * components, tp)?;
+            let mut array = DataArray::new(&name, components, values, integer);
+            array.colors = true;
+            Ok(vec![array])
+        }
+        "LOOKUP_TABLE" => {
+            let _name = name()?;
+            let size = number(words.next(), "size of the lookup table")?;
+            let tp = if reader.binary { "unsigned_char" } else { "float" };
+            reader.values(size * 4, tp)?;
+            Ok(vec![])
+        }
+        "FIELD" => {
+            let _name = name()?;
+            let arrays = number(words.next(), "number of the arrays")?;
+            let mut res = vec![];
+            for _ in 0..arrays {
+                let line = reader
+                    .line()
+                    .ok_or(FileError::Custom("The field data ends unexpectedly".to_string()))?;
+                let mut words = line.split_whitespace();
+                let name = words.next().unwrap_or_default().replace("%20", " ");
+                let components = number(words.next(), "number of the components")?;
+                let tuples = number(words.next(), "number of the tuples")?;
+                let tp = words.next().unwrap_or("float");
+                let (values, integer) = reader.values(components * tuples, tp)?;
+                if tuples == n {
+                    res.push(DataArray::new(&name, components, values, integer));
+                }
+            }
+            Ok(res)
+        }
+        _ => err(format!("Unknown keyword {}", keyword)),
+    }
+}
+
+pub(super) fn write<W: Write>(dataset: &Dataset, w: &mut W) -> Result<(), FileError> {
+    writeln!(w, "# vtk DataFile Version 3.0")?;
+    writeln!(w, "tessellate")?;
+    writeln!(w, "ASCII")?;
+    writeln!(w, "DATASET POLYDATA")?;
+    writeln!(w, "POINTS {} float", dataset.points.len())?;
+    for v in dataset.points.iter() {
+        writeln!(w, "{} {} {}", v.x, v.y, v.z)?;
+    }
+
+    // the cells are grouped by the sections in the same order the reader collects them
+    let section = |kind: u8| match kind {
+        VTK_VERTEX | VTK_POLY_VERTEX => 0,
+        VTK_LINE | VTK_POLY_LINE => 1,
+        VTK_TRIANGLE_STRIP => 2,
+        _ => 3,
+    };
+    let mut order: Vec<usize> = (0..dataset.cells.len()).collect();
+    order.sort_by_key(|i| section(dataset.cells[*i].kind));
+    for (idx, name) in ["VERTICES", "LINES", "TRIANGLE_STRIPS", "POLYGONS"].iter().enumerate() {
+        let cells: Vec<usize> = order
+            .iter()
+            .copied()
+            .filter(|i| section(dataset.cells[*i].kind) == idx)
+            .collect();
+        write_cells(w, name, dataset, &cells)?;
+    }
+
+    if !dataset.point_data.is_empty() {
+        writeln!(w, "POINT_DATA {}", dataset.points.len())?;
+        let all: Vec<usize> = (0..dataset.points.len()).collect();
+        for array in dataset.point_data.iter() {
+            write_array(w, array, &all)?;
+        }
+    }
+    if !dataset.cell_data.is_empty() {
+        writeln!(w, "CELL_DATA {}", dataset.cells.len())?;
+        for array in dataset.cell_data.iter() {
+            write_array(w, array, &order)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_cells<W: Write>(
+    w: &mut W,
+    section: &str,
+    dataset: &Dataset,
+    cells: &[usize],
+) -> Result<(), FileError> {
+    if cells.is_empty() {
+        return Ok(());
+    }
+    let size: usize = cells.iter().map(|i| dataset.cells[*i].points.len() + 1).sum();
+    writeln!(w, "{} {} {}", section, cells.len(), size)?;
+    for i in cells {
+        let points = &dataset.cells[*i].points;
+        write!(w, "{}", points.len())?;
+        for p in points {
+            write!(w, " {}", p)?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+fn write_array<W: Write>(w: &mut W, array: &DataArray, order: &[usize]) -> Result<(), FileError> {
+    let name = array.name.replace(' ', "%20");
+    let c = array.components;
+    if array.colors {
+        writeln!(w, "COLOR_SCALARS {} {}", name, c)?;
+    } else if c == 3 && !array.integer {
+        writeln!(w, "VECTORS {} float", name)?;
+    } else {
+        let tp = if array.integer { "long" } else { "float" };
+        writeln!(w, "SCALARS {} {} {}", name, tp, c)?;
+        writeln!(w, "LOOKUP_TABLE default")?;
+    }
+    for i in order {
+        let tuple = &array.values[i * c..(i + 1) * c];
+        let line: Vec<String> = tuple
+            .iter()
+            .map(|v| {
+                if array.colors && array.integer {
+                    (v / 255.0).to_string()
+                } else if array.integer {
+                    (*v as i64).to_string()
+                } else {
+                    (*v as f32).to_string()
+                }
+            })
+            .collect();
+        writeln!(w, "{}", line.join(" "))?;
+    }
+    Ok(())
+}
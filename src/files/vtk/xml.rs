@@ -0,0 +1,565 @@
+//! The XML `.vtu` (`UnstructuredGrid`) and `.vtp` (`PolyData`) files.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::files::vtk::{
+    Cell, DataArray, Dataset, VTK_LINE, VTK_POLYGON, VTK_POLY_LINE, VTK_POLY_VERTEX,
+    VTK_TRIANGLE_STRIP, VTK_VERTEX,
+};
+use crate::files::FileError;
+use crate::mesh::parts::vertex::Vertex;
+
+fn err<T>(msg: String) -> Result<T, FileError> {
+    Err(FileError::Custom(msg))
+}
+
+/// The attributes of the root element affecting the decoding of the arrays.
+struct Encoding {
+    little_endian: bool,
+    header_u64: bool,
+    zlib: bool,
+}
+
+/// A `DataArray` element as it is in the file.
+struct RawArray {
+    /// The element containing the array, e.g. `Points` or `PointData`.
+    section: String,
+    name: String,
+    tp: String,
+    components: usize,
+    format: String,
+    offset: usize,
+    text: String,
+}
+
+impl RawArray {
+    fn decode(&self, enc: &Encoding, appended: &Appended) -> Result<DataArray, FileError> {
+        let (size, integer) = type_size(&self.tp)?;
+        let values = match self.format.as_str() {
+            "ascii" => self
+                .text
+                .split_whitespace()
+                .map(|t| {
+                    t.parse::<f64>()
+                        .or_else(|_| err(format!("The value {} of {} is not a number", t, self.name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            "binary" => {
+                let bytes = decode_base64(&self.text)?;
+                let block = read_block(&bytes, enc)?;
+                decode_values(&block, &self.tp, size, enc.little_endian)?
+            }
+            "appended" => {
+                let bytes = appended.block(self.offset)?;
+                let block = read_block(&bytes, enc)?;
+                decode_values(&block, &self.tp, size, enc.little_endian)?
+            }
+            f => return err(format!("The format {} of {} is not supported", f, self.name)),
+        };
+        Ok(DataArray::new(&self.name, self.components, values, integer))
+    }
+}
+
+/// The arrays of the `Cells` element or the `Verts`, `Lines`, `Strips` and `Polys` elements.
+#[derive(Default)]
+struct CellSection {
+    connectivity: Vec<usize>,
+    offsets: Vec<usize>,
+    /// The cell types, only in `Cells`.
+    types: Vec<u8>,
+}
+
+/// The content of the `AppendedData` element.
+struct Appended {
+    base64: bool,
+    data: Vec<u8>,
+    /// All offsets of the arrays to find where a base64 block ends.
+    offsets: Vec<usize>,
+}
+
+impl Appended {
+    /// The bytes of the block starting at the offset (the header is included).
+    fn block(&self, offset: usize) -> Result<Vec<u8>, FileError> {
+        if offset > self.data.len() {
+            return err(format!("The offset {} is beyond the appended data", offset));
+        }
+        if self.base64 {
+            let end = self
+                .offsets
+                .iter()
+                .copied()
+                .filter(|o| *o > offset)
+                .min()
+                .unwrap_or(self.data.len());
+            decode_base64(&String::from_utf8_lossy(&self.data[offset..end]))
+        } else {
+            Ok(self.data[offset..].to_vec())
+        }
+    }
+}
+
+/// The size in bytes and whether it is an integer type.
+fn type_size(tp: &str) -> Result<(usize, bool), FileError> {
+    match tp {
+        "Int8" | "UInt8" => Ok((1, true)),
+        "Int16" | "UInt16" => Ok((2, true)),
+        "Int32" | "UInt32" => Ok((4, true)),
+        "Int64" | "UInt64" => Ok((8, true)),
+        "Float32" => Ok((4, false)),
+        "Float64" => Ok((8, false)),
+        _ => err(format!("The data type {} is not supported", tp)),
+    }
+}
+
+fn decode_values(bytes: &[u8], tp: &str, size: usize, le: bool) -> Result<Vec<f64>, FileError> {
+    if !bytes.len().is_multiple_of(size) {
+        return err(format!("The size of the {} data {} is not a multiple of {}", tp, bytes.len(), size));
+    }
+    macro_rules! conv {
+        ($t:ty, $b:expr) => {{
+            let arr = $b.try_into().unwrap();
+            (if le { <$t>::from_le_bytes(arr) } else { <$t>::from_be_bytes(arr) }) as f64
+        }};
+    }
+    bytes
+        .chunks(size)
+        .map(|b| {
+            Ok(match tp {
+                "Int8" => b[0] as i8 as f64,
+                "UInt8" => b[0] as f64,
+                "Int16" => conv!(i16, b),
+                "UInt16" => conv!(u16, b),
+                "Int32" => conv!(i32, b),
+                "UInt32" => conv!(u32, b),
+                "Int64" => conv!(i64, b),
+                "UInt64" => conv!(u64, b),
+                "Float32" => conv!(f32, b),
+                "Float64" => conv!(f64, b),
+                _ => return err(format!("The data type {} is not supported", tp)),
+            })
+        })
+        .collect()
+}
+
+/// Decodes the base64 text that can consist of several separately encoded parts
+/// (VTK encodes the header and the data apart).
+fn decode_base64(text: &str) -> Result<Vec<u8>, FileError> {
+    let mut res = vec![];
+    let mut part = String::new();
+    let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+    while let Some(c) = chars.next() {
+        part.push(c);
+        if c == '=' && chars.peek() != Some(&'=') {
+            res.extend(decode_part(&part)?);
+            part.clear();
+        }
+    }
+    if !part.is_empty() {
+        res.extend(decode_part(&part)?);
+    }
+    Ok(res)
+}
+
+fn decode_part(part: &str) -> Result<Vec<u8>, FileError> {
+    base64::decode(part).map_err(|e| FileError::Custom(format!("Invalid base64 data: {}", e)))
+}
+
+/// Reads the data of a binary array skipping the header and decompressing it if needed.
+fn read_block(bytes: &[u8], enc: &Encoding) -> Result<Vec<u8>, FileError> {
+    let h = if enc.header_u64 { 8 } else { 4 };
+    let int = |idx: usize| -> Result<usize, FileError> {
+        let b = bytes
+            .get(idx * h..(idx + 1) * h)
+            .ok_or(FileError::Custom("The binary data is shorter than its header".to_string()))?;
+        Ok(match (h, enc.little_endian) {
+            (8, true) => u64::from_le_bytes(b.try_into().unwrap()) as usize,
+            (8, false) => u64::from_be_bytes(b.try_into().unwrap()) as usize,
+            (_, true) => u32::from_le_bytes(b.try_into().unwrap()) as usize,
+            (_, false) => u32::from_be_bytes(b.try_into().unwrap()) as usize,
+        })
+    };
+    let short = || FileError::Custom("The binary data is shorter than its header".to_string());
+
+    if !enc.zlib {
+        let len = int(0)?;
+        return bytes.get(h..h + len).map(|b| b.to_vec()).ok_or_else(short);
+    }
+
+    // [blocks, block size, last block size, compressed sizes...]
+    let blocks = int(0)?;
+    let mut res = vec![];
+    let mut start = (3 + blocks) * h;
+    for i in 0..blocks {
+        let len = int(3 + i)?;
+        let compressed = bytes.get(start..start + len).ok_or_else(short)?;
+        ZlibDecoder::new(compressed).read_to_end(&mut res)?;
+        start += len;
+    }
+    Ok(res)
+}
+
+fn attributes(e: &BytesStart) -> Result<HashMap<String, String>, FileError> {
+    e.attributes()
+        .map(|a| {
+            let a = a.map_err(|e| FileError::Custom(format!("Invalid XML attribute: {}", e)))?;
+            let value = a
+                .unescape_value()
+                .map_err(|e| FileError::Custom(format!("Invalid XML attribute: {}", e)))?;
+            Ok((String::from_utf8_lossy(a.key.as_ref()).to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+pub(super) fn read(data: &[u8]) -> Result<Dataset, FileError> {
+    // the raw appended data is not a valid XML, so it is cut off before parsing
+    let (xml, appended) = match find(data, b"<AppendedData") {
+        Some(start) => {
+            let open_end = start
+                + find(&data[start..], b">")
+                    .ok_or(FileError::Custom("The AppendedData is not closed".to_string()))?;
+            let underscore = open_end
+                + find(&data[open_end..], b"_")
+                    .ok_or(FileError::Custom("The AppendedData has no data".to_string()))?;
+            let end = rfind(data, b"</AppendedData>").unwrap_or(data.len()).max(underscore + 1);
+            let mut xml = data[..=open_end].to_vec();
+            xml.extend_from_slice(b"</AppendedData>");
+            if let Some(tail) = data.get(end..).and_then(|t| find(t, b">").map(|p| &t[p + 1..])) {
+                xml.extend_from_slice(tail);
+            }
+            let encoding_attr = String::from_utf8_lossy(&data[start..open_end]).to_string();
+            (
+                xml,
+                Some((encoding_attr.contains("base64"), data[underscore + 1..end].to_vec())),
+            )
+        }
+        None => (data.to_vec(), None),
+    };
+
+    let mut reader = Reader::from_reader(&xml[..]);
+    let mut buf = vec![];
+    let mut stack: Vec<String> = vec![];
+    let mut root: Option<HashMap<String, String>> = None;
+    let mut arrays: Vec<RawArray> = vec![];
+    let mut current: Option<RawArray> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| FileError::Custom(format!("Invalid XML: {}", e)))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                let attrs = attributes(e)?;
+                match name.as_str() {
+                    "VTKFile" => root = Some(attrs),
+                    "DataArray" => {
+                        let array = RawArray {
+                            section: stack.last().cloned().unwrap_or_default(),
+                            name: attrs.get("Name").cloned().unwrap_or_default(),
+                            tp: attrs.get("type").cloned().unwrap_or_default(),
+                            components: attrs
+                                .get("NumberOfComponents")
+                                .and_then(|c| c.parse().ok())
+                                .unwrap_or(1),
+                            format: attrs.get("format").cloned().unwrap_or("ascii".to_string()),
+                            offset: attrs.get("offset").and_then(|c| c.parse().ok()).unwrap_or(0),
+                            text: String::new(),
+                        };
+                        if matches!(event, Event::Empty(_)) {
+                            arrays.push(array);
+                        } else {
+                            current = Some(array);
+                        }
+                    }
+                    _ => {}
+                }
+                if matches!(event, Event::Start(_)) {
+                    stack.push(name);
+                }
+            }
+            Event::Text(ref t) => {
+                if let Some(array) = current.as_mut() {
+                    let text = t
+                        .unescape()
+                        .map_err(|e| FileError::Custom(format!("Invalid XML text: {}", e)))?;
+                    array.text.push_str(&text);
+                }
+            }
+            Event::End(_) => {
+                if let Some(name) = stack.pop() {
+                    if name == "DataArray" {
+                        arrays.extend(current.take());
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    if !stack.is_empty() {
+        return err(format!("The XML elements {:?} are not closed", stack));
+    }
+
+    let root = root.ok_or(FileError::Custom("The file has no VTKFile element".to_string()))?;
+    let compressor = root.get("compressor").map(String::as_str).unwrap_or_default();
+    let enc = Encoding {
+        little_endian: root.get("byte_order").map(String::as_str) != Some("BigEndian"),
+        header_u64: root.get("header_type").map(String::as_str) == Some("UInt64"),
+        zlib: match compressor {
+            "" => false,
+            "vtkZLibDataCompressor" => true,
+            c => return err(format!("The compressor {} is not supported", c)),
+        },
+    };
+    let appended = match appended {
+        Some((base64, data)) => Appended {
+            base64,
+            data,
+            offsets: arrays.iter().filter(|a| a.format == "appended").map(|a| a.offset).collect(),
+        },
+        None => Appended {
+            base64: false,
+            data: vec![],
+            offsets: vec![],
+        },
+    };
+
+    let mut dataset = Dataset::default();
+    let mut sections: HashMap<String, CellSection> = HashMap::new();
+    for raw in arrays {
+        let array = raw.decode(&enc, &appended)?;
+        match raw.section.as_str() {
+            "Points" => {
+                if array.components != 3 {
+                    return err("The points should have 3 components".to_string());
+                }
+                dataset.points = array
+                    .values
+                    .chunks(3)
+                    .map(|c| Vertex::new(c[0] as f32, c[1] as f32, c[2] as f32))
+                    .collect();
+            }
+            "PointData" => {
+                let mut array = array;
+                array.colors = is_colors(&array);
+                dataset.point_data.push(array);
+            }
+            "CellData" => {
+                let mut array = array;
+                array.colors = is_colors(&array);
+                dataset.cell_data.push(array);
+            }
+            "Cells" | "Verts" | "Lines" | "Strips" | "Polys" => {
+                let entry = sections.entry(raw.section.clone()).or_default();
+                let as_indexes = |values: Vec<f64>| values.into_iter().map(|v| v as usize).collect();
+                match raw.name.as_str() {
+                    "connectivity" => entry.connectivity = as_indexes(array.values),
+                    "offsets" => entry.offsets = as_indexes(array.values),
+                    "types" => entry.types = array.values.into_iter().map(|v| v as u8).collect(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (section, kind) in [
+        ("Cells", None),
+        ("Verts", Some(VTK_POLY_VERTEX)),
+        ("Lines", Some(VTK_POLY_LINE)),
+        ("Strips", Some(VTK_TRIANGLE_STRIP)),
+        ("Polys", Some(VTK_POLYGON)),
+    ] {
+        let Some(CellSection {
+            connectivity,
+            offsets,
+            types,
+        }) = sections.remove(section)
+        else {
+            continue;
+        };
+        if kind.is_none() && types.len() != offsets.len() {
+            return err(format!("There are {} cell types for {} cells", types.len(), offsets.len()));
+        }
+        let mut start = 0;
+        for (i, end) in offsets.iter().enumerate() {
+            let points = connectivity
+                .get(start..*end)
+                .ok_or(FileError::Custom("The offsets do not match the connectivity".to_string()))?
+                .to_vec();
+            let kind = match (kind, points.len()) {
+                (None, _) => types[i],
+                (Some(VTK_POLY_VERTEX), 1) => VTK_VERTEX,
+                (Some(VTK_POLY_LINE), 2) => VTK_LINE,
+                (Some(k), _) => k,
+            };
+            dataset.cells.push(Cell { kind, points });
+            start = *end;
+        }
+    }
+    Ok(dataset)
+}
+
+fn is_colors(array: &DataArray) -> bool {
+    array.name == super::COLORS && array.integer && (3..=4).contains(&array.components)
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).position(|w| w == pattern)
+}
+
+fn rfind(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).rposition(|w| w == pattern)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes the inline binary array with the `UInt64` header.
+fn write_array<W: Write>(
+    w: &mut W,
+    name: &str,
+    tp: &str,
+    components: usize,
+    bytes: &[u8],
+) -> Result<(), FileError> {
+    let mut block = (bytes.len() as u64).to_le_bytes().to_vec();
+    block.extend_from_slice(bytes);
+    writeln!(
+        w,
+        r#"        <DataArray type="{}" Name="{}" NumberOfComponents="{}" format="binary">"#,
+        tp,
+        escape(name),
+        components
+    )?;
+    writeln!(w, "          {}", base64::encode(&block))?;
+    writeln!(w, "        </DataArray>")?;
+    Ok(())
+}
+
+fn write_data<W: Write>(w: &mut W, tag: &str, arrays: &[DataArray], order: &[usize]) -> Result<(), FileError> {
+    writeln!(w, "      <{}>", tag)?;
+    for array in arrays {
+        let c = array.components;
+        let tuples = order.iter().flat_map(|i| &array.values[i * c..(i + 1) * c]);
+        if array.colors {
+            let bytes: Vec<u8> = tuples.map(|v| *v as u8).collect();
+            write_array(w, &array.name, "UInt8", c, &bytes)?;
+        } else if array.integer {
+            let bytes: Vec<u8> = tuples.flat_map(|v| (*v as i64).to_le_bytes()).collect();
+            write_array(w, &array.name, "Int64", c, &bytes)?;
+        } else {
+            let bytes: Vec<u8> = tuples.flat_map(|v| (*v as f32).to_le_bytes()).collect();
+            write_array(w, &array.name, "Float32", c, &bytes)?;
+        }
+    }
+    writeln!(w, "      </{}>", tag)?;
+    Ok(())
+}
+
+fn write_cells<W: Write>(w: &mut W, tag: &str, cells: &[&Cell], types: bool) -> Result<(), FileError> {
+    writeln!(w, "      <{}>", tag)?;
+    let connectivity: Vec<u8> = cells
+        .iter()
+        .flat_map(|c| c.points.iter())
+        .flat_map(|p| (*p as i64).to_le_bytes())
+        .collect();
+    write_array(w, "connectivity", "Int64", 1, &connectivity)?;
+    let offsets: Vec<u8> = cells
+        .iter()
+        .scan(0i64, |acc, c| {
+            *acc += c.points.len() as i64;
+            Some(*acc)
+        })
+        .flat_map(|o| o.to_le_bytes())
+        .collect();
+    write_array(w, "offsets", "Int64", 1, &offsets)?;
+    if types {
+        let kinds: Vec<u8> = cells.iter().map(|c| c.kind).collect();
+        write_array(w, "types", "UInt8", 1, &kinds)?;
+    }
+    writeln!(w, "      </{}>", tag)?;
+    Ok(())
+}
+
+/// Writes the dataset as the `PolyData` or the `UnstructuredGrid` with the inline binary arrays.
+pub(super) fn write<W: Write>(dataset: &Dataset, w: &mut W, poly_data: bool) -> Result<(), FileError> {
+    let tp = if poly_data { "PolyData" } else { "UnstructuredGrid" };
+    writeln!(w, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        w,
+        r#"<VTKFile type="{}" version="1.0" byte_order="LittleEndian" header_type="UInt64">"#,
+        tp
+    )?;
+    writeln!(w, "  <{}>", tp)?;
+
+    // the poly data keeps the cells grouped by the sections, the cell data follows the same order
+    let section = |kind: u8| match kind {
+        VTK_VERTEX | VTK_POLY_VERTEX => 0,
+        VTK_LINE | VTK_POLY_LINE => 1,
+        VTK_TRIANGLE_STRIP => 2,
+        _ => 3,
+    };
+    let mut order: Vec<usize> = (0..dataset.cells.len()).collect();
+    if poly_data {
+        order.sort_by_key(|i| section(dataset.cells[*i].kind));
+    }
+    let cells: Vec<&Cell> = order.iter().map(|i| &dataset.cells[*i]).collect();
+    let count = |s: usize| cells.iter().filter(|c| section(c.kind) == s).count();
+
+    if poly_data {
+        writeln!(
+            w,
+            r#"    <Piece NumberOfPoints="{}" NumberOfVerts="{}" NumberOfLines="{}" NumberOfStrips="{}" NumberOfPolys="{}">"#,
+            dataset.points.len(),
+            count(0),
+            count(1),
+            count(2),
+            count(3)
+        )?;
+    } else {
+        writeln!(
+            w,
+            r#"    <Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+            dataset.points.len(),
+            cells.len()
+        )?;
+    }
+
+    let all: Vec<usize> = (0..dataset.points.len()).collect();
+    write_data(w, "PointData", &dataset.point_data, &all)?;
+    write_data(w, "CellData", &dataset.cell_data, &order)?;
+
+    writeln!(w, "      <Points>")?;
+    let points: Vec<u8> = dataset
+        .points
+        .iter()
+        .flat_map(|v| [v.x, v.y, v.z])
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    write_array(w, "Points", "Float32", 3, &points)?;
+    writeln!(w, "      </Points>")?;
+
+    if poly_data {
+        for (idx, tag) in ["Verts", "Lines", "Strips", "Polys"].iter().enumerate() {
+            let group: Vec<&Cell> = cells.iter().copied().filter(|c| section(c.kind) == idx).collect();
+            write_cells(w, tag, &group, false)?;
+        }
+    } else {
+        write_cells(w, "Cells", &cells, true)?;
+    }
+
+    writeln!(w, "    </Piece>")?;
+    writeln!(w, "  </{}>", tp)?;
+    writeln!(w, "</VTKFile>")?;
+    Ok(())
+}
@@ -2,7 +2,10 @@ use crate::gpu::camera::position::CameraPosition;
 use crate::gpu::error::GpuError;
 use crate::gpu::processor::GpuProcessor;
 use crate::mesh::Mesh;
+use image::{ImageFormat, RgbaImage};
 use options::GpuOptions;
+use scene::{MeshId, SceneCommand, SceneHandle};
+use std::path::Path;
 use winit::event_loop::{ControlFlow, EventLoop};
 
 pub mod camera;
@@ -11,14 +14,25 @@ mod gui;
 mod light;
 pub mod material;
 pub mod options;
+pub mod picking;
 mod processor;
+pub mod scene;
+mod section;
 mod vertex;
 
-async fn run(meshes: Vec<Mesh>, options: GpuOptions) -> Result<(), GpuError> {
-    let camera_pos = CameraPosition::new(options.camera_opts().position(), 0.0, 0.0);
-    let event_loop = EventLoop::new()?;
+fn event_loop() -> Result<EventLoop<SceneCommand>, GpuError> {
+    let event_loop = EventLoop::<SceneCommand>::with_user_event().build()?;
     event_loop.set_control_flow(ControlFlow::Poll);
     event_loop.set_control_flow(ControlFlow::Wait);
+    Ok(event_loop)
+}
+
+async fn run(
+    event_loop: EventLoop<SceneCommand>,
+    meshes: Vec<Mesh>,
+    options: GpuOptions,
+) -> Result<(), GpuError> {
+    let camera_pos = CameraPosition::new(options.camera_opts().position(), 0.0, 0.0);
     Ok(event_loop.run_app(&mut GpuProcessor::new(meshes, camera_pos, options))?)
 }
 
@@ -45,5 +59,102 @@ async fn run(meshes: Vec<Mesh>, options: GpuOptions) -> Result<(), GpuError> {
 /// visualize(meshes, options)?;
 /// ```
 pub fn visualize(meshes: Vec<Mesh>, options: GpuOptions) -> Result<(), GpuError> {
-    pollster::block_on(run(meshes, options))
+    pollster::block_on(run(event_loop()?, meshes, options))
+}
+
+/// Visualizes the given meshes and lets the job change the scene while the window is open.
+///
+/// The job runs in a separate thread and gets the [`SceneHandle`] to add, remove, replace
+/// or re-color the meshes and to move the camera or the light,
+/// with the identifiers of the given meshes in their order.
+/// The function blocks the current thread until the window is closed,
+/// after that the methods of the handle return errors, so the job can stop.
+///
+/// # Arguments
+///
+/// * `meshes` - A vector of `Mesh` objects to start with. They define the bounding box
+///   of the scene for the camera and the axes.
+/// * `options` - `GpuOptions` containing the configuration for the GPU.
+/// * `job` - The function changing the scene, e.g. the steps of a simulation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::gpu::options::GpuOptions;
+/// use tessellate::gpu::error::GpuError;
+/// use tessellate::gpu::visualize_live;
+/// let meshes = vec![/* ... */];
+/// visualize_live(meshes, GpuOptions::default(), |scene, ids| {
+///     // let next = simulate(...);
+///     // scene.replace(ids[0], next)?;
+/// })?;
+/// # Ok::<(), GpuError>(())
+/// ```
+pub fn visualize_live<F>(meshes: Vec<Mesh>, options: GpuOptions, job: F) -> Result<(), GpuError>
+where
+    F: FnOnce(SceneHandle, Vec<MeshId>) + Send + 'static,
+{
+    let event_loop = event_loop()?;
+    let (handle, ids) = SceneHandle::new(event_loop.create_proxy(), meshes.len());
+    std::thread::spawn(move || job(handle, ids));
+    pollster::block_on(run(event_loop, meshes, options))
+}
+
+/// Renders the meshes offscreen, without a window, into an image of the given size.
+///
+/// The scene is set up the same way as in [`visualize`], including the scalar bars of the options.
+/// It needs no display, so it can be used in batch jobs and CI with a software adapter
+/// (e.g. llvmpipe or lavapipe).
+///
+/// # Arguments
+///
+/// * `meshes` - A vector of `Mesh` objects to be rendered.
+/// * `options` - `GpuOptions` containing the configuration for the GPU.
+/// * `width`, `height` - The size of the image in pixels.
+///
+/// # Returns
+///
+/// * `Result<RgbaImage, GpuError>` - The rendered image, or a `GpuError` if no adapter is found or the rendering fails.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::gpu::options::GpuOptions;
+/// use tessellate::gpu::error::GpuError;
+/// use tessellate::gpu::render_to_image;
+/// let meshes = vec![/* ... */];
+/// let image = render_to_image(meshes, GpuOptions::default(), 800, 600)?;
+/// # Ok::<(), GpuError>(())
+/// ```
+pub fn render_to_image(
+    meshes: Vec<Mesh>,
+    options: GpuOptions,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, GpuError> {
+    processor::headless::render_offscreen(&meshes, &options, width, height)
+}
+
+/// Renders the meshes offscreen like [`render_to_image`] and saves the image as a PNG file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tessellate::gpu::options::GpuOptions;
+/// use tessellate::gpu::error::GpuError;
+/// use tessellate::gpu::render_to_png;
+/// let meshes = vec![/* ... */];
+/// render_to_png(meshes, GpuOptions::default(), 800, 600, "figure.png")?;
+/// # Ok::<(), GpuError>(())
+/// ```
+pub fn render_to_png<P: AsRef<Path>>(
+    meshes: Vec<Mesh>,
+    options: GpuOptions,
+    width: u32,
+    height: u32,
+    path: P,
+) -> Result<(), GpuError> {
+    let image = render_to_image(meshes, options, width, height)?;
+    image.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
 }
@@ -1,6 +1,6 @@
 use egui_wgpu::wgpu;
 use egui_wgpu::wgpu::util::DeviceExt;
-use egui_wgpu::wgpu::{BindGroupLayout, Device, SurfaceConfiguration};
+use egui_wgpu::wgpu::{BindGroupLayout, Device};
 use glam::{Mat4, Vec3};
 use winit::dpi::PhysicalPosition;
 use winit::event::MouseScrollDelta;
@@ -32,13 +32,14 @@ pub struct Camera {
 
 impl Camera {
     pub fn init(
-        config: &SurfaceConfiguration,
+        width: u32,
+        height: u32,
         device: &Device,
         camera_pos: CameraPosition,
         aabb: BoundingBox,
         gnu_options: &GpuOptions,
     ) -> Self {
-        let projection = Projection::new(config.width, config.height, 45.0, 0.1, 100.0);
+        let projection = Projection::new(width, height, 45.0, 0.1, 100.0);
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera_pos, &projection);
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -140,6 +141,11 @@ impl Camera {
     pub fn camera_buffer(&self) -> &wgpu::Buffer {
         &self.camera_buffer
     }
+    /// The matrix from the world coordinates to the clip space of the current frame.
+    pub fn view_proj(&self) -> Mat4 {
+        self.projection.calc_matrix() * self.camera_pos.calc_matrix()
+    }
+
     pub fn update_camera(&mut self) {
         let new_source = self.camera_coord.eye();
         let new_target = self.camera_coord.target();
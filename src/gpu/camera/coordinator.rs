@@ -66,6 +66,25 @@ impl CameraCoordinator {
         self.ver_angle = va;
     }
 
+    /// Moves the eye keeping the target.
+    pub fn set_eye(&mut self, eye: Vertex) {
+        self.eye = eye;
+        self.look_from_eye();
+    }
+
+    /// Turns the eye to the target keeping its position.
+    pub fn set_target(&mut self, target: Vertex) {
+        self.target = target;
+        self.look_from_eye();
+    }
+
+    fn look_from_eye(&mut self) {
+        self.distance = self.target.distance(&self.eye);
+        let (ha, va) = angles(&self.eye, &self.target);
+        self.hor_angle = ha;
+        self.ver_angle = va;
+    }
+
     pub fn set_x_plus_zero(&mut self) {
         self.hor_angle = 0.0;
         self.derive_new_source();
@@ -1,10 +1,15 @@
 use winit::dpi::PhysicalPosition;
 
+/// The distance in pixels the cursor can move between the press and the release to make a click.
+const CLICK_TOLERANCE: f64 = 3.0;
+
 #[derive(Debug, Default)]
 pub struct Mouse {
     lb_pressed: bool,
     rb_pressed: bool,
     last_pos: Option<PhysicalPosition<f64>>,
+    cursor: Option<PhysicalPosition<f64>>,
+    lb_pressed_at: Option<PhysicalPosition<f64>>,
 }
 
 impl Mouse {
@@ -34,12 +39,35 @@ impl Mouse {
 
     pub fn set_left_button(&mut self, pressed: bool) -> bool{
         self.lb_pressed = pressed;
-        if !pressed {
+        if pressed {
+            self.lb_pressed_at = self.cursor;
+        } else {
             self.clean_pos();
         }
         true
     }
 
+    /// Tracks the cursor, pressed or not.
+    pub fn set_cursor(&mut self, pos: PhysicalPosition<f64>) {
+        self.cursor = Some(pos);
+    }
+
+    pub fn cursor(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor
+    }
+
+    /// Returns the cursor position if the left button is released
+    /// near the place it was pressed, i.e. the camera was not rotated.
+    pub fn take_click(&mut self) -> Option<PhysicalPosition<f64>> {
+        if self.lb_pressed {
+            return None;
+        }
+        let pressed_at = self.lb_pressed_at.take()?;
+        let cursor = self.cursor?;
+        let (dx, dy) = (cursor.x - pressed_at.x, cursor.y - pressed_at.y);
+        (dx.hypot(dy) <= CLICK_TOLERANCE).then_some(cursor)
+    }
+
     pub fn set_right_button(&mut self, pressed: bool) -> bool {
         self.rb_pressed = pressed;
         if !pressed {
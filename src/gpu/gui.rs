@@ -1,5 +1,8 @@
 pub mod camera_info;
 pub mod controls;
+pub mod pick_info;
+pub mod scalar_bar;
+pub mod section;
 
 use egui::{Context, FontData, FontDefinitions, FontFamily, Visuals};
 use egui_wgpu::Renderer;
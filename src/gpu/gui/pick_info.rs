@@ -0,0 +1,51 @@
+use crate::gpu::picking::Pick;
+use egui::{Align2, Context, Grid};
+
+pub struct PickInfo;
+
+impl PickInfo {
+    /// Shows the picked element, returns false when the window is closed.
+    pub fn show(ctx: &Context, pick: &Pick) -> bool {
+        let mut open = true;
+        egui::Window::new("Pick Info")
+            .anchor(Align2::LEFT_BOTTOM, egui::vec2(1.0, -1.0))
+            .open(&mut open)
+            .vscroll(true)
+            .auto_sized()
+            .show(ctx, |ui| {
+                Grid::new("Picked Element")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Mesh");
+                        ui.label(format!("{:?}", pick.mesh()));
+                        ui.end_row();
+
+                        if let Some(face) = pick.face() {
+                            ui.label("Face");
+                            ui.label(face.to_string());
+                            ui.end_row();
+                        }
+
+                        ui.label("Vertex");
+                        ui.label(pick.vertex().to_string());
+                        ui.end_row();
+
+                        let position = pick.position();
+                        ui.label("Position");
+                        ui.label(format!(
+                            "{:.3}, {:.3}, {:.3}",
+                            position.x, position.y, position.z
+                        ));
+                        ui.end_row();
+
+                        for (name, value) in pick.fields() {
+                            ui.label(name);
+                            ui.label(value.to_string());
+                            ui.end_row();
+                        }
+                    })
+            });
+        open
+    }
+}
@@ -0,0 +1,97 @@
+use crate::mesh::material::colormap::ScalarBar;
+use crate::mesh::material::RgbaColor;
+use egui::{Align2, Color32, Context, FontId, Mesh, Pos2, Rect, Sense, Stroke};
+
+const BAR_WIDTH: f32 = 20.0;
+const BAR_HEIGHT: f32 = 180.0;
+const LABELS_WIDTH: f32 = 70.0;
+const STEPS: usize = 32;
+const TICKS: usize = 5;
+
+pub struct ScalarBarLegend;
+
+impl ScalarBarLegend {
+    /// Shows the legends stacked from the right bottom corner.
+    pub fn show_all(ctx: &Context, bars: &[ScalarBar]) {
+        for (idx, bar) in bars.iter().enumerate() {
+            Self::show(ctx, bar, idx);
+        }
+    }
+
+    pub fn show(ctx: &Context, bar: &ScalarBar, idx: usize) {
+        let title = if bar.label().is_empty() {
+            bar.colormap().name()
+        } else {
+            bar.label()
+        };
+        let offset = -(BAR_WIDTH + LABELS_WIDTH + 30.0) * idx as f32;
+        egui::Window::new(title)
+            .id(egui::Id::new(("scalar bar", idx)))
+            .anchor(Align2::RIGHT_BOTTOM, egui::vec2(offset - 1.0, -1.0))
+            .default_open(true)
+            .resizable(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(BAR_WIDTH + LABELS_WIDTH, BAR_HEIGHT),
+                    Sense::hover(),
+                );
+                let bar_rect = Rect::from_min_size(rect.min, egui::vec2(BAR_WIDTH, BAR_HEIGHT));
+                let painter = ui.painter();
+
+                // the gradient goes from the minimum at the bottom to the maximum at the top
+                let mut mesh = Mesh::default();
+                for step in 0..=STEPS {
+                    let t = step as f32 / STEPS as f32;
+                    let y = bar_rect.bottom() - t * BAR_HEIGHT;
+                    let color = to_color32(&bar.colormap().sample(t));
+                    mesh.colored_vertex(Pos2::new(bar_rect.left(), y), color);
+                    mesh.colored_vertex(Pos2::new(bar_rect.right(), y), color);
+                    if step > 0 {
+                        let i = (2 * step) as u32;
+                        mesh.add_triangle(i - 2, i - 1, i);
+                        mesh.add_triangle(i - 1, i + 1, i);
+                    }
+                }
+                painter.add(mesh);
+                let text_color = ui.visuals().text_color();
+                painter.rect_stroke(bar_rect, 0.0, Stroke::new(1.0, text_color));
+
+                for tick in 0..TICKS {
+                    let t = tick as f32 / (TICKS - 1) as f32;
+                    let y = bar_rect.bottom() - t * BAR_HEIGHT;
+                    painter.line_segment(
+                        [Pos2::new(bar_rect.right(), y), Pos2::new(bar_rect.right() + 4.0, y)],
+                        Stroke::new(1.0, text_color),
+                    );
+                    painter.text(
+                        Pos2::new(bar_rect.right() + 6.0, y),
+                        Align2::LEFT_CENTER,
+                        format_value(bar.value(t)),
+                        FontId::proportional(11.0),
+                        text_color,
+                    );
+                }
+
+                ui.horizontal(|ui| {
+                    let (swatch, _) = ui.allocate_exact_size(egui::vec2(BAR_WIDTH, 10.0), Sense::hover());
+                    ui.painter().rect_filled(swatch, 0.0, to_color32(bar.nan_color()));
+                    ui.label(if bar.log_scale() { "NaN, log scale" } else { "NaN" });
+                });
+            });
+    }
+}
+
+fn to_color32(color: &RgbaColor) -> Color32 {
+    let [r, g, b, a] = color.0;
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn format_value(value: f32) -> String {
+    let abs = value.abs();
+    if abs != 0.0 && !(1e-2..1e4).contains(&abs) {
+        format!("{:.2e}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::gpu::options::SectionOptions;
+use crate::mesh::material::RgbaColor;
+use egui::{Align2, Color32, Context, DragValue, Grid, RichText};
+use glam::Vec3;
+
+pub struct SectionControls;
+
+impl SectionControls {
+    /// Shows the settings of the section plane, returns the new ones if they are changed.
+    pub fn show(ctx: &Context, section: &SectionOptions) -> Option<SectionOptions> {
+        let mut enabled = section.enabled();
+        let mut normal = section.normal();
+        let mut offset = section.offset();
+        let mut show_contour = section.show_contour();
+        let mut contour_color = section.contour_color().0;
+
+        egui::Window::new("Section")
+            .anchor(Align2::CENTER_TOP, egui::vec2(0.0, 1.0))
+            .default_open(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.checkbox(&mut enabled, "Cut by the plane");
+                Grid::new("Section Plane")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Normal");
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut normal.x).speed(0.01).prefix("X: "));
+                            ui.add(DragValue::new(&mut normal.y).speed(0.01).prefix("Y: "));
+                            ui.add(DragValue::new(&mut normal.z).speed(0.01).prefix("Z: "));
+                        });
+                        ui.end_row();
+
+                        ui.label("Axis");
+                        ui.horizontal(|ui| {
+                            for (label, color, axis) in [
+                                ("X", Color32::RED, Vec3::X),
+                                ("Y", Color32::GREEN, Vec3::Y),
+                                ("Z", Color32::BLUE, Vec3::Z),
+                            ] {
+                                if ui.button(RichText::new(label).color(color).size(12.0)).clicked() {
+                                    normal = axis;
+                                }
+                            }
+                            if ui.button(RichText::new("Flip").size(12.0)).clicked() {
+                                normal = -normal;
+                                offset = -offset;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Offset");
+                        ui.add(DragValue::new(&mut offset).speed(0.01));
+                        ui.end_row();
+
+                        ui.label("Contour");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut show_contour, "");
+                            ui.color_edit_button_srgba_unmultiplied(&mut contour_color);
+                        });
+                        ui.end_row();
+                    });
+            });
+
+        let mut changed = section.clone();
+        changed.with_enabled(enabled);
+        changed.with_normal(normal);
+        changed.with_offset(offset);
+        changed.with_contour(show_contour);
+        changed.with_contour_color(RgbaColor(contour_color));
+        (changed != *section).then_some(changed)
+    }
+}
@@ -76,6 +76,11 @@ impl Light {
     pub fn position(&self) -> Vec3 {
         self.light_uniform.position()
     }
+
+    /// Takes the light from the options, the buffer is written on the next update.
+    pub fn set_options(&mut self, gnu_options: &GpuOptions) {
+        self.light_uniform = gnu_options.into();
+    }
 }
 
 #[repr(C)]
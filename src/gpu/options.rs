@@ -1,10 +1,17 @@
+use crate::gpu::picking::PickCallback;
+use crate::mesh::material::colormap::ScalarBar;
 use crate::mesh::material::RgbaColor;
+use crate::mesh::parts::plane::Plane;
 use glam::Vec3;
-/// GpuOptions holds the configuration for the GPU, including camera and light options.
+/// GpuOptions holds the configuration for the GPU, including camera and light options,
+/// the section plane and the scalar bars (legends) of the colormaps to show.
 #[derive(Debug, Clone, Default)]
 pub struct GpuOptions {
     light_opts: LightOptions,
     camera_opts: CameraOptions,
+    section_opts: SectionOptions,
+    scalar_bars: Vec<ScalarBar>,
+    pick_callback: Option<PickCallback>,
 }
 
 impl GpuOptions {
@@ -12,6 +19,9 @@ impl GpuOptions {
         Self {
             camera_opts,
             light_opts,
+            section_opts: SectionOptions::default(),
+            scalar_bars: Vec::new(),
+            pick_callback: None,
         }
     }
 
@@ -19,6 +29,9 @@ impl GpuOptions {
         Self {
             camera_opts: CameraOptions::new_position(pos),
             light_opts: LightOptions::default(),
+            section_opts: SectionOptions::default(),
+            scalar_bars: Vec::new(),
+            pick_callback: None,
         }
     }
 
@@ -26,6 +39,9 @@ impl GpuOptions {
         Self {
             camera_opts: CameraOptions::new_position(camera_pos),
             light_opts: LightOptions::new_position(light_pos),
+            section_opts: SectionOptions::default(),
+            scalar_bars: Vec::new(),
+            pick_callback: None,
         }
     }
 
@@ -39,6 +55,29 @@ impl GpuOptions {
         self
     }
 
+    /// Sets the plane cutting the meshes in the viewer, it can be changed later in the gui.
+    pub fn with_section(&mut self, section: SectionOptions) -> &Self {
+        self.section_opts = section;
+        self
+    }
+
+    /// Adds the legend of a colormap, e.g. the one returned by `Mesh::color_by_field`.
+    pub fn with_scalar_bar(&mut self, scalar_bar: ScalarBar) -> &Self {
+        self.scalar_bars.push(scalar_bar);
+        self
+    }
+
+    pub fn with_scalar_bars(&mut self, scalar_bars: Vec<ScalarBar>) -> &Self {
+        self.scalar_bars = scalar_bars;
+        self
+    }
+
+    /// Sets the function called when a mesh is clicked in the viewer.
+    pub fn with_pick_callback(&mut self, callback: PickCallback) -> &Self {
+        self.pick_callback = Some(callback);
+        self
+    }
+
     pub fn camera_opts(&self) -> &CameraOptions {
         &self.camera_opts
     }
@@ -46,6 +85,22 @@ impl GpuOptions {
     pub fn light_opts(&self) -> &LightOptions {
         &self.light_opts
     }
+
+    pub fn section_opts(&self) -> &SectionOptions {
+        &self.section_opts
+    }
+
+    pub fn section_opts_mut(&mut self) -> &mut SectionOptions {
+        &mut self.section_opts
+    }
+
+    pub fn scalar_bars(&self) -> &[ScalarBar] {
+        &self.scalar_bars
+    }
+
+    pub fn pick_callback(&self) -> Option<&PickCallback> {
+        self.pick_callback.as_ref()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -136,6 +191,105 @@ impl Default for CameraOptions {
     }
 }
 
+/// The plane cutting the meshes on the GPU to look inside them, the meshes are not rebuilt.
+/// The points with `normal · p > offset` (in front of the plane) are hidden.
+/// The contour is the section of every mesh by the plane drawn with the lines.
+///
+/// # Example
+/// ```no_run
+/// use glam::Vec3;
+/// use tessellate::gpu::options::{GpuOptions, SectionOptions};
+/// let mut section = SectionOptions::new(Vec3::Z, 0.5);
+/// section.with_contour(true);
+/// let mut options = GpuOptions::default();
+/// options.with_section(section);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionOptions {
+    enabled: bool,
+    normal: Vec3,
+    offset: f32,
+    show_contour: bool,
+    contour_color: RgbaColor,
+}
+
+impl Default for SectionOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            normal: Vec3::X,
+            offset: 0.0,
+            show_contour: false,
+            contour_color: RgbaColor::RED,
+        }
+    }
+}
+
+impl SectionOptions {
+    /// Creates the enabled section by the plane with the normal and the offset from the origin.
+    pub fn new(normal: Vec3, offset: f32) -> Self {
+        Self {
+            enabled: true,
+            normal,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_enabled(&mut self, enabled: bool) -> &Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_normal(&mut self, normal: Vec3) -> &Self {
+        self.normal = normal;
+        self
+    }
+
+    pub fn with_offset(&mut self, offset: f32) -> &Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_contour(&mut self, show_contour: bool) -> &Self {
+        self.show_contour = show_contour;
+        self
+    }
+
+    pub fn with_contour_color(&mut self, contour_color: RgbaColor) -> &Self {
+        self.contour_color = contour_color;
+        self
+    }
+}
+
+impl SectionOptions {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub fn show_contour(&self) -> bool {
+        self.show_contour
+    }
+
+    pub fn contour_color(&self) -> &RgbaColor {
+        &self.contour_color
+    }
+
+    /// The cutting plane with the unit normal, the zero normal is taken as the x axis.
+    pub fn plane(&self) -> Plane {
+        let normal = self.normal.try_normalize().unwrap_or(Vec3::X);
+        Plane::new(normal, normal * self.offset)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LightOptions {
     position: Vec3,
@@ -0,0 +1,276 @@
+use crate::gpu::scene::MeshId;
+use crate::gpu::vertex::point_radius;
+use crate::mesh::attributes::MeshType;
+use crate::mesh::fields::FieldValue;
+use crate::mesh::parts::plane::Plane;
+use crate::mesh::parts::ray::Ray;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::query::bvh::BVHTree;
+use crate::mesh::Mesh;
+use glam::{Mat4, Vec3};
+use log::warn;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// The element of a mesh clicked in the viewer.
+#[derive(Debug, Clone)]
+pub struct Pick {
+    mesh: MeshId,
+    face: Option<usize>,
+    vertex: usize,
+    position: Vertex,
+    fields: Vec<(String, FieldValue)>,
+}
+
+impl Pick {
+    pub fn mesh(&self) -> MeshId {
+        self.mesh
+    }
+
+    /// The hit face, the points of a cloud have no faces.
+    pub fn face(&self) -> Option<usize> {
+        self.face
+    }
+
+    /// The clicked point of a cloud or the vertex of the hit face closest to the cursor.
+    pub fn vertex(&self) -> usize {
+        self.vertex
+    }
+
+    /// The hit point in the world coordinates.
+    pub fn position(&self) -> Vertex {
+        self.position
+    }
+
+    /// The values of the vertex fields at the vertex and of the face fields at the face.
+    pub fn fields(&self) -> &[(String, FieldValue)] {
+        &self.fields
+    }
+}
+
+/// The function called with every pick made in the viewer.
+///
+/// # Example
+/// ```no_run
+/// use tessellate::gpu::options::GpuOptions;
+/// use tessellate::gpu::picking::PickCallback;
+/// let mut options = GpuOptions::default();
+/// options.with_pick_callback(PickCallback::new(|pick| {
+///     println!("{:?} at {}", pick.mesh(), pick.position());
+/// }));
+/// ```
+#[derive(Clone)]
+pub struct PickCallback(Arc<dyn Fn(&Pick) + Send + Sync>);
+
+impl PickCallback {
+    pub fn new<F: Fn(&Pick) + Send + Sync + 'static>(func: F) -> Self {
+        PickCallback(Arc::new(func))
+    }
+
+    pub(crate) fn call(&self, pick: &Pick) {
+        (self.0)(pick)
+    }
+}
+
+impl Debug for PickCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PickCallback")
+    }
+}
+
+/// Casts the ray from the cursor through the near and the far planes of the camera.
+/// The cursor is given in pixels from the top left corner of the window.
+pub(crate) fn cursor_ray(view_proj: Mat4, x: f32, y: f32, width: u32, height: u32) -> Ray {
+    let ndc_x = 2.0 * x / width as f32 - 1.0;
+    let ndc_y = 1.0 - 2.0 * y / height as f32;
+    let inverse = view_proj.inverse();
+    // the depth of wgpu goes from 0 at the near plane to 1 at the far plane
+    let near = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+    let far = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+    Ray::new(near.into(), (far - near).normalize().into())
+}
+
+/// Finds the meshes under the cursor.
+/// The spatial indexes of the meshes are built with the first click and kept until the mesh changes.
+#[derive(Default)]
+pub(crate) struct Picker {
+    trees: HashMap<MeshId, Option<BVHTree>>,
+}
+
+impl Picker {
+    /// Drops the index of the changed or removed mesh.
+    pub(crate) fn forget(&mut self, id: MeshId) {
+        self.trees.remove(&id);
+    }
+
+    /// Finds the nearest element hit by the ray, the lines are not picked.
+    /// The elements in front of the section plane are hidden, so they are not picked either.
+    pub(crate) fn pick<'a, I>(&mut self, meshes: I, ray: &Ray, section: Option<&Plane>) -> Option<Pick>
+    where
+        I: IntoIterator<Item = (MeshId, &'a Mesh)>,
+    {
+        let (start, end) = match section {
+            Some(plane) => visible_span(ray, plane)?,
+            None => (0.0, f32::INFINITY),
+        };
+        let ray = &Ray::new(ray.at(start), ray.direction);
+        let mut best: Option<(f32, Pick)> = None;
+        for (id, mesh) in meshes {
+            let hit = match mesh.attributes().mesh_type() {
+                MeshType::Polygons => self.cast_faces(id, mesh, ray),
+                MeshType::Cloud(size) => cast_points(mesh, point_radius(size), ray),
+                MeshType::Lines => None,
+            };
+            if let Some((distance, face, vertex, position)) = hit.filter(|hit| start + hit.0 <= end) {
+                if best.as_ref().is_none_or(|(d, _)| distance < *d) {
+                    let pick = Pick {
+                        mesh: id,
+                        face,
+                        vertex,
+                        position,
+                        fields: mesh.fields().values_at(vertex, face),
+                    };
+                    best = Some((distance, pick));
+                }
+            }
+        }
+        best.map(|(_, pick)| pick)
+    }
+
+    fn cast_faces(
+        &mut self,
+        id: MeshId,
+        mesh: &Mesh,
+        ray: &Ray,
+    ) -> Option<(f32, Option<usize>, usize, Vertex)> {
+        let tree = self.trees.entry(id).or_insert_with(|| {
+            BVHTree::try_from_mesh(mesh)
+                .map_err(|e| warn!("The mesh {:?} can not be picked: {}", id, e))
+                .ok()
+        });
+        let hit = tree.as_ref()?.cast(ray)?;
+        let vertex = mesh.faces()[hit.face]
+            .flatten()
+            .into_iter()
+            .min_by(|a, b| {
+                let (a, b) = (mesh.vertices()[*a], mesh.vertices()[*b]);
+                a.distance(&hit.point).total_cmp(&b.distance(&hit.point))
+            })?;
+        Some((hit.distance, Some(hit.face), vertex, hit.point))
+    }
+}
+
+/// The part of the ray behind the section plane, `normal · p <= offset`,
+/// as the distances from its origin. None if the ray does not go there.
+fn visible_span(ray: &Ray, plane: &Plane) -> Option<(f32, f32)> {
+    let from = plane.distance(ray.origin);
+    let rate = plane.normal().dot(ray.direction.into());
+    if rate == 0.0 {
+        return (from <= 0.0).then_some((0.0, f32::INFINITY));
+    }
+    let cross = -from / rate;
+    if rate > 0.0 {
+        (cross >= 0.0).then_some((0.0, cross))
+    } else {
+        Some((cross.max(0.0), f32::INFINITY))
+    }
+}
+
+/// Finds the nearest point of the cloud drawn as a sphere of the radius.
+fn cast_points(mesh: &Mesh, radius: f32, ray: &Ray) -> Option<(f32, Option<usize>, usize, Vertex)> {
+    mesh.vertices()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, v)| {
+            let (t, distance) = ray.closest(v);
+            (distance <= radius).then_some((t, None, idx, *v))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::fields::FieldData;
+    use crate::mesh::parts::face::Face;
+
+    #[test]
+    fn pick_nearest() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(45f32.to_radians(), 2.0, 0.1, 100.0);
+        let ray = cursor_ray(proj * view, 100.0, 50.0, 200, 100);
+        assert!(ray.origin.distance(&Vertex::new(0.0, 0.0, 4.9)) < 1e-4);
+        assert!(ray.direction.distance(&Vertex::new(0.0, 0.0, -1.0)) < 1e-4);
+
+        let faces: Vec<Face> = vec![(0, 1, 2, 3).into()];
+        let square = |z: f32| {
+            Mesh::from_vertices(
+                vec![[-1.0, -1.0, z], [1.0, -1.0, z], [1.0, 1.0, z], [-1.0, 1.0, z]],
+                faces.clone(),
+                Default::default(),
+            )
+        };
+        let mut front = square(1.0);
+        front
+            .add_vertex_field("t", FieldData::Scalar(vec![0.0, 1.0, 2.0, 3.0]))
+            .unwrap();
+        front.add_face_field("id", FieldData::Integer(vec![7])).unwrap();
+        let back = square(-1.0);
+        let cloud = Mesh::cloud(vec![[0.0, 0.0, 3.0], [0.5, 0.0, 2.0]], 10, Default::default());
+
+        let mut picker = Picker::default();
+        let (a, b, c) = (MeshId::initial(0), MeshId::initial(1), MeshId::initial(2));
+        let ray = Ray::new(Vertex::new(0.9, 0.8, 5.0), Vertex::new(0.0, 0.0, -1.0));
+        let pick = picker.pick([(a, &back), (b, &front)], &ray, None).unwrap();
+        assert_eq!(pick.mesh(), b);
+        assert_eq!((pick.face(), pick.vertex()), (Some(0), 2));
+        assert_eq!(pick.position(), Vertex::new(0.9, 0.8, 1.0));
+        assert_eq!(
+            pick.fields(),
+            &[
+                ("t".to_string(), FieldValue::Scalar(2.0)),
+                ("id".to_string(), FieldValue::Integer(7))
+            ]
+        );
+
+        let ray = Ray::new(Vertex::new(0.0, 0.05, 5.0), Vertex::new(0.0, 0.0, -1.0));
+        let pick = picker.pick([(a, &back), (b, &front), (c, &cloud)], &ray, None).unwrap();
+        assert_eq!((pick.mesh(), pick.face(), pick.vertex()), (c, None, 0));
+        picker.forget(a);
+        assert!(picker.pick([(a, &back)], &Ray::new(Vertex::new(3.0, 0.0, 5.0), ray.direction), None).is_none());
+    }
+
+    #[test]
+    fn pick_behind_section() {
+        let faces: Vec<Face> = vec![(0, 1, 2, 3).into()];
+        let square = |z: f32| {
+            Mesh::from_vertices(
+                vec![[-1.0, -1.0, z], [1.0, -1.0, z], [1.0, 1.0, z], [-1.0, 1.0, z]],
+                faces.clone(),
+                Default::default(),
+            )
+        };
+        let (front, back) = (square(1.0), square(-1.0));
+        let (a, b) = (MeshId::initial(0), MeshId::initial(1));
+        let mut picker = Picker::default();
+        // the half-space z > 0 is hidden
+        let plane = Plane::new(Vec3::Z, Vec3::ZERO);
+
+        let down = Ray::new(Vertex::new(0.5, 0.5, 5.0), Vertex::new(0.0, 0.0, -1.0));
+        let pick = picker.pick([(a, &front), (b, &back)], &down, Some(&plane)).unwrap();
+        assert_eq!(pick.mesh(), b);
+        assert_eq!(pick.position(), Vertex::new(0.5, 0.5, -1.0));
+
+        // the ray leaves the visible part before it reaches the square
+        let up = Ray::new(Vertex::new(0.5, 0.5, -5.0), Vertex::new(0.0, 0.0, 1.0));
+        assert!(picker.pick([(a, &front)], &up, Some(&plane)).is_none());
+        assert_eq!(picker.pick([(a, &front)], &up, None).unwrap().mesh(), a);
+
+        // the ray goes along the plane inside the hidden part
+        let cloud = Mesh::cloud(vec![[0.0, 0.5, 0.5]], 10, Default::default());
+        let along = Ray::new(Vertex::new(-5.0, 0.5, 0.5), Vertex::new(1.0, 0.0, 0.0));
+        assert!(picker.pick([(a, &cloud)], &along, Some(&plane)).is_none());
+        assert!(picker.pick([(a, &cloud)], &along, None).is_some());
+    }
+}
@@ -8,10 +8,14 @@ use std::collections::HashMap;
 use crate::gpu::gui::GuiRenderer;
 use crate::gpu::light::Light;
 use crate::gpu::material::Material;
-use crate::gpu::vertex::GpuVertex;
+use crate::gpu::vertex::{GpuGeometry, GpuInstance};
 use crate::gpu::options::GpuOptions;
+use crate::gpu::picking::{Pick, Picker};
+use crate::gpu::scene::{MeshId, PendingMeshes, SceneCommand};
+use crate::gpu::section::Section;
 use crate::mesh::attributes::MeshType;
 use egui_wgpu::wgpu;
+use egui_wgpu::wgpu::util::DeviceExt;
 use egui_wgpu::wgpu::{Buffer, RenderPipeline, Surface};
 use std::sync::Arc;
 use winit::application::ApplicationHandler;
@@ -21,35 +25,72 @@ use winit::event_loop::ActiveEventLoop;
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 
+pub(crate) mod headless;
 mod init;
+mod picking;
 mod render;
+mod scene;
+mod section;
 
 pub struct GpuProcessor {
     state: State,
+    /// The commands of the scene handles received before the initialization.
+    commands: Vec<SceneCommand>,
 }
 
+/// The buffers of a mesh on the device.
+/// The vertices are drawn by the indices, the clouds draw one sprite for every instance.
 struct GpuMesh {
+    /// The meshes of the scene have the identifiers, the auxiliary items (axes, light source) do not.
+    id: Option<MeshId>,
     vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    instance_buffer: Option<Buffer>,
+    instance_count: u32,
     mesh: Mesh,
-    vertices: Vec<GpuVertex>,
     material: Material,
     affected_by_light: wgpu::BindGroup,
 }
 
 impl GpuMesh {
     pub fn new(
-        vertex_buffer: Buffer,
-        vertices: Vec<GpuVertex>,
+        device: &wgpu::Device,
+        id: Option<MeshId>,
+        geometry: GpuGeometry,
+        instances: Option<Vec<GpuInstance>>,
         mesh: Mesh,
         material: Material,
         affected_by_light: wgpu::BindGroup,
     ) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&geometry.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&geometry.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_count = instances.as_ref().map_or(1, |i| i.len() as u32);
+        let instance_buffer = instances.map(|instances| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: bytemuck::cast_slice(&instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
         GpuMesh {
+            id,
             vertex_buffer,
+            index_buffer,
+            index_count: geometry.indices.len() as u32,
+            instance_buffer,
+            instance_count,
             mesh,
-            vertices,
             material,
-            affected_by_light
+            affected_by_light,
         }
     }
 }
@@ -58,6 +99,7 @@ impl GpuProcessor {
     pub fn new(meshes: Vec<Mesh>, camera: CameraPosition, options: GpuOptions) -> Self {
         GpuProcessor {
             state: State::NotInitialized(meshes, camera, options),
+            commands: Vec::new(),
         }
     }
 }
@@ -71,6 +113,8 @@ enum State {
 enum Topology {
     LineList,
     TriangleList,
+    /// The triangles of a sprite repeated for every instance.
+    Instances,
 }
 pub struct GpuHandler {
     window: Arc<Window>,
@@ -84,8 +128,14 @@ pub struct GpuHandler {
     meshes: Vec<GpuMesh>,
     camera: Camera,
     light: Light,
+    section: Section,
+    /// The sections of the meshes by the plane drawn over them.
+    contours: Vec<GpuMesh>,
     gui: GuiRenderer,
     gpu_options: GpuOptions,
+    pending: PendingMeshes,
+    picker: Picker,
+    picked: Option<Pick>,
 }
 
 impl GpuHandler {
@@ -102,6 +152,8 @@ impl GpuHandler {
         camera: Camera,
         gui: GuiRenderer,
         light: Light,
+        section: Section,
+        contours: Vec<GpuMesh>,
         gpu_options: GpuOptions,
     ) -> Self {
         Self {
@@ -117,7 +169,12 @@ impl GpuHandler {
             camera,
             gui,
             light,
-            gpu_options
+            section,
+            contours,
+            gpu_options,
+            pending: PendingMeshes::default(),
+            picker: Picker::default(),
+            picked: None,
         }
     }
 }
@@ -142,16 +199,22 @@ impl Default for GpuProcessor {
     fn default() -> Self {
         GpuProcessor {
             state: State::NotInitialized(vec![], CameraPosition::default(), GpuOptions::default()),
+            commands: Vec::new(),
         }
     }
 }
 
-impl ApplicationHandler for GpuProcessor {
+impl ApplicationHandler<SceneCommand> for GpuProcessor {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         match &self.state {
             State::NotInitialized(meshes, camera, opts) => {
                 match GpuProcessor::try_init(event_loop, meshes, camera.clone(), opts.clone()) {
-                    Ok(s) => {
+                    Ok(mut s) => {
+                        for command in self.commands.drain(..) {
+                            if !s.apply(command) {
+                                event_loop.exit();
+                            }
+                        }
                         self.state = State::Initialized(s);
                     }
                     Err(e) => {
@@ -166,6 +229,19 @@ impl ApplicationHandler for GpuProcessor {
             }
         }
     }
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, command: SceneCommand) {
+        match &mut self.state {
+            State::Initialized(s) => {
+                if !s.apply(command) {
+                    info!("The viewer is closed by the scene handle");
+                    event_loop.exit();
+                }
+            }
+            State::NotInitialized(..) => self.commands.push(command),
+            State::Failed(_) => {}
+        }
+    }
+
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match self.state() {
             Ok(s) => {
@@ -190,6 +266,7 @@ impl ApplicationHandler for GpuProcessor {
                         }
                         WindowEvent::RedrawRequested if _id == s.window().id() => {
                             s.window.request_redraw();
+                            s.sync_meshes();
                             s.update();
                             match s.render() {
                                 Ok(_) => {}
@@ -0,0 +1,278 @@
+use crate::gpu::camera::position::CameraPosition;
+use crate::gpu::camera::Camera;
+use crate::gpu::error::{GpuError, GpuResult};
+use crate::gpu::gui::scalar_bar::ScalarBarLegend;
+use crate::gpu::light::Light;
+use crate::gpu::options::GpuOptions;
+use crate::gpu::processor::init::{create_gpu_meshes, create_pipelines};
+use crate::gpu::processor::render::{depth_view, draw_meshes};
+use crate::gpu::processor::section::create_contours;
+use crate::gpu::section::Section;
+use crate::mesh::Mesh;
+use egui_wgpu::wgpu;
+use egui_wgpu::ScreenDescriptor;
+use image::RgbaImage;
+use log::info;
+use std::iter;
+
+/// The format of the offscreen target, the same sRGB encoding the window surface gets.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Renders the meshes into an offscreen texture of the given size and reads it back.
+/// No window or display is needed, the software adapters (llvmpipe, lavapipe, GL) are accepted.
+pub(crate) fn render_offscreen(
+    meshes: &[Mesh],
+    options: &GpuOptions,
+    width: u32,
+    height: u32,
+) -> GpuResult<RgbaImage> {
+    if width == 0 || height == 0 {
+        return Err(GpuError::new("The image size should be positive"));
+    }
+    let (device, queue) = pollster::block_on(request_device())?;
+
+    let section = Section::init(&device, options.section_opts());
+    let (gpu_meshes, aabb) = create_gpu_meshes(&device, meshes, options, &section)?;
+    let contours = create_contours(&device, &gpu_meshes, options.section_opts(), &section);
+    let camera_pos = CameraPosition::new(options.camera_opts().position(), 0.0, 0.0);
+    let mut camera = Camera::init(width, height, &device, camera_pos, aabb, options);
+    let light = Light::init(&device, options);
+    let pipelines = create_pipelines(&device, FORMAT, &camera, &light);
+
+    camera.update_camera();
+    queue.write_buffer(camera.camera_buffer(), 0, bytemuck::cast_slice(&[*camera.uniform()]));
+    queue.write_buffer(light.light_buffer(), 0, bytemuck::cast_slice(&[*light.light_uniform()]));
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let depth_view = depth_view(&device, width, height);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Offscreen Encoder"),
+    });
+    draw_meshes(
+        &mut encoder,
+        &view,
+        &depth_view,
+        options.light_opts().background_color(),
+        &pipelines,
+        &camera,
+        &light,
+        gpu_meshes.iter().chain(&contours),
+    )?;
+    let mut egui_renderer = egui_wgpu::Renderer::new(&device, FORMAT, None, 1, false);
+    if !options.scalar_bars().is_empty() {
+        draw_overlay(&device, &queue, &mut encoder, &mut egui_renderer, &view, options, width, height);
+    }
+
+    // the rows of the copied texture are padded to the alignment of wgpu
+    let unpadded = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded = unpadded.div_ceil(align) * align;
+    let output = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Offscreen Output"),
+        size: (padded * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &output,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(iter::once(encoder.finish()));
+
+    let slice = output.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |r| {
+        let _ = sender.send(r);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|e| GpuError::General(format!("Failed to read the image: {}", e)))?
+        .map_err(|e| GpuError::General(format!("Failed to read the image: {}", e)))?;
+
+    let data = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((unpadded * height) as usize);
+    for row in data.chunks(padded as usize) {
+        pixels.extend_from_slice(&row[..unpadded as usize]);
+    }
+    drop(data);
+    output.unmap();
+
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or(GpuError::new("The image does not match the size of the target"))
+}
+
+/// The hardware adapter is preferred, the software one is the fallback.
+async fn request_adapter() -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await;
+        if adapter.is_some() {
+            return adapter;
+        }
+    }
+    None
+}
+
+async fn request_device() -> GpuResult<(wgpu::Device, wgpu::Queue)> {
+    let adapter = request_adapter()
+        .await
+        .ok_or(GpuError::new("Failed to request adapter"))?;
+    info!("Offscreen rendering with {:?}", adapter.get_info());
+
+    Ok(adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                    .using_resolution(adapter.limits()),
+                label: None,
+                memory_hints: Default::default(),
+            },
+            None,
+        )
+        .await?)
+}
+
+/// Draws the scalar bars over the rendered meshes.
+#[allow(clippy::too_many_arguments)]
+fn draw_overlay(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: &mut wgpu::CommandEncoder,
+    renderer: &mut egui_wgpu::Renderer,
+    view: &wgpu::TextureView,
+    options: &GpuOptions,
+    width: u32,
+    height: u32,
+) {
+    let ctx = egui::Context::default();
+    // a single picture has no time for the windows to fade in
+    ctx.style_mut(|style| style.animation_time = 0.0);
+    let input = egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(width as f32, height as f32),
+        )),
+        ..Default::default()
+    };
+    // the windows are laid out in the first pass and painted in the second one
+    let mut textures = Vec::new();
+    let mut output = None;
+    for _ in 0..2 {
+        let full = ctx.run(input.clone(), |ctx| {
+            ScalarBarLegend::show_all(ctx, options.scalar_bars());
+        });
+        textures.extend(full.textures_delta.set.clone());
+        output = Some(full);
+    }
+    let Some(output) = output else { return };
+
+    let screen_descriptor = ScreenDescriptor {
+        size_in_pixels: [width, height],
+        pixels_per_point: 1.0,
+    };
+    let tris = ctx.tessellate(output.shapes, 1.0);
+    for (id, image_delta) in &textures {
+        renderer.update_texture(device, queue, *id, image_delta);
+    }
+    renderer.update_buffers(device, queue, encoder, &tris, &screen_descriptor);
+    let rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        label: Some("egui offscreen render pass"),
+        occlusion_query_set: None,
+    });
+    renderer.render(&mut rpass.forget_lifetime(), &tris, &screen_descriptor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::{Color, RgbaColor};
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::parts::face::FaceType;
+    use crate::gpu::options::SectionOptions;
+
+    /// The machines without any adapter, even a software one, can not render.
+    fn no_adapter() -> bool {
+        let missing = pollster::block_on(request_adapter()).is_none();
+        if missing {
+            eprintln!("no adapter found, the offscreen rendering is not checked");
+        }
+        missing
+    }
+
+    #[test]
+    fn render_cube() {
+        if no_adapter() {
+            return;
+        }
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::Mesh(RgbaColor::RED)).into();
+        let image = render_offscreen(&[cube], &GpuOptions::default(), 64, 48).unwrap();
+        assert_eq!(image.dimensions(), (64, 48));
+        assert_eq!(image.get_pixel(0, 0).0, RgbaColor::WHITE.0);
+        assert!(image.pixels().any(|p| p.0[0] > p.0[1] && p.0[0] > p.0[2]));
+    }
+
+    #[test]
+    fn render_cut_cube() {
+        if no_adapter() {
+            return;
+        }
+        let magenta = RgbaColor([255, 0, 255, 255]);
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::Mesh(magenta)).into();
+        let is_magenta = |p: &image::Rgba<u8>| p.0[0].min(p.0[2]) > p.0[1].saturating_add(20);
+        let mut options = GpuOptions::default();
+        // the plane on the cube hides a half of it
+        options.with_section(SectionOptions::new(glam::Vec3::X, 0.0));
+        let image = render_offscreen(&[cube.clone()], &options, 64, 48).unwrap();
+        assert!(image.pixels().any(is_magenta));
+
+        // the plane behind the cube hides all of it, the axes are not cut
+        options.with_section(SectionOptions::new(glam::Vec3::X, -10.0));
+        let image = render_offscreen(&[cube], &options, 64, 48).unwrap();
+        assert!(!image.pixels().any(is_magenta));
+        assert!(image.pixels().any(|p| p.0[0] > p.0[1] && p.0[0] > p.0[2]));
+    }
+}
@@ -5,8 +5,11 @@ use crate::gpu::gui::GuiRenderer;
 use crate::gpu::light::{IsAffectedByLightUniform, Light};
 use crate::gpu::material::Material;
 use crate::gpu::options::{GpuOptions, LightOptions};
+use crate::gpu::processor::section::create_contours;
 use crate::gpu::processor::{GpuHandler, GpuMesh, GpuProcessor, Topology};
-use crate::gpu::vertex::GpuVertex;
+use crate::gpu::section::Section;
+use crate::gpu::scene::MeshId;
+use crate::gpu::vertex::{GpuGeometry, GpuInstance, GpuVertex};
 use crate::mesh::attributes::MeshType;
 use crate::mesh::material::{Color, RgbaColor};
 use crate::mesh::parts::bbox::BoundingBox;
@@ -15,7 +18,7 @@ use crate::mesh::shape::sphere::Sphere;
 use crate::mesh::{HasMesh, Mesh, MeshError, MeshResult};
 use egui_wgpu::wgpu;
 use egui_wgpu::wgpu::util::DeviceExt;
-use egui_wgpu::wgpu::{BindGroup, BindGroupLayout, Device};
+use egui_wgpu::wgpu::{BindGroup, BindGroupLayout, Buffer, Device, RenderPipeline};
 use ico::IconDir;
 use std::collections::HashMap;
 use std::fs::File;
@@ -29,7 +32,7 @@ use winit::window::{Icon, Window};
 impl GpuProcessor {
     pub fn try_init(
         event_loop: &ActiveEventLoop,
-        meshes: &Vec<Mesh>,
+        meshes: &[Mesh],
         camera_pos: CameraPosition,
         options: GpuOptions,
     ) -> GpuResult<GpuHandler> {
@@ -80,200 +83,167 @@ impl GpuProcessor {
             desired_maximum_frame_latency: 2,
         };
 
-        let shader_vertex = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader Vertex"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../wgsl/shader_vertex.wgsl").into()),
-        });
-
-        let mut gpu_meshes = Vec::new();
-
-        let aabb = meshes
-            .iter()
-            .map(|m| m.aabb())
-            .reduce(|a, b| (a, b).into())
-            .ok_or(MeshError::Custom("No bounding box found".to_string()))?;
+        let section = Section::init(&device, options.section_opts());
+        let (gpu_meshes, aabb) = create_gpu_meshes(&device, meshes, &options, &section)?;
+        let contours = create_contours(&device, &gpu_meshes, options.section_opts(), &section);
+        let camera = Camera::init(size.width, size.height, &device, camera_pos, aabb, &options);
+        let light = Light::init(&device, &options);
+        let pipelines = create_pipelines(&device, config.format, &camera, &light);
 
-        for mesh in meshes
-            .into_iter()
-            .chain(auxiliary_items(&aabb, &options.light_opts())?.iter())
-        {
-            match mesh.attributes().mesh_type() {
-                MeshType::Polygons | MeshType::Lines => {
-                    let vertices: Vec<GpuVertex> = mesh.try_into()?;
-                    let vertex_buffer =
-                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: bytemuck::cast_slice(&vertices),
-                            usage: wgpu::BufferUsages::VERTEX,
-                        });
-                    gpu_meshes.push(GpuMesh::new(
-                        vertex_buffer,
-                        vertices,
-                        mesh.clone(),
-                        Material::init(&device, &mesh.attributes().material()),
-                        is_affected_by_light_bind_group(
-                            &device,
-                            mesh.attributes().affected_by_light(),
-                        ),
-                    ));
-                }
-                MeshType::Cloud(size) => {
-                    let color = mesh.attributes().color();
-                    let vertices_sphere: Vec<Mesh> = mesh
-                        .vertices()
-                        .into_iter()
-                        .map(|v| {
-                            Sphere::create_uv(v.clone(), size as f32 * 0.01, 8, 8, color.clone())
-                        })
-                        .map(|m| m.into())
-                        .collect();
+        let gui = GuiRenderer::new(&device, config.format, None, 1, window.clone())?;
 
-                    let vertices: Vec<GpuVertex> = vertices_sphere
-                        .iter()
-                        .map(|m| m.try_into())
-                        .collect::<MeshResult<Vec<Vec<GpuVertex>>>>()?
-                        .into_iter()
-                        .flatten()
-                        .collect();
+        Ok(GpuHandler::new(
+            window, instance, surface, device, queue, config, size, pipelines, gpu_meshes, camera,
+            gui, light, section, contours, options,
+        ))
+    }
+}
 
-                    let vertex_buffer =
-                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: bytemuck::cast_slice(&vertices),
-                            usage: wgpu::BufferUsages::VERTEX,
-                        });
+/// Uploads the meshes with the auxiliary items (axes, light source) to the device.
+/// Returns them with the bounding box of the given meshes.
+pub(crate) fn create_gpu_meshes(
+    device: &Device,
+    meshes: &[Mesh],
+    options: &GpuOptions,
+    section: &Section,
+) -> GpuResult<(Vec<GpuMesh>, BoundingBox)> {
+    let aabb = meshes
+        .iter()
+        .map(|m| m.aabb())
+        .reduce(|a, b| (a, b).into())
+        .ok_or(MeshError::Custom("No bounding box found".to_string()))?;
 
-                    gpu_meshes.push(GpuMesh::new(
-                        vertex_buffer,
-                        vertices,
-                        mesh.clone(),
-                        Material::init(&device, &mesh.attributes().material()),
-                        is_affected_by_light_bind_group(
-                            &device,
-                            mesh.attributes().affected_by_light(),
-                        ),
-                    ));
-                }
-            }
-        }
+    let mut gpu_meshes = Vec::new();
+    for (idx, mesh) in meshes.iter().enumerate() {
+        gpu_meshes.push(upload_mesh(device, mesh, Some(MeshId::initial(idx)), section)?);
+    }
+    for mesh in auxiliary_items(&aabb, options.light_opts())?.iter() {
+        gpu_meshes.push(upload_mesh(device, mesh, None, section)?);
+    }
+    Ok((gpu_meshes, aabb))
+}
 
-        let camera = Camera::init(&config, &device, camera_pos, aabb, &options);
-        let light = Light::init(&device, &options);
+/// Uploads the buffers of the mesh to the device.
+/// The meshes of the scene are cut by the section plane, the auxiliary items are not.
+pub(crate) fn upload_mesh(
+    device: &Device,
+    mesh: &Mesh,
+    id: Option<MeshId>,
+    section: &Section,
+) -> GpuResult<GpuMesh> {
+    let (geometry, instances) = match mesh.attributes().mesh_type() {
+        MeshType::Polygons | MeshType::Lines => (mesh.try_into()?, None),
+        MeshType::Cloud(_) => (sprite()?, Some(mesh.try_into()?)),
+    };
+    Ok(GpuMesh::new(
+        device,
+        id,
+        geometry,
+        instances,
+        mesh.clone(),
+        Material::init(device, &mesh.attributes().material()),
+        mesh_bind_group(
+            device,
+            mesh.attributes().affected_by_light(),
+            section.buffer(id.is_some()),
+        ),
+    ))
+}
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &camera.camera_bind_layout(),
-                    &light.light_bind_layout(),
-                    &Material::create_bind_group_layout(&device),
-                    &affected_by_light_layout(&device),
-                ],
-                push_constant_ranges: &[],
-            });
+/// Creates the render pipelines for every topology drawing into the target of the given format.
+pub(crate) fn create_pipelines(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    camera: &Camera,
+    light: &Light,
+) -> HashMap<Topology, RenderPipeline> {
+    let shader_vertex = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader Vertex"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../wgsl/shader_vertex.wgsl").into()),
+    });
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts: &[
+            camera.camera_bind_layout(),
+            light.light_bind_layout(),
+            &Material::create_bind_group_layout(device),
+            &mesh_bind_layout(device),
+        ],
+        push_constant_ranges: &[],
+    });
 
-        let mut pipelines = HashMap::new();
-        pipelines.insert(
-            Topology::TriangleList,
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline with triangles"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader_vertex,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &[GpuVertex::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_vertex,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent::REPLACE,
-                            alpha: wgpu::BlendComponent::REPLACE,
-                        }),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    ..Default::default()
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth24Plus,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::LessEqual,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
+    let pipeline = |label: &str, topology: wgpu::PrimitiveTopology, instanced: bool| {
+        let (entry_point, buffers) = if instanced {
+            ("vs_instance", vec![GpuVertex::desc(), GpuInstance::desc()])
+        } else {
+            ("vs_main", vec![GpuVertex::desc()])
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_vertex,
+                entry_point,
+                compilation_options: Default::default(),
+                buffers: &buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_vertex,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent::REPLACE,
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
-        );
-
-        pipelines.insert(
-            Topology::LineList,
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline with lines"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader_vertex,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &[GpuVertex::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_vertex,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState {
-                            color: wgpu::BlendComponent::REPLACE,
-                            alpha: wgpu::BlendComponent::REPLACE,
-                        }),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::LineList,
-                    strip_index_format: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    ..Default::default()
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth24Plus,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::LessEqual,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
+            primitive: wgpu::PrimitiveState {
+                topology,
+                strip_index_format: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24Plus,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
             }),
-        );
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    };
 
-        let gui = GuiRenderer::new(&device, config.format, None, 1, window.clone())?;
+    let mut pipelines = HashMap::new();
+    pipelines.insert(
+        Topology::TriangleList,
+        pipeline("Render Pipeline with triangles", wgpu::PrimitiveTopology::TriangleList, false),
+    );
+    pipelines.insert(
+        Topology::LineList,
+        pipeline("Render Pipeline with lines", wgpu::PrimitiveTopology::LineList, false),
+    );
+    pipelines.insert(
+        Topology::Instances,
+        pipeline("Render Pipeline with instances", wgpu::PrimitiveTopology::TriangleList, true),
+    );
+    pipelines
+}
 
-        Ok(GpuHandler::new(
-            window, instance, surface, device, queue, config, size, pipelines, gpu_meshes, camera,
-            gui, light, options,
-        ))
-    }
+/// The sphere of the unit radius drawn for every point of a cloud.
+/// It is white to take the colors of the points.
+fn sprite() -> MeshResult<GpuGeometry> {
+    let sphere: Mesh = Sphere::create_uv(Vertex::default(), 1.0, 8, 8, Color::Mesh(RgbaColor::WHITE)).into();
+    (&sphere).try_into()
 }
 
 fn load_icon(path: &Path) -> GpuResult<Icon> {
@@ -306,7 +276,8 @@ fn auxiliary_items(aabb: &BoundingBox, light_options: &LightOptions) -> MeshResu
     Ok(elems)
 }
 
-fn is_affected_by_light_bind_group(device: &Device, flag: bool) -> BindGroup {
+/// The bind group of the mesh with the light flag and the section plane.
+fn mesh_bind_group(device: &Device, flag: bool, section_buffer: &Buffer) -> BindGroup {
     let val = if flag { 1 } else { 0 };
     let is_affected_by_light_buffer =
         device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -317,30 +288,37 @@ fn is_affected_by_light_bind_group(device: &Device, flag: bool) -> BindGroup {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-    let is_affected_by_light_bind_group_layout = affected_by_light_layout(device);
+    let mesh_bind_group_layout = mesh_bind_layout(device);
 
     device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &is_affected_by_light_bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: is_affected_by_light_buffer.as_entire_binding(),
-        }],
+        layout: &mesh_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: is_affected_by_light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: section_buffer.as_entire_binding(),
+            },
+        ],
         label: None,
     })
 }
 
-fn affected_by_light_layout(device: &Device) -> BindGroupLayout {
+fn mesh_bind_layout(device: &Device) -> BindGroupLayout {
+    let uniform = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &[uniform(0), uniform(1)],
         label: None,
     })
 }
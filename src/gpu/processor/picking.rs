@@ -0,0 +1,36 @@
+use crate::gpu::picking::cursor_ray;
+use crate::gpu::processor::GpuHandler;
+use log::info;
+use winit::dpi::PhysicalPosition;
+
+impl GpuHandler {
+    /// Picks the element of the meshes under the cursor,
+    /// shows it in the info panel and passes it to the callback of the options.
+    /// The clicks on the windows of the gui are ignored.
+    pub(crate) fn pick(&mut self, cursor: PhysicalPosition<f64>) {
+        if self.gui.context().is_pointer_over_area() {
+            return;
+        }
+        let ray = cursor_ray(
+            self.camera.view_proj(),
+            cursor.x as f32,
+            cursor.y as f32,
+            self.config.width,
+            self.config.height,
+        );
+        let meshes = self
+            .meshes
+            .iter()
+            .filter_map(|m| m.id.map(|id| (id, &m.mesh)));
+        // the part in front of the section plane is not drawn
+        let section = self.gpu_options.section_opts();
+        let plane = section.enabled().then(|| section.plane());
+        self.picked = self.picker.pick(meshes, &ray, plane.as_ref());
+        if let Some(pick) = &self.picked {
+            info!("Picked {:?}", pick);
+            if let Some(callback) = self.gpu_options.pick_callback() {
+                callback.call(pick);
+            }
+        }
+    }
+}
@@ -1,13 +1,20 @@
 use crate::gpu::error::{GpuError, GpuResult};
 use crate::gpu::gui::camera_info::CameraInfo;
 use crate::gpu::gui::controls::Controls;
-use crate::gpu::processor::{GpuHandler, Topology};
+use crate::gpu::gui::pick_info::PickInfo;
+use crate::gpu::gui::scalar_bar::ScalarBarLegend;
+use crate::gpu::gui::section::SectionControls;
+use crate::gpu::camera::Camera;
+use crate::gpu::light::Light;
+use crate::gpu::processor::{GpuHandler, GpuMesh, Topology};
 use crate::gpu::vertex::face_to_vertex3;
 use crate::mesh::attributes::MeshType;
+use crate::mesh::material::RgbaColor;
 use egui::style::Widgets;
 use egui_wgpu::wgpu::util::RenderEncoder;
 use egui_wgpu::{wgpu, ScreenDescriptor};
 use log::info;
+use std::collections::HashMap;
 use std::iter;
 use std::sync::Arc;
 use winit::event::{ElementState, MouseButton, WindowEvent};
@@ -21,75 +28,22 @@ impl GpuHandler {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: self.config.width,
-                height: self.config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None,
-            view_formats: &[],
-        });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-
+        let depth_view = depth_view(&self.device, self.config.width, self.config.height);
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-
-        {
-            let b_color = self
-                .gpu_options
-                .light_opts()
-                .background_color()
-                .clone()
-                .into();
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(b_color),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Discard,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-            let pipelines = &self.pipelines;
-            render_pass.set_bind_group(0, &self.camera.camera_bind_group(), &[]);
-            render_pass.set_bind_group(1, &self.light.light_bind_group(), &[]);
-            for gpu_mesh in self.meshes.iter() {
-                let mesh_type = gpu_mesh.mesh.attributes().mesh_type();
-                let pipeline = match mesh_type {
-                    MeshType::Polygons | MeshType::Cloud(_) => {
-                        pipelines.get(&Topology::TriangleList)
-                    }
-                    MeshType::Lines => pipelines.get(&Topology::LineList),
-                }
-                .ok_or(GpuError::General("Pipeline not found".to_string()))?;
-                render_pass.set_bind_group(2, &gpu_mesh.material.material_bind_group(), &[]);
-                render_pass.set_bind_group(3, &gpu_mesh.affected_by_light, &[]);
-                render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
-                render_pass.set_pipeline(pipeline);
-                render_pass.draw(0..gpu_mesh.vertices.len() as u32, 0..1);
-            }
-        }
+        draw_meshes(
+            &mut encoder,
+            &view,
+            &depth_view,
+            self.gpu_options.light_opts().background_color(),
+            &self.pipelines,
+            &self.camera,
+            &self.light,
+            self.meshes.iter().chain(&self.contours),
+        )?;
 
         let screen_descriptor = ScreenDescriptor {
             size_in_pixels: [self.config.width, self.config.height],
@@ -100,6 +54,17 @@ impl GpuHandler {
             self.gui.begin_frame(self.window.clone());
             CameraInfo::show(&self.gui.context(), &self.camera);
             Controls::show(&self.gui.context(), &mut self.camera);
+            ScalarBarLegend::show_all(self.gui.context(), self.gpu_options.scalar_bars());
+            if let Some(section) =
+                SectionControls::show(self.gui.context(), self.gpu_options.section_opts())
+            {
+                self.set_section(section);
+            }
+            if let Some(pick) = &self.picked {
+                if !PickInfo::show(self.gui.context(), pick) {
+                    self.picked = None;
+                }
+            }
 
             self.gui.end_frame_and_draw(
                 &self.device,
@@ -146,10 +111,14 @@ impl GpuHandler {
                 button: MouseButton::Left,
                 state,
                 ..
-            } => self
-                .camera
-                .mouse_mut()
-                .set_left_button(*state == ElementState::Pressed),
+            } => {
+                let mouse = self.camera.mouse_mut();
+                let handled = mouse.set_left_button(*state == ElementState::Pressed);
+                if let Some(cursor) = mouse.take_click() {
+                    self.pick(cursor);
+                }
+                handled
+            }
             WindowEvent::MouseInput {
                 button: MouseButton::Right,
                 state,
@@ -159,8 +128,9 @@ impl GpuHandler {
                 .mouse_mut()
                 .set_right_button(*state == ElementState::Pressed),
 
-            WindowEvent::CursorMoved { position, .. } if self.camera.is_mouse_pressed() => {
-                self.camera.process_mouse(position)
+            WindowEvent::CursorMoved { position, .. } => {
+                self.camera.mouse_mut().set_cursor(*position);
+                self.camera.is_mouse_pressed() && self.camera.process_mouse(position)
             }
             _ => false,
         }
@@ -177,8 +147,94 @@ impl GpuHandler {
             0,
             bytemuck::cast_slice(&[*self.light.light_uniform()]),
         );
+        self.queue.write_buffer(
+            self.section.section_buffer(),
+            0,
+            bytemuck::cast_slice(&[*self.section.section_uniform()]),
+        );
     }
     pub fn window(&self) -> &Arc<Window> {
         &self.window
     }
 }
+
+/// Creates the depth buffer for the target of the given size.
+pub(crate) fn depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth24Plus,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        label: None,
+        view_formats: &[],
+    });
+    depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Records the render pass clearing the target with the background and drawing the meshes.
+/// It is shared by the window and the offscreen rendering.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_meshes<'a, I>(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    depth_view: &wgpu::TextureView,
+    background: &RgbaColor,
+    pipelines: &HashMap<Topology, wgpu::RenderPipeline>,
+    camera: &Camera,
+    light: &Light,
+    meshes: I,
+) -> GpuResult<()>
+where
+    I: IntoIterator<Item = &'a GpuMesh>,
+{
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(background.clone().into()),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Discard,
+            }),
+            stencil_ops: None,
+        }),
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+    render_pass.set_bind_group(0, camera.camera_bind_group(), &[]);
+    render_pass.set_bind_group(1, light.light_bind_group(), &[]);
+    for gpu_mesh in meshes {
+        if gpu_mesh.index_count == 0 || gpu_mesh.instance_count == 0 {
+            continue;
+        }
+        let pipeline = match gpu_mesh.mesh.attributes().mesh_type() {
+            MeshType::Polygons => pipelines.get(&Topology::TriangleList),
+            MeshType::Cloud(_) => pipelines.get(&Topology::Instances),
+            MeshType::Lines => pipelines.get(&Topology::LineList),
+        }
+        .ok_or(GpuError::General("Pipeline not found".to_string()))?;
+        render_pass.set_bind_group(2, gpu_mesh.material.material_bind_group(), &[]);
+        render_pass.set_bind_group(3, &gpu_mesh.affected_by_light, &[]);
+        render_pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        if let Some(instances) = &gpu_mesh.instance_buffer {
+            render_pass.set_vertex_buffer(1, instances.slice(..));
+        }
+        render_pass.set_index_buffer(gpu_mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.draw_indexed(0..gpu_mesh.index_count, 0, 0..gpu_mesh.instance_count);
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::gpu::processor::init::upload_mesh;
+use crate::gpu::processor::GpuHandler;
+use crate::gpu::scene::SceneCommand;
+use log::{error, warn};
+
+impl GpuHandler {
+    /// Applies the command sent by a scene handle.
+    /// The changes of the meshes wait for the next frame to be uploaded.
+    /// Returns false if the viewer should be closed.
+    pub(crate) fn apply(&mut self, command: SceneCommand) -> bool {
+        match command {
+            SceneCommand::Add(id, mesh) | SceneCommand::Replace(id, mesh) => {
+                self.pending.upsert(id, mesh)
+            }
+            SceneCommand::Remove(id) => self.pending.remove(id),
+            SceneCommand::SetColor(id, color) => {
+                let uploaded = self
+                    .meshes
+                    .iter()
+                    .find(|m| m.id == Some(id))
+                    .map(|m| &m.mesh);
+                if !self.pending.set_color(id, color, uploaded) {
+                    warn!("The mesh {:?} to change the color is not found", id);
+                }
+            }
+            SceneCommand::SetCameraPosition(position) => {
+                self.camera.camera_coordinator_mut().set_eye(position.into())
+            }
+            SceneCommand::SetCameraTarget(target) => {
+                self.camera.camera_coordinator_mut().set_target(target.into())
+            }
+            SceneCommand::SetLight(light) => {
+                self.gpu_options.with_light(light);
+                self.light.set_options(&self.gpu_options);
+            }
+            SceneCommand::SetScalarBars(scalar_bars) => {
+                self.gpu_options.with_scalar_bars(scalar_bars);
+            }
+            SceneCommand::SetSection(section) => self.set_section(section),
+            SceneCommand::Close => return false,
+        }
+        self.window.request_redraw();
+        true
+    }
+
+    /// Uploads the meshes changed since the last frame.
+    /// The meshes that can not be drawn are skipped with an error in the log.
+    pub(crate) fn sync_meshes(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        for (id, change) in self.pending.take() {
+            self.picker.forget(id);
+            if self.picked.as_ref().is_some_and(|p| p.mesh() == id) {
+                self.picked = None;
+            }
+            let uploaded = match change {
+                Some(mesh) => match upload_mesh(&self.device, &mesh, Some(id), &self.section) {
+                    Ok(gpu_mesh) => Some(gpu_mesh),
+                    Err(e) => {
+                        error!("The mesh {:?} can not be drawn: {}", id, e);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let position = self.meshes.iter().position(|m| m.id == Some(id));
+            match (position, uploaded) {
+                (Some(idx), Some(gpu_mesh)) => self.meshes[idx] = gpu_mesh,
+                (Some(idx), None) => {
+                    self.meshes.remove(idx);
+                }
+                (None, Some(gpu_mesh)) => self.meshes.push(gpu_mesh),
+                (None, None) => warn!("The mesh {:?} to remove is not found", id),
+            }
+        }
+        self.update_contours();
+    }
+}
@@ -0,0 +1,59 @@
+use crate::gpu::options::SectionOptions;
+use crate::gpu::processor::init::upload_mesh;
+use crate::gpu::processor::{GpuHandler, GpuMesh};
+use crate::gpu::section::Section;
+use crate::mesh::material::Color;
+use egui_wgpu::wgpu::Device;
+use log::error;
+
+impl GpuHandler {
+    /// Changes the section plane, the contours are found again if they are shown.
+    pub(crate) fn set_section(&mut self, section: SectionOptions) {
+        self.section.set_options(&section);
+        self.gpu_options.with_section(section);
+        self.update_contours();
+    }
+
+    /// Finds the contours of the meshes of the scene by the current section plane.
+    pub(crate) fn update_contours(&mut self) {
+        self.contours = create_contours(
+            &self.device,
+            &self.meshes,
+            self.gpu_options.section_opts(),
+            &self.section,
+        );
+    }
+}
+
+/// Cuts the meshes of the scene by the section plane and uploads the sections as the lines.
+/// The contours are empty if the section is disabled or the contours are not shown.
+/// The meshes that can not be cut are skipped with an error in the log.
+pub(crate) fn create_contours(
+    device: &Device,
+    meshes: &[GpuMesh],
+    options: &SectionOptions,
+    section: &Section,
+) -> Vec<GpuMesh> {
+    if !options.enabled() || !options.show_contour() {
+        return vec![];
+    }
+    let plane = options.plane();
+    let mut contours = Vec::new();
+    for gpu_mesh in meshes.iter().filter(|m| m.id.is_some() && m.mesh.is_polygons()) {
+        let mut contour = match gpu_mesh.mesh.contour_by_plane(&plane) {
+            Ok(contour) if !contour.edges().is_empty() => contour,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("The mesh {:?} can not be cut: {}", gpu_mesh.id, e);
+                continue;
+            }
+        };
+        contour.attributes_mut().set_color(Color::Mesh(options.contour_color().clone()));
+        contour.attributes_mut().with_affected_by_light(false);
+        match upload_mesh(device, &contour, None, section) {
+            Ok(gpu_contour) => contours.push(gpu_contour),
+            Err(e) => error!("The contour of {:?} can not be drawn: {}", gpu_mesh.id, e),
+        }
+    }
+    contours
+}
@@ -0,0 +1,219 @@
+use crate::gpu::error::{GpuError, GpuResult};
+use crate::gpu::options::{LightOptions, SectionOptions};
+use crate::mesh::material::colormap::ScalarBar;
+use crate::mesh::material::Color;
+use crate::mesh::Mesh;
+use glam::Vec3;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use winit::event_loop::EventLoopProxy;
+
+/// The identifier of a mesh in a running scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MeshId(u64);
+
+impl MeshId {
+    /// The identifier of the mesh passed to the viewer at the given position.
+    pub(crate) fn initial(idx: usize) -> Self {
+        MeshId(idx as u64)
+    }
+}
+
+/// The changes sent to a running viewer.
+pub(crate) enum SceneCommand {
+    Add(MeshId, Mesh),
+    Remove(MeshId),
+    Replace(MeshId, Mesh),
+    SetColor(MeshId, Color),
+    SetCameraPosition(Vec3),
+    SetCameraTarget(Vec3),
+    SetLight(LightOptions),
+    SetScalarBars(Vec<ScalarBar>),
+    SetSection(SectionOptions),
+    Close,
+}
+
+/// The handle to change the scene of a running viewer from another thread.
+///
+/// The handle can be cloned and sent to other threads.
+/// The changes of the meshes are collected and uploaded to the GPU before the next frame,
+/// so the frequent updates of the same mesh cost only one upload per frame.
+/// Every method fails once the viewer is closed.
+///
+/// # Example
+/// ```no_run
+/// use tessellate::gpu::error::GpuError;
+/// use tessellate::gpu::options::GpuOptions;
+/// use tessellate::gpu::visualize_live;
+/// use tessellate::mesh::material::{Color, RgbaColor};
+/// let meshes = vec![/* ... */];
+/// visualize_live(meshes, GpuOptions::default(), |scene, ids| {
+///     for step in 0..100 {
+///         let color = if step % 2 == 0 { RgbaColor::RED } else { RgbaColor::BLUE };
+///         if scene.set_color(ids[0], Color::Mesh(color)).is_err() {
+///             break; // the window is closed
+///         }
+///         std::thread::sleep(std::time::Duration::from_millis(100));
+///     }
+/// })?;
+/// # Ok::<(), GpuError>(())
+/// ```
+#[derive(Clone)]
+pub struct SceneHandle {
+    proxy: EventLoopProxy<SceneCommand>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl SceneHandle {
+    /// Creates the handle for the scene with the given number of initial meshes
+    /// that get the identifiers from 0 in their order.
+    pub(crate) fn new(proxy: EventLoopProxy<SceneCommand>, initial: usize) -> (Self, Vec<MeshId>) {
+        let ids = (0..initial).map(MeshId::initial).collect();
+        let handle = SceneHandle {
+            proxy,
+            next_id: Arc::new(AtomicU64::new(initial as u64)),
+        };
+        (handle, ids)
+    }
+
+    fn send(&self, command: SceneCommand) -> GpuResult<()> {
+        self.proxy
+            .send_event(command)
+            .map_err(|_| GpuError::new("The viewer is closed"))
+    }
+
+    /// Adds the mesh to the scene and returns its identifier.
+    pub fn add(&self, mesh: Mesh) -> GpuResult<MeshId> {
+        let id = MeshId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.send(SceneCommand::Add(id, mesh))?;
+        Ok(id)
+    }
+
+    pub fn remove(&self, id: MeshId) -> GpuResult<()> {
+        self.send(SceneCommand::Remove(id))
+    }
+
+    /// Replaces the mesh keeping its identifier, e.g. with the next step of a simulation.
+    pub fn replace(&self, id: MeshId, mesh: Mesh) -> GpuResult<()> {
+        self.send(SceneCommand::Replace(id, mesh))
+    }
+
+    /// Changes the color of the mesh, e.g. with the colors of a colormap.
+    pub fn set_color(&self, id: MeshId, color: Color) -> GpuResult<()> {
+        self.send(SceneCommand::SetColor(id, color))
+    }
+
+    /// Moves the camera keeping the point it looks at.
+    pub fn set_camera_position(&self, position: Vec3) -> GpuResult<()> {
+        self.send(SceneCommand::SetCameraPosition(position))
+    }
+
+    /// Turns the camera to look at the point.
+    pub fn set_camera_target(&self, target: Vec3) -> GpuResult<()> {
+        self.send(SceneCommand::SetCameraTarget(target))
+    }
+
+    /// Changes the light and the background of the scene.
+    pub fn set_light(&self, light: LightOptions) -> GpuResult<()> {
+        self.send(SceneCommand::SetLight(light))
+    }
+
+    /// Replaces the legends of the colormaps shown in the scene.
+    pub fn set_scalar_bars(&self, scalar_bars: Vec<ScalarBar>) -> GpuResult<()> {
+        self.send(SceneCommand::SetScalarBars(scalar_bars))
+    }
+
+    /// Changes the plane cutting the meshes and its contours.
+    pub fn set_section(&self, section: SectionOptions) -> GpuResult<()> {
+        self.send(SceneCommand::SetSection(section))
+    }
+
+    /// Closes the viewer.
+    pub fn close(&self) -> GpuResult<()> {
+        self.send(SceneCommand::Close)
+    }
+}
+
+/// The changes of the meshes waiting for the upload:
+/// the new state of a mesh or None when the mesh is removed.
+#[derive(Default)]
+pub(crate) struct PendingMeshes {
+    changes: BTreeMap<MeshId, Option<Mesh>>,
+}
+
+impl PendingMeshes {
+    pub(crate) fn upsert(&mut self, id: MeshId, mesh: Mesh) {
+        self.changes.insert(id, Some(mesh));
+    }
+
+    pub(crate) fn remove(&mut self, id: MeshId) {
+        self.changes.insert(id, None);
+    }
+
+    /// Changes the color of the pending mesh or of the uploaded one.
+    /// Returns false if there is no such mesh.
+    pub(crate) fn set_color(&mut self, id: MeshId, color: Color, uploaded: Option<&Mesh>) -> bool {
+        if let Entry::Vacant(entry) = self.changes.entry(id) {
+            match uploaded {
+                Some(mesh) => entry.insert(Some(mesh.clone())),
+                None => return false,
+            };
+        }
+        match self.changes.get_mut(&id) {
+            Some(Some(mesh)) => {
+                mesh.attributes_mut().set_color(color);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub(crate) fn take(&mut self) -> BTreeMap<MeshId, Option<Mesh>> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::RgbaColor;
+    use crate::mesh::parts::face::Face;
+
+    fn mesh() -> Mesh {
+        let faces: Vec<Face> = vec![(0, 1, 2).into()];
+        Mesh::from_vertices(vec![[0, 0, 0], [1, 0, 0], [0, 1, 0]], faces, Default::default())
+    }
+
+    #[test]
+    fn pending_changes() {
+        let mut pending = PendingMeshes::default();
+        let uploaded = mesh();
+        assert!(pending.set_color(MeshId(0), Color::Mesh(RgbaColor::RED), Some(&uploaded)));
+        assert!(!pending.set_color(MeshId(1), Color::Mesh(RgbaColor::RED), None));
+
+        pending.upsert(MeshId(2), mesh());
+        assert!(pending.set_color(MeshId(2), Color::Mesh(RgbaColor::BLUE), None));
+        pending.upsert(MeshId(3), mesh());
+        pending.remove(MeshId(3));
+        assert!(!pending.set_color(MeshId(3), Color::Mesh(RgbaColor::BLUE), Some(&uploaded)));
+
+        let changes = pending.take();
+        assert!(pending.is_empty());
+        assert_eq!(changes.len(), 3);
+        match changes[&MeshId(0)].as_ref().map(|m| m.attributes().color()) {
+            Some(Color::Mesh(c)) => assert_eq!(c, &RgbaColor::RED),
+            _ => panic!("the color is not changed"),
+        }
+        match changes[&MeshId(2)].as_ref().map(|m| m.attributes().color()) {
+            Some(Color::Mesh(c)) => assert_eq!(c, &RgbaColor::BLUE),
+            _ => panic!("the color is not changed"),
+        }
+        assert!(changes[&MeshId(3)].is_none());
+    }
+}
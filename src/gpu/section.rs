@@ -0,0 +1,77 @@
+use crate::gpu::options::SectionOptions;
+use egui_wgpu::wgpu;
+use egui_wgpu::wgpu::util::DeviceExt;
+use egui_wgpu::wgpu::Device;
+
+/// The section plane on the device.
+/// The meshes of the scene are cut by the plane of the options,
+/// the auxiliary items and the contours use the buffer that never cuts.
+pub struct Section {
+    section_uniform: SectionUniform,
+
+    section_buffer: wgpu::Buffer,
+    uncut_buffer: wgpu::Buffer,
+}
+
+impl Section {
+    pub fn init(device: &Device, section_options: &SectionOptions) -> Self {
+        let section_uniform: SectionUniform = section_options.into();
+        let section_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Section Buffer"),
+            contents: bytemuck::cast_slice(&[section_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let uncut_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uncut Section Buffer"),
+            contents: bytemuck::cast_slice(&[SectionUniform::from(&SectionOptions::default())]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        Self {
+            section_uniform,
+            section_buffer,
+            uncut_buffer,
+        }
+    }
+
+    pub fn section_uniform(&self) -> &SectionUniform {
+        &self.section_uniform
+    }
+
+    pub fn section_buffer(&self) -> &wgpu::Buffer {
+        &self.section_buffer
+    }
+
+    /// The buffer for the mesh, cut by the plane or not.
+    pub fn buffer(&self, is_cut: bool) -> &wgpu::Buffer {
+        if is_cut {
+            &self.section_buffer
+        } else {
+            &self.uncut_buffer
+        }
+    }
+
+    /// Takes the plane from the options, the buffer is written on the next update.
+    pub fn set_options(&mut self, section_options: &SectionOptions) {
+        self.section_uniform = section_options.into();
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SectionUniform {
+    /// The unit normal with the offset in w.
+    plane: [f32; 4],
+    enabled: u32,
+    _padding: [u32; 3],
+}
+
+impl From<&SectionOptions> for SectionUniform {
+    fn from(options: &SectionOptions) -> Self {
+        let normal = *options.plane().normal();
+        Self {
+            plane: [normal.x, normal.y, normal.z, options.offset()],
+            enabled: options.enabled() as u32,
+            _padding: [0; 3],
+        }
+    }
+}
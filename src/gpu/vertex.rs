@@ -1,4 +1,5 @@
 use crate::mesh::attributes::MeshType;
+use crate::mesh::fields::NORMALS;
 use crate::mesh::material::{Color, RgbaColor};
 use crate::mesh::parts::edge::MeshEdge;
 use crate::mesh::parts::face::Face;
@@ -6,29 +7,28 @@ use crate::mesh::{parts, Mesh, MeshError};
 use bytemuck::{Pod, Zeroable};
 use egui_wgpu::wgpu;
 use glam::Vec3;
+use std::collections::HashMap;
 use std::iter::zip;
 use std::mem;
 
+/// The cosine of the angle between the normals of a face and its vertex
+/// above which the vertex is shaded smoothly.
+const CREASE_COS: f32 = 0.8;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub(crate) struct GpuInstance {
+    /// The center of the instance with the scale in the last component.
     position: [f32; 4],
-}
-
-impl From<GpuVertex> for GpuInstance {
-    fn from(v: GpuVertex) -> Self {
-        GpuInstance {
-            position: v.position,
-        }
-    }
+    color: [f32; 4],
 }
 
 impl GpuInstance {
-    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0=>Float32x4];
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![3=>Float32x4, 4=>Float32x4];
     pub(crate) fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<GpuInstance>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
+            step_mode: wgpu::VertexStepMode::Instance,
             attributes: &Self::ATTRIBUTES,
         }
     }
@@ -65,166 +65,210 @@ impl GpuVertex {
     }
 }
 
-impl TryFrom<&Mesh> for Vec<GpuVertex> {
+/// The vertices with the indices of the triangles or the lines drawn from them.
+/// The vertices are shared between the primitives unless the colors are given per face or per line.
+pub(crate) struct GpuGeometry {
+    pub(crate) vertices: Vec<GpuVertex>,
+    pub(crate) indices: Vec<u32>,
+}
+
+impl GpuGeometry {
+    fn shared(
+        mesh: &Mesh,
+        colors: &[RgbaColor],
+        normals: &[Vec3],
+        indices: Vec<usize>,
+    ) -> Result<Self, MeshError> {
+        let vertices = mesh
+            .vertices()
+            .iter()
+            .zip(colors)
+            .zip(normals)
+            .map(|((v, c), n)| GpuVertex::from(v, c, n))
+            .collect();
+        Ok(GpuGeometry {
+            vertices,
+            indices: to_u32(indices)?,
+        })
+    }
+
+    /// Shares the vertices with the smooth normals between the faces
+    /// except the corners at the sharp edges that take the normal of the face.
+    fn creased(mesh: &Mesh, colors: &[RgbaColor]) -> Result<Self, MeshError> {
+        let normals = mesh.try_normals()?;
+        let mut vertices = Vec::with_capacity(mesh.vertices().len());
+        let mut shared: HashMap<usize, u32> = HashMap::new();
+        // the corners of the coplanar faces share the split vertex
+        let mut split: HashMap<(usize, [i32; 3]), u32> = HashMap::new();
+        let mut indices = Vec::with_capacity(mesh.faces().len() * 3);
+        for face in mesh.faces() {
+            let face_normal = normals.get_face_normal(face)?;
+            for idx in face_to_vertex3(face) {
+                let normal = normals.get_normal(idx)?;
+                let color = colors.get(idx).ok_or(MeshError::idx_vertex(idx))?;
+                let v = mesh.get(idx)?;
+                let next = u32::try_from(vertices.len()).map_err(|_| MeshError::idx_vertex(idx))?;
+                // the degenerated faces have no normal to crease by
+                let smooth = *face_normal == Vec3::ZERO || normal.dot(*face_normal) >= CREASE_COS;
+                let gpu_idx = if smooth {
+                    *shared.entry(idx).or_insert_with(|| {
+                        vertices.push(GpuVertex::from(v, color, normal));
+                        next
+                    })
+                } else {
+                    let key = (idx, face_normal.to_array().map(|c| (c * 1e4).round() as i32));
+                    *split.entry(key).or_insert_with(|| {
+                        vertices.push(GpuVertex::from(v, color, face_normal));
+                        next
+                    })
+                };
+                indices.push(gpu_idx);
+            }
+        }
+        Ok(GpuGeometry { vertices, indices })
+    }
+
+    fn expanded(vertices: Vec<GpuVertex>) -> Result<Self, MeshError> {
+        let indices = to_u32((0..vertices.len()).collect())?;
+        Ok(GpuGeometry { vertices, indices })
+    }
+}
+
+impl TryFrom<&Mesh> for GpuGeometry {
     type Error = MeshError;
     fn try_from(mesh: &Mesh) -> Result<Self, Self::Error> {
-        let normals = mesh.try_normals()?;
         match mesh.attributes().mesh_type() {
-            MeshType::Polygons => match mesh.attributes().color() {
-                Color::Face(fs) => {
-                    let faces = mesh.faces();
-                    faces_check(fs, faces)?;
-                    let mut vertices = Vec::new();
-                    for (col, face) in zip(fs.into_iter(), faces.into_iter()) {
-                        let normal = normals.get_face_normal(face)?;
-                        let vs = face_to_vertex3(face)
-                            .into_iter()
-                            .map(|i| mesh.get(i))
-                            .collect::<Result<Vec<_>, _>>()?;
-                        vertices.extend(vs.into_iter().map(|v| GpuVertex::from(v, col, normal)))
+            MeshType::Polygons => {
+                let color = mesh.attributes().color();
+                if let Some(colors) = vertex_colors(mesh, color)? {
+                    if let Some(normals) = given_normals(mesh) {
+                        let indices = mesh.faces().iter().flat_map(face_to_vertex3).collect();
+                        return GpuGeometry::shared(mesh, &colors, &normals, indices);
                     }
-                    Ok(vertices)
+                    return GpuGeometry::creased(mesh, &colors);
                 }
-                Color::Mesh(m) => {
-                    let mut vertices = Vec::new();
-                    for face in mesh.faces().iter() {
-                        let normal = normals.get_face_normal(face)?;
-                        for idx in face_to_vertex3(face) {
-                            let v = mesh.get(idx)?;
-                            vertices.push(GpuVertex::from(v, m, normal));
+                match color {
+                    Color::Face(fs) => {
+                        let faces = mesh.faces();
+                        faces_check(fs, faces)?;
+                        let normals = mesh.try_normals()?;
+                        let mut vertices = Vec::new();
+                        for (col, face) in zip(fs.iter(), faces.iter()) {
+                            let normal = normals.get_face_normal(face)?;
+                            for idx in face_to_vertex3(face) {
+                                vertices.push(GpuVertex::from(mesh.get(idx)?, col, normal));
+                            }
                         }
+                        GpuGeometry::expanded(vertices)
                     }
-                    Ok(vertices)
+                    _ => Err(MeshError::InvalidFaceType(
+                        "Line color not supported for polygon mesh".to_string(),
+                    )),
                 }
-                Color::Func(f) => {
-                    let mut vertices = Vec::new();
-                    for face in mesh.faces().into_iter() {
-                        let normal = normals.get_face_normal(face)?;
-                        for idx in face_to_vertex3(face) {
-                            let v = mesh.get(idx)?;
-                            vertices.push(GpuVertex::from(v, &f(v, idx), normal));
-                        }
-                    }
-                    Ok(vertices)
+            }
+            MeshType::Lines => {
+                let color = mesh.attributes().color();
+                let normals = vertex_normals(mesh)?;
+                if let Some(colors) = vertex_colors(mesh, color)? {
+                    let indices = mesh.edges().iter().flat_map(|e| [e.0, e.1]).collect();
+                    return GpuGeometry::shared(mesh, &colors, &normals, indices);
                 }
-                Color::Vertex(colors) => {
-                    let mut vertices = Vec::new();
-                    for face in mesh.faces().into_iter() {
-                        let normal = normals.get_face_normal(face)?;
-                        for idx in face_to_vertex3(face) {
-                            let v = mesh.get(idx)?;
-                            vertices.push(GpuVertex::from(
-                                v,
-                                &colors.get(idx).ok_or(MeshError::idx_vertex(idx))?.clone(),
-                                normal,
-                            ));
+                match color {
+                    Color::Line(colors) => {
+                        let mut vertices = Vec::new();
+                        for (MeshEdge(a, b), c) in zip(mesh.edges().iter(), colors.iter()) {
+                            for i in [*a, *b] {
+                                let n = normals.get(i).ok_or(MeshError::idx_vertex(i))?;
+                                vertices.push(GpuVertex::from(mesh.get(i)?, c, n));
+                            }
                         }
+                        GpuGeometry::expanded(vertices)
                     }
-                    Ok(vertices)
-                }
-                Color::Line(_) => Err(MeshError::InvalidFaceType(
-                    "Line color not supported for polygon mesh".to_string(),
-                )),
-            },
-            MeshType::Cloud(_) => match mesh.attributes().color() {
-                Color::Func(f) => {
-                    let vertices = mesh.vertices();
-                    let mut gpu_vertices = Vec::new();
-                    for (i, v) in vertices.into_iter().enumerate() {
-                        gpu_vertices.push(GpuVertex::from(v, &f(v, i), normals.get_normal(i)?));
-                    }
-                    Ok(gpu_vertices)
+                    _ => Err(MeshError::InvalidFaceType(
+                        "Face color not supported for lines mesh".to_string(),
+                    )),
                 }
-                Color::Vertex(colors) => {
-                    let vertices = mesh.vertices();
-                    vertices_check(colors, vertices)?;
-                    let mut gpu_vertices = Vec::new();
-                    for ((i, v), c) in vertices.into_iter().enumerate().zip(colors.into_iter()) {
-                        gpu_vertices.push(GpuVertex::from(v, c, normals.get_normal(i)?));
-                    }
-                    Ok(gpu_vertices)
-                }
-                Color::Face(_) => Err(MeshError::InvalidFaceType(
-                    "Face color not supported for cloud mesh".to_string(),
-                )),
-                Color::Mesh(c) => {
-                    let mut gpu_vertices = Vec::new();
-                    for (i, v) in mesh.vertices().into_iter().enumerate() {
-                        gpu_vertices.push(GpuVertex::from(v, c, normals.get_normal(i)?));
-                    }
-                    Ok(gpu_vertices)
-                }
-                Color::Line(colors) => {
-                    let mut vertices = Vec::new();
-                    for (MeshEdge(a, b), c) in zip(mesh.edges().into_iter(), colors.into_iter()) {
-                        let v1 = mesh.get(*a)?;
-                        let v2 = mesh.get(*b)?;
-                        vertices.push(GpuVertex::from(v1, c, normals.get_normal(*a)?));
-                        vertices.push(GpuVertex::from(v2, c, normals.get_normal(*b)?));
-                    }
-                    Ok(vertices)
-                }
-            },
-            MeshType::Lines => match mesh.attributes().color() {
-                Color::Face(_) => Err(MeshError::InvalidFaceType(
-                    "Face color not supported for cloud mesh".to_string(),
-                )),
-                Color::Mesh(m) => {
-                    let mut vertices = Vec::new();
-                    for edge in mesh.edges().iter() {
-                        for &i in &[edge.0, edge.1] {
-                            let v = mesh.get(i)?;
-                            vertices.push(GpuVertex::from(v, m, normals.get_normal(i)?));
-                        }
-                    }
-                    Ok(vertices)
-                },
+            }
+            MeshType::Cloud(_) => Err(MeshError::InvalidFaceType(
+                "The cloud is drawn by the instances of a sprite".to_string(),
+            )),
+        }
+    }
+}
 
-                Color::Func(f) => {
-                    let mut vertices = Vec::new();
-                    for MeshEdge(a, b) in mesh.edges().into_iter() {
-                        let v = mesh.get(*a)?;
-                        vertices.push(GpuVertex::from(v, &f(v, *a), normals.get_normal(*a)?));
-                        let v = mesh.get(*b)?;
-                        vertices.push(GpuVertex::from(v, &f(v, *b), normals.get_normal(*b)?));
-                    }
-                    Ok(vertices)
+impl TryFrom<&Mesh> for Vec<GpuInstance> {
+    type Error = MeshError;
+    fn try_from(mesh: &Mesh) -> Result<Self, Self::Error> {
+        let radius = match mesh.attributes().mesh_type() {
+            MeshType::Cloud(size) => point_radius(size),
+            _ => {
+                return Err(MeshError::InvalidFaceType(
+                    "Only the cloud can be drawn by instances".to_string(),
+                ))
+            }
+        };
+        let colors = vertex_colors(mesh, mesh.attributes().color())?.ok_or(
+            MeshError::InvalidFaceType("Only vertex or mesh colors are supported for cloud mesh".to_string()),
+        )?;
+        Ok(zip(mesh.vertices().iter(), colors.iter())
+            .map(|(v, c)| {
+                let [x, y, z] = v.flatten();
+                GpuInstance {
+                    position: [x, y, z, radius],
+                    color: c.clone().into(),
                 }
-                Color::Vertex(colors) => {
-                    let mut vertices = Vec::new();
-                    for MeshEdge(a, b) in mesh.edges().into_iter() {
-                        let v = mesh.get(*a)?;
-                        vertices.push(GpuVertex::from(
-                            v,
-                            &colors.get(*a).ok_or(MeshError::idx_vertex(*a))?.clone(),
-                            normals.get_normal(*a)?,
-                        ));
-                        let v = mesh.get(*b)?;
-                        vertices.push(GpuVertex::from(
-                            v,
-                            &colors.get(*b).ok_or(MeshError::idx_vertex(*b))?.clone(),
-                            normals.get_normal(*b)?,
-                        ));
-                    }
+            })
+            .collect())
+    }
+}
 
-                    Ok(vertices)
-                }
-                Color::Line(colors) => {
-                    let mut vertices = Vec::new();
-                    for (MeshEdge(a, b), c) in zip(mesh.edges().into_iter(), colors.into_iter()) {
-                        let v1 = mesh.get(*a)?;
-                        let v2 = mesh.get(*b)?;
-                        vertices.push(GpuVertex::from(v1, c, normals.get_normal(*a)?));
-                        vertices.push(GpuVertex::from(v2, c, normals.get_normal(*b)?));
-                    }
-                    Ok(vertices)
-                }
-            },
+/// The radius of the sprite drawn for every point of a cloud with the given size.
+pub(crate) fn point_radius(size: usize) -> f32 {
+    size as f32 * 0.01
+}
+
+/// The color of every vertex or None if the colors are given for the faces or the lines.
+fn vertex_colors(mesh: &Mesh, color: &Color) -> Result<Option<Vec<RgbaColor>>, MeshError> {
+    let vertices = mesh.vertices();
+    match color {
+        Color::Mesh(c) => Ok(Some(vec![c.clone(); vertices.len()])),
+        Color::Func(f) => Ok(Some(vertices.iter().enumerate().map(|(i, v)| f(v, i)).collect())),
+        Color::Vertex(colors) => {
+            vertices_check(colors, vertices)?;
+            Ok(Some(colors.clone()))
         }
+        Color::Face(_) | Color::Line(_) => Ok(None),
+    }
+}
+
+/// The normals of the vertices provided by the source of the mesh (e.g. a file).
+fn given_normals(mesh: &Mesh) -> Option<Vec<Vec3>> {
+    mesh.fields()
+        .vertex(NORMALS)
+        .and_then(|f| f.as_vector())
+        .filter(|normals| normals.len() == mesh.vertices().len())
+        .map(|normals| normals.iter().map(|n| Vec3::from(*n).normalize_or_zero()).collect())
+}
+
+/// The normals of the vertices, the given ones or calculated from the faces.
+fn vertex_normals(mesh: &Mesh) -> Result<Vec<Vec3>, MeshError> {
+    if let Some(normals) = given_normals(mesh) {
+        return Ok(normals);
     }
+    let normals = mesh.try_normals()?;
+    (0..mesh.vertices().len())
+        .map(|i| normals.get_normal(i).copied())
+        .collect()
 }
 
-fn faces_check(fs: &Vec<RgbaColor>, faces: &Vec<Face>) -> Result<(), MeshError> {
+fn to_u32(indices: Vec<usize>) -> Result<Vec<u32>, MeshError> {
+    indices
+        .into_iter()
+        .map(|i| u32::try_from(i).map_err(|_| MeshError::idx_vertex(i)))
+        .collect()
+}
+
+fn faces_check(fs: &[RgbaColor], faces: &[Face]) -> Result<(), MeshError> {
     if fs.len() != faces.len() {
         Err(MeshError::InvalidFaceType(format!(
             "Face color count {} does not match face count {}",
@@ -236,8 +280,8 @@ fn faces_check(fs: &Vec<RgbaColor>, faces: &Vec<Face>) -> Result<(), MeshError>
     }
 }
 fn vertices_check(
-    vs: &Vec<RgbaColor>,
-    vertices: &Vec<parts::vertex::Vertex>,
+    vs: &[RgbaColor],
+    vertices: &[parts::vertex::Vertex],
 ) -> Result<(), MeshError> {
     if vs.len() != vertices.len() {
         Err(MeshError::InvalidIndex(format!(
@@ -256,3 +300,55 @@ pub fn face_to_vertex3(face: &Face) -> Vec<usize> {
         Face::Quad(a, b, c, d) => vec![*a, *b, *c, *a, *c, *d],
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::attributes::Attributes;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::fields::FieldData;
+    use crate::mesh::parts::vertex::Vertex;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::shape::sphere::Sphere;
+
+    #[test]
+    fn shared_vertices() {
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default()).into();
+        let geometry = GpuGeometry::try_from(&cube).unwrap();
+        // the sharp edges split every corner into the vertices of three sides
+        assert_eq!(geometry.vertices.len(), 24);
+        assert_eq!(geometry.indices.len(), 36);
+
+        let sphere: Mesh = Sphere::create_ico(Vertex::default(), 1.0, 2, Attributes::default())
+            .unwrap()
+            .into();
+        let geometry = GpuGeometry::try_from(&sphere).unwrap();
+        assert_eq!(geometry.vertices.len(), sphere.vertices().len());
+
+        let mut given = cube.clone();
+        let normals = vec![[0.0, 0.0, 1.0]; cube.vertices().len()];
+        given.add_vertex_field(NORMALS, FieldData::Vector(normals)).unwrap();
+        assert_eq!(GpuGeometry::try_from(&given).unwrap().vertices.len(), 8);
+
+        let mut colored = cube.clone();
+        let colors = vec![RgbaColor::RED; cube.faces().len()];
+        colored.attributes_mut().set_color(Color::Face(colors));
+        let geometry = GpuGeometry::try_from(&colored).unwrap();
+        assert_eq!(geometry.vertices.len(), 36);
+        assert_eq!(geometry.indices, (0..36).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn cloud_instances() {
+        let vertices = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        let mut attrs = Attributes::new(MeshType::Cloud(5));
+        attrs.set_color(Color::Vertex(vec![RgbaColor::RED, RgbaColor::BLUE]));
+        let cloud = Mesh::from_vertices(vertices, Vec::<Face>::new(), attrs);
+        let instances = Vec::<GpuInstance>::try_from(&cloud).unwrap();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1].position[..3], [1.0, 0.0, 0.0]);
+        assert!((instances[1].position[3] - 0.05).abs() < 1e-6);
+        assert_eq!(instances[1].color, <[f32; 4]>::from(RgbaColor::BLUE));
+        assert!(GpuGeometry::try_from(&cloud).is_err());
+    }
+}
@@ -16,10 +16,16 @@ struct MaterialUniforms {
     specular: vec3<f32>,
     shininess: f32,
 }
+
+struct Section {
+    plane: vec4<f32>,  // the unit normal with the offset in w
+    enabled: u32,
+}
 @group(0) @binding(0) var<uniform> camera: Camera;
 @group(1) @binding(0) var<uniform> light: Light;
 @group(2) @binding(0) var<uniform> material: MaterialUniforms;
 @group(3) @binding(0) var<uniform> is_affected_by_light: u32;
+@group(3) @binding(1) var<uniform> section: Section;
 
 struct VertexInput {
     @location(0) position: vec4<f32>,
@@ -53,8 +59,33 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     return out;
 }
 
+struct InstanceInput {
+    @location(3) position: vec4<f32>,  // the center with the scale in w
+    @location(4) color: vec4<f32>,
+}
+
+@vertex
+fn vs_instance(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Place the sprite at the center of the instance
+    let world_position = instance.position.xyz + in.position.xyz * instance.position.w;
+    out.clip_position = camera.proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+
+    // The sprite is tinted by the color of the instance
+    out.color = in.color * instance.color;
+    out.normal = normalize(in.normal.xyz);
+
+    return out;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Hide the part in front of the section plane
+    if (section.enabled > 0 && dot(section.plane.xyz, in.world_position) > section.plane.w) {
+        discard;
+    }
     if (is_affected_by_light > 0) {
         // Normalize vectors
         let N = normalize(in.normal);
@@ -1,13 +1,27 @@
 use crate::mesh::attributes::{Attributes, MeshType};
+use crate::mesh::clip::{box_distance, clip, contour, ClipOptions, ClipSide};
+use crate::mesh::decimation::{decimate, DecimationOptions};
 use crate::mesh::distance::distance_between_surfaces;
+use crate::mesh::fields::{FieldData, Fields};
+use crate::mesh::halfedge::HalfEdgeMesh;
+use crate::mesh::isosurface::{IsosurfaceOptions, ScalarGrid};
+use crate::mesh::material::colormap::{ColorMapping, ScalarBar};
 use crate::mesh::material::Color;
 use crate::mesh::normals::MeshNormals;
 use crate::mesh::parts::edge::Edge;
+use crate::mesh::quality::{face_quality, QualityMeasure};
+use crate::mesh::reconstruction::{estimate_normals, reconstruct, ReconstructionOptions};
+use crate::mesh::smoothing::{smooth, SmoothingOptions};
+use crate::mesh::statistics::MeshStatistics;
 use crate::mesh::subdivision::{by_butterfly, by_loop};
 use crate::mesh::tables::MeshTables;
+use crate::mesh::volume::MassProperties;
+use crate::mesh::voxel::{voxelize, VoxelGrid, VoxelMode};
+use glam::Vec3;
 use parts::bbox::BoundingBox;
 use parts::edge::MeshEdge;
 use parts::face::Face;
+use parts::plane::Plane;
 use parts::polygon::Polygon;
 use parts::vertex::Vertex;
 use std::collections::{HashMap, HashSet};
@@ -18,16 +32,31 @@ use std::num::TryFromIntError;
 
 pub mod attributes;
 pub mod bool;
+pub mod clip;
+pub mod decimation;
 pub mod distance;
+pub mod fields;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod geometry;
+pub mod halfedge;
+pub mod isosurface;
+pub(crate) mod linalg;
 pub mod material;
 pub mod normals;
 pub mod parts;
 pub mod properties;
+pub mod quality;
 pub mod query;
+pub mod reconstruction;
 pub mod shape;
+pub mod smoothing;
+pub mod statistics;
 pub mod subdivision;
 pub mod tables;
 pub mod transform;
+pub mod volume;
+pub mod voxel;
 
 pub type MeshResult<T> = Result<T, MeshError>;
 
@@ -100,6 +129,7 @@ impl MeshError {
 /// * `edges` - A vector of edges in the mesh.
 /// * `faces` - A vector of faces in the mesh.
 /// * `attributes` - Additional attributes associated with the mesh.
+/// * `fields` - Named data arrays attached to the vertices of the mesh.
 ///
 /// # Example
 ///
@@ -126,6 +156,7 @@ pub struct Mesh {
     edges: Vec<MeshEdge>,
     faces: Vec<Face>,
     attributes: Attributes,
+    fields: Fields,
 }
 impl Mesh {
     /// Creates a new `Mesh` from vertices and faces.
@@ -157,6 +188,7 @@ impl Mesh {
             edges,
             faces,
             attributes,
+            fields: Fields::default(),
         }
     }
 
@@ -209,6 +241,18 @@ impl Mesh {
         Mesh::from_vertices(vertices, faces, attributes)
     }
 
+    /// Creates the surface where the implicit function equals the iso value.
+    ///
+    /// The function is sampled at the points of the grid of the given dimensions spanning the bounding box,
+    /// and the surface is extracted by the marching cubes, see `ScalarGrid::isosurface`.
+    pub fn from_implicit<F, O>(f: F, bb: &BoundingBox, dims: [usize; 3], options: O) -> MeshResult<Self>
+    where
+        F: Fn(Vertex) -> f32,
+        O: Into<IsosurfaceOptions>,
+    {
+        ScalarGrid::sample(bb, dims, f)?.isosurface(options)
+    }
+
     /// Creates a new `Mesh` representing a cloud of vertices.
     ///
     /// # Parameters
@@ -232,6 +276,7 @@ impl Mesh {
             edges: vec![],
             faces: vec![],
             attributes,
+            fields: Fields::default(),
         }
     }
 
@@ -263,6 +308,35 @@ impl Mesh {
             edges: mesh_edges,
             faces: vec![],
             attributes,
+            fields: Fields::default(),
+        })
+    }
+
+    /// Creates a new lines `Mesh` from the vertices and the edges referring to them by index.
+    /// Unlike `Mesh::lines`, the vertices are kept as they are, even if some of them coincide.
+    pub fn from_edges<V>(
+        vertices: Vec<V>,
+        edges: Vec<MeshEdge>,
+        attributes: Attributes,
+    ) -> MeshResult<Self>
+    where
+        V: Into<Vertex>,
+    {
+        let vertices: Vec<Vertex> = vertices.into_iter().map(Into::into).collect();
+        if let Some(MeshEdge(a, b)) = edges
+            .iter()
+            .find(|MeshEdge(a, b)| *a >= vertices.len() || *b >= vertices.len())
+        {
+            return Err(MeshError::idx_edge(*a, *b));
+        }
+        let mut attributes = attributes;
+        attributes.set_mesh_type(MeshType::Lines);
+        Ok(Mesh {
+            vertices,
+            edges,
+            faces: vec![],
+            attributes,
+            fields: Fields::default(),
         })
     }
 }
@@ -284,10 +358,183 @@ impl Mesh {
         &mut self.attributes
     }
 
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+    /// The fields are changed in place only inside the crate,
+    /// the arrays should keep a value for every vertex or face.
+    pub(crate) fn fields_mut(&mut self) -> &mut Fields {
+        &mut self.fields
+    }
+
+    /// Attaches a named data array to the vertices of the mesh.
+    /// The array should have a value for every vertex.
+    pub fn add_vertex_field<N: Into<String>>(&mut self, name: N, data: FieldData) -> MeshResult<()> {
+        let name = name.into();
+        if data.len() != self.vertices.len() {
+            return Err(MeshError::Custom(format!(
+                "The field {} has {} values but the mesh has {} vertices",
+                name,
+                data.len(),
+                self.vertices.len()
+            )));
+        }
+        self.fields.insert_vertex(name, data);
+        Ok(())
+    }
+
+    /// Attaches a named data array to the faces of the mesh.
+    /// The array should have a value for every face.
+    pub fn add_face_field<N: Into<String>>(&mut self, name: N, data: FieldData) -> MeshResult<()> {
+        let name = name.into();
+        if data.len() != self.faces.len() {
+            return Err(MeshError::Custom(format!(
+                "The field {} has {} values but the mesh has {} faces",
+                name,
+                data.len(),
+                self.faces.len()
+            )));
+        }
+        self.fields.insert_face(name, data);
+        Ok(())
+    }
+
+    /// Detaches the named vertex field from the mesh.
+    pub fn remove_vertex_field(&mut self, name: &str) -> Option<FieldData> {
+        self.fields_mut().remove_vertex(name)
+    }
+
+    /// Detaches the named face field from the mesh.
+    pub fn remove_face_field(&mut self, name: &str) -> Option<FieldData> {
+        self.fields_mut().remove_face(name)
+    }
+
+    /// Colors the mesh by a named field mapped through the colormap.
+    /// The vertex fields are looked up first and give the vertex colors,
+    /// the face fields give the face colors.
+    /// The vectors are mapped by their length.
+    ///
+    /// Returns the scalar bar to show the legend (labeled by the field name unless set in the mapping).
+    pub fn color_by_field(&mut self, name: &str, mapping: &ColorMapping) -> MeshResult<ScalarBar> {
+        let (values, per_vertex) = match (self.fields.vertex(name), self.fields.face(name)) {
+            (Some(data), _) => (data.to_scalars(), true),
+            (None, Some(data)) => (data.to_scalars(), false),
+            (None, None) => return Err(MeshError::Custom(format!("The field {} is not found", name))),
+        };
+        let (colors, bar) = mapping.apply(&values)?;
+        let bar = match mapping.label() {
+            Some(_) => bar,
+            None => bar.with_label(name),
+        };
+        if per_vertex {
+            self.attributes.set_color(Color::Vertex(colors));
+        } else {
+            self.attributes.set_color(Color::Face(colors));
+        }
+        Ok(bar)
+    }
+
+    /// Colors the vertices by the values of the function mapped through the colormap.
+    /// Unlike `Color::Func`, the function can capture its environment.
+    ///
+    /// Returns the scalar bar to show the legend.
+    pub fn color_by<F>(&mut self, func: F, mapping: &ColorMapping) -> MeshResult<ScalarBar>
+    where
+        F: Fn(&Vertex, usize) -> f32,
+    {
+        let values: Vec<f32> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| func(v, i))
+            .collect();
+        let (colors, bar) = mapping.apply(&values)?;
+        self.attributes.set_color(Color::Vertex(colors));
+        Ok(bar)
+    }
+
     pub fn aabb(&self) -> BoundingBox {
         BoundingBox::from(self)
     }
 
+    /// Cuts the mesh by the plane keeping the faces on one side of it.
+    /// The faces crossing the plane are split exactly along the cut
+    /// and the vertex fields are interpolated for the new vertices.
+    ///
+    /// # Arguments
+    /// * `plane` - The cutting plane, its normal points to the front side.
+    /// * `options` - The side to keep, or `ClipOptions` to close the cut with a cap.
+    pub fn clip_by_plane<O: Into<ClipOptions>>(&self, plane: &Plane, options: O) -> MeshResult<Mesh> {
+        clip(self, |v| plane.distance(*v), options.into())
+    }
+
+    /// Cuts the mesh by the surface of the box, the back side is the inside of the box.
+    ///
+    /// The inside is cut exactly by the six planes of the box and can be capped.
+    /// The outside is cut along the signed distance to the box, so the faces crossing
+    /// its edges and corners are cut by a straight line. That cut can not be capped.
+    pub fn clip_by_box<O: Into<ClipOptions>>(&self, bb: &BoundingBox, options: O) -> MeshResult<Mesh> {
+        let options = options.into();
+        match options.keep() {
+            // the inside is the intersection of the half-spaces behind the six faces
+            ClipSide::Back => {
+                let (min, max) = (*bb.min(), *bb.max());
+                let planes = [
+                    Plane::new(Vec3::X, max.into()),
+                    Plane::new(Vec3::Y, max.into()),
+                    Plane::new(Vec3::Z, max.into()),
+                    Plane::new(Vec3::NEG_X, min.into()),
+                    Plane::new(Vec3::NEG_Y, min.into()),
+                    Plane::new(Vec3::NEG_Z, min.into()),
+                ];
+                planes
+                    .iter()
+                    .try_fold(self.clone(), |mesh, plane| mesh.clip_by_plane(plane, options))
+            }
+            // the loops around the box are not planar, the ear clipping would give a wrong cap
+            ClipSide::Front if options.cap() => Err(MeshError::Custom(
+                "The cut by the outside of the box can not be capped".to_string(),
+            )),
+            ClipSide::Front => clip(self, |v| box_distance(bb, v), options),
+        }
+    }
+
+    /// Cuts the mesh by the surface where the signed distance function is zero,
+    /// e.g. `|v| v.magnitude() - 1.0` for the sphere of radius 1.
+    /// The cut points on the edges are found by the root search along the edge.
+    ///
+    /// The cut along a curved surface is not planar, so it can not be capped,
+    /// use `clip_by_plane` for a cap.
+    pub fn clip_by<F, O>(&self, distance: F, options: O) -> MeshResult<Mesh>
+    where
+        F: Fn(&Vertex) -> f32,
+        O: Into<ClipOptions>,
+    {
+        let options = options.into();
+        // the ear clipping of the loop projected onto a plane would fold the cap
+        if options.cap() {
+            return Err(MeshError::Custom(
+                "The cut by a signed distance function can not be capped".to_string(),
+            ));
+        }
+        clip(self, distance, options)
+    }
+
+    /// Finds the section of the mesh by the plane as the lines mesh,
+    /// e.g. to outline the cut made by `clip_by_plane`.
+    /// The vertex fields are interpolated for the points of the lines.
+    pub fn contour_by_plane(&self, plane: &Plane) -> MeshResult<Mesh> {
+        contour(self, |v| plane.distance(*v))
+    }
+
+    /// Finds the lines where the mesh crosses the surface with the zero signed distance.
+    pub fn contour_by<F>(&self, distance: F) -> MeshResult<Mesh>
+    where
+        F: Fn(&Vertex) -> f32,
+    {
+        contour(self, distance)
+    }
+
     /// Subdivides the mesh using the specified subdivision algorithm.
     ///
     /// This function returns a `MeshSubdivision` instance that can be used to
@@ -317,6 +564,25 @@ impl Mesh {
     pub fn subdivide_by_butterfly(&self, iterations:usize) -> MeshResult<Mesh> {
         (0..iterations).try_fold(by_butterfly(self)?, |mesh, _| by_butterfly(&mesh))
     }
+    /// Reduces the number of the faces by collapsing the edges with the smallest quadric error.
+    ///
+    /// # Arguments
+    /// * `options` - The number of the triangles to keep (`usize`), the part of them (`f32`)
+    ///   or `DecimationOptions` to keep the boundary or the feature edges.
+    pub fn decimate<O: Into<DecimationOptions>>(&self, options: O) -> MeshResult<Mesh> {
+        decimate(self, options.into())
+    }
+
+    /// Moves the vertices to smooth the surface or the lines, the connectivity is kept.
+    ///
+    /// # Arguments
+    /// * `options` - The number of the iterations of the uniform Laplacian (`usize`)
+    ///   or `SmoothingOptions` with the method (cotangent, gaussian, Taubin, HC)
+    ///   and the locks of the boundary and the feature edges.
+    pub fn smooth<O: Into<SmoothingOptions>>(&self, options: O) -> MeshResult<Mesh> {
+        smooth(self, options.into())
+    }
+
     pub fn contains(&self, v: &Vertex) -> bool {
         self.vertices.contains(v)
     }
@@ -340,6 +606,11 @@ impl Mesh {
     pub fn try_normals(&self) -> MeshResult<MeshNormals> {
         self.try_into()
     }
+    /// Builds the half-edge structure for the traversal and the topological edits,
+    /// fails if the mesh is not manifold or its faces are not oriented the same way.
+    pub fn try_half_edges(&self) -> MeshResult<HalfEdgeMesh> {
+        self.try_into()
+    }
     pub fn try_polygons(&self) -> MeshResult<Vec<Polygon>> {
         self.faces
             .iter()
@@ -408,12 +679,17 @@ impl Mesh {
             .ok_or(MeshError::idx_vertex(idx))
     }
     pub fn triangulate(&self) -> MeshResult<Mesh> {
-        let faces = self
-            .faces()
-            .iter()
-            .flat_map(|f| f.triangulate())
-            .collect::<Vec<_>>();
-        Ok(Mesh::from_vertices(self.vertices().to_vec(), faces, self.attributes().clone()))
+        let mut faces = vec![];
+        // the face fields are copied to every triangle of the face
+        let mut origins = vec![];
+        for (idx, face) in self.faces().iter().enumerate() {
+            let triangles = face.triangulate();
+            origins.extend(triangles.iter().map(|_| idx));
+            faces.extend(triangles);
+        }
+        let mut mesh = Mesh::from_vertices(self.vertices().to_vec(), faces, self.attributes().clone());
+        mesh.fields = self.fields.select_faces(&origins);
+        Ok(mesh)
     }
     pub fn vertices(&self) -> &Vec<Vertex> {
         &self.vertices
@@ -429,6 +705,61 @@ impl Mesh {
         properties::MeshProperties::new(self)
     }
 
+    pub fn geometry(&self) -> geometry::MeshGeometry {
+        geometry::MeshGeometry::new(self)
+    }
+
+    /// Counts the parts of the mesh and finds its topology, area and volume.
+    pub fn statistics(&self) -> MeshResult<MeshStatistics> {
+        MeshStatistics::new(self)
+    }
+
+    /// Integrates the volume, the center of mass and the inertia tensor of the closed mesh.
+    /// Unlike `centroid`, the center of mass does not depend on how the vertices are spread.
+    pub fn mass_properties(&self) -> MeshResult<MassProperties> {
+        MassProperties::new(self)
+    }
+
+    /// Splits the space around the mesh into the cubes of the given size
+    /// and fills the ones on the surface or inside the mesh, see `VoxelMode`.
+    pub fn voxelize(&self, voxel_size: f32, mode: VoxelMode) -> MeshResult<VoxelGrid> {
+        voxelize(self, voxel_size, mode)
+    }
+
+    /// Estimates the normals of the vertices from their nearest neighbours, e.g. of a cloud of points,
+    /// and stores them in the `normals` vertex field. See `reconstruction::estimate_normals`.
+    pub fn estimate_normals(&mut self, neighbours: usize) -> MeshResult<()> {
+        let normals = estimate_normals(&self.vertices, neighbours)?;
+        self.add_vertex_field(fields::NORMALS, FieldData::Vector(normals.into_iter().map(|n| n.to_array()).collect()))
+    }
+
+    /// Reconstructs the closed surface from the vertices, e.g. of a cloud of points,
+    /// by the screened Poisson method. The normals are taken from the `normals` vertex field
+    /// or estimated if there are none.
+    pub fn reconstruct<O: Into<ReconstructionOptions>>(&self, options: O) -> MeshResult<Mesh> {
+        let options = options.into();
+        let normals = match self.fields.vertex(fields::NORMALS).and_then(|f| f.as_vector()) {
+            Some(normals) if normals.len() == self.vertices.len() => {
+                normals.iter().map(|n| Vec3::from(*n).normalize_or_zero()).collect()
+            }
+            _ => estimate_normals(&self.vertices, options.neighbours())?,
+        };
+        reconstruct(&self.vertices, &normals, options)
+    }
+
+    /// Measures the shape of every face, see `QualityMeasure`.
+    /// The result is the scalar face field.
+    pub fn quality(&self, measure: QualityMeasure) -> MeshResult<FieldData> {
+        Ok(FieldData::Scalar(face_quality(self, measure)?))
+    }
+
+    /// Measures the shape of every face and stores it as the face field named after the measure,
+    /// e.g. to color the mesh by it.
+    pub fn add_quality_field(&mut self, measure: QualityMeasure) -> MeshResult<()> {
+        let values = self.quality(measure)?;
+        self.add_face_field(measure.name(), values)
+    }
+
     pub fn face_idx_to_polygon(&self, idx: usize) -> MeshResult<Polygon> {
         self.face_to_polygon(self.faces().get(idx).ok_or("Invalid face index")?)
     }
@@ -508,4 +839,140 @@ mod tests {
         assert_eq!(normals.get_normal(2), Ok(&Vec3::new(0.0, 0.0, 1.0)));
         assert_eq!(normals.get_normal(3), Ok(&Vec3::new(0.0, 0.0, 1.0)));
     }
+
+    #[test]
+    fn given_normals_follow_vertices() {
+        use super::*;
+        use crate::mesh::transform::Transform;
+        let vertices = vec![[0, 0, 0], [1, 0, 0], [1, 1, 0], [0, 1, 0]];
+        let faces: Vec<Face> = vec![(0, 1, 2).into(), (0, 2, 3).into()];
+        let mut mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        // the normals tilted towards x, unlike the normals of the faces
+        let tilted = [std::f32::consts::FRAC_1_SQRT_2, 0.0, std::f32::consts::FRAC_1_SQRT_2];
+        mesh.add_vertex_field(fields::NORMALS, FieldData::Vector(vec![tilted; 4])).unwrap();
+
+        mesh.transform(glam::Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2)).unwrap();
+        let normals = mesh.try_normals().unwrap();
+        let expected = Vec3::new(0.0, 1.0, 1.0).normalize();
+        for idx in 0..4 {
+            assert!(normals.get_normal(idx).unwrap().distance(expected) < 1e-6);
+        }
+
+        // the smoothed surface gets the normals of its faces
+        let smooth = mesh.smooth(1).unwrap();
+        assert!(smooth.fields().vertex(fields::NORMALS).is_none());
+        assert!(smooth.try_normals().unwrap().get_normal(0).unwrap().distance(Vec3::Z) < 1e-6);
+    }
+
+    #[test]
+    fn fields_survive_operations() {
+        use super::*;
+        use crate::mesh::shape::cuboid::cube::Cube;
+        use crate::mesh::parts::face::FaceType;
+
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        let mut mesh = cube.mesh().clone();
+        let temperature: Vec<f32> = (0..mesh.vertices().len()).map(|i| i as f32).collect();
+        mesh.add_vertex_field("temperature", FieldData::Scalar(temperature)).unwrap();
+        let ids: Vec<i64> = (0..mesh.faces().len() as i64).collect();
+        mesh.add_face_field("id", FieldData::Integer(ids)).unwrap();
+
+        let triangulated = mesh.triangulate().unwrap();
+        assert_eq!(
+            triangulated.fields().face("id"),
+            Some(&FieldData::Integer(vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5]))
+        );
+
+        let vertices = vec![[0, 0, 0], [1, 0, 0], [0, 1, 0], [0, 0, 1]];
+        let faces: Vec<Face> = vec![(0, 2, 1).into(), (0, 1, 3).into(), (1, 2, 3).into(), (0, 3, 2).into()];
+        let mut tetra = Mesh::from_vertices(vertices, faces, Default::default());
+        tetra.add_vertex_field("t", FieldData::Scalar(vec![0.0, 2.0, 4.0, 8.0])).unwrap();
+        tetra.add_face_field("id", FieldData::Integer(vec![0, 1, 2, 3])).unwrap();
+
+        for subdivided in [tetra.subdivide_by_loop(0).unwrap(), tetra.subdivide_by_butterfly(0).unwrap()] {
+            let Some(FieldData::Scalar(values)) = subdivided.fields().vertex("t") else {
+                panic!("expected the scalar field")
+            };
+            assert_eq!(values.len(), 10);
+            // the new vertices are in the middle of the 6 edges
+            let mut middles = values[4..].to_vec();
+            middles.sort_by(f32::total_cmp);
+            assert_eq!(values[..4], [0.0, 2.0, 4.0, 8.0]);
+            assert_eq!(middles, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+            assert_eq!(
+                subdivided.fields().face("id"),
+                Some(&FieldData::Integer(vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3]))
+            );
+        }
+    }
+
+    #[test]
+    fn fields_keep_the_length() {
+        use super::*;
+        let vertices = vec![[0, 0, 0], [1, 0, 0], [0, 1, 0]];
+        let faces: Vec<Face> = vec![(0, 1, 2).into()];
+        let mut mesh = Mesh::from_vertices(vertices, faces, Default::default());
+
+        assert!(mesh.add_vertex_field("t", FieldData::Scalar(vec![0.0, 1.0])).is_err());
+        assert!(mesh.add_face_field("id", FieldData::Scalar(vec![0.0, 1.0])).is_err());
+        assert!(mesh.fields().is_empty());
+
+        mesh.add_vertex_field("t", FieldData::Scalar(vec![0.0, 1.0, 2.0])).unwrap();
+        assert_eq!(mesh.remove_vertex_field("t"), Some(FieldData::Scalar(vec![0.0, 1.0, 2.0])));
+        assert_eq!(mesh.remove_face_field("id"), None);
+        assert!(mesh.fields().is_empty());
+    }
+
+    #[test]
+    fn fields_sliced_by_regions() {
+        use super::*;
+        let vertices = vec![[0, 0, 0], [1, 0, 0], [0, 1, 0], [5, 5, 5], [6, 5, 5], [5, 6, 5]];
+        let faces: Vec<Face> = vec![(0, 1, 2).into(), (3, 4, 5).into()];
+        let mut mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        mesh.add_vertex_field("t", FieldData::Scalar(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0])).unwrap();
+        mesh.add_face_field("id", FieldData::Integer(vec![10, 20])).unwrap();
+
+        let mut regions = mesh.query().extract_connected_regions().unwrap();
+        regions.sort_by_key(|r| r.fields().face("id").and_then(|f| f.as_integer()).map(|v| v[0]));
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[1].faces(), &vec![Face::new3(0, 1, 2)]);
+        assert_eq!(regions[1].fields().vertex("t"), Some(&FieldData::Scalar(vec![3.0, 4.0, 5.0])));
+        assert_eq!(regions[1].fields().face("id"), Some(&FieldData::Integer(vec![20])));
+    }
+
+    #[test]
+    fn color_by_colormap() {
+        use super::*;
+        use crate::mesh::material::colormap::Colormap;
+        use crate::mesh::material::RgbaColor;
+        let vertices = vec![[0, 0, 0], [1, 0, 0], [0, 1, 0], [1, 1, 0]];
+        let faces: Vec<Face> = vec![(0, 1, 2).into(), (1, 3, 2).into()];
+        let mut mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        mesh.add_vertex_field("t", FieldData::Scalar(vec![0.0, 1.0, 2.0, 4.0])).unwrap();
+        mesh.add_face_field("id", FieldData::Integer(vec![1, 2])).unwrap();
+        let mapping = ColorMapping::new(Colormap::Grayscale);
+
+        let bar = mesh.color_by_field("t", &mapping).unwrap();
+        assert_eq!(bar.label(), "t");
+        assert_eq!(bar.range(), (0.0, 4.0));
+        match mesh.attributes().color() {
+            Color::Vertex(colors) => {
+                assert_eq!(colors[0], RgbaColor::BLACK);
+                assert_eq!(colors[3], RgbaColor::WHITE);
+            }
+            _ => panic!("vertex colors are expected"),
+        }
+
+        mesh.color_by_field("id", &mapping.clone().with_label("ids")).unwrap();
+        assert!(matches!(mesh.attributes().color(), Color::Face(colors) if colors.len() == 2));
+        assert!(mesh.color_by_field("missing", &mapping).is_err());
+
+        let threshold = 0.5;
+        let bar = mesh.color_by(|v, _| if v.x > threshold { 1.0 } else { 0.0 }, &mapping).unwrap();
+        assert_eq!(bar.range(), (0.0, 1.0));
+        match mesh.attributes().color() {
+            Color::Vertex(colors) => assert_eq!(colors[1], RgbaColor::WHITE),
+            _ => panic!("vertex colors are expected"),
+        }
+    }
 }
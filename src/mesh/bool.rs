@@ -1,27 +1,245 @@
 mod analyzer;
 mod dsu;
+mod intersect;
+mod triangulate;
 
+use crate::mesh::bool::analyzer::{Location, MeshBoolAnalyzer};
 use crate::mesh::material::Color;
-use crate::mesh::parts::vertex::Vertex;
-use crate::mesh::query::MeshQuery;
-use crate::mesh::{Mesh, MeshError, MeshResult};
-use std::collections::HashSet;
+use crate::mesh::parts::face::Face;
+use crate::mesh::{Mesh, MeshResult};
+use std::collections::HashMap;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BoolType {
     Union, // A ∪ B
     Intersection, // A ∩ B
     Difference, // A - B
     SymmetricDifference, // A ⊕ B
 }
+
+/// What to do with a piece of the split mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Keep {
+    No,
+    AsIs,
+    Flipped,
+}
+
+impl BoolType {
+    /// Decides whether a piece belongs to the result.
+    /// The flag is 0 for the pieces of the first mesh and 1 for the second one.
+    fn keep(&self, flag: u8, location: Location) -> Keep {
+        use Location::*;
+        let from_a = flag == 0;
+        match (self, location) {
+            (BoolType::Union, Outside) => Keep::AsIs,
+            (BoolType::Union, OnSame) if from_a => Keep::AsIs,
+            (BoolType::Intersection, Inside) => Keep::AsIs,
+            (BoolType::Intersection, OnSame) if from_a => Keep::AsIs,
+            (BoolType::Difference, Outside) if from_a => Keep::AsIs,
+            (BoolType::Difference, Inside) if !from_a => Keep::Flipped,
+            (BoolType::Difference, OnOpposite) if from_a => Keep::AsIs,
+            // (A - B) ∪ (B - A)
+            (BoolType::SymmetricDifference, _) => match BoolType::Difference.keep(flag, location) {
+                Keep::No => BoolType::Difference.keep(1 - flag, location),
+                keep => keep,
+            },
+            _ => Keep::No,
+        }
+    }
+
+    /// Checks whether a kept piece belongs to `B - A`, the second shell of the symmetric difference.
+    fn second_shell(&self, flag: u8, location: Location) -> bool {
+        *self == BoolType::SymmetricDifference
+            && BoolType::Difference.keep(flag, location) == Keep::No
+    }
+}
+
 /// Perform a boolean operation on two meshes
 /// The implementation from this function is based on the paper:
 /// Exact, Robust, and Efficient Boolean Operations (Cork Algorithm)
+///
+/// The meshes are triangulated and split along the curve where they intersect each other.
+/// The split pieces are grouped into regions bounded by the curve,
+/// every region is classified as inside or outside the other mesh by casting rays,
+/// and the regions required by the operation are stitched together into the resulting mesh.
+///
+/// The meshes are expected to be closed and consistently oriented.
+/// The pieces of the second mesh that bound the result from inside
+/// (e.g. the cavity of the difference) are flipped.
+/// The symmetric difference is made of two shells, `A - B` and `B - A`,
+/// touching along the intersection curve and the shared faces;
+/// they do not share the vertices there, so each of them stays closed.
+///
+/// # Parameters
+///
+/// * `mesh_a` - The first operand.
+/// * `mesh_b` - The second operand.
+/// * `op` - The operation to perform.
+/// * `color` - The color of the result, the attributes of `mesh_a` are used otherwise.
 pub fn perform_bool(
     mesh_a: &Mesh,
     mesh_b: &Mesh,
     op: BoolType,
     color: Option<Color>,
 ) -> MeshResult<Mesh> {
-    Err(MeshError::Custom("not implemented".to_string()))
+    let mut analyzer = MeshBoolAnalyzer::new(mesh_a)?;
+    analyzer.prepare(mesh_b)?;
+
+    let split = analyzer.split()?;
+    let locations = analyzer.classify(&split)?;
+
+    let mut vertices = vec![];
+    let mut mapping = HashMap::new();
+    let mut faces = vec![];
+    for (piece, location) in split.pieces.iter().zip(locations) {
+        let [a, b, c] = match op.keep(piece.flag, location) {
+            Keep::No => continue,
+            Keep::AsIs => piece.face,
+            Keep::Flipped => [piece.face[0], piece.face[2], piece.face[1]],
+        };
+        let shell = op.second_shell(piece.flag, location);
+        let [a, b, c] = [a, b, c].map(|idx| {
+            *mapping.entry((idx, shell)).or_insert_with(|| {
+                vertices.push(split.vertices[idx]);
+                vertices.len() - 1
+            })
+        });
+        faces.push(Face::new3(a, b, c));
+    }
+
+    let mut attributes = mesh_a.attributes().clone();
+    match color {
+        Some(color) => attributes.set_color(color),
+        // the per vertex and per face colors do not fit the new mesh
+        None if matches!(attributes.color(), Color::Vertex(_) | Color::Face(_)) => {
+            attributes.set_color(Color::default())
+        }
+        None => {}
+    }
+
+    Ok(Mesh::from_vertices(vertices, faces, attributes))
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::shape::cuboid::rect_cuboid::RectCuboid;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::{HasMesh, Mesh};
+    use glam::Vec3;
+
+    fn cube(center: [f32; 3]) -> Cube {
+        Cube::create(center, 1.0, FaceType::Triangle, Color::default())
+    }
+
+    /// The volume enclosed by the outward wound faces.
+    fn volume(mesh: &Mesh) -> f32 {
+        let point = |idx: usize| Into::<Vec3>::into(*mesh.get(idx).unwrap());
+        mesh.faces()
+            .iter()
+            .flat_map(|face| face.triangulate())
+            .map(|t| match t.flatten()[..] {
+                [a, b, c] => point(a).dot(point(b).cross(point(c))) / 6.0,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// The result is closed and encloses the expected volume.
+    fn assert_solid(res: &Mesh, expected: f32) {
+        assert!(res.properties().is_watertight());
+        let volume = volume(res);
+        assert!((volume - expected).abs() < 1e-4, "{} != {}", volume, expected);
+    }
+
+    // the cubes overlap in the cube of the side 0.5
+    const OVERLAP: f32 = 0.125;
+
+    #[test]
+    fn intersection_of_cubes() {
+        let res = cube([0.0, 0.0, 0.0]).intersection(cube([0.5, 0.5, 0.5])).unwrap();
+        assert_solid(&res, OVERLAP);
+
+        let bb = res.aabb();
+        let expected = RectCuboid::create_bbox(
+            [0.0, 0.0, 0.0],
+            [0.5, 0.5, 0.5],
+            FaceType::Triangle,
+            Color::default(),
+        )
+        .aabb();
+        assert_eq!(bb.min(), expected.min());
+        assert_eq!(bb.max(), expected.max());
+    }
+
+    #[test]
+    fn union_of_cubes() {
+        let res = cube([0.0, 0.0, 0.0]).union(cube([0.5, 0.5, 0.5])).unwrap();
+        assert_solid(&res, 2.0 - OVERLAP);
+
+        let bb = res.aabb();
+        assert_eq!(bb.min(), &[-0.5, -0.5, -0.5].into());
+        assert_eq!(bb.max(), &[1.0, 1.0, 1.0].into());
+    }
+
+    #[test]
+    fn difference_of_cubes() {
+        let res = cube([0.0, 0.0, 0.0]).difference(cube([0.5, 0.5, 0.5])).unwrap();
+        assert_solid(&res, 1.0 - OVERLAP);
+
+        let bb = res.aabb();
+        assert_eq!(bb.min(), &[-0.5, -0.5, -0.5].into());
+        assert_eq!(bb.max(), &[0.5, 0.5, 0.5].into());
+    }
+
+    #[test]
+    fn sym_difference_of_cubes() {
+        let lhs = cube([0.0, 0.0, 0.0]);
+        let rhs = cube([0.5, 0.5, 0.5]);
+        let res = lhs.sym_difference(rhs.mesh().clone()).unwrap();
+        assert_solid(&res, 2.0 - 2.0 * OVERLAP);
+        assert_eq!(res.query().extract_connected_regions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cubes_sharing_a_face() {
+        // the coplanar faces go through the exact coplanarity test
+        let (lhs, rhs) = (cube([0.0, 0.0, 0.0]), cube([1.0, 0.0, 0.0]));
+        assert_solid(&lhs.union(rhs.mesh().clone()).unwrap(), 2.0);
+        assert_solid(&lhs.difference(rhs.mesh().clone()).unwrap(), 1.0);
+        assert_solid(&lhs.sym_difference(rhs.mesh().clone()).unwrap(), 2.0);
+        assert!(lhs.intersection(rhs).unwrap().faces().is_empty());
+    }
+
+    #[test]
+    fn sphere_minus_cube() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Color::default().into()).unwrap();
+        // the cuboid is wound inside out, the operands are oriented before the split
+        let cap = RectCuboid::create(
+            [1.5, 0.0, 0.0],
+            2.0,
+            3.0,
+            3.0,
+            FaceType::Triangle,
+            Color::default(),
+        );
+        let whole = volume(sphere.mesh());
+        let res = sphere.difference(cap.mesh().clone()).unwrap();
+        assert!(res.properties().is_watertight());
+        assert!((res.aabb().max().x - 0.5).abs() < 1e-5);
+
+        let rest = volume(&sphere.intersection(cap).unwrap());
+        let left = volume(&res);
+        assert!((left + rest - whole).abs() < 1e-4, "{} + {} != {}", left, rest, whole);
+        assert!(left > rest);
+    }
+
+    #[test]
+    fn disjoint_intersection_is_empty() {
+        let res = cube([0.0, 0.0, 0.0]).intersection(cube([5.0, 0.0, 0.0])).unwrap();
+        assert!(res.faces().is_empty());
+    }
+}
@@ -1,69 +1,78 @@
-use crate::mesh::parts::edge::MeshEdge;
+use crate::mesh::bool::dsu::UnionFindSet;
+use crate::mesh::bool::intersect::{
+    coplanar_segments, drop_axis, intersect, PointKey, PointPool, Tri, TriTri,
+};
+use crate::mesh::bool::triangulate::triangulate;
 use crate::mesh::parts::face::Face;
-use crate::mesh::parts::polygon::{Polygon, Triangle};
-use crate::mesh::parts::ray::Ray;
+use crate::mesh::parts::predicates::orient3d_sos;
 use crate::mesh::parts::vertex::Vertex;
 use crate::mesh::{Mesh, MeshError, MeshResult};
-use std::collections::HashMap;
+use glam::{DVec2, DVec3, Vec3};
+use std::collections::{HashMap, HashSet};
 
-struct CacheEntry<T> {
-    data: T,
-    triangles: [usize; 2],
+/// Where a piece of one mesh lies relatively to the other one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Location {
+    Inside,
+    Outside,
+    /// lies on the surface of the other mesh and looks in the same direction
+    OnSame,
+    /// lies on the surface of the other mesh and looks in the opposite direction
+    OnOpposite,
 }
 
-impl<T: Default> CacheEntry<T> {
-    fn add_triangle(&mut self, idx: usize) {
-        if self.triangles.is_empty() {
-            self.triangles[0] = idx;
-        } else {
-            self.triangles[1] = idx;
-        }
-    }
-    fn new(idx: usize) -> Self {
-        Self {
-            data: T::default(),
-            triangles: [idx, 2],
-        }
-    }
+/// A triangle of the split mesh
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Piece {
+    pub(crate) face: [usize; 3],
+    pub(crate) flag: u8,
 }
 
-struct MeshCache<T: Default> {
-    cache: HashMap<MeshEdge, CacheEntry<T>>,
-}
-
-impl<T: Default> MeshCache<T> {
-    fn new(faces: &Vec<Face>) -> MeshResult<Self> {
-        let mut cache = HashMap::new();
-        for (idx, face) in faces.iter().enumerate() {
-            for edge in face.edges() {
-                cache
-                    .entry(edge)
-                    .or_insert(CacheEntry::new(idx))
-                    .add_triangle(idx);
-
-                cache
-                    .entry(edge.inv())
-                    .or_insert(CacheEntry::new(idx))
-                    .add_triangle(idx);
-            }
-        }
-
-        Ok(Self { cache })
-    }
+/// Both meshes split along the intersection curve.
+pub(crate) struct SplitMeshes {
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) pieces: Vec<Piece>,
+    cuts: HashSet<(usize, usize)>,
+    points: Vec<DVec3>,
 }
 
-pub(crate) struct MeshBoolAnalyzer  {
+pub(crate) struct MeshBoolAnalyzer {
     vertices: Vec<Vertex>,
     faces: Vec<Face>,
     flags: Vec<u8>,
 }
 
-impl<'a,> MeshBoolAnalyzer {
-    pub(crate) fn new(mesh: &'a Mesh) -> MeshResult<MeshBoolAnalyzer> {
+/// The triangles of the mesh wound so that the normals look outside.
+/// The shapes are not wound the same way, so the faces are flipped
+/// when the mesh encloses a negative volume.
+fn outward(mesh: &Mesh) -> MeshResult<(Vec<Vertex>, Vec<Face>)> {
+    let mesh = mesh.triangulate()?;
+    let vertices = mesh.vertices().to_vec();
+    let mut volume = 0.0;
+    for face in mesh.faces() {
+        if let Face::Triangle(a, b, c) = face {
+            let [a, b, c] = [*a, *b, *c].map(|i| Into::<Vec3>::into(vertices[i]).as_dvec3());
+            volume += a.dot(b.cross(c));
+        }
+    }
+    let faces = mesh
+        .faces()
+        .iter()
+        .map(|face| match face {
+            Face::Triangle(a, b, c) if volume < 0.0 => Face::Triangle(*a, *c, *b),
+            _ => *face,
+        })
+        .collect();
+    Ok((vertices, faces))
+}
+
+impl MeshBoolAnalyzer {
+    pub(crate) fn new(mesh: &Mesh) -> MeshResult<MeshBoolAnalyzer> {
+        let (vertices, faces) = outward(mesh)?;
         Ok(Self {
-            vertices: mesh.vertices().to_vec(),
-            faces: mesh.faces().to_vec(),
-            flags: mesh.faces().iter().map(|_| 0).collect(),
+            vertices,
+            flags: faces.iter().map(|_| 0).collect(),
+            faces,
         })
     }
 
@@ -74,61 +83,343 @@ impl<'a,> MeshBoolAnalyzer {
             .ok_or(MeshError::InvalidIndex(format!("vertex {:?}", idx)))
     }
 
-    fn face_idx_to_poly(&self, idx: usize) -> MeshResult<Polygon> {
-        Ok(Polygon::new(
-            self.faces
-                .get(idx)
-                .ok_or(MeshError::InvalidIndex(format!("face {:?}", idx)))?
-                .flatten()
-                .iter()
-                .map(|i| self.v(*i))
-                .collect::<Result<Vec<_>, _>>()?,
-        ))
+    fn tri(&self, idx: usize) -> MeshResult<Tri> {
+        match self.faces.get(idx) {
+            Some(Face::Triangle(a, b, c)) => Ok(Tri::new(
+                idx,
+                [*a, *b, *c],
+                [&self.v(*a)?, &self.v(*b)?, &self.v(*c)?],
+            )),
+            Some(_) => Err(MeshError::InvalidFaceType(format!("face {} is not a triangle", idx))),
+            None => Err(MeshError::idx_face(idx)),
+        }
     }
 
-    pub fn is_inside(&self, face: usize, flag: u8) -> MeshResult<bool> {
-        let centroid = self.face_idx_to_poly(face)?.centroid()?;
-
-        let ray = Ray::new_rand(centroid);
-        let mut winding = 0;
-        for (i, _) in self.faces.iter().enumerate() {
-            if self.flags.get(i).map(|f| *f != flag).unwrap_or(false) {
-                let poly = self.face_idx_to_poly(i)?;
-                if ray.intersects(poly.clone().try_into()?) {
-                    if poly.normal().dot(&ray.direction) > 0.0 {
-                        winding += 1;
-                    } else {
-                        winding -= 1;
-                    }
-                }
+    /// Checks whether the point is inside the mesh marked by the other flag.
+    /// The segment from the point to a point far outside crosses the closed surface
+    /// an odd number of times if the point is inside. The crossings are decided by the exact
+    /// predicates with the ties (the segment passing through an edge or a vertex) broken
+    /// by Simulation of Simplicity, so every crossing is counted exactly once.
+    ///
+    /// `ids` gives the vertices at the same position the same symbolic index,
+    /// otherwise the perturbation would open the seams of the meshes with duplicated vertices.
+    fn contains(&self, point: Vertex, flag: u8, ids: &[usize]) -> MeshResult<bool> {
+        let (min, max) = self
+            .vertices
+            .iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), v| {
+                let v: Vec3 = v.into();
+                (min.min(v), max.max(v))
+            });
+        let far: Vertex = (max + (max - min + Vec3::ONE) * Vec3::new(1.0, 0.37, 0.71)).into();
+        let (p_id, far_id) = (self.vertices.len(), self.vertices.len() + 1);
+
+        let mut crossings = 0;
+        for (face, f) in self.faces.iter().zip(self.flags.iter()) {
+            if *f == flag {
+                continue;
+            }
+            let [a, b, c] = match face {
+                Face::Triangle(a, b, c) => [*a, *b, *c],
+                Face::Quad(..) => return Err(MeshError::InvalidFaceType("quad".to_string())),
+            };
+            let [va, vb, vc] = [self.v(a)?, self.v(b)?, self.v(c)?];
+            let [ia, ib, ic] = [ids[a], ids[b], ids[c]];
+            if ia == ib || ib == ic || ic == ia {
+                continue;
+            }
+            let tri = [(ia, &va), (ib, &vb), (ic, &vc)];
+            let side = |q: (usize, &Vertex)| orient3d_sos([tri[0], tri[1], tri[2], q]);
+            if side((p_id, &point)) == side((far_id, &far)) {
+                continue;
+            }
+            let p = (p_id, &point);
+            let q = (far_id, &far);
+            let around = [(0, 1), (1, 2), (2, 0)].map(|(i, j)| orient3d_sos([p, q, tri[i], tri[j]]));
+            if around[0] == around[1] && around[1] == around[2] {
+                crossings += 1;
             }
         }
+        Ok(crossings % 2 == 1)
+    }
 
-        Ok(winding > 0)
+    /// The index of the first vertex at the same position for every vertex.
+    fn symbolic_ids(&self) -> Vec<usize> {
+        let mut first = HashMap::new();
+        self.vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| *first.entry(*v).or_insert(i))
+            .collect()
     }
 
     pub fn prepare(&mut self, rhs: &Mesh) -> MeshResult<()> {
-        let rhs_vertices = rhs.vertices().to_vec();
-        let rhs_faces = rhs.faces().to_vec();
-
-        let offset = self.faces.len();
-        self.vertices.extend(rhs_vertices);
-        for face in rhs_faces {
+        let (vertices, faces) = outward(rhs)?;
+        let offset = self.vertices.len();
+        self.vertices.extend(vertices);
+        for face in faces.iter() {
             self.faces.push(face.with_offset(offset));
             self.flags.push(1);
         }
 
-
         Ok(())
     }
+
+    /// Pairs of faces from different meshes whose bounding boxes overlap (sweep and prune along x).
+    fn candidate_pairs(&self, tris: &[Tri], tol: f64) -> Vec<(usize, usize)> {
+        let bounds: Vec<(DVec3, DVec3)> = tris.iter().map(|t| (t.min(), t.max())).collect();
+        let mut order: Vec<usize> = (0..tris.len()).collect();
+        order.sort_by(|l, r| bounds[*l].0.x.total_cmp(&bounds[*r].0.x));
+
+        let mut pairs = vec![];
+        let mut active: Vec<usize> = vec![];
+        for i in order {
+            let (min, max) = bounds[i];
+            active.retain(|j| bounds[*j].1.x >= min.x - tol);
+            for &j in active.iter() {
+                if self.flags[i] == self.flags[j] {
+                    continue;
+                }
+                let (jmin, jmax) = bounds[j];
+                if (1..3).all(|k| jmin[k] <= max[k] + tol && min[k] <= jmax[k] + tol) {
+                    pairs.push(if self.flags[i] == 0 { (i, j) } else { (j, i) });
+                }
+            }
+            active.push(i);
+        }
+        pairs
+    }
+
+    /// Splits the faces of both meshes along the curve where the meshes intersect each other.
+    /// The points of the curve are shared by the both sides, so the pieces can be stitched
+    /// together without gaps.
+    pub(crate) fn split(&self) -> MeshResult<SplitMeshes> {
+        let tris = (0..self.faces.len())
+            .map(|i| self.tri(i))
+            .collect::<MeshResult<Vec<_>>>()?;
+
+        let (min, max) = tris.iter().fold(
+            (DVec3::INFINITY, DVec3::NEG_INFINITY),
+            |(min, max), t| (min.min(t.min()), max.max(t.max())),
+        );
+        let diag = if tris.is_empty() { 1.0 } else { min.distance(max) };
+        let tol = (diag * 1e-6).max(f64::EPSILON);
+
+        let mut pool = PointPool::new(tol);
+        let ids: Vec<usize> = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| pool.insert(PointKey::Vertex(i), Into::<Vec3>::into(v).as_dvec3()))
+            .collect();
+
+        let mut constraints: Vec<Vec<(usize, usize)>> = vec![vec![]; tris.len()];
+        for (a, b) in self.candidate_pairs(&tris, tol) {
+            let (ta, tb) = (&tris[a], &tris[b]);
+            match intersect(&mut pool, ta, tb) {
+                // the touching point is kept as a degenerated constraint,
+                // otherwise the face on the other side of the touched edge gets a t-junction
+                TriTri::Point(p) => {
+                    constraints[a].push((p, p));
+                    constraints[b].push((p, p));
+                }
+                TriTri::Segment(p, q) => {
+                    constraints[a].push((p, q));
+                    constraints[b].push((p, q));
+                }
+                TriTri::Coplanar => {
+                    let on_a = coplanar_segments(&mut pool, ta, tb);
+                    let on_b = coplanar_segments(&mut pool, tb, ta);
+                    constraints[a].extend(on_a);
+                    constraints[b].extend(on_b);
+                }
+                TriTri::None => {}
+            }
+        }
+
+        let mut pieces = vec![];
+        let mut cuts = HashSet::new();
+        for (i, tri) in tris.iter().enumerate() {
+            let corners = tri.idx.map(|v| ids[v]);
+            if corners[0] == corners[1] || corners[1] == corners[2] || corners[2] == corners[0] {
+                continue;
+            }
+            let flag = self.flags[i];
+            if constraints[i].is_empty() {
+                pieces.push(Piece { face: corners, flag });
+                continue;
+            }
+            let Some(normal) = tri.normal() else {
+                continue;
+            };
+
+            let mut local: Vec<usize> = corners.to_vec();
+            let local_idx = |p: usize, local: &mut Vec<usize>| match local
+                .iter()
+                .position(|l| *l == p)
+            {
+                Some(idx) => idx,
+                None => {
+                    local.push(p);
+                    local.len() - 1
+                }
+            };
+            let local_constraints: Vec<(usize, usize)> = constraints[i]
+                .iter()
+                .map(|(p, q)| (local_idx(*p, &mut local), local_idx(*q, &mut local)))
+                .collect();
+
+            let (u, v) = drop_axis(normal);
+            let points2d: Vec<DVec2> = local
+                .iter()
+                .map(|p| {
+                    let p = pool.get(*p);
+                    DVec2::new(p[u], p[v])
+                })
+                .collect();
+            let ccw = (points2d[1] - points2d[0]).perp_dot(points2d[2] - points2d[0]) > 0.0;
+
+            let res = triangulate(&points2d, &local_constraints, tol)?;
+            for [a, b, c] in res.triangles {
+                let face = if ccw {
+                    [local[a], local[b], local[c]]
+                } else {
+                    [local[a], local[c], local[b]]
+                };
+                pieces.push(Piece { face, flag });
+            }
+            for (a, b) in res.cuts {
+                let (a, b) = (local[a], local[b]);
+                cuts.insert((a.min(b), a.max(b)));
+            }
+        }
+
+        let points = (0..pool.len()).map(|i| pool.get(i)).collect();
+        Ok(SplitMeshes {
+            vertices: pool.to_vertices(),
+            pieces,
+            cuts,
+            points,
+        })
+    }
+
+    /// Classifies the pieces relatively to the other mesh.
+    /// The pieces connected without crossing the intersection curve share the location,
+    /// so only one piece per connected region is tested.
+    pub(crate) fn classify(&self, split: &SplitMeshes) -> MeshResult<Vec<Location>> {
+        let mut dsu = UnionFindSet::new(split.pieces.len());
+        let mut edges: HashMap<(usize, usize, u8), usize> = HashMap::new();
+        for (i, piece) in split.pieces.iter().enumerate() {
+            let [a, b, c] = piece.face;
+            for (l, r) in [(a, b), (b, c), (c, a)] {
+                let edge = (l.min(r), l.max(r));
+                if split.cuts.contains(&edge) {
+                    continue;
+                }
+                match edges.get(&(edge.0, edge.1, piece.flag)) {
+                    Some(&other) => dsu.union(i, other)?,
+                    None => {
+                        edges.insert((edge.0, edge.1, piece.flag), i);
+                    }
+                }
+            }
+        }
+
+        let area = |piece: &Piece| {
+            let [a, b, c] = piece.face.map(|i| split.points[i]);
+            (b - a).cross(c - a).length()
+        };
+        let mut representatives: HashMap<usize, usize> = HashMap::new();
+        for (i, piece) in split.pieces.iter().enumerate() {
+            let root = dsu.find(i)?;
+            let best = representatives.entry(root).or_insert(i);
+            if area(piece) > area(&split.pieces[*best]) {
+                *best = i;
+            }
+        }
+
+        let ids = self.symbolic_ids();
+        let mut locations = HashMap::new();
+        for (root, i) in representatives {
+            locations.insert(root, self.locate(split, &split.pieces[i], &ids)?);
+        }
+        split
+            .pieces
+            .iter()
+            .enumerate()
+            .map(|(i, _)| Ok(locations[&dsu.find(i)?]))
+            .collect()
+    }
+
+    fn locate(&self, split: &SplitMeshes, piece: &Piece, ids: &[usize]) -> MeshResult<Location> {
+        let [a, b, c] = piece.face.map(|i| split.points[i]);
+        let centroid = (a + b + c) / 3.0;
+        let normal = (b - a).cross(c - a);
+        let tol = a.distance(b).max(a.distance(c)) * 1e-6;
+
+        for (i, flag) in self.flags.iter().enumerate() {
+            if *flag == piece.flag {
+                continue;
+            }
+            let tri = self.tri(i)?;
+            let Some(n) = tri.normal() else {
+                continue;
+            };
+            if n.dot(centroid - tri.p[0]).abs() <= tol && tri.contains(centroid, tol) {
+                return Ok(if n.dot(normal) > 0.0 {
+                    Location::OnSame
+                } else {
+                    Location::OnOpposite
+                });
+            }
+        }
+
+        if self.contains(centroid.as_vec3().into(), piece.flag, ids)? {
+            Ok(Location::Inside)
+        } else {
+            Ok(Location::Outside)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::mesh::shape::cuboid::rect_cuboid::RectCuboid;
+    use crate::mesh::bool::analyzer::{Location, MeshBoolAnalyzer};
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use glam::Vec3;
 
     #[test]
-    fn smoke() {
-        let cube = RectCuboid::default();
+    fn split_crossing_cubes() {
+        let a = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default());
+        let b = Cube::create([0.5, 0.5, 0.5], 1.0, FaceType::Triangle, Color::default());
+        let mut analyzer = MeshBoolAnalyzer::new(&a).unwrap();
+        analyzer.prepare(&b).unwrap();
+
+        let split = analyzer.split().unwrap();
+        assert!(split.pieces.len() > 24);
+
+        let locations = analyzer.classify(&split).unwrap();
+        assert!(locations.contains(&Location::Inside));
+        assert!(locations.contains(&Location::Outside));
+    }
+
+    #[test]
+    fn segment_through_vertex_and_edge() {
+        let cube = Cube::create([0.5, 0.5, 0.5], 1.0, FaceType::Triangle, Color::default());
+        let mut analyzer = MeshBoolAnalyzer::new(&cube).unwrap();
+        analyzer.prepare(&cube).unwrap();
+        let ids = analyzer.symbolic_ids();
+
+        // the same far point as `contains` picks for the unit cube
+        let far = Vec3::ONE + (Vec3::ONE + Vec3::ONE) * Vec3::new(1.0, 0.37, 0.71);
+        // the segment from the point to the far one goes exactly through the given point of the surface:
+        // a corner, an edge and a diagonal of the side
+        for on_surface in [[1.0, 1.0, 1.0], [1.0, 1.0, 0.5], [1.0, 0.5, 0.5]] {
+            let point = Vec3::from(on_surface) * 2.0 - far;
+            assert!(!analyzer.contains(point.into(), 0, &ids).unwrap());
+        }
+        assert!(analyzer.contains([0.5, 0.5, 0.5].into(), 0, &ids).unwrap());
     }
 }
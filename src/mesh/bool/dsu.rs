@@ -1,6 +1,6 @@
 use crate::mesh::{MeshError, MeshResult};
 
-struct UnionFindSet {
+pub(crate) struct UnionFindSet {
     ids: Vec<usize>,
     rank: Vec<usize>,
 }
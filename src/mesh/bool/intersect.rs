@@ -0,0 +1,322 @@
+use crate::mesh::parts::polygon::intersection::{
+    point_in_triangle_3d, triangle_intersects_triangle, SimplexIntersection,
+};
+use crate::mesh::parts::predicates::{orient3d, Sign};
+use crate::mesh::parts::vertex::Vertex;
+use glam::DVec3;
+use std::collections::HashMap;
+
+/// The symbolic identity of a point produced while intersecting two meshes.
+/// Every triangle sharing the same edge computes the same key for the same crossing,
+/// what keeps the split faces connected along the intersection curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum PointKey {
+    /// An original vertex of the merged vertex soup.
+    Vertex(usize),
+    /// An edge (sorted vertex indexes) crossing a face.
+    EdgeFace(usize, usize, usize),
+    /// Two coplanar edges (sorted vertex indexes) crossing each other.
+    EdgeEdge((usize, usize), (usize, usize)),
+}
+
+impl PointKey {
+    fn edge_face(a: usize, b: usize, face: usize) -> Self {
+        PointKey::EdgeFace(a.min(b), a.max(b), face)
+    }
+    fn edge_edge(lhs: (usize, usize), rhs: (usize, usize)) -> Self {
+        let lhs = (lhs.0.min(lhs.1), lhs.0.max(lhs.1));
+        let rhs = (rhs.0.min(rhs.1), rhs.0.max(rhs.1));
+        PointKey::EdgeEdge(lhs.min(rhs), lhs.max(rhs))
+    }
+}
+
+/// Stores the points of the boolean operation.
+/// Points are looked up by the symbolic key first and then welded by the distance
+/// so the degenerated crossings (an edge going through a vertex etc.) collapse into one point.
+pub(crate) struct PointPool {
+    points: Vec<DVec3>,
+    keys: HashMap<PointKey, usize>,
+    grid: HashMap<(i64, i64, i64), Vec<usize>>,
+    tol: f64,
+}
+
+impl PointPool {
+    pub(crate) fn new(tol: f64) -> Self {
+        Self {
+            points: Vec::new(),
+            keys: HashMap::new(),
+            grid: HashMap::new(),
+            tol,
+        }
+    }
+
+    pub(crate) fn tol(&self) -> f64 {
+        self.tol
+    }
+
+    pub(crate) fn get(&self, idx: usize) -> DVec3 {
+        self.points[idx]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub(crate) fn to_vertices(&self) -> Vec<Vertex> {
+        self.points.iter().map(|p| p.as_vec3().into()).collect()
+    }
+
+    fn cell(&self, p: DVec3) -> (i64, i64, i64) {
+        let cell = (p / (self.tol * 2.0)).floor();
+        (cell.x as i64, cell.y as i64, cell.z as i64)
+    }
+
+    pub(crate) fn insert(&mut self, key: PointKey, p: DVec3) -> usize {
+        if let Some(idx) = self.keys.get(&key) {
+            return *idx;
+        }
+        let (cx, cy, cz) = self.cell(p);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(ids) = self.grid.get(&(cx + dx, cy + dy, cz + dz)) {
+                        if let Some(&idx) =
+                            ids.iter().find(|&&i| self.points[i].distance(p) <= self.tol)
+                        {
+                            self.keys.insert(key, idx);
+                            return idx;
+                        }
+                    }
+                }
+            }
+        }
+        let idx = self.points.len();
+        self.points.push(p);
+        self.keys.insert(key, idx);
+        self.grid.entry((cx, cy, cz)).or_default().push(idx);
+        idx
+    }
+}
+
+/// A triangle of the merged soup.
+/// The original vertices feed the exact predicates, the double precision copies
+/// are used to construct the new points.
+#[derive(Debug, Clone)]
+pub(crate) struct Tri {
+    pub(crate) face: usize,
+    pub(crate) idx: [usize; 3],
+    pub(crate) v: [Vertex; 3],
+    pub(crate) p: [DVec3; 3],
+}
+
+impl Tri {
+    pub(crate) fn new(face: usize, idx: [usize; 3], vs: [&Vertex; 3]) -> Self {
+        let v = vs.map(|v| *v);
+        let p = v.map(|v| Into::<glam::Vec3>::into(v).as_dvec3());
+        Self { face, idx, v, p }
+    }
+
+    /// The unit normal or None if the triangle is degenerated.
+    pub(crate) fn normal(&self) -> Option<DVec3> {
+        (self.p[1] - self.p[0]).cross(self.p[2] - self.p[0]).try_normalize()
+    }
+
+    pub(crate) fn min(&self) -> DVec3 {
+        self.p[0].min(self.p[1]).min(self.p[2])
+    }
+
+    pub(crate) fn max(&self) -> DVec3 {
+        self.p[0].max(self.p[1]).max(self.p[2])
+    }
+
+    /// Checks if the constructed point lying in the plane of the triangle is inside it (boundary included).
+    /// The point is not exact, so it is tested against the edges with the tolerance.
+    pub(crate) fn contains(&self, x: DVec3, tol: f64) -> bool {
+        let Some(n) = self.normal() else {
+            return false;
+        };
+        (0..3).all(|i| {
+            let (a, b) = (self.p[i], self.p[(i + 1) % 3]);
+            let e = b - a;
+            // the signed distance to the edge line, positive inside
+            n.dot(e.cross(x - a)) / e.length().max(f64::EPSILON) >= -tol
+        })
+    }
+
+    /// Checks exactly whether the edge `a b` of another triangle meets this one,
+    /// given that the ends of the edge lie on the different sides of its plane.
+    fn crossed_by(&self, a: &Vertex, b: &Vertex) -> bool {
+        let [s0, s1, s2] = [0, 1, 2].map(|i| {
+            Sign::of(orient3d(a, b, &self.v[i], &self.v[(i + 1) % 3])).as_i8()
+        });
+        (s0 >= 0 && s1 >= 0 && s2 >= 0) || (s0 <= 0 && s1 <= 0 && s2 <= 0)
+    }
+}
+
+/// The way two triangles from different meshes meet each other.
+pub(crate) enum TriTri {
+    None,
+    /// The triangles touch each other in a single point.
+    Point(usize),
+    Segment(usize, usize),
+    Coplanar,
+}
+
+/// The exact sides of the vertices of `tri` relatively to the plane of `plane`,
+/// positive along its normal, along with the values (proportional to the distances)
+/// to interpolate the crossings.
+fn signed_distances(plane: &Tri, tri: &Tri) -> ([f64; 3], [i8; 3]) {
+    let [a, b, c] = &plane.v;
+    // orient3d is positive below the counterclockwise triangle
+    let d = tri.v.map(|p| -orient3d(a, b, c, &p));
+    (d, d.map(|d| Sign::of(d).as_i8()))
+}
+
+/// Collects the points where the triangle `lhs` pierces the triangle `rhs`:
+/// the vertices of `lhs` lying on `rhs` and the edges of `lhs` crossing `rhs`.
+fn piercing_points(pool: &mut PointPool, lhs: &Tri, rhs: &Tri, res: &mut Vec<usize>) {
+    let (d, s) = signed_distances(rhs, lhs);
+    let [t0, t1, t2] = &rhs.v;
+    for (i, side) in s.iter().enumerate() {
+        if *side == 0 && point_in_triangle_3d(&lhs.v[i], t0, t1, t2).is_inside() {
+            res.push(pool.insert(PointKey::Vertex(lhs.idx[i]), lhs.p[i]));
+        }
+    }
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if s[i] * s[j] < 0 && rhs.crossed_by(&lhs.v[i], &lhs.v[j]) {
+            // always go from the lower index to the higher one
+            // so the neighbour faces get the bitwise same point
+            let (a, b) = if lhs.idx[i] < lhs.idx[j] { (i, j) } else { (j, i) };
+            let t = d[a] / (d[a] - d[b]);
+            let x = lhs.p[a].lerp(lhs.p[b], t);
+            let key = PointKey::edge_face(lhs.idx[a], lhs.idx[b], rhs.face);
+            res.push(pool.insert(key, x));
+        }
+    }
+}
+
+/// Finds the segment along which two triangles intersect.
+/// The sides and the crossings are decided by the exact predicates,
+/// only the positions of the new points are rounded.
+pub(crate) fn intersect(pool: &mut PointPool, lhs: &Tri, rhs: &Tri) -> TriTri {
+    if lhs.normal().is_none() || rhs.normal().is_none() {
+        return TriTri::None;
+    }
+    let [a0, a1, a2] = &lhs.v;
+    let [b0, b1, b2] = &rhs.v;
+    if let Ok(SimplexIntersection::DoNotIntersect) =
+        triangle_intersects_triangle((a0, a1, a2), (b0, b1, b2))
+    {
+        return TriTri::None;
+    }
+
+    let (_, sl) = signed_distances(rhs, lhs);
+    let (_, sr) = signed_distances(lhs, rhs);
+    if sl.iter().all(|s| *s == 0) || sr.iter().all(|s| *s == 0) {
+        return TriTri::Coplanar;
+    }
+    if sl.iter().all(|s| *s == sl[0]) || sr.iter().all(|s| *s == sr[0]) {
+        return TriTri::None;
+    }
+
+    let mut points = vec![];
+    piercing_points(pool, lhs, rhs, &mut points);
+    piercing_points(pool, rhs, lhs, &mut points);
+    points.sort();
+    points.dedup();
+
+    match points[..] {
+        [] => return TriTri::None,
+        [p] => return TriTri::Point(p),
+        _ => {}
+    }
+    let mut best = (points[0], points[1]);
+    let mut best_dist = -1.0;
+    for (i, a) in points.iter().enumerate() {
+        for b in points.iter().skip(i + 1) {
+            let d = pool.get(*a).distance(pool.get(*b));
+            if d > best_dist {
+                best_dist = d;
+                best = (*a, *b);
+            }
+        }
+    }
+    TriTri::Segment(best.0, best.1)
+}
+
+/// Clips the edges of `rhs` by the coplanar triangle `lhs`.
+/// The resulting segments split `lhs` into the parts covered and not covered by `rhs`.
+pub(crate) fn coplanar_segments(pool: &mut PointPool, lhs: &Tri, rhs: &Tri) -> Vec<(usize, usize)> {
+    let Some(n) = lhs.normal() else {
+        return vec![];
+    };
+    let tol = pool.tol();
+    let mut res = vec![];
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        let (s0, s1) = (rhs.p[i], rhs.p[j]);
+        let dir = s1 - s0;
+        let len = dir.length();
+        if len <= tol {
+            continue;
+        }
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        let (mut e0, mut e1) = (None, None);
+        let mut empty = false;
+        for k in 0..3 {
+            let a = lhs.p[k];
+            let e = lhs.p[(k + 1) % 3] - a;
+            let elen = e.length().max(f64::EPSILON);
+            // signed distance to the edge line as a linear function of t
+            let f0 = n.dot(e.cross(s0 - a)) / elen;
+            let df = n.dot(e.cross(dir)) / elen;
+            if df.abs() <= f64::EPSILON {
+                if f0 < -tol {
+                    empty = true;
+                    break;
+                }
+                continue;
+            }
+            let t = -f0 / df;
+            let edge = (lhs.idx[k], lhs.idx[(k + 1) % 3]);
+            if df > 0.0 {
+                if t > t0 {
+                    t0 = t;
+                    e0 = Some(edge);
+                }
+            } else if t < t1 {
+                t1 = t;
+                e1 = Some(edge);
+            }
+        }
+        if empty || (t1 - t0) * len <= tol {
+            continue;
+        }
+        let own = (rhs.idx[i], rhs.idx[j]);
+        let mut endpoint = |t: f64, edge: Option<(usize, usize)>, vert: usize, p: DVec3| match edge {
+            Some(edge) if t * len > tol && (1.0 - t) * len > tol => {
+                pool.insert(PointKey::edge_edge(own, edge), s0 + dir * t)
+            }
+            _ => pool.insert(PointKey::Vertex(vert), p),
+        };
+        let a = endpoint(t0, e0, own.0, s0);
+        let b = endpoint(t1, e1, own.1, s1);
+        if a != b {
+            res.push((a, b));
+        }
+    }
+    res
+}
+
+/// Picks the two axes to project a plane with the given normal onto.
+pub(crate) fn drop_axis(n: DVec3) -> (usize, usize) {
+    let ax = n.abs();
+    if ax.x >= ax.y && ax.x >= ax.z {
+        (1, 2)
+    } else if ax.y >= ax.z {
+        (2, 0)
+    } else {
+        (0, 1)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::mesh::{MeshError, MeshResult};
+use glam::DVec2;
+use std::collections::HashSet;
+
+/// The result of the constrained triangulation of a single face.
+pub(crate) struct FaceTriangulation {
+    /// Triangles (indexes of the given points) in the counter-clockwise order.
+    pub(crate) triangles: Vec<[usize; 3]>,
+    /// The constraints split by the points lying on them.
+    pub(crate) cuts: Vec<(usize, usize)>,
+}
+
+fn orient(a: &DVec2, b: &DVec2, c: &DVec2) -> f64 {
+    (*b - *a).perp_dot(*c - *a)
+}
+
+fn len(a: &DVec2, b: &DVec2) -> f64 {
+    a.distance(*b)
+}
+
+/// The side of the line `ab` the point `c` lies on, zero if it is closer than `tol` to the line.
+fn side(a: &DVec2, b: &DVec2, c: &DVec2, tol: f64) -> i8 {
+    let d = orient(a, b, c) / len(a, b).max(f64::EPSILON);
+    if d > tol {
+        1
+    } else if d < -tol {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Triangulates the triangle given by the first three points
+/// so that every constraint becomes a chain of edges of the triangulation.
+///
+/// The triangulation is built greedily: the constraints and the boundary go first,
+/// then the rest of the point pairs ordered by length are added unless they cross
+/// an accepted edge. The maximal planar graph is a triangulation of the triangle
+/// and its faces are the empty 3-cycles.
+///
+/// Every pair of points is checked against the accepted edges, that is O(n³)
+/// in the number of the points on the face. It is fine for the few cuts a face usually gets
+/// but does not scale to a face crossed by many triangles of the other mesh.
+///
+/// The constraints crossing each other (or the boundary) away from the given points
+/// can not be kept, the error is returned then.
+pub(crate) fn triangulate(
+    points: &[DVec2],
+    constraints: &[(usize, usize)],
+    tol: f64,
+) -> MeshResult<FaceTriangulation> {
+    let n = points.len();
+    let on_segment = |k: usize, a: usize, b: usize| -> Option<f64> {
+        if k == a || k == b {
+            return None;
+        }
+        let (pa, pb, pk) = (&points[a], &points[b], &points[k]);
+        let l = len(pa, pb);
+        if l <= tol || side(pa, pb, pk, tol) != 0 {
+            return None;
+        }
+        let t = (*pk - *pa).dot(*pb - *pa) / l;
+        if t > tol && t < l - tol {
+            Some(t)
+        } else {
+            None
+        }
+    };
+    let split = |a: usize, b: usize| -> Vec<(usize, usize)> {
+        let mut inner: Vec<(f64, usize)> =
+            (0..n).filter_map(|k| on_segment(k, a, b).map(|t| (t, k))).collect();
+        inner.sort_by(|l, r| l.0.total_cmp(&r.0));
+        let chain: Vec<usize> = std::iter::once(a)
+            .chain(inner.into_iter().map(|(_, k)| k))
+            .chain(std::iter::once(b))
+            .collect();
+        chain.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let norm = |(a, b): (usize, usize)| (a.min(b), a.max(b));
+
+    let mut cuts = vec![];
+    let mut fixed = vec![];
+    for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+        fixed.extend(split(a, b).into_iter().map(norm));
+    }
+    for &(a, b) in constraints {
+        if a != b {
+            let parts: Vec<_> = split(a, b).into_iter().map(norm).collect();
+            cuts.extend(parts.iter().cloned());
+            fixed.extend(parts);
+        }
+    }
+
+    let crosses = |(a, b): (usize, usize), (c, d): (usize, usize)| -> bool {
+        if a == c || a == d || b == c || b == d {
+            return false;
+        }
+        let (pa, pb, pc, pd) = (&points[a], &points[b], &points[c], &points[d]);
+        side(pa, pb, pc, tol) * side(pa, pb, pd, tol) < 0
+            && side(pc, pd, pa, tol) * side(pc, pd, pb, tol) < 0
+    };
+
+    let mut accepted: Vec<(usize, usize)> = vec![];
+    let mut present = HashSet::new();
+    let mut adj = vec![HashSet::new(); n];
+    let mut accept = |e: (usize, usize), accepted: &mut Vec<(usize, usize)>| {
+        if e.0 != e.1 && present.insert(e) {
+            accepted.push(e);
+            adj[e.0].insert(e.1);
+            adj[e.1].insert(e.0);
+        }
+    };
+
+    for e in fixed {
+        if let Some(o) = accepted.iter().find(|o| crosses(e, **o)) {
+            return Err(MeshError::Custom(format!(
+                "the cut {:?} crosses the edge {:?} of the split face",
+                e, o
+            )));
+        }
+        accept(e, &mut accepted);
+    }
+
+    let mut candidates = vec![];
+    for a in 0..n {
+        for b in a + 1..n {
+            if len(&points[a], &points[b]) > tol {
+                candidates.push((a, b));
+            }
+        }
+    }
+    candidates.sort_by(|l, r| {
+        len(&points[l.0], &points[l.1]).total_cmp(&len(&points[r.0], &points[r.1]))
+    });
+    for e in candidates {
+        if (0..n).any(|k| on_segment(k, e.0, e.1).is_some())
+            || accepted.iter().any(|o| crosses(e, *o))
+        {
+            continue;
+        }
+        accept(e, &mut accepted);
+    }
+
+    let mut triangles = vec![];
+    for &(a, b) in accepted.iter() {
+        for &c in adj[a].iter() {
+            if c <= b || !adj[b].contains(&c) {
+                continue;
+            }
+            let (pa, pb, pc) = (&points[a], &points[b], &points[c]);
+            let area = orient(pa, pb, pc);
+            let longest = len(pa, pb).max(len(pb, pc)).max(len(pc, pa));
+            if area.abs() <= tol * longest {
+                continue;
+            }
+            let tri = if area > 0.0 { [a, b, c] } else { [a, c, b] };
+            let empty = (0..n).filter(|k| !tri.contains(k)).all(|k| {
+                let pk = &points[k];
+                let inside = (0..3).all(|i| {
+                    side(&points[tri[i]], &points[tri[(i + 1) % 3]], pk, tol) > 0
+                });
+                !inside
+            });
+            if empty {
+                triangles.push(tri);
+            }
+        }
+    }
+
+    Ok(FaceTriangulation { triangles, cuts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraint_is_kept() {
+        let points = [[0.0, 0.0], [4.0, 0.0], [0.0, 4.0], [1.0, 0.0], [1.0, 3.0]].map(DVec2::from_array);
+        let res = triangulate(&points, &[(3, 4)], 1e-9).unwrap();
+        let area: f64 = res
+            .triangles
+            .iter()
+            .map(|t| orient(&points[t[0]], &points[t[1]], &points[t[2]]) / 2.0)
+            .sum();
+        assert!((area - 8.0).abs() < 1e-9);
+        assert!(res
+            .triangles
+            .iter()
+            .any(|t| t.contains(&3) && t.contains(&4)));
+        assert_eq!(res.cuts, vec![(3, 4)]);
+    }
+
+    #[test]
+    fn crossing_constraints_are_refused() {
+        // the cuts 3-4 and 5-6 cross at (1, 1) that is not among the points
+        let points = [[0.0, 0.0], [4.0, 0.0], [0.0, 4.0], [1.0, 0.5], [1.0, 2.0], [0.5, 1.0]];
+        let points = [points.to_vec(), vec![[2.0, 1.0]]].concat();
+        let points: Vec<DVec2> = points.into_iter().map(DVec2::from_array).collect();
+        assert!(triangulate(&points, &[(3, 4), (5, 6)], 1e-9).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::mesh::material::{Color, RgbaColor};
+use crate::mesh::parts::bbox::BoundingBox;
+use crate::mesh::parts::edge::MeshEdge;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The tolerance, relative to the size of the mesh, to consider a vertex lying on the cut.
+const SNAP_EPS: f32 = 1e-6;
+/// The maximum number of steps to find the cut point on an edge for the curved surfaces.
+const ROOT_STEPS: usize = 32;
+
+/// The side of the cutting surface that is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipSide {
+    /// The side with the positive distance, i.e. in the direction of the normal of a plane
+    /// or outside of a box.
+    Front,
+    /// The side with the negative distance, i.e. behind a plane or inside a box.
+    #[default]
+    Back,
+}
+
+/// The settings of the clipping.
+///
+/// # Example
+/// ```rust
+/// use glam::Vec3;
+/// use tessellate::mesh::clip::{ClipOptions, ClipSide};
+/// use tessellate::mesh::parts::plane::Plane;
+/// use tessellate::mesh::shape::sphere::Sphere;
+/// use tessellate::mesh::HasMesh;
+///
+/// let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Default::default()).unwrap();
+/// let plane = Plane::new(Vec3::Z, Vec3::ZERO);
+/// let open = sphere.mesh().clip_by_plane(&plane, ClipSide::Back).unwrap();
+/// let closed = sphere.mesh().clip_by_plane(&plane, ClipOptions::new(ClipSide::Back).with_cap(true)).unwrap();
+/// assert!(!open.properties().is_watertight());
+/// assert!(closed.properties().is_watertight());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClipOptions {
+    keep: ClipSide,
+    cap: bool,
+}
+
+impl From<ClipSide> for ClipOptions {
+    fn from(keep: ClipSide) -> Self {
+        ClipOptions::new(keep)
+    }
+}
+
+impl ClipOptions {
+    pub fn new(keep: ClipSide) -> Self {
+        ClipOptions { keep, cap: false }
+    }
+
+    /// Closes the cut with the cap faces, the mesh to clip should be watertight.
+    /// Only the planar cuts can be capped.
+    pub fn with_cap(mut self, cap: bool) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn keep(&self) -> ClipSide {
+        self.keep
+    }
+
+    pub fn cap(&self) -> bool {
+        self.cap
+    }
+}
+
+/// The signed distance to the surface of the box, negative inside.
+pub fn box_distance(bb: &BoundingBox, v: &Vertex) -> f32 {
+    let (min, max) = (bb.min(), bb.max());
+    let q = [
+        (min.x - v.x).max(v.x - max.x),
+        (min.y - v.y).max(v.y - max.y),
+        (min.z - v.z).max(v.z - max.z),
+    ];
+    let outside = q.iter().map(|d| d.max(0.0).powi(2)).sum::<f32>().sqrt();
+    let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+    outside + inside
+}
+
+/// Clips the mesh by the surface where the distance function is zero.
+///
+/// The faces crossing the surface are split along the cut,
+/// the cut points are interpolated on the edges where the distance changes its sign.
+/// The vertex fields and the vertex colors are interpolated for the cut points,
+/// the split faces keep the face fields and the face colors of the source faces.
+///
+/// The cap fills every loop of the cut with the triangles (ear clipping),
+/// so the loops with holes in them (e.g. the section of a tube) are not capped correctly.
+/// The cap faces take the face values of a face along the loop.
+pub(crate) fn clip<F>(mesh: &Mesh, distance: F, options: ClipOptions) -> MeshResult<Mesh>
+where
+    F: Fn(&Vertex) -> f32,
+{
+    if !mesh.is_polygons() {
+        return Err(MeshError::Custom("Only the meshes of polygons can be clipped".to_string()));
+    }
+    if options.cap && !mesh.properties().is_watertight() {
+        return Err(MeshError::Custom("The cut can be capped only for a watertight mesh".to_string()));
+    }
+    let sign = match options.keep {
+        ClipSide::Front => 1.0,
+        ClipSide::Back => -1.0,
+    };
+    let level = |v: &Vertex| sign * distance(v);
+    let bb = mesh.aabb();
+    let eps = SNAP_EPS * bb.max().distance(bb.min()).max(1.0);
+    let levels: Vec<f32> = mesh
+        .vertices()
+        .iter()
+        .map(|v| {
+            let l = level(v);
+            if l.abs() <= eps { 0.0 } else { l }
+        })
+        .collect();
+
+    let mut cut = Cut {
+        mesh,
+        sources: vec![],
+        vertices: vec![],
+        by_vertex: HashMap::new(),
+        by_edge: HashMap::new(),
+    };
+    let mut faces = vec![];
+    let mut origins = vec![];
+    for (idx, face) in mesh.faces().iter().enumerate() {
+        let vs = face.flatten();
+        let mut polygon = vec![];
+        if vs.iter().all(|v| levels[*v] >= 0.0) {
+            polygon = vs.iter().map(|v| cut.vertex(*v)).collect();
+        } else if vs.iter().any(|v| levels[*v] > 0.0) {
+            for (i, a) in vs.iter().enumerate() {
+                let b = vs[(i + 1) % vs.len()];
+                if levels[*a] >= 0.0 {
+                    polygon.push(cut.vertex(*a));
+                }
+                if levels[*a] * levels[b] < 0.0 {
+                    polygon.push(cut.edge(*a, b, &levels, &level, eps));
+                }
+            }
+        }
+        if polygon.len() >= 3 {
+            let split = Face::new(polygon)?;
+            origins.extend(split.iter().map(|_| idx));
+            faces.extend(split);
+        }
+    }
+
+    if options.cap {
+        let (cap_faces, cap_origins) = cap(&cut.vertices, &faces, &origins);
+        faces.extend(cap_faces);
+        origins.extend(cap_origins);
+    }
+
+    let mut attributes = mesh.attributes().clone();
+    match mesh.attributes().color() {
+        Color::Vertex(colors) if colors.len() == mesh.vertices().len() => attributes.set_color(
+            Color::Vertex(cut.sources.iter().map(|(a, b, t)| lerp_color(&colors[*a], &colors[*b], *t)).collect()),
+        ),
+        Color::Face(colors) if colors.len() == mesh.faces().len() => {
+            attributes.set_color(Color::Face(origins.iter().map(|f| colors[*f].clone()).collect()))
+        }
+        _ => {}
+    }
+    let mut result = Mesh::from_vertices(cut.vertices, faces, attributes);
+    result.fields = mesh.fields().interpolate(&cut.sources, &origins);
+    Ok(result)
+}
+
+/// Finds the lines where the mesh crosses the surface with the zero distance.
+///
+/// Every face crossing the surface gives the segments between its cut points,
+/// the edges lying on the surface are taken once.
+/// The vertex fields are interpolated for the points of the lines.
+/// Returns the lines mesh, it has no edges when the mesh does not cross the surface.
+pub(crate) fn contour<F>(mesh: &Mesh, distance: F) -> MeshResult<Mesh>
+where
+    F: Fn(&Vertex) -> f32,
+{
+    if !mesh.is_polygons() {
+        return Err(MeshError::Custom("Only the meshes of polygons can be cut".to_string()));
+    }
+    let bb = mesh.aabb();
+    let eps = SNAP_EPS * bb.max().distance(bb.min()).max(1.0);
+    let levels: Vec<f32> = mesh
+        .vertices()
+        .iter()
+        .map(|v| {
+            let l = distance(v);
+            if l.abs() <= eps { 0.0 } else { l }
+        })
+        .collect();
+
+    let mut cut = Cut {
+        mesh,
+        sources: vec![],
+        vertices: vec![],
+        by_vertex: HashMap::new(),
+        by_edge: HashMap::new(),
+    };
+    let mut edges = vec![];
+    let mut seen = HashSet::new();
+    for face in mesh.faces() {
+        let vs = face.flatten();
+        if vs.iter().all(|v| levels[*v] == 0.0) {
+            continue;
+        }
+        let mut points = vec![];
+        for (i, a) in vs.iter().enumerate() {
+            let b = vs[(i + 1) % vs.len()];
+            if levels[*a] == 0.0 {
+                points.push(cut.vertex(*a));
+            }
+            if levels[*a] * levels[b] < 0.0 {
+                points.push(cut.edge(*a, b, &levels, &distance, eps));
+            }
+        }
+        // the points go along the face, so a concave face gives several segments
+        for pair in points.chunks_exact(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if a != b && seen.insert((a.min(b), a.max(b))) {
+                edges.push(MeshEdge::new(a, b));
+            }
+        }
+    }
+
+    let mut attributes = mesh.attributes().clone();
+    match mesh.attributes().color() {
+        Color::Mesh(_) => {}
+        Color::Vertex(colors) if colors.len() == mesh.vertices().len() => attributes.set_color(
+            Color::Vertex(cut.sources.iter().map(|(a, b, t)| lerp_color(&colors[*a], &colors[*b], *t)).collect()),
+        ),
+        _ => attributes.set_color(Color::default()),
+    }
+    let mut result = Mesh::from_edges(cut.vertices, edges, attributes)?;
+    result.fields = mesh.fields().interpolate(&cut.sources, &[]);
+    Ok(result)
+}
+
+/// The vertices of the clipped mesh with their sources in the original one.
+struct Cut<'a> {
+    mesh: &'a Mesh,
+    sources: Vec<(usize, usize, f32)>,
+    vertices: Vec<Vertex>,
+    by_vertex: HashMap<usize, usize>,
+    by_edge: HashMap<(usize, usize), usize>,
+}
+
+impl Cut<'_> {
+    fn vertex(&mut self, v: usize) -> usize {
+        *self.by_vertex.entry(v).or_insert_with(|| {
+            self.vertices.push(self.mesh.vertices()[v]);
+            self.sources.push((v, v, 0.0));
+            self.vertices.len() - 1
+        })
+    }
+
+    /// The cut point on the edge, shared by the faces on both sides of the edge.
+    fn edge<F>(&mut self, a: usize, b: usize, levels: &[f32], level: &F, eps: f32) -> usize
+    where
+        F: Fn(&Vertex) -> f32,
+    {
+        let (a, b) = (a.min(b), a.max(b));
+        if let Some(idx) = self.by_edge.get(&(a, b)) {
+            return *idx;
+        }
+        let (p, q) = (self.mesh.vertices()[a], self.mesh.vertices()[b]);
+        let t = find_root(|t| level(&(p + (q - p) * t)), levels[a], levels[b], eps);
+        self.vertices.push(p + (q - p) * t);
+        self.sources.push((a, b, t));
+        let idx = self.vertices.len() - 1;
+        self.by_edge.insert((a, b), idx);
+        idx
+    }
+}
+
+/// Finds the zero of the function between 0 and 1 by the false position method (the Illinois variant),
+/// the values at the ends have the different signs.
+/// The linear functions, like the distance to a plane, are solved in the first step.
+fn find_root<F: Fn(f32) -> f32>(func: F, start: f32, end: f32, eps: f32) -> f32 {
+    let (mut lo, mut hi, mut f_lo, mut f_hi) = (0.0, 1.0, start, end);
+    let mut t = f_lo / (f_lo - f_hi);
+    let mut side = 0;
+    for _ in 0..ROOT_STEPS {
+        let f = func(t);
+        if f.abs() <= eps {
+            break;
+        }
+        if (f > 0.0) == (f_lo > 0.0) {
+            lo = t;
+            f_lo = f;
+            // the end kept twice is pulled closer to speed up the convergence
+            if side == -1 {
+                f_hi *= 0.5;
+            }
+            side = -1;
+        } else {
+            hi = t;
+            f_hi = f;
+            if side == 1 {
+                f_lo *= 0.5;
+            }
+            side = 1;
+        }
+        t = lo + (hi - lo) * f_lo / (f_lo - f_hi);
+    }
+    t
+}
+
+pub(crate) fn lerp_color(lhs: &RgbaColor, rhs: &RgbaColor, t: f32) -> RgbaColor {
+    let mut rgba = [0u8; 4];
+    for (i, c) in rgba.iter_mut().enumerate() {
+        let (l, r) = (lhs.0[i] as f32, rhs.0[i] as f32);
+        *c = (l + (r - l) * t).round() as u8;
+    }
+    RgbaColor(rgba)
+}
+
+/// Fills the boundary loops of the faces with the triangles.
+/// Every loop goes against the boundary edge of its face it starts from,
+/// so the caps keep the orientation of the mesh.
+/// Returns the cap faces with the origins taken from the faces along the loops.
+fn cap(vertices: &[Vertex], faces: &[Face], origins: &[usize]) -> (Vec<Face>, Vec<usize>) {
+    let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+    for face in faces {
+        for MeshEdge(a, b) in face.edges() {
+            *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    // the boundary edges in the direction of their faces
+    let mut directed = HashMap::new();
+    let mut neighbours: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (face, origin) in faces.iter().zip(origins) {
+        for MeshEdge(a, b) in face.edges() {
+            if edge_count[&(a.min(b), a.max(b))] == 1 {
+                directed.insert((a, b), *origin);
+                neighbours.entry(a).or_default().push(b);
+                neighbours.entry(b).or_default().push(a);
+            }
+        }
+    }
+
+    let mut cap_faces = vec![];
+    let mut cap_origins = vec![];
+    let starts: Vec<usize> = neighbours.keys().copied().collect();
+    for start in starts {
+        while let Some(mut current) = take_edge(&mut neighbours, start) {
+            let mut boundary = vec![start];
+            while current != start {
+                boundary.push(current);
+                match take_edge(&mut neighbours, current) {
+                    Some(v) => current = v,
+                    None => break,
+                }
+            }
+            if current != start || boundary.len() < 3 {
+                continue;
+            }
+            let origin = match directed.get(&(boundary[0], boundary[1])) {
+                Some(origin) => {
+                    boundary.reverse();
+                    *origin
+                }
+                None => directed[&(boundary[1], boundary[0])],
+            };
+            for [a, b, c] in ear_clip(&boundary, vertices) {
+                cap_faces.push(Face::new3(a, b, c));
+                cap_origins.push(origin);
+            }
+        }
+    }
+    (cap_faces, cap_origins)
+}
+
+/// Removes the boundary edge going from the vertex, returning its other end.
+fn take_edge(neighbours: &mut BTreeMap<usize, Vec<usize>>, from: usize) -> Option<usize> {
+    let to = neighbours.get_mut(&from)?.pop()?;
+    if let Some(back) = neighbours.get_mut(&to) {
+        if let Some(pos) = back.iter().position(|v| *v == from) {
+            back.swap_remove(pos);
+        }
+    }
+    Some(to)
+}
+
+/// Splits the loop into the triangles keeping its orientation.
+/// The loop is projected to the plane of its Newell normal,
+/// the loops that are not simple in the projection are closed by a fan.
+fn ear_clip(boundary: &[usize], vertices: &[Vertex]) -> Vec<[usize; 3]> {
+    let mut normal = Vertex::default();
+    for (i, a) in boundary.iter().enumerate() {
+        let (p, q) = (vertices[*a], vertices[boundary[(i + 1) % boundary.len()]]);
+        normal = normal + p.cross(&q);
+    }
+    let mut rest = boundary.to_vec();
+    let mut triangles = vec![];
+    let turn = |a: usize, b: usize, c: usize| {
+        (vertices[b] - vertices[a]).cross(&(vertices[c] - vertices[b])).dot(&normal)
+    };
+    while rest.len() > 3 {
+        let n = rest.len();
+        let ear = (0..n).find(|i| {
+            let (a, b, c) = (rest[(i + n - 1) % n], rest[*i], rest[(i + 1) % n]);
+            turn(a, b, c) > 0.0
+                && rest.iter().all(|p| {
+                    *p == a
+                        || *p == b
+                        || *p == c
+                        || !(turn(a, b, *p) >= 0.0 && turn(b, c, *p) >= 0.0 && turn(c, a, *p) >= 0.0)
+                })
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([rest[(i + n - 1) % n], rest[i], rest[(i + 1) % n]]);
+                rest.remove(i);
+            }
+            None => break,
+        }
+    }
+    for i in 1..rest.len() - 1 {
+        triangles.push([rest[0], rest[i], rest[i + 1]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::fields::FieldData;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::parts::plane::Plane;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use glam::Vec3;
+
+    fn cube() -> Mesh {
+        let mut cube: Mesh = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Quad, Color::default()).into();
+        let heights = cube.vertices().iter().map(|v| v.z).collect();
+        cube.add_vertex_field("z", FieldData::Scalar(heights)).unwrap();
+        cube.add_face_field("id", FieldData::Integer((0..6).collect())).unwrap();
+        cube
+    }
+
+    #[test]
+    fn clip_by_plane_with_fields() {
+        let cube = cube();
+        let plane = Plane::new(Vec3::Z, Vec3::new(0.0, 0.0, 0.5));
+        let lower = cube.clip_by_plane(&plane, ClipSide::Back).unwrap();
+        assert!(lower.vertices().iter().all(|v| v.z <= 0.5 + 1e-6));
+        assert_eq!(lower.vertices().len(), 8);
+        // the bottom and the four split sides
+        assert_eq!(lower.faces().len(), 5);
+        assert!(!lower.properties().is_watertight());
+
+        let z = lower.fields().vertex("z").and_then(|f| f.as_scalar()).unwrap();
+        for (v, z) in lower.vertices().iter().zip(z) {
+            assert!((v.z - z).abs() < 1e-6);
+        }
+        assert_eq!(lower.fields().face("id").map(|f| f.len()), Some(5));
+
+        let upper = cube.clip_by_plane(&plane, ClipSide::Front).unwrap();
+        assert!(upper.vertices().iter().all(|v| v.z >= 0.5 - 1e-6));
+        assert_eq!(upper.faces().len(), 5);
+    }
+
+    #[test]
+    fn cap_keeps_watertight() {
+        let cube = cube();
+        let plane = Plane::new(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.2, 0.0, 0.0));
+        let options = ClipOptions::new(ClipSide::Back).with_cap(true);
+        let clipped = cube.clip_by_plane(&plane, options).unwrap();
+        assert!(clipped.properties().is_watertight());
+        let face_ids = clipped.fields().face("id").unwrap();
+        assert_eq!(face_ids.len(), clipped.faces().len());
+
+        let open = cube.clip_by_plane(&plane, ClipSide::Back).unwrap();
+        assert!(open.clip_by_plane(&plane, options).is_err());
+    }
+
+    #[test]
+    fn contour_by_plane() {
+        let cube = cube();
+        let section = cube.contour_by_plane(&Plane::new(Vec3::Z, Vec3::new(0.0, 0.0, 0.5))).unwrap();
+        assert!(section.is_lines());
+        assert_eq!(section.vertices().len(), 4);
+        assert_eq!(section.edges().len(), 4);
+        let z = section.fields().vertex("z").and_then(|f| f.as_scalar()).unwrap();
+        assert!(z.iter().all(|z| (z - 0.5).abs() < 1e-6));
+
+        // the edges of the top face are shared by the sides
+        let top = cube.contour_by_plane(&Plane::new(Vec3::Z, Vec3::new(0.0, 0.0, 1.0))).unwrap();
+        assert_eq!(top.edges().len(), 4);
+
+        let outside = cube.contour_by_plane(&Plane::new(Vec3::Z, Vec3::new(0.0, 0.0, 2.0))).unwrap();
+        assert!(outside.edges().is_empty());
+    }
+
+    #[test]
+    fn clip_by_box_and_sphere() {
+        let cube = cube();
+        let bb = BoundingBox::new(Vertex::new(-2.0, -2.0, -0.5), Vertex::new(2.0, 2.0, 0.5));
+        let slab = cube
+            .clip_by_box(&bb, ClipOptions::new(ClipSide::Back).with_cap(true))
+            .unwrap();
+        assert!(slab.properties().is_watertight());
+        assert!(slab.vertices().iter().all(|v| v.z.abs() <= 0.5 + 1e-6));
+        let shell = cube.clip_by_box(&bb, ClipSide::Front).unwrap();
+        assert!(!shell.faces().is_empty());
+        assert!(cube
+            .clip_by_box(&bb, ClipOptions::new(ClipSide::Front).with_cap(true))
+            .is_err());
+
+        // the corner of the cube cut by the sphere around it
+        let corner = Vertex::new(1.0, 1.0, 1.0);
+        let sphere = |v: &Vertex| v.distance(&corner) - 1.5;
+        let rounded = cube.clip_by(sphere, ClipSide::Back).unwrap();
+        assert_eq!(rounded.vertices().len(), 4);
+        assert!(rounded.vertices().iter().all(|v| *v == corner || sphere(v).abs() < 1e-4));
+        let zs = rounded.fields().vertex("z").and_then(|f| f.as_scalar()).unwrap();
+        assert!(rounded.vertices().iter().zip(zs).all(|(v, z)| (v.z - z).abs() < 1e-4));
+        assert!(cube.clip_by(|_| 1.0, ClipSide::Back).unwrap().faces().is_empty());
+        assert!(cube
+            .clip_by(sphere, ClipOptions::new(ClipSide::Back).with_cap(true))
+            .is_err());
+    }
+}
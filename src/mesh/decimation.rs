@@ -0,0 +1,592 @@
+use crate::mesh::clip::lerp_color;
+use crate::mesh::linalg::points;
+use crate::mesh::material::Color;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::query::edges::extract_feature_edges;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::{DMat3, DVec3};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// The smallest determinant of the quadric to find the optimal position by solving it.
+const SINGULAR_EPS: f64 = 1e-10;
+
+/// The number of the faces to keep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecimationTarget {
+    /// The number of the triangles left.
+    Faces(usize),
+    /// The part of the triangles left, from 0 to 1.
+    Ratio(f32),
+}
+
+impl From<usize> for DecimationTarget {
+    fn from(faces: usize) -> Self {
+        DecimationTarget::Faces(faces)
+    }
+}
+
+impl From<f32> for DecimationTarget {
+    fn from(ratio: f32) -> Self {
+        DecimationTarget::Ratio(ratio)
+    }
+}
+
+/// The settings of the decimation.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::decimation::DecimationOptions;
+/// use tessellate::mesh::shape::sphere::Sphere;
+/// use tessellate::mesh::HasMesh;
+///
+/// let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Default::default()).unwrap();
+/// let coarse = sphere.mesh().decimate(0.25).unwrap();
+/// assert!(coarse.faces().len() <= sphere.mesh().faces().len() / 4);
+///
+/// let options = DecimationOptions::new(100).with_feature_angle(Some(30.0));
+/// let coarse = sphere.mesh().decimate(options).unwrap();
+/// assert!(coarse.faces().len() <= 100);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecimationOptions {
+    target: DecimationTarget,
+    preserve_boundary: bool,
+    feature_angle: Option<f32>,
+    prevent_flips: bool,
+}
+
+impl From<usize> for DecimationOptions {
+    fn from(faces: usize) -> Self {
+        DecimationOptions::new(faces)
+    }
+}
+
+impl From<f32> for DecimationOptions {
+    fn from(ratio: f32) -> Self {
+        DecimationOptions::new(ratio)
+    }
+}
+
+impl DecimationOptions {
+    /// The options keeping the boundary and preventing the flips of the faces.
+    pub fn new<T: Into<DecimationTarget>>(target: T) -> Self {
+        DecimationOptions {
+            target: target.into(),
+            preserve_boundary: true,
+            feature_angle: None,
+            prevent_flips: true,
+        }
+    }
+
+    /// Keeps the vertices of the boundary edges in place.
+    pub fn with_preserve_boundary(mut self, preserve_boundary: bool) -> Self {
+        self.preserve_boundary = preserve_boundary;
+        self
+    }
+
+    /// Keeps the vertices of the feature edges, the edges with the dihedral angle
+    /// greater than the given one in degrees.
+    pub fn with_feature_angle(mut self, feature_angle: Option<f32>) -> Self {
+        self.feature_angle = feature_angle;
+        self
+    }
+
+    /// Rejects the collapses turning a face over.
+    pub fn with_prevent_flips(mut self, prevent_flips: bool) -> Self {
+        self.prevent_flips = prevent_flips;
+        self
+    }
+
+    pub fn target(&self) -> DecimationTarget {
+        self.target
+    }
+
+    pub fn preserve_boundary(&self) -> bool {
+        self.preserve_boundary
+    }
+
+    pub fn feature_angle(&self) -> Option<f32> {
+        self.feature_angle
+    }
+
+    pub fn prevent_flips(&self) -> bool {
+        self.prevent_flips
+    }
+}
+
+/// Reduces the number of the faces by collapsing the edges (Garland and Heckbert).
+///
+/// Every vertex keeps the quadric of the planes of its faces, the sum of the squared distances to them.
+/// The edges are collapsed in the order of the error of the merged vertex placed
+/// at the point minimizing the sum of the quadrics of the ends.
+/// The collapses changing the topology (the link condition) are rejected.
+///
+/// The mesh is triangulated first. The vertex fields and the vertex colors are interpolated
+/// for the merged vertices, the faces keep the face fields and the face colors.
+pub(crate) fn decimate(mesh: &Mesh, options: DecimationOptions) -> MeshResult<Mesh> {
+    if !mesh.is_polygons() {
+        return Err(MeshError::Custom("Only the meshes of polygons can be decimated".to_string()));
+    }
+    let mut origins = vec![];
+    let mut triangles = vec![];
+    for (idx, face) in mesh.faces().iter().enumerate() {
+        for triangle in face.triangulate() {
+            if let Face::Triangle(a, b, c) = triangle {
+                triangles.push([a, b, c]);
+                origins.push(idx);
+            }
+        }
+    }
+    let target = match options.target {
+        DecimationTarget::Faces(faces) => faces,
+        DecimationTarget::Ratio(ratio) => (triangles.len() as f32 * ratio.clamp(0.0, 1.0)).round() as usize,
+    };
+
+    let mut state = State::new(mesh, triangles)?;
+    state.lock(mesh, &options)?;
+    let mut heap = BinaryHeap::new();
+    for (a, b) in state.edges() {
+        if let Some(collapse) = state.collapse(a, b) {
+            heap.push(collapse);
+        }
+    }
+
+    while state.face_count > target {
+        let Some(collapse) = heap.pop() else { break };
+        if !state.is_actual(&collapse) || !state.can_collapse(&collapse, options.prevent_flips) {
+            continue;
+        }
+        let kept = state.apply(&collapse);
+        for n in state.neighbors(kept) {
+            if let Some(collapse) = state.collapse(kept, n) {
+                heap.push(collapse);
+            }
+        }
+    }
+    Ok(state.into_mesh(mesh, &origins))
+}
+
+/// The sum of the squared distances to the planes, `v·Av + 2b·v + c`.
+#[derive(Debug, Clone, Copy)]
+struct Quadric {
+    a: DMat3,
+    b: DVec3,
+    c: f64,
+}
+
+impl Quadric {
+    fn zero() -> Self {
+        Quadric {
+            a: DMat3::ZERO,
+            b: DVec3::ZERO,
+            c: 0.0,
+        }
+    }
+
+    /// The quadric of the plane through the point with the unit normal.
+    fn plane(normal: DVec3, point: DVec3) -> Self {
+        let d = -normal.dot(point);
+        Quadric {
+            a: DMat3::from_cols(normal * normal.x, normal * normal.y, normal * normal.z),
+            b: normal * d,
+            c: d * d,
+        }
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        Quadric {
+            a: self.a + other.a,
+            b: self.b + other.b,
+            c: self.c + other.c,
+        }
+    }
+
+    fn error(&self, v: DVec3) -> f64 {
+        v.dot(self.a * v) + 2.0 * self.b.dot(v) + self.c
+    }
+
+    /// The point with the smallest error, if the quadric is not singular.
+    fn optimum(&self) -> Option<DVec3> {
+        (self.a.determinant().abs() > SINGULAR_EPS).then(|| -(self.a.inverse() * self.b))
+    }
+}
+
+/// The collapse of the edge moving `from` into `to` placed at `position`.
+#[derive(Debug, Clone)]
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    position: DVec3,
+    /// The weight of `from` in the merged vertex, to interpolate the values.
+    t: f32,
+    versions: (usize, usize),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // the cheapest collapse goes first from the max-heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+/// The triangles and the vertices being collapsed.
+struct State {
+    positions: Vec<DVec3>,
+    quadrics: Vec<Quadric>,
+    triangles: Vec<Option<[usize; 3]>>,
+    vertex_faces: Vec<HashSet<usize>>,
+    locked: Vec<bool>,
+    alive: Vec<bool>,
+    /// The number of the changes of every vertex, the collapses computed before are outdated.
+    versions: Vec<usize>,
+    /// The merged vertices with the vertex they are merged into and the weight of the merged one.
+    merges: Vec<(usize, usize, f32)>,
+    face_count: usize,
+}
+
+impl State {
+    fn new(mesh: &Mesh, triangles: Vec<[usize; 3]>) -> MeshResult<Self> {
+        let count = mesh.vertices().len();
+        let positions: Vec<DVec3> = points(mesh);
+        let mut quadrics = vec![Quadric::zero(); count];
+        let mut vertex_faces = vec![HashSet::new(); count];
+        for (idx, t) in triangles.iter().enumerate() {
+            if let Some(v) = t.iter().find(|v| **v >= count) {
+                return Err(MeshError::idx_vertex(*v));
+            }
+            let normal = (positions[t[1]] - positions[t[0]]).cross(positions[t[2]] - positions[t[0]]);
+            if let Some(normal) = normal.try_normalize() {
+                let quadric = Quadric::plane(normal, positions[t[0]]);
+                for v in t {
+                    quadrics[*v] = quadrics[*v].add(&quadric);
+                }
+            }
+            for v in t {
+                vertex_faces[*v].insert(idx);
+            }
+        }
+        Ok(State {
+            positions,
+            quadrics,
+            face_count: triangles.len(),
+            triangles: triangles.into_iter().map(Some).collect(),
+            vertex_faces,
+            locked: vec![false; count],
+            alive: vec![true; count],
+            versions: vec![0; count],
+            merges: vec![],
+        })
+    }
+
+    /// Locks the vertices of the boundary and the feature edges.
+    fn lock(&mut self, mesh: &Mesh, options: &DecimationOptions) -> MeshResult<()> {
+        if options.preserve_boundary {
+            let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+            for t in self.triangles.iter().flatten() {
+                for i in 0..3 {
+                    let (a, b) = (t[i], t[(i + 1) % 3]);
+                    *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+                }
+            }
+            for ((a, b), count) in edge_count {
+                if count == 1 {
+                    self.locked[a] = true;
+                    self.locked[b] = true;
+                }
+            }
+        }
+        if let Some(angle) = options.feature_angle {
+            let mut by_position: HashMap<&Vertex, Vec<usize>> = HashMap::new();
+            for (idx, v) in mesh.vertices().iter().enumerate() {
+                by_position.entry(v).or_default().push(idx);
+            }
+            for edge in extract_feature_edges(mesh, angle)? {
+                for v in [&edge.a, &edge.b] {
+                    for idx in by_position.get(v).into_iter().flatten() {
+                        self.locked[*idx] = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges = HashSet::new();
+        for t in self.triangles.iter().flatten() {
+            for i in 0..3 {
+                let (a, b) = (t[i], t[(i + 1) % 3]);
+                edges.insert((a.min(b), a.max(b)));
+            }
+        }
+        let mut edges: Vec<_> = edges.into_iter().collect();
+        edges.sort();
+        edges
+    }
+
+    fn neighbors(&self, v: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.vertex_faces[v]
+            .iter()
+            .filter_map(|f| self.triangles[*f])
+            .flatten()
+            .filter(|n| *n != v)
+            .collect();
+        neighbors.sort();
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// The best collapse of the edge, the locked vertex stays in place.
+    fn collapse(&self, a: usize, b: usize) -> Option<Collapse> {
+        let (from, to) = match (self.locked[a], self.locked[b]) {
+            (true, true) => return None,
+            (true, false) => (b, a),
+            _ => (a, b),
+        };
+        let quadric = self.quadrics[from].add(&self.quadrics[to]);
+        let (p, q) = (self.positions[from], self.positions[to]);
+        let position = if self.locked[to] {
+            q
+        } else {
+            let mut candidates = vec![p, q, (p + q) * 0.5];
+            candidates.extend(quadric.optimum());
+            candidates
+                .into_iter()
+                .min_by(|l, r| quadric.error(*l).total_cmp(&quadric.error(*r)))?
+        };
+        // the weight of `from` is the position of the projection on the edge
+        let len = (p - q).length_squared();
+        let t = if len > 0.0 {
+            ((position - q).dot(p - q) / len).clamp(0.0, 1.0) as f32
+        } else {
+            0.5
+        };
+        Some(Collapse {
+            cost: quadric.error(position).max(0.0),
+            from,
+            to,
+            position,
+            t,
+            versions: (self.versions[from], self.versions[to]),
+        })
+    }
+
+    fn is_actual(&self, c: &Collapse) -> bool {
+        self.alive[c.from]
+            && self.alive[c.to]
+            && (self.versions[c.from], self.versions[c.to]) == c.versions
+            && !self.locked[c.from]
+    }
+
+    /// Checks the link condition and, if asked, that no face turns over.
+    fn can_collapse(&self, c: &Collapse, prevent_flips: bool) -> bool {
+        let shared = self.vertex_faces[c.from]
+            .iter()
+            .filter(|f| self.vertex_faces[c.to].contains(f))
+            .count();
+        if shared == 0 {
+            return false;
+        }
+        let from_neighbors = self.neighbors(c.from);
+        let common = self
+            .neighbors(c.to)
+            .into_iter()
+            .filter(|n| from_neighbors.binary_search(n).is_ok())
+            .count();
+        if common != shared {
+            return false;
+        }
+        if prevent_flips {
+            for (moved, other) in [(c.from, c.to), (c.to, c.from)] {
+                for f in self.vertex_faces[moved].iter() {
+                    let Some(t) = self.triangles[*f] else { continue };
+                    if t.contains(&other) {
+                        continue;
+                    }
+                    let before = self.normal(t, None);
+                    let after = self.normal(t, Some((moved, c.position)));
+                    if after.length_squared() == 0.0 || before.dot(after) <= 0.0 {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn normal(&self, t: [usize; 3], replaced: Option<(usize, DVec3)>) -> DVec3 {
+        let p = t.map(|v| match replaced {
+            Some((r, pos)) if r == v => pos,
+            _ => self.positions[v],
+        });
+        (p[1] - p[0]).cross(p[2] - p[0])
+    }
+
+    /// Merges the vertex into the other one, returns the kept vertex.
+    fn apply(&mut self, c: &Collapse) -> usize {
+        let (from, to) = (c.from, c.to);
+        let faces: Vec<usize> = self.vertex_faces[from].drain().collect();
+        for f in faces {
+            let Some(mut t) = self.triangles[f] else { continue };
+            if t.contains(&to) {
+                self.triangles[f] = None;
+                self.face_count -= 1;
+                for v in t {
+                    self.vertex_faces[v].remove(&f);
+                }
+            } else {
+                for v in t.iter_mut() {
+                    if *v == from {
+                        *v = to;
+                    }
+                }
+                self.triangles[f] = Some(t);
+                self.vertex_faces[to].insert(f);
+            }
+        }
+        self.positions[to] = c.position;
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+        self.alive[from] = false;
+        self.versions[to] += 1;
+        self.merges.push((from, to, c.t));
+        to
+    }
+
+    /// Builds the mesh of the left faces and the vertices used by them.
+    fn into_mesh(self, mesh: &Mesh, origins: &[usize]) -> Mesh {
+        let mut fields = mesh.fields().clone();
+        let mut colors = match mesh.attributes().color() {
+            Color::Vertex(colors) if colors.len() == mesh.vertices().len() => Some(colors.clone()),
+            _ => None,
+        };
+        for (from, to, t) in self.merges.iter() {
+            fields.blend_vertex(*to, *from, *t);
+            if let Some(colors) = colors.as_mut() {
+                colors[*to] = lerp_color(&colors[*to], &colors[*from], *t);
+            }
+        }
+
+        let mut indexes = vec![None; self.positions.len()];
+        let mut vertices = vec![];
+        let mut sources = vec![];
+        let mut faces = vec![];
+        let mut face_sources = vec![];
+        for (idx, t) in self.triangles.iter().enumerate() {
+            let Some(t) = t else { continue };
+            let t = t.map(|v| {
+                *indexes[v].get_or_insert_with(|| {
+                    vertices.push(Vertex::from(self.positions[v].as_vec3()));
+                    sources.push(v);
+                    vertices.len() - 1
+                })
+            });
+            faces.push(Face::Triangle(t[0], t[1], t[2]));
+            face_sources.push(origins[idx]);
+        }
+
+        let mut attributes = mesh.attributes().clone();
+        match mesh.attributes().color() {
+            Color::Vertex(_) => {
+                if let Some(colors) = colors {
+                    attributes.set_color(Color::Vertex(sources.iter().map(|v| colors[*v].clone()).collect()))
+                }
+            }
+            Color::Face(colors) if colors.len() == mesh.faces().len() => attributes
+                .set_color(Color::Face(face_sources.iter().map(|f| colors[*f].clone()).collect())),
+            _ => {}
+        }
+        let mut result = Mesh::from_vertices(vertices, faces, attributes);
+        result.fields = fields.select(&sources, &face_sources);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::fixtures;
+    use crate::mesh::fields::FieldData;
+    use crate::mesh::material::RgbaColor;
+
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn decimate_sphere() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Default::default()).unwrap();
+        let mut mesh = sphere.mesh().clone();
+        let heights = mesh.vertices().iter().map(|v| v.z).collect();
+        mesh.add_vertex_field("z", FieldData::Scalar(heights)).unwrap();
+
+        let coarse = mesh.decimate(0.2).unwrap();
+        assert!(coarse.faces().len() <= mesh.faces().len() / 5);
+        assert!(coarse.properties().is_watertight());
+        // the vertices stay close to the surface
+        let radius = |m: &Mesh| {
+            m.vertices()
+                .iter()
+                .map(|v| v.magnitude())
+                .fold((f32::MAX, f32::MIN), |(lo, hi), r| (lo.min(r), hi.max(r)))
+        };
+        let ((lo, hi), (coarse_lo, coarse_hi)) = (radius(&mesh), radius(&coarse));
+        assert!(coarse_lo > lo - 0.05 && coarse_hi < hi + 0.05);
+        let z = coarse.fields().vertex("z").and_then(|f| f.as_scalar()).unwrap();
+        assert_eq!(z.len(), coarse.vertices().len());
+        for (v, z) in coarse.vertices().iter().zip(z) {
+            assert!((v.z - z).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn flat_grid_keeps_boundary() {
+        let n = 6;
+        let mut grid = fixtures::grid(n, |_, _| 0.0);
+        let colors = vec![RgbaColor::GREEN; grid.faces().len()];
+        grid.attributes_mut().set_color(Color::Face(colors));
+
+        let coarse = grid.decimate(DecimationOptions::new(0)).unwrap();
+        // only the boundary vertices are left, all of them
+        assert_eq!(coarse.vertices().len(), 4 * n);
+        assert!(coarse.vertices().iter().all(|v| v.z == 0.0));
+        assert!(matches!(coarse.attributes().color(), Color::Face(c) if c.len() == coarse.faces().len()));
+
+        let free = grid
+            .decimate(DecimationOptions::new(0).with_preserve_boundary(false))
+            .unwrap();
+        assert!(free.faces().len() < coarse.faces().len());
+    }
+
+    #[test]
+    fn features_are_kept() {
+        let (n, roof) = (6, fixtures::roof());
+        let ridge = fixtures::ridge;
+
+        let options = DecimationOptions::new(0).with_preserve_boundary(false);
+        let coarse = roof.decimate(options.with_feature_angle(Some(30.0))).unwrap();
+        assert!(coarse.faces().len() < roof.faces().len() / 2);
+        assert_eq!(ridge(&coarse), n + 1);
+
+        let coarse = roof.decimate(options).unwrap();
+        assert!(ridge(&coarse) < n + 1);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The name of the vector field keeping the normals of the vertices
+/// when they are provided by the source of the mesh (e.g. a file).
+pub const NORMALS: &str = "normals";
+
+/// The values of a field, one value per vertex or per face.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldData {
+    Scalar(Vec<f32>),
+    Vector(Vec<[f32; 3]>),
+    /// The integer values, e.g. ids or labels, that can not be interpolated.
+    Integer(Vec<i64>),
+}
+
+impl FieldData {
+    pub fn len(&self) -> usize {
+        match self {
+            FieldData::Scalar(v) => v.len(),
+            FieldData::Vector(v) => v.len(),
+            FieldData::Integer(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_scalar(&self) -> Option<&Vec<f32>> {
+        match self {
+            FieldData::Scalar(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<&Vec<[f32; 3]>> {
+        match self {
+            FieldData::Vector(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<&Vec<i64>> {
+        match self {
+            FieldData::Integer(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The field as one number per element, the vectors are taken by their length.
+    pub fn to_scalars(&self) -> Vec<f32> {
+        match self {
+            FieldData::Scalar(v) => v.clone(),
+            FieldData::Vector(v) => v
+                .iter()
+                .map(|[x, y, z]| (x * x + y * y + z * z).sqrt())
+                .collect(),
+            FieldData::Integer(v) => v.iter().map(|i| *i as f32).collect(),
+        }
+    }
+
+    /// The value of the element at the index.
+    pub fn value(&self, idx: usize) -> Option<FieldValue> {
+        match self {
+            FieldData::Scalar(v) => v.get(idx).copied().map(FieldValue::Scalar),
+            FieldData::Vector(v) => v.get(idx).copied().map(FieldValue::Vector),
+            FieldData::Integer(v) => v.get(idx).copied().map(FieldValue::Integer),
+        }
+    }
+
+    /// The values at the indexes, the indexes can repeat.
+    pub(crate) fn select(&self, indexes: &[usize]) -> FieldData {
+        match self {
+            FieldData::Scalar(v) => FieldData::Scalar(indexes.iter().map(|i| v[*i]).collect()),
+            FieldData::Vector(v) => FieldData::Vector(indexes.iter().map(|i| v[*i]).collect()),
+            FieldData::Integer(v) => FieldData::Integer(indexes.iter().map(|i| v[*i]).collect()),
+        }
+    }
+
+    /// The values interpolated linearly between the pairs of the values, `(a, b, t)` gives `a + (b - a) * t`.
+    /// The integers are not interpolated, the value of the closer element is taken.
+    pub(crate) fn interpolate(&self, sources: &[(usize, usize, f32)]) -> FieldData {
+        match self {
+            FieldData::Scalar(v) => {
+                FieldData::Scalar(sources.iter().map(|(a, b, t)| v[*a] + (v[*b] - v[*a]) * t).collect())
+            }
+            FieldData::Vector(v) => FieldData::Vector(
+                sources
+                    .iter()
+                    .map(|(a, b, t)| {
+                        let (l, r) = (v[*a], v[*b]);
+                        [l[0] + (r[0] - l[0]) * t, l[1] + (r[1] - l[1]) * t, l[2] + (r[2] - l[2]) * t]
+                    })
+                    .collect(),
+            ),
+            FieldData::Integer(v) => FieldData::Integer(
+                sources
+                    .iter()
+                    .map(|(a, b, t)| if *t <= 0.5 { v[*a] } else { v[*b] })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Moves the value at `target` towards the value at `source`, `t = 1` takes the value of `source`.
+    /// The integers are not interpolated, the value of the closer element is taken.
+    pub(crate) fn blend(&mut self, target: usize, source: usize, t: f32) {
+        match self {
+            FieldData::Scalar(v) => v[target] += (v[source] - v[target]) * t,
+            FieldData::Vector(v) => {
+                let (l, r) = (v[target], v[source]);
+                v[target] = [l[0] + (r[0] - l[0]) * t, l[1] + (r[1] - l[1]) * t, l[2] + (r[2] - l[2]) * t];
+            }
+            FieldData::Integer(v) => {
+                if t > 0.5 {
+                    v[target] = v[source];
+                }
+            }
+        }
+    }
+
+    /// Appends the values in the middle between the pairs of the existing values.
+    /// The integers are not interpolated, the value of the first element of a pair is taken.
+    pub(crate) fn push_midpoints(&mut self, pairs: &[(usize, usize)]) {
+        match self {
+            FieldData::Scalar(v) => {
+                for (a, b) in pairs {
+                    v.push((v[*a] + v[*b]) * 0.5);
+                }
+            }
+            FieldData::Vector(v) => {
+                for (a, b) in pairs {
+                    let (l, r) = (v[*a], v[*b]);
+                    v.push([(l[0] + r[0]) * 0.5, (l[1] + r[1]) * 0.5, (l[2] + r[2]) * 0.5]);
+                }
+            }
+            FieldData::Integer(v) => {
+                for (a, _) in pairs {
+                    v.push(v[*a]);
+                }
+            }
+        }
+    }
+}
+
+/// The value of a field at one vertex or face.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue {
+    Scalar(f32),
+    Vector([f32; 3]),
+    Integer(i64),
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Scalar(v) => write!(f, "{}", v),
+            FieldValue::Vector([x, y, z]) => write!(f, "({}, {}, {})", x, y, z),
+            FieldValue::Integer(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Named data arrays attached to the vertices or the faces of a mesh,
+/// e.g. the temperature or the normal of every vertex or the stress of every face.
+///
+/// # Examples
+///
+/// ```
+/// use tessellate::mesh::fields::{FieldData, Fields};
+///
+/// let mut fields = Fields::default();
+/// fields.insert_vertex("temperature", FieldData::Scalar(vec![1.0, 2.0, 3.0]));
+/// assert_eq!(fields.vertex("temperature").map(|f| f.len()), Some(3));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fields {
+    vertex: BTreeMap<String, FieldData>,
+    face: BTreeMap<String, FieldData>,
+}
+
+impl Fields {
+    pub fn vertex(&self, name: &str) -> Option<&FieldData> {
+        self.vertex.get(name)
+    }
+
+    /// Iterates over the vertex fields ordered by name.
+    pub fn vertex_fields(&self) -> impl Iterator<Item = (&String, &FieldData)> {
+        self.vertex.iter()
+    }
+
+    /// Adds the field replacing the previous one with the same name.
+    pub fn insert_vertex<N: Into<String>>(&mut self, name: N, data: FieldData) -> Option<FieldData> {
+        self.vertex.insert(name.into(), data)
+    }
+
+    pub fn remove_vertex(&mut self, name: &str) -> Option<FieldData> {
+        self.vertex.remove(name)
+    }
+
+    pub fn face(&self, name: &str) -> Option<&FieldData> {
+        self.face.get(name)
+    }
+
+    /// Iterates over the face fields ordered by name.
+    pub fn face_fields(&self) -> impl Iterator<Item = (&String, &FieldData)> {
+        self.face.iter()
+    }
+
+    /// Adds the field replacing the previous one with the same name.
+    pub fn insert_face<N: Into<String>>(&mut self, name: N, data: FieldData) -> Option<FieldData> {
+        self.face.insert(name.into(), data)
+    }
+
+    pub fn remove_face(&mut self, name: &str) -> Option<FieldData> {
+        self.face.remove(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertex.is_empty() && self.face.is_empty()
+    }
+
+    /// The values of the fields at the vertex and, if given, at the face,
+    /// the vertex fields go first, each group ordered by name.
+    pub fn values_at(&self, vertex: usize, face: Option<usize>) -> Vec<(String, FieldValue)> {
+        let vertex_values = self
+            .vertex
+            .iter()
+            .filter_map(|(name, data)| data.value(vertex).map(|v| (name.clone(), v)));
+        let face_values = face.into_iter().flat_map(|face| {
+            self.face
+                .iter()
+                .filter_map(move |(name, data)| data.value(face).map(|v| (name.clone(), v)))
+        });
+        vertex_values.chain(face_values).collect()
+    }
+
+    /// The fields of a mesh built from the elements of this one,
+    /// `vertices` and `faces` are the indexes of the source vertices and faces.
+    pub(crate) fn select(&self, vertices: &[usize], faces: &[usize]) -> Fields {
+        Fields {
+            vertex: self
+                .vertex
+                .iter()
+                .map(|(name, data)| (name.clone(), data.select(vertices)))
+                .collect(),
+            face: self
+                .face
+                .iter()
+                .map(|(name, data)| (name.clone(), data.select(faces)))
+                .collect(),
+        }
+    }
+
+    /// The fields of a mesh whose vertices are interpolated between the pairs of the source vertices,
+    /// `faces` are the indexes of the source faces.
+    pub(crate) fn interpolate(&self, vertices: &[(usize, usize, f32)], faces: &[usize]) -> Fields {
+        Fields {
+            vertex: self
+                .vertex
+                .iter()
+                .map(|(name, data)| (name.clone(), data.interpolate(vertices)))
+                .collect(),
+            face: self
+                .face
+                .iter()
+                .map(|(name, data)| (name.clone(), data.select(faces)))
+                .collect(),
+        }
+    }
+
+    /// The same vertex fields and the face fields of the faces split into several ones,
+    /// `faces` are the indexes of the source faces.
+    pub(crate) fn select_faces(&self, faces: &[usize]) -> Fields {
+        Fields {
+            vertex: self.vertex.clone(),
+            face: self
+                .face
+                .iter()
+                .map(|(name, data)| (name.clone(), data.select(faces)))
+                .collect(),
+        }
+    }
+
+    /// Moves the vertex values at `target` towards the ones at `source`, e.g. when two vertices are merged.
+    pub(crate) fn blend_vertex(&mut self, target: usize, source: usize, t: f32) {
+        for data in self.vertex.values_mut() {
+            data.blend(target, source, t);
+        }
+    }
+
+    /// Interpolates the vertex fields for the vertices added in the middle of the pairs of vertices.
+    pub(crate) fn push_vertex_midpoints(&mut self, pairs: &[(usize, usize)]) {
+        for data in self.vertex.values_mut() {
+            data.push_midpoints(pairs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_and_interpolate() {
+        let mut fields = Fields::default();
+        fields.insert_vertex("t", FieldData::Scalar(vec![0.0, 1.0, 2.0]));
+        fields.insert_vertex("id", FieldData::Integer(vec![7, 8, 9]));
+        fields.insert_face("v", FieldData::Vector(vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]));
+
+        let selected = fields.select(&[2, 0], &[1, 1]);
+        assert_eq!(selected.vertex("t"), Some(&FieldData::Scalar(vec![2.0, 0.0])));
+        assert_eq!(selected.vertex("id"), Some(&FieldData::Integer(vec![9, 7])));
+        assert_eq!(
+            selected.face("v"),
+            Some(&FieldData::Vector(vec![[0.0, 1.0, 0.0], [0.0, 1.0, 0.0]]))
+        );
+
+        fields.push_vertex_midpoints(&[(0, 2), (2, 1)]);
+        assert_eq!(
+            fields.vertex("t"),
+            Some(&FieldData::Scalar(vec![0.0, 1.0, 2.0, 1.0, 1.5]))
+        );
+        assert_eq!(fields.vertex("id"), Some(&FieldData::Integer(vec![7, 8, 9, 7, 9])));
+    }
+}
@@ -0,0 +1,34 @@
+//! The meshes shared by the tests.
+
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::Mesh;
+
+/// The grid of `n x n` unit quads in the xy plane with the height given for every node.
+/// The vertex `(i, j)` is at `(i, j, height(i, j))` and has the index `i * (n + 1) + j`.
+pub(crate) fn grid<F: Fn(usize, usize) -> f32>(n: usize, height: F) -> Mesh {
+    let mut vertices = vec![];
+    let mut faces = vec![];
+    for i in 0..=n {
+        for j in 0..=n {
+            vertices.push(Vertex::new(i as f32, j as f32, height(i, j)));
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            let v = i * (n + 1) + j;
+            faces.push(Face::new4(v, v + n + 1, v + n + 2, v + 1));
+        }
+    }
+    Mesh::from_vertices(vertices, faces, Default::default())
+}
+
+/// The roof of 6 x 6 quads with the ridge along y at x = 3 and z = 3, the slopes are at 45 degrees.
+pub(crate) fn roof() -> Mesh {
+    grid(6, |i, _| i.min(6 - i) as f32)
+}
+
+/// The vertices lying on the ridge of the [`roof`], all 7 of them are there unless the ridge is smoothed out.
+pub(crate) fn ridge(mesh: &Mesh) -> usize {
+    mesh.vertices().iter().filter(|v| v.x == 3.0 && v.z == 3.0).count()
+}
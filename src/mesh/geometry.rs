@@ -0,0 +1,459 @@
+use crate::mesh::fields::FieldData;
+use crate::mesh::linalg::cot;
+use crate::mesh::parts::face::Face;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::{Mat3, Vec3};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// The principal curvatures and their directions at the vertices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalCurvatures {
+    /// The smaller curvature (`FieldData::Scalar`).
+    pub min: FieldData,
+    /// The greater curvature (`FieldData::Scalar`).
+    pub max: FieldData,
+    /// The tangent direction of the smaller curvature (`FieldData::Vector`).
+    pub min_direction: FieldData,
+    /// The tangent direction of the greater curvature (`FieldData::Vector`).
+    pub max_direction: FieldData,
+}
+
+/// The discrete differential quantities of a surface (Meyer, Desbrun, Schroeder and Barr).
+/// The quads are split into the triangles, the results are the field arrays
+/// to attach to the mesh and to color it by.
+///
+/// The curvatures of the boundary vertices are zero since their neighborhood is not closed.
+///
+/// # Examples
+///
+/// ```
+/// use tessellate::mesh::shape::sphere::Sphere;
+/// use tessellate::mesh::HasMesh;
+///
+/// let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Default::default()).unwrap();
+/// let mut mesh = sphere.mesh().clone();
+/// let curvature = mesh.geometry().gaussian_curvature().unwrap();
+/// mesh.add_vertex_field("gaussian", curvature).unwrap();
+/// ```
+pub struct MeshGeometry<'a> {
+    mesh: &'a Mesh,
+}
+
+impl MeshGeometry<'_> {
+    pub fn new(mesh: &Mesh) -> MeshGeometry<'_> {
+        MeshGeometry { mesh }
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        self.mesh
+    }
+}
+
+impl<'a> From<&'a Mesh> for MeshGeometry<'a> {
+    fn from(value: &'a Mesh) -> Self {
+        MeshGeometry::new(value)
+    }
+}
+
+impl MeshGeometry<'_> {
+    /// The mixed Voronoi areas of the vertices, the parts of the faces closer to the vertex.
+    /// The obtuse triangles give a half of the area to the obtuse corner and a quarter to the others.
+    /// The areas of all the vertices sum up to the area of the surface.
+    pub fn voronoi_areas(&self) -> MeshResult<FieldData> {
+        Ok(FieldData::Scalar(Surface::new(self.mesh)?.voronoi_areas()))
+    }
+
+    /// The normals of the vertices weighted by the angles of the faces at them.
+    pub fn vertex_normals(&self) -> MeshResult<FieldData> {
+        let normals = Surface::new(self.mesh)?.normals();
+        Ok(FieldData::Vector(normals.into_iter().map(|n| n.to_array()).collect()))
+    }
+
+    /// The Gaussian curvature of the vertices by the angle deficit:
+    /// `(2 * PI - sum of the angles at the vertex) / voronoi area`.
+    pub fn gaussian_curvature(&self) -> MeshResult<FieldData> {
+        Ok(FieldData::Scalar(Surface::new(self.mesh)?.gaussian()))
+    }
+
+    /// The mean curvature of the vertices by the cotangent Laplacian:
+    /// the half length of the mean curvature normal,
+    /// positive where the surface bends away from the normal like a sphere.
+    pub fn mean_curvature(&self) -> MeshResult<FieldData> {
+        let surface = Surface::new(self.mesh)?;
+        Ok(FieldData::Scalar(surface.mean(&surface.normals())))
+    }
+
+    /// The principal curvatures `H -+ sqrt(H^2 - K)` of the mean `H` and the Gaussian `K` curvatures.
+    /// The directions are the eigenvectors of the shape operator fitted to the neighbors of the vertex.
+    pub fn principal_curvatures(&self) -> MeshResult<PrincipalCurvatures> {
+        let surface = Surface::new(self.mesh)?;
+        let normals = surface.normals();
+        let gaussian = surface.gaussian();
+        let mean = surface.mean(&normals);
+        let (min, max): (Vec<f32>, Vec<f32>) = mean
+            .iter()
+            .zip(&gaussian)
+            .map(|(h, k)| {
+                let d = (h * h - k).max(0.0).sqrt();
+                (h - d, h + d)
+            })
+            .unzip();
+        let (min_direction, max_direction) = surface.directions(&normals);
+        Ok(PrincipalCurvatures {
+            min: FieldData::Scalar(min),
+            max: FieldData::Scalar(max),
+            min_direction: FieldData::Vector(min_direction),
+            max_direction: FieldData::Vector(max_direction),
+        })
+    }
+
+    /// The gradient of the scalar vertex field on every face, linear over the triangles.
+    /// The quads get the area weighted mean of their two triangles.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the vertex field, the vectors are taken by their length.
+    pub fn gradient(&self, name: &str) -> MeshResult<FieldData> {
+        let values = self
+            .mesh
+            .fields()
+            .vertex(name)
+            .ok_or(MeshError::Custom(format!("The vertex field {} is not found", name)))?
+            .to_scalars();
+        self.gradient_of(&values)
+    }
+
+    /// The gradient of the values of the vertices on every face, see `gradient`.
+    pub fn gradient_of(&self, values: &[f32]) -> MeshResult<FieldData> {
+        let mesh = self.mesh;
+        if values.len() != mesh.vertices().len() {
+            return Err(MeshError::Custom(format!(
+                "The gradient needs {} values but {} are given",
+                mesh.vertices().len(),
+                values.len()
+            )));
+        }
+        let points = points(mesh)?;
+        let mut gradients = Vec::with_capacity(mesh.faces().len());
+        for face in mesh.faces() {
+            let mut sum = Vec3::ZERO;
+            let mut total = 0.0;
+            for t in face.triangulate() {
+                let [a, b, c] = corners(&t);
+                let (pa, pb, pc) = (points[a], points[b], points[c]);
+                let normal = (pb - pa).cross(pc - pa);
+                let double_area = normal.length();
+                if double_area <= f32::EPSILON {
+                    continue;
+                }
+                let n = normal / double_area;
+                // the edges opposite to the corners turned in the plane of the triangle
+                let grad = (values[a] * n.cross(pc - pb)
+                    + values[b] * n.cross(pa - pc)
+                    + values[c] * n.cross(pb - pa))
+                    / double_area;
+                sum += grad * double_area;
+                total += double_area;
+            }
+            let grad = if total > 0.0 { sum / total } else { Vec3::ZERO };
+            gradients.push(grad.to_array());
+        }
+        Ok(FieldData::Vector(gradients))
+    }
+}
+
+/// The triangles of the mesh with the positions of the vertices.
+struct Surface {
+    points: Vec<Vec3>,
+    triangles: Vec<[usize; 3]>,
+    boundary: Vec<bool>,
+}
+
+/// The corner of a triangle, the vertices go counterclockwise.
+struct Corner {
+    at: usize,
+    next: usize,
+    prev: usize,
+    angle: f32,
+    cot: f32,
+}
+
+impl Surface {
+    fn new(mesh: &Mesh) -> MeshResult<Self> {
+        let points = points(mesh)?;
+        let triangles: Vec<[usize; 3]> = mesh
+            .faces()
+            .iter()
+            .flat_map(Face::triangulate)
+            .map(|t| corners(&t))
+            .collect();
+
+        let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+        for face in mesh.faces() {
+            let vs = face.flatten();
+            for i in 0..vs.len() {
+                let (a, b) = (vs[i], vs[(i + 1) % vs.len()]);
+                *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let mut boundary = vec![false; points.len()];
+        for ((a, b), count) in edge_count {
+            if count == 1 {
+                boundary[a] = true;
+                boundary[b] = true;
+            }
+        }
+        Ok(Surface {
+            points,
+            triangles,
+            boundary,
+        })
+    }
+
+    /// The corners of the triangles with the angles at them and the cotangents of the angles.
+    fn corners(&self) -> impl Iterator<Item = Corner> + '_ {
+        self.triangles.iter().flat_map(|t| {
+            (0..3).map(|i| {
+                let (at, next, prev) = (t[i], t[(i + 1) % 3], t[(i + 2) % 3]);
+                let [p_at, p_next, p_prev] = [at, next, prev].map(|v| self.points[v]);
+                Corner {
+                    at,
+                    next,
+                    prev,
+                    angle: (p_next - p_at).angle_between(p_prev - p_at),
+                    cot: cot(p_at.as_dvec3(), p_next.as_dvec3(), p_prev.as_dvec3()) as f32,
+                }
+            })
+        })
+    }
+
+    fn voronoi_areas(&self) -> Vec<f32> {
+        let mut areas = vec![0.0; self.points.len()];
+        for t in &self.triangles {
+            let p = t.map(|i| self.points[i]);
+            let area = 0.5 * (p[1] - p[0]).cross(p[2] - p[0]).length();
+            if area <= f32::EPSILON {
+                continue;
+            }
+            let obtuse = (0..3).find(|i| (p[(i + 1) % 3] - p[*i]).dot(p[(i + 2) % 3] - p[*i]) < 0.0);
+            for i in 0..3 {
+                areas[t[i]] += match obtuse {
+                    Some(o) if o == i => area / 2.0,
+                    Some(_) => area / 4.0,
+                    None => {
+                        let (a, b, c) = (p[i], p[(i + 1) % 3], p[(i + 2) % 3]);
+                        // the edges from the vertex by the cotangents of the opposite angles
+                        let [a, b, c] = [a, b, c].map(|p| p.as_dvec3());
+                        ((a.distance_squared(b) * cot(c, a, b) + a.distance_squared(c) * cot(b, c, a)) / 8.0) as f32
+                    }
+                };
+            }
+        }
+        areas
+    }
+
+    fn normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![Vec3::ZERO; self.points.len()];
+        for t in &self.triangles {
+            let p = t.map(|i| self.points[i]);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+            for i in 0..3 {
+                let angle = (p[(i + 1) % 3] - p[i]).angle_between(p[(i + 2) % 3] - p[i]);
+                if angle.is_finite() {
+                    normals[t[i]] += angle * n;
+                }
+            }
+        }
+        normals.into_iter().map(Vec3::normalize_or_zero).collect()
+    }
+
+    fn gaussian(&self) -> Vec<f32> {
+        let areas = self.voronoi_areas();
+        let mut angles = vec![0.0; self.points.len()];
+        for corner in self.corners() {
+            if corner.angle.is_finite() {
+                angles[corner.at] += corner.angle;
+            }
+        }
+        (0..self.points.len())
+            .map(|i| self.curvature(i, &areas, 2.0 * PI - angles[i]))
+            .collect()
+    }
+
+    fn mean(&self, normals: &[Vec3]) -> Vec<f32> {
+        let areas = self.voronoi_areas();
+        let mut laplacian = vec![Vec3::ZERO; self.points.len()];
+        for c in self.corners() {
+            // the angle at the corner weights the opposite edge
+            let edge = self.points[c.prev] - self.points[c.next];
+            laplacian[c.next] += c.cot * edge;
+            laplacian[c.prev] -= c.cot * edge;
+        }
+        (0..self.points.len())
+            .map(|i| {
+                let k = laplacian[i] / 2.0;
+                let sign = if k.dot(normals[i]) > 0.0 { -1.0 } else { 1.0 };
+                self.curvature(i, &areas, sign * k.length() / 2.0)
+            })
+            .collect()
+    }
+
+    /// The value over the voronoi area, zero for the boundary and the lonely vertices.
+    fn curvature(&self, idx: usize, areas: &[f32], value: f32) -> f32 {
+        if self.boundary[idx] || areas[idx] <= f32::EPSILON {
+            0.0
+        } else {
+            value / areas[idx]
+        }
+    }
+
+    /// The eigenvectors of the shape operators fitted to the rings of the vertices
+    /// by the height function `h = a * x^2 + b * x * y + c * y^2` in the tangent planes.
+    /// Returns the directions of the smaller and the greater curvatures.
+    fn directions(&self, normals: &[Vec3]) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+        let mut rings = vec![Vec::new(); self.points.len()];
+        for c in self.corners() {
+            rings[c.at].push(c.next);
+            rings[c.at].push(c.prev);
+        }
+        rings
+            .iter_mut()
+            .zip(normals)
+            .enumerate()
+            .map(|(idx, (ring, n))| {
+                ring.sort_unstable();
+                ring.dedup();
+                let (t1, t2) = n.any_orthonormal_pair();
+                let mut lhs = Mat3::ZERO;
+                let mut rhs = Vec3::ZERO;
+                for v in ring.iter() {
+                    let d = self.points[*v] - self.points[idx];
+                    let (x, y) = (d.dot(t1), d.dot(t2));
+                    let row = Vec3::new(x * x, x * y, y * y);
+                    lhs += Mat3::from_cols(row * row.x, row * row.y, row * row.z);
+                    rhs += row * d.dot(*n);
+                }
+                if n.length_squared() == 0.0 || lhs.determinant().abs() <= f32::EPSILON * f32::EPSILON {
+                    return ([0.0; 3], [0.0; 3]);
+                }
+                let fit = lhs.inverse() * rhs;
+                // the convex bending away from the normal is positive as the mean curvature
+                let (a, b, c) = (-2.0 * fit.x, -fit.y, -2.0 * fit.z);
+                // the eigenvector of the greater eigenvalue of the symmetric 2x2 operator
+                let theta = 0.5 * (2.0 * b).atan2(a - c);
+                let max = theta.cos() * t1 + theta.sin() * t2;
+                (n.cross(max).to_array(), max.to_array())
+            })
+            .unzip()
+    }
+}
+
+fn points(mesh: &Mesh) -> MeshResult<Vec<Vec3>> {
+    for face in mesh.faces() {
+        for idx in face.flatten() {
+            mesh.get(idx)?;
+        }
+    }
+    Ok(mesh.vertices().iter().map(|v| (*v).into()).collect())
+}
+
+fn corners(triangle: &Face) -> [usize; 3] {
+    match triangle {
+        Face::Triangle(a, b, c) => [*a, *b, *c],
+        Face::Quad(a, b, c, _) => [*a, *b, *c],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::parts::vertex::Vertex;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn sphere_curvatures() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Default::default()).unwrap();
+        // the subdivided icosahedron is pulled onto the sphere
+        let radius = 2.0;
+        let vertices = sphere.mesh().vertices().iter().map(|v| *v * (radius / v.magnitude())).collect();
+        let mesh = Mesh::from_vertices(vertices, sphere.mesh().faces().clone(), Default::default());
+        let geometry = mesh.geometry();
+
+        let areas = geometry.voronoi_areas().unwrap();
+        let area: f32 = areas.as_scalar().unwrap().iter().sum();
+        assert!((area - 4.0 * PI * radius * radius).abs() < 0.1 * area);
+
+        let gaussian = geometry.gaussian_curvature().unwrap();
+        for k in gaussian.as_scalar().unwrap() {
+            assert!((k * radius * radius - 1.0).abs() < 0.2, "{}", k);
+        }
+        let mean_curvature = geometry.mean_curvature().unwrap();
+        for h in mean_curvature.as_scalar().unwrap() {
+            assert!((h * radius - 1.0).abs() < 0.2, "{}", h);
+        }
+        let normals = geometry.vertex_normals().unwrap();
+        for (n, v) in normals.as_vector().unwrap().iter().zip(mesh.vertices()) {
+            let v: Vec3 = (*v).into();
+            assert!(Vec3::from(*n).dot(v.normalize()) > 0.99);
+        }
+    }
+
+    #[test]
+    fn cylinder_directions() {
+        // the open tube of the radius 1 along z
+        let (rings, segments) = (9, 32);
+        let mut vertices = vec![];
+        let mut faces = vec![];
+        for r in 0..rings {
+            for s in 0..segments {
+                let a = 2.0 * PI * s as f32 / segments as f32;
+                vertices.push(Vertex::new(a.cos(), a.sin(), r as f32 * 0.2));
+            }
+        }
+        for r in 0..rings - 1 {
+            for s in 0..segments {
+                let (v, next) = (r * segments + s, r * segments + (s + 1) % segments);
+                faces.push(Face::new4(v, next, next + segments, v + segments));
+            }
+        }
+        let mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        let pc = mesh.geometry().principal_curvatures().unwrap();
+        let (min, max) = (pc.min.as_scalar().unwrap(), pc.max.as_scalar().unwrap());
+        let min_dir = pc.min_direction.as_vector().unwrap();
+        let max_dir = pc.max_direction.as_vector().unwrap();
+        for i in segments..(rings - 1) * segments {
+            assert!(min[i].abs() < 0.1, "{}", min[i]);
+            assert!((max[i] - 1.0).abs() < 0.1, "{}", max[i]);
+            assert!(Vec3::from(min_dir[i]).dot(Vec3::Z).abs() > 0.99);
+            assert!(Vec3::from(max_dir[i]).dot(Vec3::Z).abs() < 0.1);
+        }
+        // the rims are the boundary
+        assert_eq!(max[0], 0.0);
+    }
+
+    #[test]
+    fn gradient_of_linear_field() {
+        let vertices = vec![
+            Vertex::new(0.0, 0.0, 0.0),
+            Vertex::new(2.0, 0.0, 0.0),
+            Vertex::new(2.0, 1.0, 0.0),
+            Vertex::new(0.0, 1.0, 0.0),
+            Vertex::new(3.0, 0.0, 1.0),
+        ];
+        let faces = vec![Face::new4(0, 1, 2, 3), Face::new3(1, 4, 2)];
+        let mut mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        let values = mesh.vertices().iter().map(|v| 3.0 * v.x + v.y).collect();
+        mesh.add_vertex_field("f", FieldData::Scalar(values)).unwrap();
+
+        let gradient = mesh.geometry().gradient("f").unwrap();
+        let gradient = gradient.as_vector().unwrap();
+        assert_eq!(gradient.len(), 2);
+        assert!(Vec3::from(gradient[0]).abs_diff_eq(Vec3::new(3.0, 1.0, 0.0), 1e-5));
+        // the tilted triangle sees the projection of the gradient on its plane
+        let normal = Vec3::new(-1.0, 0.0, 1.0).normalize();
+        let projected = Vec3::new(3.0, 1.0, 0.0) - normal * normal.dot(Vec3::new(3.0, 1.0, 0.0));
+        assert!(Vec3::from(gradient[1]).abs_diff_eq(projected, 1e-5));
+        assert!(mesh.geometry().gradient("g").is_err());
+    }
+}
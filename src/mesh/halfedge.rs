@@ -0,0 +1,464 @@
+use crate::mesh::attributes::Attributes;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::parts::Idx;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use std::collections::HashMap;
+
+mod edit;
+
+/// The directed half of an edge going around a face or along the boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HalfEdge {
+    /// The vertex the half-edge starts from.
+    pub origin: Idx,
+    /// The half of the same edge going in the opposite direction.
+    pub twin: Idx,
+    /// The next half-edge around the face or the boundary loop.
+    pub next: Idx,
+    pub prev: Idx,
+    /// The face on the left side, `None` for the half-edges on the boundary.
+    pub face: Option<Idx>,
+}
+
+/// The half-edge structure of an oriented manifold polygonal mesh.
+///
+/// Every edge is split into two half-edges going in the opposite directions,
+/// every face and every boundary loop is a cycle of the half-edges,
+/// so the neighbours of a vertex, a face or an edge are reached in constant time
+/// without the lookup tables. Unlike `MeshTables`, the structure can be edited in place:
+/// the edges can be flipped, split and collapsed, the faces split, the vertices removed
+/// and the holes filled, see the `edit` methods.
+///
+/// The removed elements leave the gaps, the indexes of the others stay the same
+/// until the structure is turned back into a `Mesh`.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::parts::face::FaceType;
+/// use tessellate::mesh::shape::cuboid::cube::Cube;
+/// use tessellate::mesh::material::Color;
+/// use tessellate::mesh::HasMesh;
+///
+/// let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default());
+/// let mut he = cube.mesh().try_half_edges().unwrap();
+/// assert!(he.is_closed());
+///
+/// let h = he.face_half_edges(0).unwrap()[0];
+/// let v = he.split_edge(h, [0.0, 0.0, 0.0].into()).unwrap();
+/// assert_eq!(he.vertex_neighbors(v).unwrap().len(), 4);
+/// let mesh = he.to_mesh(Color::default().into()).unwrap();
+/// assert_eq!(mesh.faces().len(), 14);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HalfEdgeMesh {
+    vertices: Vec<Option<Vertex>>,
+    /// A half-edge starting from the vertex, the boundary one if the vertex is on the boundary.
+    outgoing: Vec<Option<Idx>>,
+    half_edges: Vec<Option<HalfEdge>>,
+    /// A half-edge of the face.
+    faces: Vec<Option<Idx>>,
+}
+
+impl TryFrom<&Mesh> for HalfEdgeMesh {
+    type Error = MeshError;
+
+    fn try_from(value: &Mesh) -> Result<Self, Self::Error> {
+        HalfEdgeMesh::new(value)
+    }
+}
+
+impl HalfEdgeMesh {
+    /// Builds the structure, fails if the faces are not oriented the same way
+    /// or the mesh is not manifold, i.e. an edge is shared by more than two faces
+    /// or the faces around a vertex do not make a single fan.
+    pub fn new(mesh: &Mesh) -> MeshResult<Self> {
+        let count = mesh.vertices().len();
+        let mut he = HalfEdgeMesh {
+            vertices: mesh.vertices().iter().map(|v| Some(*v)).collect(),
+            outgoing: vec![None; count],
+            half_edges: vec![],
+            faces: vec![],
+        };
+
+        let mut directed: HashMap<(Idx, Idx), Idx> = HashMap::new();
+        for face in mesh.faces() {
+            let vs = face.flatten();
+            if let Some(v) = vs.iter().find(|v| **v >= count) {
+                return Err(MeshError::idx_vertex(*v));
+            }
+            let (k, first, f) = (vs.len(), he.half_edges.len(), he.faces.len());
+            he.faces.push(Some(first));
+            for i in 0..k {
+                let (a, b) = (vs[i], vs[(i + 1) % k]);
+                if a == b {
+                    return Err(MeshError::WrongMesh(format!("The face {} is degenerate", f)));
+                }
+                if directed.insert((a, b), first + i).is_some() {
+                    return Err(MeshError::WrongMesh(format!(
+                        "The edge {} - {} is passed twice in the same direction, \
+                         the faces are not oriented the same way or the edge is shared by more than two faces",
+                        a, b
+                    )));
+                }
+                he.half_edges.push(Some(HalfEdge {
+                    origin: a,
+                    twin: Idx::MAX,
+                    next: first + (i + 1) % k,
+                    prev: first + (i + k - 1) % k,
+                    face: Some(f),
+                }));
+                he.outgoing[a] = Some(first + i);
+            }
+        }
+
+        // the edges of one face become the boundary half-edges
+        let inner = he.half_edges.len();
+        let mut boundary_from = HashMap::new();
+        for h in 0..inner {
+            let (a, b) = (he.he(h).origin, he.he(he.he(h).next).origin);
+            let twin = match directed.get(&(b, a)) {
+                Some(t) => *t,
+                None => {
+                    let t = he.half_edges.len();
+                    he.half_edges.push(Some(HalfEdge {
+                        origin: b,
+                        twin: h,
+                        next: Idx::MAX,
+                        prev: Idx::MAX,
+                        face: None,
+                    }));
+                    if boundary_from.insert(b, t).is_some() {
+                        return Err(MeshError::WrongMesh(format!(
+                            "The vertex {} is shared by several pieces of the boundary",
+                            b
+                        )));
+                    }
+                    he.outgoing[b] = Some(t);
+                    t
+                }
+            };
+            he.he_mut(h).twin = twin;
+        }
+        for t in inner..he.half_edges.len() {
+            let end = he.he(he.he(t).twin).origin;
+            let next = *boundary_from.get(&end).ok_or_else(|| {
+                MeshError::WrongMesh(format!("The boundary is broken at the vertex {}", end))
+            })?;
+            he.link(t, next);
+        }
+
+        he.validate()?;
+        Ok(he)
+    }
+
+    /// Turns the structure back into a mesh dropping the removed vertices.
+    /// The faces with more than four sides are triangulated.
+    pub fn to_mesh(&self, attributes: Attributes) -> MeshResult<Mesh> {
+        let mut indexes = vec![Idx::MAX; self.vertices.len()];
+        let mut vertices = vec![];
+        for (idx, v) in self.vertices.iter().enumerate() {
+            if let Some(v) = v {
+                indexes[idx] = vertices.len();
+                vertices.push(*v);
+            }
+        }
+        let mut faces = vec![];
+        for f in self.face_indices() {
+            let vs: Vec<Idx> = self
+                .cycle(self.faces[f].unwrap())
+                .into_iter()
+                .map(|h| indexes[self.he(h).origin])
+                .collect();
+            faces.extend(Face::new(vs)?);
+        }
+        Ok(Mesh::from_vertices(vertices, faces, attributes))
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.iter().flatten().count()
+    }
+    /// The number of the edges, i.e. the pairs of the half-edges.
+    pub fn edge_count(&self) -> usize {
+        self.half_edges.iter().flatten().count() / 2
+    }
+    pub fn face_count(&self) -> usize {
+        self.faces.iter().flatten().count()
+    }
+
+    pub fn vertex_indices(&self) -> impl Iterator<Item = Idx> + '_ {
+        self.vertices.iter().enumerate().filter(|(_, v)| v.is_some()).map(|(i, _)| i)
+    }
+    pub fn half_edge_indices(&self) -> impl Iterator<Item = Idx> + '_ {
+        self.half_edges.iter().enumerate().filter(|(_, h)| h.is_some()).map(|(i, _)| i)
+    }
+    pub fn face_indices(&self) -> impl Iterator<Item = Idx> + '_ {
+        self.faces.iter().enumerate().filter(|(_, f)| f.is_some()).map(|(i, _)| i)
+    }
+
+    pub fn vertex(&self, v: Idx) -> MeshResult<&Vertex> {
+        self.vertices.get(v).and_then(Option::as_ref).ok_or(MeshError::idx_vertex(v))
+    }
+    pub fn half_edge(&self, h: Idx) -> MeshResult<&HalfEdge> {
+        self.half_edges
+            .get(h)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| MeshError::InvalidIndex(format!("Invalid index for the half-edge: {}", h)))
+    }
+    /// A half-edge going around the face.
+    pub fn face_half_edge(&self, f: Idx) -> MeshResult<Idx> {
+        self.faces.get(f).copied().flatten().ok_or(MeshError::idx_face(f))
+    }
+
+    /// The vertex the half-edge points to.
+    pub fn target(&self, h: Idx) -> MeshResult<Idx> {
+        self.half_edge(h)?;
+        Ok(self.end(h))
+    }
+
+    /// The half-edge going from `from` to `to` if the vertices are connected.
+    pub fn find_half_edge(&self, from: Idx, to: Idx) -> Option<Idx> {
+        self.vertices.get(from)?.as_ref()?;
+        self.fan(from).into_iter().find(|h| self.end(*h) == to)
+    }
+
+    /// The half-edges starting from the vertex in the clockwise order,
+    /// from the boundary one if the vertex is on the boundary.
+    pub fn outgoing(&self, v: Idx) -> MeshResult<Vec<Idx>> {
+        self.vertex(v)?;
+        Ok(self.fan(v))
+    }
+    /// The vertices connected to the vertex by the edges.
+    pub fn vertex_neighbors(&self, v: Idx) -> MeshResult<Vec<Idx>> {
+        Ok(self.outgoing(v)?.into_iter().map(|h| self.end(h)).collect())
+    }
+    pub fn vertex_faces(&self, v: Idx) -> MeshResult<Vec<Idx>> {
+        Ok(self.outgoing(v)?.into_iter().filter_map(|h| self.he(h).face).collect())
+    }
+    /// The number of the edges at the vertex.
+    pub fn degree(&self, v: Idx) -> MeshResult<usize> {
+        Ok(self.outgoing(v)?.len())
+    }
+
+    /// The half-edges of the face in the order of its vertices.
+    pub fn face_half_edges(&self, f: Idx) -> MeshResult<Vec<Idx>> {
+        Ok(self.cycle(self.face_half_edge(f)?))
+    }
+    pub fn face_vertices(&self, f: Idx) -> MeshResult<Vec<Idx>> {
+        Ok(self.face_half_edges(f)?.into_iter().map(|h| self.he(h).origin).collect())
+    }
+
+    /// The vertex with a missing face around it. The vertices without edges are not on the boundary.
+    pub fn is_boundary_vertex(&self, v: Idx) -> bool {
+        matches!(self.outgoing.get(v), Some(Some(h)) if self.he(*h).face.is_none())
+    }
+    /// The edge with a face on one side only.
+    pub fn is_boundary_edge(&self, h: Idx) -> bool {
+        self.half_edge(h)
+            .map(|e| e.face.is_none() || self.he(e.twin).face.is_none())
+            .unwrap_or(false)
+    }
+    pub fn is_closed(&self) -> bool {
+        self.half_edge_indices().all(|h| self.he(h).face.is_some())
+    }
+
+    /// The boundary loops as the cycles of the half-edges without faces,
+    /// their origins are the boundary vertices in order.
+    pub fn boundary_loops(&self) -> Vec<Vec<Idx>> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut loops = vec![];
+        for h in self.half_edge_indices() {
+            if !visited[h] && self.he(h).face.is_none() {
+                let cycle = self.cycle(h);
+                cycle.iter().for_each(|h| visited[*h] = true);
+                loops.push(cycle);
+            }
+        }
+        loops
+    }
+
+    /// Checks the links between the elements: the twins, the cycles of the faces and the boundary,
+    /// the consistent orientation and the single fan of the half-edges around every vertex.
+    pub fn validate(&self) -> MeshResult<()> {
+        let wrong = |msg: String| Err(MeshError::WrongMesh(msg));
+        let live = |h: Idx| self.half_edges.get(h).and_then(Option::as_ref);
+        let mut outgoing = vec![0; self.vertices.len()];
+        for h in self.half_edge_indices() {
+            let e = self.he(h);
+            let (Some(twin), Some(next), Some(prev)) = (live(e.twin), live(e.next), live(e.prev)) else {
+                return wrong(format!("The half-edge {} refers to a removed one", h));
+            };
+            if twin.twin != h || next.prev != h || prev.next != h {
+                return wrong(format!("The links of the half-edge {} are not mutual", h));
+            }
+            if twin.origin == e.origin || next.origin != twin.origin {
+                return wrong(format!("The half-edge {} is not oriented the same way as its neighbours", h));
+            }
+            if next.face != e.face || e.face.is_some_and(|f| !matches!(self.faces.get(f), Some(Some(_)))) {
+                return wrong(format!("The face of the half-edge {} is wrong", h));
+            }
+            if self.vertex(e.origin).is_err() {
+                return wrong(format!("The half-edge {} starts from a removed vertex", h));
+            }
+            outgoing[e.origin] += 1;
+        }
+        for f in self.face_indices() {
+            let h = self.faces[f].unwrap();
+            if live(h).map(|e| e.face) != Some(Some(f)) {
+                return wrong(format!("The face {} refers to a wrong half-edge", f));
+            }
+        }
+        for v in self.vertex_indices() {
+            let Some(start) = self.outgoing[v] else {
+                if outgoing[v] > 0 {
+                    return wrong(format!("The vertex {} has no half-edge", v));
+                }
+                continue;
+            };
+            if live(start).map(|e| e.origin) != Some(v) {
+                return wrong(format!("The half-edge of the vertex {} does not start from it", v));
+            }
+            let fan = self.fan(v);
+            if fan.len() != outgoing[v] {
+                return wrong(format!("The faces around the vertex {} do not make a single fan", v));
+            }
+            if self.he(start).face.is_some() && fan.iter().any(|h| self.he(*h).face.is_none()) {
+                return wrong(format!("The half-edge of the vertex {} should be on the boundary", v));
+            }
+        }
+        Ok(())
+    }
+
+    fn he(&self, h: Idx) -> &HalfEdge {
+        self.half_edges[h].as_ref().expect("the half-edge is removed")
+    }
+    fn he_mut(&mut self, h: Idx) -> &mut HalfEdge {
+        self.half_edges[h].as_mut().expect("the half-edge is removed")
+    }
+    fn end(&self, h: Idx) -> Idx {
+        self.he(self.he(h).twin).origin
+    }
+    fn link(&mut self, prev: Idx, next: Idx) {
+        self.he_mut(prev).next = next;
+        self.he_mut(next).prev = prev;
+    }
+
+    /// The next half-edge starting from the same vertex.
+    fn rotate(&self, h: Idx) -> Idx {
+        self.he(self.he(h).twin).next
+    }
+    fn fan(&self, v: Idx) -> Vec<Idx> {
+        let mut fan = vec![];
+        if let Some(start) = self.outgoing[v] {
+            let mut h = start;
+            loop {
+                fan.push(h);
+                h = self.rotate(h);
+                if h == start || fan.len() > self.half_edges.len() {
+                    break;
+                }
+            }
+        }
+        fan
+    }
+    /// The half-edges following the given one till it is reached again.
+    fn cycle(&self, start: Idx) -> Vec<Idx> {
+        let mut cycle = vec![start];
+        let mut h = self.he(start).next;
+        while h != start && cycle.len() <= self.half_edges.len() {
+            cycle.push(h);
+            h = self.he(h).next;
+        }
+        cycle
+    }
+
+    /// Points the vertex to its boundary half-edge if there is one.
+    fn adjust_outgoing(&mut self, v: Idx) {
+        if let Some(h) = self.fan(v).into_iter().find(|h| self.he(*h).face.is_none()) {
+            self.outgoing[v] = Some(h);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::HasMesh;
+
+    fn grid_with_hole() -> Mesh {
+        // the 3x3 grid of quads without the middle one
+        let mut vertices = vec![];
+        let mut faces = vec![];
+        for i in 0..4 {
+            for j in 0..4 {
+                vertices.push(Vertex::new(i as f32, j as f32, 0.0));
+            }
+        }
+        for i in 0..3 {
+            for j in 0..3 {
+                if (i, j) != (1, 1) {
+                    let v = i * 4 + j;
+                    faces.push(Face::new4(v, v + 4, v + 5, v + 1));
+                }
+            }
+        }
+        Mesh::from_vertices(vertices, faces, Color::default().into())
+    }
+
+    #[test]
+    fn closed_cube() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Triangle, Color::default());
+        let he = cube.mesh().try_half_edges().unwrap();
+        assert_eq!((he.vertex_count(), he.edge_count(), he.face_count()), (8, 18, 12));
+        assert!(he.is_closed());
+        assert!(he.boundary_loops().is_empty());
+        for v in he.vertex_indices() {
+            let neighbors = he.vertex_neighbors(v).unwrap();
+            assert_eq!(neighbors.len(), he.vertex_faces(v).unwrap().len());
+            for n in neighbors {
+                let h = he.find_half_edge(v, n).unwrap();
+                assert_eq!(he.target(h).unwrap(), n);
+                assert_eq!(he.half_edge(h).unwrap().twin, he.find_half_edge(n, v).unwrap());
+            }
+        }
+
+        let mesh = he.to_mesh(Color::default().into()).unwrap();
+        assert_eq!(mesh.vertices(), cube.mesh().vertices());
+        assert_eq!(mesh.faces(), cube.mesh().faces());
+
+        // one face turned over
+        let mut faces = cube.mesh().faces().clone();
+        let vs = faces[0].flatten();
+        faces[0] = Face::new3(vs[0], vs[2], vs[1]);
+        let mesh = Mesh::from_vertices(mesh.vertices().clone(), faces, Color::default().into());
+        assert!(matches!(mesh.try_half_edges(), Err(MeshError::WrongMesh(_))));
+    }
+
+    #[test]
+    fn boundary() {
+        let he = grid_with_hole().try_half_edges().unwrap();
+        assert_eq!((he.vertex_count(), he.edge_count(), he.face_count()), (16, 24, 8));
+        assert!(!he.is_closed());
+        let mut loops: Vec<usize> = he.boundary_loops().iter().map(|l| l.len()).collect();
+        loops.sort();
+        assert_eq!(loops, vec![4, 12]);
+
+        // the corner, the inner and the outer sides
+        assert!(he.is_boundary_vertex(0));
+        assert!(he.is_boundary_vertex(5));
+        assert_eq!(he.vertex_neighbors(0).unwrap().len(), 2);
+        assert_eq!(he.vertex_faces(5).unwrap().len(), 3);
+        let outgoing = he.outgoing(5).unwrap();
+        assert_eq!(he.half_edge(outgoing[0]).unwrap().face, None);
+        assert!(he.is_boundary_edge(he.find_half_edge(5, 6).unwrap()));
+        assert!(!he.is_boundary_edge(he.find_half_edge(4, 5).unwrap()));
+
+        let mut faces = grid_with_hole().faces().clone();
+        faces.push(Face::new3(0, 5, 1));
+        let mesh = Mesh::from_vertices(grid_with_hole().vertices().clone(), faces, Color::default().into());
+        assert!(HalfEdgeMesh::try_from(&mesh).is_err());
+    }
+}
@@ -0,0 +1,506 @@
+use crate::mesh::halfedge::{HalfEdge, HalfEdgeMesh};
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::parts::Idx;
+use crate::mesh::{MeshError, MeshResult};
+use std::collections::HashSet;
+
+/// The topological edits. They keep the structure valid or fail leaving it untouched.
+impl HalfEdgeMesh {
+    /// Adds the vertex without edges.
+    pub fn add_vertex(&mut self, vertex: Vertex) -> Idx {
+        self.vertices.push(Some(vertex));
+        self.outgoing.push(None);
+        self.vertices.len() - 1
+    }
+
+    /// Moves the vertex keeping its edges.
+    pub fn set_vertex(&mut self, v: Idx, vertex: Vertex) -> MeshResult<()> {
+        self.vertex(v)?;
+        self.vertices[v] = Some(vertex);
+        Ok(())
+    }
+
+    /// The edge between two triangles can be turned to connect their opposite vertices
+    /// if they are not connected yet.
+    pub fn is_flip_ok(&self, h: Idx) -> bool {
+        let Ok(e) = self.half_edge(h) else {
+            return false;
+        };
+        if e.face.is_none() || self.he(e.twin).face.is_none() {
+            return false;
+        }
+        if self.cycle(h).len() != 3 || self.cycle(e.twin).len() != 3 {
+            return false;
+        }
+        let (r, s) = (self.he(e.prev).origin, self.he(self.he(e.twin).prev).origin);
+        r != s && self.find_half_edge(r, s).is_none()
+    }
+
+    /// Turns the edge between two triangles so it connects their opposite vertices,
+    /// the triangles `p q r` and `q p s` become `s r p` and `r s q`.
+    pub fn flip_edge(&mut self, h: Idx) -> MeshResult<()> {
+        if !self.is_flip_ok(h) {
+            return Err(MeshError::WrongMesh(format!("The half-edge {} can not be flipped", h)));
+        }
+        let a0 = h;
+        let b0 = self.he(h).twin;
+        let (a1, a2) = (self.he(a0).next, self.he(a0).prev);
+        let (b1, b2) = (self.he(b0).next, self.he(b0).prev);
+        let (p, q) = (self.he(a0).origin, self.he(b0).origin);
+        let (r, s) = (self.he(a2).origin, self.he(b2).origin);
+        let (fa, fb) = (self.he(a0).face, self.he(b0).face);
+
+        self.he_mut(a0).origin = s;
+        self.he_mut(b0).origin = r;
+        self.link(a0, a2);
+        self.link(a2, b1);
+        self.link(b1, a0);
+        self.link(b0, b2);
+        self.link(b2, a1);
+        self.link(a1, b0);
+        self.he_mut(a1).face = fb;
+        self.he_mut(b1).face = fa;
+        self.faces[fa.unwrap()] = Some(a0);
+        self.faces[fb.unwrap()] = Some(b0);
+        if self.outgoing[p] == Some(a0) {
+            self.outgoing[p] = Some(b1);
+        }
+        if self.outgoing[q] == Some(b0) {
+            self.outgoing[q] = Some(a1);
+        }
+        Ok(())
+    }
+
+    /// Inserts the new vertex at the given position into the edge.
+    /// The triangles at the edge are split into two connecting the new vertex
+    /// to their opposite vertices, the other faces just get one more side.
+    /// Returns the new vertex.
+    pub fn split_edge(&mut self, h: Idx, vertex: Vertex) -> MeshResult<Idx> {
+        self.half_edge(h)?;
+        let t = self.he(h).twin;
+        let triangles = [h, t].map(|x| self.he(x).face.is_some() && self.cycle(x).len() == 3);
+        let (hn, tn) = (self.he(h).next, self.he(t).next);
+        let m = self.add_vertex(vertex);
+
+        // p -> m -> q and q -> m -> p
+        let h2 = self.push(HalfEdge {
+            origin: m,
+            twin: t,
+            next: hn,
+            prev: h,
+            face: self.he(h).face,
+        });
+        let t2 = self.push(HalfEdge {
+            origin: m,
+            twin: h,
+            next: tn,
+            prev: t,
+            face: self.he(t).face,
+        });
+        self.link(h, h2);
+        self.link(h2, hn);
+        self.link(t, t2);
+        self.link(t2, tn);
+        self.he_mut(h).twin = t2;
+        self.he_mut(t).twin = h2;
+        self.outgoing[m] = Some(h2);
+        self.adjust_outgoing(m);
+
+        if triangles[0] {
+            self.insert_diagonal(h2, self.he(h).prev);
+        }
+        if triangles[1] {
+            self.insert_diagonal(t2, self.he(t).prev);
+        }
+        Ok(m)
+    }
+
+    /// The edge between two triangles (or a triangle and the boundary) can be collapsed
+    /// if the vertices share no neighbours but the opposite ones of the triangles
+    /// (the link condition) and the result is not degenerate.
+    pub fn is_collapse_ok(&self, h: Idx) -> bool {
+        let Ok(e) = self.half_edge(h) else {
+            return false;
+        };
+        let o = e.twin;
+        let (v0, v1) = (e.origin, self.he(o).origin);
+        let opposite = |x: Idx| -> Result<Option<Idx>, ()> {
+            match self.he(x).face {
+                Some(_) if self.cycle(x).len() == 3 => Ok(Some(self.he(self.he(x).prev).origin)),
+                Some(_) => Err(()),
+                None => Ok(None),
+            }
+        };
+        let (Ok(vl), Ok(vr)) = (opposite(h), opposite(o)) else {
+            return false;
+        };
+        if vl == vr {
+            return false;
+        }
+        // the edge inside connecting two pieces of the boundary would pinch the surface
+        if self.is_boundary_vertex(v0) && self.is_boundary_vertex(v1) && !self.is_boundary_edge(h) {
+            return false;
+        }
+        let around: HashSet<Idx> = self.fan(v0).into_iter().map(|x| self.end(x)).collect();
+        let shared = self
+            .fan(v1)
+            .into_iter()
+            .map(|x| self.end(x))
+            .filter(|v| around.contains(v))
+            .any(|v| Some(v) != vl && Some(v) != vr);
+        if shared {
+            return false;
+        }
+        // the tetrahedron would turn into two triangles on top of each other
+        if let (Some(l), Some(r)) = (vl, vr) {
+            if self.find_half_edge(l, r).is_some() && self.fan(l).len() == 3 && self.fan(r).len() == 3 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Merges the ends of the edge into one vertex at the given position
+    /// removing the triangles at the edge, see `is_collapse_ok`.
+    /// Returns the remaining vertex, the end of the half-edge.
+    pub fn collapse_edge(&mut self, h: Idx, vertex: Vertex) -> MeshResult<Idx> {
+        if !self.is_collapse_ok(h) {
+            return Err(MeshError::WrongMesh(format!("The half-edge {} can not be collapsed", h)));
+        }
+        let o = self.he(h).twin;
+        let (hn, hp) = (self.he(h).next, self.he(h).prev);
+        let (on, op) = (self.he(o).next, self.he(o).prev);
+        let (fh, fo) = (self.he(h).face, self.he(o).face);
+        let (v0, v1) = (self.he(h).origin, self.he(o).origin);
+
+        for x in self.fan(v0) {
+            self.he_mut(x).origin = v1;
+        }
+        self.link(hp, hn);
+        self.link(op, on);
+        if let Some(f) = fh {
+            self.faces[f] = Some(hn);
+        }
+        if let Some(f) = fo {
+            self.faces[f] = Some(on);
+        }
+        if self.outgoing[v1] == Some(o) {
+            self.outgoing[v1] = Some(hn);
+        }
+        self.half_edges[h] = None;
+        self.half_edges[o] = None;
+        self.vertices[v0] = None;
+        self.outgoing[v0] = None;
+        self.vertices[v1] = Some(vertex);
+
+        // the triangles became the loops of two edges
+        if self.he(self.he(hn).next).next == hn {
+            self.collapse_loop(hn);
+        }
+        if self.he(self.he(on).next).next == on {
+            self.collapse_loop(on);
+        }
+        self.adjust_outgoing(v1);
+        Ok(v1)
+    }
+
+    /// Inserts the new vertex at the given position into the face
+    /// and connects it to every vertex of the face making a fan of triangles.
+    /// Returns the new vertex.
+    pub fn split_face(&mut self, f: Idx, vertex: Vertex) -> MeshResult<Idx> {
+        let sides = self.face_half_edges(f)?;
+        let k = sides.len();
+        let c = self.add_vertex(vertex);
+        let mut faces = vec![f];
+        for _ in 1..k {
+            faces.push(self.faces.len());
+            self.faces.push(None);
+        }
+
+        // the triangle i is made of the side i, the spoke r(i + 1) to the center and the spoke s(i) back
+        let base = self.half_edges.len();
+        let s = |i: usize| base + 2 * (i % k);
+        let r = |i: usize| base + 2 * (i % k) + 1;
+        for (i, side) in sides.iter().enumerate() {
+            let origin = self.he(*side).origin;
+            self.half_edges.push(Some(HalfEdge {
+                origin: c,
+                twin: r(i),
+                next: *side,
+                prev: r(i + 1),
+                face: Some(faces[i]),
+            }));
+            self.half_edges.push(Some(HalfEdge {
+                origin,
+                twin: s(i),
+                next: s(i + k - 1),
+                prev: sides[(i + k - 1) % k],
+                face: Some(faces[(i + k - 1) % k]),
+            }));
+        }
+        for (i, side) in sides.iter().enumerate() {
+            let e = self.he_mut(*side);
+            e.next = r(i + 1);
+            e.prev = s(i);
+            e.face = Some(faces[i]);
+            self.faces[faces[i]] = Some(*side);
+        }
+        self.outgoing[c] = Some(s(0));
+        Ok(c)
+    }
+
+    /// Removes the face leaving a hole, the edges without faces on both sides are removed as well.
+    /// The vertices left without edges stay isolated.
+    pub fn remove_face(&mut self, f: Idx) -> MeshResult<()> {
+        let sides = self.face_half_edges(f)?;
+        self.faces[f] = None;
+        for h in &sides {
+            self.he_mut(*h).face = None;
+        }
+        let vertices: Vec<Idx> = sides.iter().map(|h| self.he(*h).origin).collect();
+        let removed: Vec<Idx> = sides
+            .into_iter()
+            .filter(|h| self.he(self.he(*h).twin).face.is_none())
+            .collect();
+        for h in removed {
+            let t = self.he(h).twin;
+            let (hn, hp) = (self.he(h).next, self.he(h).prev);
+            let (tn, tp) = (self.he(t).next, self.he(t).prev);
+            self.link(hp, tn);
+            self.link(tp, hn);
+            for (x, alt) in [(h, tn), (t, hn)] {
+                let v = self.he(x).origin;
+                if self.outgoing[v] == Some(x) {
+                    self.outgoing[v] = (alt != x).then_some(alt);
+                }
+            }
+            self.half_edges[h] = None;
+            self.half_edges[t] = None;
+        }
+        for v in vertices {
+            self.adjust_outgoing(v);
+        }
+        Ok(())
+    }
+
+    /// Removes the vertex with its edges and faces leaving a hole, see `fill_hole`.
+    pub fn remove_vertex(&mut self, v: Idx) -> MeshResult<()> {
+        for f in self.vertex_faces(v)? {
+            self.remove_face(f)?;
+        }
+        if self.outgoing[v].is_some() {
+            return Err(MeshError::WrongMesh(format!("The vertex {} keeps the edges without faces", v)));
+        }
+        self.vertices[v] = None;
+        Ok(())
+    }
+
+    /// Closes the boundary loop of the half-edge by a fan of triangles from its origin,
+    /// the fan suits the convex holes. Returns the new faces.
+    pub fn fill_hole(&mut self, h: Idx) -> MeshResult<Vec<Idx>> {
+        if self.half_edge(h)?.face.is_some() {
+            return Err(MeshError::WrongMesh(format!("The half-edge {} is not on the boundary", h)));
+        }
+        let sides = self.cycle(h);
+        if sides.len() < 3 {
+            return Err(MeshError::WrongMesh(format!("The hole at the half-edge {} has less than three sides", h)));
+        }
+        let f = self.faces.len();
+        self.faces.push(Some(h));
+        for x in &sides {
+            self.he_mut(*x).face = Some(f);
+        }
+        for x in &sides {
+            self.adjust_outgoing(self.he(*x).origin);
+        }
+
+        let mut faces = vec![f];
+        let mut rest = h;
+        for _ in 3..sides.len() {
+            let next = self.he(self.he(rest).next).next;
+            let g = self.insert_diagonal(rest, next);
+            faces.push(g);
+            rest = self.faces[g].unwrap();
+        }
+        Ok(faces)
+    }
+
+    /// Fills every boundary loop, see `fill_hole`. Returns the new faces.
+    pub fn fill_holes(&mut self) -> MeshResult<Vec<Idx>> {
+        let mut faces = vec![];
+        for hole in self.boundary_loops() {
+            faces.extend(self.fill_hole(hole[0])?);
+        }
+        Ok(faces)
+    }
+
+    fn push(&mut self, half_edge: HalfEdge) -> Idx {
+        self.half_edges.push(Some(half_edge));
+        self.half_edges.len() - 1
+    }
+
+    /// Splits the face of `a` by the new edge from the origin of `a` to the origin of `b`.
+    /// The face keeps the part starting from `a`, the new face gets the rest. Returns the new face.
+    fn insert_diagonal(&mut self, a: Idx, b: Idx) -> Idx {
+        let f = self.he(a).face;
+        let (pa, pb) = (self.he(a).prev, self.he(b).prev);
+        let g = self.faces.len();
+        let x = self.half_edges.len();
+        let y = x + 1;
+        self.push(HalfEdge {
+            origin: self.he(a).origin,
+            twin: y,
+            next: b,
+            prev: pa,
+            face: Some(g),
+        });
+        self.push(HalfEdge {
+            origin: self.he(b).origin,
+            twin: x,
+            next: a,
+            prev: pb,
+            face: f,
+        });
+        self.link(pa, x);
+        self.link(x, b);
+        self.link(pb, y);
+        self.link(y, a);
+        self.faces.push(Some(x));
+        for h in self.cycle(x) {
+            self.he_mut(h).face = Some(g);
+        }
+        if let Some(f) = f {
+            self.faces[f] = Some(a);
+        }
+        g
+    }
+
+    /// Removes the loop of two half-edges `h0` and its next left by a collapse,
+    /// the face of the loop goes away and the two edges merge into one.
+    fn collapse_loop(&mut self, h0: Idx) {
+        let h1 = self.he(h0).next;
+        let o0 = self.he(h0).twin;
+        let o1 = self.he(h1).twin;
+        let (v0, v1) = (self.he(h1).origin, self.he(h0).origin);
+        let (fh, fo) = (self.he(h0).face, self.he(o0).face);
+
+        let (on, op) = (self.he(o0).next, self.he(o0).prev);
+        self.link(h1, on);
+        self.link(op, h1);
+        self.he_mut(h1).face = fo;
+        self.outgoing[v0] = Some(h1);
+        self.outgoing[v1] = Some(o1);
+        if let Some(f) = fo {
+            if self.faces[f] == Some(o0) {
+                self.faces[f] = Some(h1);
+            }
+        }
+        if let Some(f) = fh {
+            self.faces[f] = None;
+        }
+        self.half_edges[h0] = None;
+        self.half_edges[o0] = None;
+        self.adjust_outgoing(v0);
+        self.adjust_outgoing(v1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mesh::halfedge::HalfEdgeMesh;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::vertex::Vertex;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+
+    fn sphere() -> HalfEdgeMesh {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 1, Color::default().into()).unwrap();
+        sphere.mesh().try_half_edges().unwrap()
+    }
+
+    fn euler(he: &HalfEdgeMesh) -> i64 {
+        he.vertex_count() as i64 - he.edge_count() as i64 + he.face_count() as i64
+    }
+
+    #[test]
+    fn flip_and_split() {
+        let mut he = sphere();
+        let (v, e, f) = (he.vertex_count(), he.edge_count(), he.face_count());
+
+        let h = he.face_half_edges(0).unwrap()[0];
+        let (p, q) = (he.half_edge(h).unwrap().origin, he.target(h).unwrap());
+        he.flip_edge(h).unwrap();
+        he.validate().unwrap();
+        assert!(he.find_half_edge(p, q).is_none());
+        assert_eq!(he.edge_count(), e);
+
+        let m = he.split_edge(h, Vertex::new(0.0, 0.0, 1.0)).unwrap();
+        he.validate().unwrap();
+        assert_eq!(he.degree(m).unwrap(), 4);
+        assert_eq!((he.vertex_count(), he.edge_count(), he.face_count()), (v + 1, e + 3, f + 2));
+
+        let c = he.split_face(0, Vertex::new(0.0, 1.0, 0.0)).unwrap();
+        he.validate().unwrap();
+        assert_eq!(he.vertex_faces(c).unwrap().len(), 3);
+        assert_eq!(euler(&he), 2);
+        assert!(he.is_closed());
+        let mesh = he.to_mesh(Color::default().into()).unwrap();
+        assert!(mesh.properties().is_watertight());
+    }
+
+    #[test]
+    fn collapse() {
+        let mut he = sphere();
+        let mut collapsed = 0;
+        for h in 0..200 {
+            if he.is_collapse_ok(h) {
+                let end = he.target(h).unwrap();
+                let at = *he.vertex(end).unwrap();
+                he.collapse_edge(h, at).unwrap();
+                he.validate().unwrap();
+                assert_eq!(euler(&he), 2);
+                collapsed += 1;
+            }
+        }
+        assert!(collapsed > 10);
+        assert!(he.face_count() >= 4);
+        let mesh = he.to_mesh(Color::default().into()).unwrap();
+        assert!(mesh.properties().is_watertight());
+        assert!(he.collapse_edge(10_000, Vertex::default()).is_err());
+    }
+
+    #[test]
+    fn remove_and_fill() {
+        let mut he = sphere();
+        let faces = he.face_count();
+        let v = he.vertex_indices().next().unwrap();
+        let degree = he.degree(v).unwrap();
+        he.remove_vertex(v).unwrap();
+        he.validate().unwrap();
+        assert!(he.vertex(v).is_err());
+        assert_eq!(he.face_count(), faces - degree);
+        let holes = he.boundary_loops();
+        assert_eq!(holes.len(), 1);
+        assert_eq!(holes[0].len(), degree);
+        assert!(holes[0].iter().all(|h| he.is_boundary_vertex(he.half_edge(*h).unwrap().origin)));
+
+        let filled = he.fill_holes().unwrap();
+        he.validate().unwrap();
+        assert_eq!(filled.len(), degree - 2);
+        assert!(he.is_closed());
+        assert_eq!(euler(&he), 2);
+
+        // a lone face goes away with its vertices left isolated
+        let f = filled[0];
+        let mut single = he.clone();
+        for other in he.face_indices().filter(|g| *g != f) {
+            single.remove_face(other).unwrap();
+        }
+        single.validate().unwrap();
+        assert_eq!((single.face_count(), single.edge_count()), (1, 3));
+        single.remove_face(f).unwrap();
+        single.validate().unwrap();
+        assert_eq!(single.edge_count(), 0);
+        assert!(single.vertex_indices().all(|v| single.degree(v).unwrap() == 0));
+    }
+}
@@ -0,0 +1,384 @@
+use crate::mesh::fields::{FieldData, NORMALS};
+use crate::mesh::parts::bbox::BoundingBox;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// The values sampled at the points of a regular 3d grid, e.g. a CT scan or a simulation result.
+/// The point `[i, j, k]` is at `origin + [i, j, k] * spacing`
+/// and its value is at `i + nx * (j + ny * k)` in the values.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::isosurface::ScalarGrid;
+/// use tessellate::mesh::parts::bbox::BoundingBox;
+/// use tessellate::mesh::parts::vertex::Vertex;
+///
+/// let bb = BoundingBox::new(Vertex::new(-1.5, -1.5, -1.5), Vertex::new(1.5, 1.5, 1.5));
+/// let grid = ScalarGrid::sample(&bb, [31, 31, 31], |v| v.distance(&Vertex::default())).unwrap();
+/// let sphere = grid.isosurface(1.0).unwrap();
+/// assert!(sphere.properties().is_watertight());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarGrid {
+    origin: Vertex,
+    spacing: Vertex,
+    dims: [usize; 3],
+    values: Vec<f32>,
+}
+
+/// The settings of the isosurface extraction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsosurfaceOptions {
+    iso_value: f32,
+    weld: bool,
+    normals: bool,
+}
+
+impl From<f32> for IsosurfaceOptions {
+    fn from(iso_value: f32) -> Self {
+        IsosurfaceOptions::new(iso_value)
+    }
+}
+
+impl IsosurfaceOptions {
+    /// The welded surface with the normals.
+    pub fn new(iso_value: f32) -> Self {
+        IsosurfaceOptions {
+            iso_value,
+            weld: true,
+            normals: true,
+        }
+    }
+
+    /// Shares the vertices between the neighbouring triangles, otherwise every triangle has its own ones.
+    pub fn with_weld(self, weld: bool) -> Self {
+        IsosurfaceOptions { weld, ..self }
+    }
+
+    /// Stores the normals interpolated from the gradient of the values in the `normals` vertex field.
+    pub fn with_normals(self, normals: bool) -> Self {
+        IsosurfaceOptions { normals, ..self }
+    }
+
+    pub fn iso_value(&self) -> f32 {
+        self.iso_value
+    }
+
+    pub fn weld(&self) -> bool {
+        self.weld
+    }
+
+    pub fn normals(&self) -> bool {
+        self.normals
+    }
+}
+
+impl ScalarGrid {
+    /// Fails if the number of the values does not match the dimensions
+    /// or the grid has less than two points along some axis.
+    pub fn new(origin: Vertex, spacing: Vertex, dims: [usize; 3], values: Vec<f32>) -> MeshResult<Self> {
+        if dims.iter().any(|d| *d < 2) {
+            return Err(MeshError::Custom(format!("The grid needs at least 2 points along every axis, got {:?}", dims)));
+        }
+        if values.len() != dims.iter().product::<usize>() {
+            return Err(MeshError::Custom(format!(
+                "The grid {:?} has {} values, expected {}",
+                dims,
+                values.len(),
+                dims.iter().product::<usize>()
+            )));
+        }
+        Ok(ScalarGrid { origin, spacing, dims, values })
+    }
+
+    /// Samples the function at the points of the grid spanning the bounding box,
+    /// at least two points along every axis.
+    pub fn sample<F>(bb: &BoundingBox, dims: [usize; 3], f: F) -> MeshResult<Self>
+    where
+        F: Fn(Vertex) -> f32,
+    {
+        let extent = *bb.max() - *bb.min();
+        let [nx, ny, nz] = dims.map(|d| d.max(2));
+        let spacing = Vertex::new(
+            extent.x / (nx - 1) as f32,
+            extent.y / (ny - 1) as f32,
+            extent.z / (nz - 1) as f32,
+        );
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    values.push(f(point(bb.min(), &spacing, [i, j, k])));
+                }
+            }
+        }
+        ScalarGrid::new(*bb.min(), spacing, [nx, ny, nz], values)
+    }
+
+    pub fn origin(&self) -> &Vertex {
+        &self.origin
+    }
+
+    pub fn spacing(&self) -> &Vertex {
+        &self.spacing
+    }
+
+    /// The number of the points along x, y and z.
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn value(&self, idx: [usize; 3]) -> f32 {
+        let [nx, ny, _] = self.dims;
+        self.values[idx[0] + nx * (idx[1] + ny * idx[2])]
+    }
+
+    pub fn point(&self, idx: [usize; 3]) -> Vertex {
+        point(&self.origin, &self.spacing, idx)
+    }
+
+    /// The gradient by the central differences, the one-sided ones on the sides of the grid.
+    pub fn gradient(&self, idx: [usize; 3]) -> Vec3 {
+        let spacing = [self.spacing.x, self.spacing.y, self.spacing.z];
+        let gradient = [0, 1, 2].map(|a| {
+            let (mut lower, mut upper) = (idx, idx);
+            lower[a] = idx[a].saturating_sub(1);
+            upper[a] = (idx[a] + 1).min(self.dims[a] - 1);
+            (self.value(upper) - self.value(lower)) / ((upper[a] - lower[a]) as f32 * spacing[a])
+        });
+        Vec3::from_array(gradient)
+    }
+
+    /// Extracts the surface where the values equal the iso value by the marching cubes.
+    ///
+    /// The contour of the surface in every cell is traced over the sides of the cell,
+    /// so no lookup tables are needed, and the ambiguous sides are resolved by the asymptotic decider
+    /// the same way for both cells sharing it, so the welded surface has no cracks.
+    /// The triangles look towards the larger values, e.g. outside for the signed distance.
+    pub fn isosurface<O: Into<IsosurfaceOptions>>(&self, options: O) -> MeshResult<Mesh> {
+        let options = options.into();
+        let mut surface = Surface::new(self, options);
+        let [nx, ny, nz] = self.dims;
+        for k in 0..nz - 1 {
+            for j in 0..ny - 1 {
+                for i in 0..nx - 1 {
+                    surface.add_cell([i, j, k]);
+                }
+            }
+        }
+        surface.into_mesh()
+    }
+}
+
+fn point(origin: &Vertex, spacing: &Vertex, idx: [usize; 3]) -> Vertex {
+    *origin + Vertex::new(idx[0] as f32 * spacing.x, idx[1] as f32 * spacing.y, idx[2] as f32 * spacing.z)
+}
+
+/// The grid edge from the point along the axis.
+type EdgeKey = ([usize; 3], usize);
+
+struct Surface<'a> {
+    grid: &'a ScalarGrid,
+    options: IsosurfaceOptions,
+    vertices: Vec<Vertex>,
+    normals: Vec<[f32; 3]>,
+    welded: HashMap<EdgeKey, usize>,
+    faces: Vec<Face>,
+}
+
+impl<'a> Surface<'a> {
+    fn new(grid: &'a ScalarGrid, options: IsosurfaceOptions) -> Self {
+        Surface {
+            grid,
+            options,
+            vertices: vec![],
+            normals: vec![],
+            welded: HashMap::new(),
+            faces: vec![],
+        }
+    }
+
+    fn level(&self, idx: [usize; 3]) -> f32 {
+        self.grid.value(idx) - self.options.iso_value
+    }
+
+    fn add_cell(&mut self, cell: [usize; 3]) {
+        // the contour goes from the crossing where a side is left to the crossing where it is entered
+        let mut next: HashMap<EdgeKey, EdgeKey> = HashMap::new();
+        for axis in 0..3 {
+            let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+            for high in [false, true] {
+                let mut base = cell;
+                base[axis] += high as usize;
+                let shift = |mut idx: [usize; 3], along: &[usize]| {
+                    along.iter().for_each(|a| idx[*a] += 1);
+                    idx
+                };
+                // counterclockwise looking from outside the cell
+                let mut corners = [base, shift(base, &[b]), shift(base, &[b, c]), shift(base, &[c])];
+                if !high {
+                    corners.swap(1, 3);
+                }
+                self.trace_side(&corners, &mut next);
+            }
+        }
+
+        while let Some(&start) = next.keys().next() {
+            let mut contour = vec![start];
+            let mut current = next.remove(&start).unwrap_or(start);
+            while current != start {
+                contour.push(current);
+                match next.remove(&current) {
+                    Some(following) => current = following,
+                    None => break,
+                }
+            }
+            if contour.len() >= 3 {
+                self.add_contour(&contour);
+            }
+        }
+    }
+
+    /// Adds the segments of the contour on one side of the cell,
+    /// the larger values are on the left of the segments looking from outside the cell.
+    fn trace_side(&self, corners: &[[usize; 3]; 4], next: &mut HashMap<EdgeKey, EdgeKey>) {
+        let levels = corners.map(|c| self.level(c));
+        let above = levels.map(|l| l >= 0.0);
+        let crossing = |i: usize| above[i] != above[(i + 1) % 4];
+        let exits: Vec<usize> = (0..4).filter(|i| crossing(*i) && above[*i]).collect();
+        let entries: Vec<usize> = (0..4).filter(|i| crossing(*i) && !above[*i]).collect();
+
+        for exit in exits {
+            let entry = if entries.len() == 1 {
+                entries[0]
+            } else {
+                // the saddle of the bilinear interpolation decides whether the larger values are connected
+                let [v0, v1, v2, v3] = levels;
+                let saddle = (v0 * v2 - v1 * v3) / (v0 + v2 - v1 - v3);
+                if saddle >= 0.0 {
+                    (exit + 1) % 4
+                } else {
+                    (exit + 3) % 4
+                }
+            };
+            let key = |i: usize| edge_key(corners[i], corners[(i + 1) % 4]);
+            next.insert(key(exit), key(entry));
+        }
+    }
+
+    fn add_contour(&mut self, contour: &[EdgeKey]) {
+        if self.options.weld {
+            let points: Vec<usize> = contour.iter().map(|key| self.welded_vertex(*key)).collect();
+            for i in 1..points.len() - 1 {
+                self.faces.push(Face::new3(points[0], points[i], points[i + 1]));
+            }
+        } else {
+            for i in 1..contour.len() - 1 {
+                let face = Face::new3(self.vertex(contour[0]), self.vertex(contour[i]), self.vertex(contour[i + 1]));
+                self.faces.push(face);
+            }
+        }
+    }
+
+    fn welded_vertex(&mut self, key: EdgeKey) -> usize {
+        match self.welded.get(&key) {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.vertex(key);
+                self.welded.insert(key, idx);
+                idx
+            }
+        }
+    }
+
+    /// Adds the crossing of the iso value on the grid edge.
+    fn vertex(&mut self, (from, axis): EdgeKey) -> usize {
+        let mut to = from;
+        to[axis] += 1;
+        let (lhs, rhs) = (self.level(from), self.level(to));
+        let t = if lhs == rhs { 0.5 } else { lhs / (lhs - rhs) };
+        let (p0, p1) = (self.grid.point(from), self.grid.point(to));
+        self.vertices.push(p0 + (p1 - p0) * t);
+        let normal = self.grid.gradient(from).lerp(self.grid.gradient(to), t).normalize_or_zero();
+        self.normals.push(normal.to_array());
+        self.vertices.len() - 1
+    }
+
+    fn into_mesh(self) -> MeshResult<Mesh> {
+        let mut mesh = Mesh::from_vertices(self.vertices, self.faces, Default::default());
+        if self.options.normals && !mesh.vertices().is_empty() {
+            mesh.add_vertex_field(NORMALS, FieldData::Vector(self.normals))?;
+        }
+        Ok(mesh)
+    }
+}
+
+fn edge_key(lhs: [usize; 3], rhs: [usize; 3]) -> EdgeKey {
+    let from = lhs.min(rhs);
+    let axis = (0..3).find(|a| lhs[*a] != rhs[*a]).unwrap_or(0);
+    (from, axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(size: f32) -> BoundingBox {
+        BoundingBox::new(Vertex::new(-size, -size, -size), Vertex::new(size, size, size))
+    }
+
+    #[test]
+    fn sphere() {
+        let grid = ScalarGrid::sample(&cube(1.5), [31, 31, 31], |v| v.distance(&Vertex::default())).unwrap();
+        let mesh = grid.isosurface(1.0).unwrap();
+        let stats = mesh.statistics().unwrap();
+        assert!(stats.is_watertight);
+        assert_eq!(stats.genus, Some(0));
+        let volume = mesh.mass_properties().unwrap().volume;
+        assert!((volume - 4.0 / 3.0 * std::f32::consts::PI).abs() < 0.05, "{}", volume);
+        assert!(stats.volume.unwrap() > 0.0);
+
+        let normals = mesh.fields().vertex(NORMALS).and_then(|f| f.as_vector()).unwrap();
+        for (v, n) in mesh.vertices().iter().zip(normals) {
+            let radial: Vec3 = (*v).into();
+            assert!(radial.normalize().dot(Vec3::from(*n)) > 0.99);
+        }
+        assert_eq!(mesh.try_normals().unwrap().get_normal(0).unwrap(), &Vec3::from(normals[0]).normalize());
+    }
+
+    #[test]
+    fn torus_without_welding() {
+        let torus = |v: Vertex| {
+            let ring = (v.x * v.x + v.y * v.y).sqrt() - 1.0;
+            (ring * ring + v.z * v.z).sqrt() - 0.4
+        };
+        let options = IsosurfaceOptions::new(0.0);
+        let welded = Mesh::from_implicit(torus, &cube(1.6), [33, 33, 17], options).unwrap();
+        assert_eq!(welded.statistics().unwrap().genus, Some(1));
+
+        let soup = Mesh::from_implicit(torus, &cube(1.6), [33, 33, 17], options.with_weld(false).with_normals(false)).unwrap();
+        assert_eq!(soup.faces().len(), welded.faces().len());
+        assert_eq!(soup.vertices().len(), 3 * soup.faces().len());
+        assert!(soup.fields().vertex(NORMALS).is_none());
+    }
+
+    #[test]
+    fn ambiguous_sides_and_errors() {
+        // the diagonal corners above the iso value on every side of the cell
+        let values = vec![1.0, -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0];
+        let grid = ScalarGrid::new(Vertex::default(), Vertex::new(1.0, 1.0, 1.0), [2, 2, 2], values).unwrap();
+        let mesh = grid.isosurface(0.0).unwrap();
+        assert!(!mesh.faces().is_empty());
+        assert!(mesh.properties().is_non_self_intersecting());
+
+        assert!(ScalarGrid::new(Vertex::default(), Vertex::new(1.0, 1.0, 1.0), [2, 2, 2], vec![0.0; 7]).is_err());
+        assert!(ScalarGrid::new(Vertex::default(), Vertex::new(1.0, 1.0, 1.0), [1, 2, 2], vec![0.0; 4]).is_err());
+    }
+}
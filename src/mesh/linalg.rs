@@ -0,0 +1,134 @@
+//! The sparse linear systems and the cotangent weights shared by the mesh algorithms.
+
+use crate::mesh::Mesh;
+use glam::{DVec3, Vec3};
+
+/// The symmetric sparse matrix by the rows.
+pub(crate) struct Sparse {
+    rows: Vec<Vec<(usize, f64)>>,
+}
+
+impl Sparse {
+    pub(crate) fn new(size: usize) -> Self {
+        Sparse {
+            rows: vec![vec![]; size],
+        }
+    }
+
+    pub(crate) fn add(&mut self, row: usize, col: usize, value: f64) {
+        match self.rows[row].iter_mut().find(|(c, _)| *c == col) {
+            Some((_, v)) => *v += value,
+            None => self.rows[row].push((col, value)),
+        }
+    }
+
+    pub(crate) fn add_diagonal(&mut self, diagonal: &[f64]) {
+        for (idx, value) in diagonal.iter().enumerate() {
+            self.add(idx, idx, *value);
+        }
+    }
+
+    pub(crate) fn scaled(&self, factor: f64) -> Self {
+        Sparse {
+            rows: self
+                .rows
+                .iter()
+                .map(|row| row.iter().map(|(c, v)| (*c, v * factor)).collect())
+                .collect(),
+        }
+    }
+
+    pub(crate) fn mul(&self, x: &[f64]) -> Vec<f64> {
+        self.rows
+            .iter()
+            .map(|row| row.iter().map(|(c, v)| v * x[*c]).sum())
+            .collect()
+    }
+
+    /// Solves the system from zero by the conjugate gradients.
+    /// The singular stiffness matrix is fine as long as the right side sums up to zero,
+    /// the solution is then defined up to a constant.
+    pub(crate) fn solve(&self, rhs: &[f64], eps: f64) -> Vec<f64> {
+        let steps = rhs.len().max(100) * 2;
+        conjugate_gradients(|x| self.mul(x), rhs, vec![0.0; rhs.len()], eps, steps)
+    }
+}
+
+/// Solves the symmetric positive (semi)definite system by the conjugate gradients
+/// starting from the guess `x`. The matrix is given by its product with a vector,
+/// so the systems on the grids need not be stored.
+/// Stops once the residual is below `eps` relative to the right side or after the given number of steps.
+pub(crate) fn conjugate_gradients<F>(apply: F, rhs: &[f64], mut x: Vec<f64>, eps: f64, steps: usize) -> Vec<f64>
+where
+    F: Fn(&[f64]) -> Vec<f64>,
+{
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+    let ax = apply(&x);
+    let mut r: Vec<f64> = rhs.iter().zip(&ax).map(|(b, a)| b - a).collect();
+    let mut p = r.clone();
+    let mut rr = dot(&r, &r);
+    let limit = eps * eps * dot(rhs, rhs);
+    for _ in 0..steps {
+        if rr <= limit {
+            break;
+        }
+        let ap = apply(&p);
+        let pap = dot(&p, &ap);
+        if pap.abs() <= f64::MIN_POSITIVE {
+            break;
+        }
+        let step = rr / pap;
+        x.iter_mut().zip(&p).for_each(|(x, p)| *x += step * p);
+        r.iter_mut().zip(&ap).for_each(|(r, ap)| *r -= step * ap);
+        let next = dot(&r, &r);
+        p = r.iter().zip(&p).map(|(r, p)| r + next / rr * p).collect();
+        rr = next;
+    }
+    x
+}
+
+/// The cotangent of the angle at the first point, zero for the degenerated angle.
+pub(crate) fn cot(at: DVec3, a: DVec3, b: DVec3) -> f64 {
+    let (u, v) = (a - at, b - at);
+    let cross = u.cross(v).length();
+    if cross > f64::EPSILON {
+        u.dot(v) / cross
+    } else {
+        0.0
+    }
+}
+
+/// The vertices of the mesh in the double precision.
+pub(crate) fn points(mesh: &Mesh) -> Vec<DVec3> {
+    mesh.vertices()
+        .iter()
+        .map(|v| Into::<Vec3>::into(*v).as_dvec3())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_laplacian_of_path() {
+        // -u'' = 0 on the path with u = 0 before the first node and u = 6 after the last one
+        let n = 5;
+        let mut matrix = Sparse::new(n);
+        for i in 0..n {
+            matrix.add(i, i, 2.0);
+            if i + 1 < n {
+                matrix.add(i, i + 1, -1.0);
+                matrix.add(i + 1, i, -1.0);
+            }
+        }
+        let rhs = [0.0, 0.0, 0.0, 0.0, 6.0];
+        let x = matrix.solve(&rhs, 1e-12);
+        for (i, x) in x.iter().enumerate() {
+            assert!((x - (1.0 + i as f64)).abs() < 1e-9, "{:?}", x);
+        }
+
+        let cot = cot(DVec3::ZERO, DVec3::X, DVec3::new(1.0, 1.0, 0.0));
+        assert!((cot - 1.0).abs() < 1e-12);
+    }
+}
@@ -3,6 +3,8 @@ use egui_wgpu::wgpu;
 use glam::Vec3;
 use rand::Rng;
 
+pub mod colormap;
+
 /// Represents the material properties of a mesh, which affect how it interacts with light.
 #[derive(Clone, Debug)]
 pub struct Material {
@@ -54,7 +56,7 @@ impl Default for Material {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RgbaColor(pub [u8; 4]);
 impl From<RgbaColor> for [f32; 4] {
     fn from(value: RgbaColor) -> Self {
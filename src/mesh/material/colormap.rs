@@ -0,0 +1,379 @@
+use crate::mesh::material::RgbaColor;
+use crate::mesh::{MeshError, MeshResult};
+
+/// The predefined colormaps to turn the scalar values into colors.
+/// Every colormap is a set of evenly spaced color stops interpolated linearly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Plasma,
+    Inferno,
+    /// The diverging blue-white-red colormap.
+    Coolwarm,
+    Jet,
+    Grayscale,
+}
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [72, 40, 120],
+    [62, 73, 137],
+    [49, 104, 142],
+    [38, 130, 142],
+    [31, 158, 137],
+    [53, 183, 121],
+    [110, 206, 88],
+    [253, 231, 37],
+];
+
+const PLASMA: [[u8; 3]; 11] = [
+    [13, 8, 135],
+    [65, 4, 157],
+    [106, 0, 168],
+    [143, 13, 164],
+    [177, 42, 144],
+    [204, 71, 120],
+    [225, 100, 98],
+    [242, 132, 75],
+    [252, 166, 54],
+    [252, 206, 37],
+    [240, 249, 33],
+];
+
+const INFERNO: [[u8; 3]; 11] = [
+    [0, 0, 4],
+    [22, 11, 57],
+    [66, 10, 104],
+    [106, 23, 110],
+    [147, 38, 103],
+    [188, 55, 84],
+    [221, 81, 58],
+    [243, 120, 25],
+    [252, 165, 10],
+    [246, 215, 70],
+    [252, 255, 164],
+];
+
+const COOLWARM: [[u8; 3]; 5] = [
+    [59, 76, 192],
+    [141, 176, 254],
+    [221, 221, 221],
+    [244, 154, 123],
+    [180, 4, 38],
+];
+
+const JET: [[u8; 3]; 9] = [
+    [0, 0, 128],
+    [0, 0, 255],
+    [0, 128, 255],
+    [0, 255, 255],
+    [128, 255, 128],
+    [255, 255, 0],
+    [255, 128, 0],
+    [255, 0, 0],
+    [128, 0, 0],
+];
+
+const GRAYSCALE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+
+impl Colormap {
+    pub const ALL: [Colormap; 6] = [
+        Colormap::Viridis,
+        Colormap::Plasma,
+        Colormap::Inferno,
+        Colormap::Coolwarm,
+        Colormap::Jet,
+        Colormap::Grayscale,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Plasma => "plasma",
+            Colormap::Inferno => "inferno",
+            Colormap::Coolwarm => "coolwarm",
+            Colormap::Jet => "jet",
+            Colormap::Grayscale => "grayscale",
+        }
+    }
+
+    /// Finds the colormap by the name, ignoring the case.
+    pub fn by_name(name: &str) -> Option<Colormap> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Plasma => &PLASMA,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Coolwarm => &COOLWARM,
+            Colormap::Jet => &JET,
+            Colormap::Grayscale => &GRAYSCALE,
+        }
+    }
+
+    /// The color at the position `t` in the range [0, 1].
+    /// The positions outside the range are clamped.
+    pub fn sample(&self, t: f32) -> RgbaColor {
+        let stops = self.stops();
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let pos = t * (stops.len() - 1) as f32;
+        let idx = (pos.floor() as usize).min(stops.len() - 2);
+        let frac = pos - idx as f32;
+        let (lhs, rhs) = (stops[idx], stops[idx + 1]);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * frac).round() as u8;
+        RgbaColor::from_rgb(
+            mix(lhs[0], rhs[0]),
+            mix(lhs[1], rhs[1]),
+            mix(lhs[2], rhs[2]),
+        )
+    }
+}
+
+/// The settings to map the scalar values through a colormap.
+///
+/// The range is taken from the values when it is not set explicitly.
+/// The values that are not finite (or not positive for the log scale) get the NaN color.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::material::colormap::{ColorMapping, Colormap};
+/// let mapping = ColorMapping::new(Colormap::Plasma)
+///     .with_range(0.0, 10.0)
+///     .with_label("temperature");
+/// let bar = mapping.resolve(&[1.0, 2.0, 3.0]).unwrap();
+/// assert_eq!(bar.range(), (0.0, 10.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ColorMapping {
+    colormap: Colormap,
+    range: Option<(f32, f32)>,
+    log_scale: bool,
+    nan_color: RgbaColor,
+    label: Option<String>,
+}
+
+impl Default for ColorMapping {
+    fn default() -> Self {
+        ColorMapping {
+            colormap: Colormap::default(),
+            range: None,
+            log_scale: false,
+            nan_color: RgbaColor::GRAY,
+            label: None,
+        }
+    }
+}
+
+impl ColorMapping {
+    pub fn new(colormap: Colormap) -> Self {
+        ColorMapping {
+            colormap,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    pub fn with_log_scale(mut self, log_scale: bool) -> Self {
+        self.log_scale = log_scale;
+        self
+    }
+
+    pub fn with_nan_color(mut self, nan_color: RgbaColor) -> Self {
+        self.nan_color = nan_color;
+        self
+    }
+
+    pub fn with_label<L: Into<String>>(mut self, label: L) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn colormap(&self) -> Colormap {
+        self.colormap
+    }
+
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.range
+    }
+
+    pub fn log_scale(&self) -> bool {
+        self.log_scale
+    }
+
+    pub fn nan_color(&self) -> &RgbaColor {
+        &self.nan_color
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Fixes the range of the mapping for the given values.
+    ///
+    /// # Errors
+    /// The explicit range is not valid for the scale
+    /// or there are no valid values to take the range from.
+    pub fn resolve(&self, values: &[f32]) -> MeshResult<ScalarBar> {
+        let valid = |v: f32| v.is_finite() && (!self.log_scale || v > 0.0);
+        let (min, max) = match self.range {
+            Some((min, max)) => {
+                if !valid(min) || !valid(max) || min > max {
+                    return Err(MeshError::Custom(format!(
+                        "The range [{}, {}] is not valid for the colormap",
+                        min, max
+                    )));
+                }
+                (min, max)
+            }
+            None => values
+                .iter()
+                .copied()
+                .filter(|v| valid(*v))
+                .fold(None, |acc, v| match acc {
+                    None => Some((v, v)),
+                    Some((min, max)) => Some((f32::min(min, v), f32::max(max, v))),
+                })
+                .ok_or(MeshError::Custom(
+                    "There are no valid values to define the range of the colormap".to_string(),
+                ))?,
+        };
+        Ok(ScalarBar {
+            colormap: self.colormap,
+            min,
+            max,
+            log_scale: self.log_scale,
+            nan_color: self.nan_color.clone(),
+            label: self.label.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Maps the values to colors, returning them with the resolved scalar bar.
+    pub fn apply(&self, values: &[f32]) -> MeshResult<(Vec<RgbaColor>, ScalarBar)> {
+        let bar = self.resolve(values)?;
+        Ok((values.iter().map(|v| bar.color(*v)).collect(), bar))
+    }
+}
+
+/// The color mapping with the fixed range.
+/// It colors the values and describes the legend shown in the visualization.
+#[derive(Debug, Clone)]
+pub struct ScalarBar {
+    colormap: Colormap,
+    min: f32,
+    max: f32,
+    log_scale: bool,
+    nan_color: RgbaColor,
+    label: String,
+}
+
+impl ScalarBar {
+    pub fn colormap(&self) -> Colormap {
+        self.colormap
+    }
+
+    pub fn range(&self) -> (f32, f32) {
+        (self.min, self.max)
+    }
+
+    pub fn log_scale(&self) -> bool {
+        self.log_scale
+    }
+
+    pub fn nan_color(&self) -> &RgbaColor {
+        &self.nan_color
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn with_label<L: Into<String>>(mut self, label: L) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// The position of the value in the range, from 0 to 1.
+    /// Returns None for the values that can not be mapped.
+    pub fn position(&self, value: f32) -> Option<f32> {
+        if !value.is_finite() || (self.log_scale && value <= 0.0) {
+            return None;
+        }
+        let (value, min, max) = if self.log_scale {
+            (value.log10(), self.min.log10(), self.max.log10())
+        } else {
+            (value, self.min, self.max)
+        };
+        if max > min {
+            Some(((value - min) / (max - min)).clamp(0.0, 1.0))
+        } else {
+            Some(0.5)
+        }
+    }
+
+    /// The value at the position `t` in the range [0, 1], the inverse of [`ScalarBar::position`].
+    pub fn value(&self, t: f32) -> f32 {
+        if self.log_scale {
+            let (min, max) = (self.min.log10(), self.max.log10());
+            10f32.powf(min + (max - min) * t)
+        } else {
+            self.min + (self.max - self.min) * t
+        }
+    }
+
+    pub fn color(&self, value: f32) -> RgbaColor {
+        match self.position(value) {
+            Some(t) => self.colormap.sample(t),
+            None => self.nan_color.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_ends() {
+        assert_eq!(Colormap::Viridis.sample(0.0), RgbaColor::from_rgb(68, 1, 84));
+        assert_eq!(Colormap::Viridis.sample(1.0), RgbaColor::from_rgb(253, 231, 37));
+        assert_eq!(Colormap::Grayscale.sample(0.5), RgbaColor::from_rgb(128, 128, 128));
+        assert_eq!(Colormap::Jet.sample(2.0), Colormap::Jet.sample(1.0));
+        assert_eq!(Colormap::by_name("CoolWarm"), Some(Colormap::Coolwarm));
+        assert_eq!(Colormap::by_name("rainbow"), None);
+    }
+
+    #[test]
+    fn range_and_nan() {
+        let mapping = ColorMapping::new(Colormap::Grayscale).with_nan_color(RgbaColor::RED);
+        let (colors, bar) = mapping.apply(&[2.0, f32::NAN, 4.0, 3.0]).unwrap();
+        assert_eq!(bar.range(), (2.0, 4.0));
+        assert_eq!(colors[0], RgbaColor::BLACK);
+        assert_eq!(colors[1], RgbaColor::RED);
+        assert_eq!(colors[2], RgbaColor::WHITE);
+
+        let bar = mapping.clone().with_range(0.0, 1.0).resolve(&[]).unwrap();
+        assert_eq!(bar.color(5.0), RgbaColor::WHITE);
+        assert!(mapping.resolve(&[f32::NAN]).is_err());
+        assert!(mapping.with_range(1.0, 0.0).resolve(&[]).is_err());
+    }
+
+    #[test]
+    fn log_scale() {
+        let mapping = ColorMapping::new(Colormap::Grayscale).with_log_scale(true);
+        let bar = mapping.resolve(&[-1.0, 1.0, 10.0, 100.0]).unwrap();
+        assert_eq!(bar.range(), (1.0, 100.0));
+        assert_eq!(bar.position(10.0), Some(0.5));
+        assert_eq!(bar.position(0.0), None);
+        assert!((bar.value(0.5) - 10.0).abs() < 1e-4);
+        assert!(mapping.with_range(0.0, 1.0).resolve(&[]).is_err());
+    }
+}
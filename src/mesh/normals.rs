@@ -1,3 +1,4 @@
+use crate::mesh::fields::NORMALS;
 use crate::mesh::parts::face::Face;
 use crate::mesh::parts::vertex::Vertex;
 use crate::mesh::{Mesh, MeshError, MeshResult};
@@ -8,7 +9,9 @@ use std::collections::HashMap;
 /// The structure to store normals for vertices and faces
 /// The normals are calculated based on the faces
 /// The normals for vertices are calculated
-/// as the sum of the normals of the faces that share the vertex
+/// as the sum of the normals of the faces that share the vertex,
+/// unless the mesh keeps them in the `normals` vertex field (e.g. read from a file or taken from a gradient).
+/// The field is turned by `transform` along with the vertices and dropped by `smooth`.
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct MeshNormals {
     normals_vert: Vec<Vec3>,
@@ -70,6 +73,15 @@ impl MeshNormals {
             }
         }
 
+        let given = mesh
+            .fields()
+            .vertex(NORMALS)
+            .and_then(|f| f.as_vector())
+            .filter(|given| given.len() == normals.len());
+        if let Some(given) = given {
+            normals = given.iter().map(|n| Vec3::from(*n)).collect();
+        }
+
         Ok(Self {
             normals_vert: normals.into_iter().map(|n| n.normalize()).collect(),
             normals_face: face_normals.into_iter().map(|n| n.normalize()).collect(),
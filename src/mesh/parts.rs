@@ -5,6 +5,7 @@ mod r#macro;
 pub mod polygon;
 pub mod vertex;
 pub mod plane;
+pub mod predicates;
 pub mod ray;
 
 pub type Idx = usize;
@@ -33,4 +34,16 @@ mod tests {
 
         assert!(e1.is_intersected(&e1).unwrap());
     }
+    #[test]
+    fn find_intersection_edges() {
+        let e1 = edge!(v!(), v!(1, 1, 1));
+        let e2 = edge!(v!(1,,), v!(,1,1));
+        assert_eq!(e1.find_intersection(&e2), Some(v!(0.5, 0.5, 0.5)));
+    }
+    #[test]
+    fn find_intersection_skew_edges() {
+        let e1 = edge!(v!(), v!(1, 1, 0));
+        let e2 = edge!(v!(1, 0, 0.001), v!(0, 1, 0.001));
+        assert_eq!(e1.find_intersection(&e2), None);
+    }
 }
@@ -1,10 +1,11 @@
 use crate::mesh::attributes::Attributes;
 use crate::mesh::material::Color;
 use crate::mesh::parts::face::FaceType;
-use crate::mesh::parts::polygon::Polygon;
+use crate::mesh::parts::polygon::{Polygon, Triangle};
 use crate::mesh::parts::vertex::Vertex;
 use crate::mesh::shape::cuboid::rect_cuboid::RectCuboid;
 use crate::mesh::Mesh;
+use glam::Vec3;
 
 /// Bounding box
 /// The structure to store the bounding box of a mesh
@@ -67,6 +68,32 @@ impl BoundingBox {
         false
     }
 
+    /// Check if the bounding box overlaps a triangle by the separating axis test.
+    /// Unlike `intersects_polygon` it catches the triangles crossing the box without a vertex in it.
+    /// Touching the box counts as overlapping.
+    pub fn intersects_triangle(&self, triangle: &Triangle) -> bool {
+        let center: Vec3 = self.center().into();
+        let max: Vec3 = self.max_vertex.into();
+        let half = max - center;
+        let (v0, v1, v2) = triangle.vertices();
+        let points = [v0, v1, v2].map(|v| Into::<Vec3>::into(v) - center);
+        let edges = [points[1] - points[0], points[2] - points[1], points[0] - points[2]];
+
+        let mut axes = vec![Vec3::X, Vec3::Y, Vec3::Z, edges[0].cross(edges[1])];
+        for edge in edges {
+            axes.extend([Vec3::X.cross(edge), Vec3::Y.cross(edge), Vec3::Z.cross(edge)]);
+        }
+        axes.into_iter()
+            .filter(|axis| axis.length_squared() > f32::EPSILON * f32::EPSILON)
+            .all(|axis| {
+                let projections = points.map(|p| p.dot(axis));
+                let radius = half.dot(axis.abs());
+                let min = projections.into_iter().fold(f32::MAX, f32::min);
+                let max = projections.into_iter().fold(f32::MIN, f32::max);
+                min <= radius && max >= -radius
+            })
+    }
+
     pub fn from_polygons(polygons: &Vec<Polygon>) -> BoundingBox {
         polygons
             .into_iter()
@@ -1,7 +1,10 @@
 use std::hash::Hash;
 use crate::mesh::parts::vertex::Vertex;
 use crate::mesh::{MeshResult};
-use glam::Vec3;
+use crate::mesh::parts::polygon::intersection::{
+    segment_segment_intersect_3d, SimplexIntersection,
+};
+use glam::{DVec3, Vec3};
 
 /// Mesh edge
 /// The structure to store the edge of a mesh
@@ -142,31 +145,34 @@ impl Edge {
         }
     }
 
+    /// Finds the point where two edges intersect.
+    /// Whether the edges intersect is decided by the exact predicates,
+    /// the point itself is calculated in double precision.
+    /// Returns `None` if the edges do not intersect or overlap (being collinear).
     pub fn find_intersection(&self, edge: &Edge) -> Option<Vertex> {
-        match (self, edge) {
-            (Edge { a: a1, b: b1 }, Edge { a: a2, b: b2 }) => {
-                let diff_e1: Vec3 = (*b1 - *a1).into();
-                let diff_e2: Vec3 = (*b2 - *a2).into();
-                let cross = diff_e1.cross(diff_e2);
-
-                if cross.length_squared() == 0.0 {
-                    // Lines are parallel, no intersection unless they are collinear
-                    return None;
-                }
-
-                // Parameterize lines a1 + t * de1 and a2 + u * de2 and solve for t and u
-                let denom = cross.dot(cross);
-                let diff: Vec3 = (*a2 - *a1).into();
+        let (Edge { a: a1, b: b1 }, Edge { a: a2, b: b2 }) = (self, edge);
+        match segment_segment_intersect_3d(a1, b1, a2, b2) {
+            Ok(SimplexIntersection::Intersect | SimplexIntersection::SimplicialComplex) => {}
+            _ => return None,
+        }
 
-                let t = diff.cross(diff_e2).dot(cross) / denom;
-                let u = diff.cross(diff_e1).dot(cross) / denom;
-                // Check if t and u are within [0, 1] which means the intersection lies within both segments
-                if t >= 0.0 && t <= 1.0 && u >= 0.0 && u <= 1.0 {
-                    Some(*a1 + <Vec3 as Into<Vertex>>::into((diff_e1 * t).into()))
-                } else {
-                    None
-                }
-            }
+        let (a1, b1, a2, b2) = (dvec(a1), dvec(b1), dvec(a2), dvec(b2));
+        let diff_e1 = b1 - a1;
+        let diff_e2 = b2 - a2;
+        let cross = diff_e1.cross(diff_e2);
+        let denom = cross.length_squared();
+        if denom == 0.0 {
+            // the edges are collinear
+            return None;
         }
+
+        // Parameterize lines a1 + t * de1 and a2 + u * de2 and solve for t
+        let t = (a2 - a1).cross(diff_e2).dot(cross) / denom;
+        let p = a1 + diff_e1 * t.clamp(0.0, 1.0);
+        Some(Vertex::new(p.x as f32, p.y as f32, p.z as f32))
     }
 }
+
+fn dvec(v: &Vertex) -> DVec3 {
+    DVec3::new(v.x as f64, v.y as f64, v.z as f64)
+}
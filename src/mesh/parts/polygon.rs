@@ -238,6 +238,15 @@ mod tests {
         assert!(p1.intersects(&p2).unwrap());
     }
 
+    #[test]
+    fn intersects_nearly_coplanar() {
+        let p1 = poly!(ref &v!(0,0,0), &v!(1,0,0), &v!(0,1,0));
+        let p2 = poly!(ref &Vertex::new(0.2, 0.2, 1e-30), &v!(1,1,0), &v!(0.2,1,1));
+        assert!(p1.intersects_precise(&p2).unwrap().do_not_intersect());
+        let p3 = poly!(ref &Vertex::new(0.2, 0.2, -1e-30), &v!(1,1,1), &v!(0.2,1,1));
+        assert!(!p1.intersects_precise(&p3).unwrap().do_not_intersect());
+    }
+
     #[test]
     fn test_wnv() {
         // Define a polygon
@@ -1,4 +1,5 @@
 use crate::mesh::parts::polygon::Polygon;
+use crate::mesh::parts::predicates;
 use crate::mesh::parts::vertex::{Vertex, Vertex2};
 use crate::mesh::{MeshError, MeshResult};
 
@@ -175,7 +176,11 @@ pub(crate) fn triangle_is_colinear2d(
     (x1, y1): &(f32, f32),
     (x2, y2): &(f32, f32),
 ) -> bool {
-    (x1 - x0) * (y2 - y0) == (x2 - x0) * (y1 - y0)
+    orient2d(
+        &Vertex2::new(*x0, *y0),
+        &Vertex2::new(*x1, *y1),
+        &Vertex2::new(*x2, *y2),
+    ) == 0.0
 }
 pub(crate) fn calculate_segment_wntv(start: Vertex, end: Vertex, reference: Vertex) -> f32 {
     let cross = (end - start).cross(&(reference - start));
@@ -267,27 +272,14 @@ pub(crate) fn segment_triangle_intersect_3d(
 
     Ok(SimplexIntersection::Intersect)
 }
-pub fn orient3d(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex) -> f32 {
-    let adx = a.x - d.x;
-    let bdx = b.x - d.x;
-    let cdx = c.x - d.x;
-    let ady = a.y - d.y;
-    let bdy = b.y - d.y;
-    let cdy = c.y - d.y;
-    let adz = a.z - d.z;
-    let bdz = b.z - d.z;
-    let cdz = c.z - d.z;
-
-    adz * (bdx * cdy - cdx * bdy) + bdz * (cdx * ady - adx * cdy) + cdz * (adx * bdy - bdx * ady)
+/// The orientation of the point `d` relatively to the plane passing through `a`, `b` and `c`.
+/// The sign is exact, see [`predicates::orient3d`].
+pub fn orient3d(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex) -> f64 {
+    predicates::orient3d(a, b, c, d)
 }
 
-pub(crate) fn orient2d(pa: &Vertex2, pb: &Vertex2, pc: &Vertex2) -> f32 {
-    let adx = pa.x - pc.x;
-    let bdx = pb.x - pc.x;
-    let ady = pa.y - pc.y;
-    let bdy = pb.y - pc.y;
-
-    adx * bdy - bdx * ady
+pub(crate) fn orient2d(pa: &Vertex2, pb: &Vertex2, pc: &Vertex2) -> f64 {
+    predicates::orient2d(pa, pb, pc)
 }
 
 pub fn point_in_triangle_3d(p: &Vertex, t0: &Vertex, t1: &Vertex, t2: &Vertex) -> PointInSimplex {
@@ -468,7 +460,7 @@ pub(crate) fn segment_segment_intersect_2d(
     s10: &Vertex2,
     s11: &Vertex2,
 ) -> SimplexIntersection {
-    fn sign(det: f32) -> i32 {
+    fn sign(det: f64) -> i32 {
         if det > 0.0 {
             1
         } else if det < 0.0 {
@@ -0,0 +1,296 @@
+//! Exact geometric predicates.
+//!
+//! The predicates are evaluated with Shewchuk's adaptive precision arithmetic,
+//! hence the sign of the result is always correct regardless of how close the input is
+//! to the degenerate configuration.
+//! The `_sos` variants additionally break the ties (when the points are exactly
+//! collinear or coplanar) by Simulation of Simplicity (Edelsbrunner and Mücke),
+//! so they never return zero for distinct points.
+use crate::mesh::parts::vertex::{Vertex, Vertex2};
+use robust::{Coord, Coord3D};
+use std::ops::Neg;
+
+/// The sign of a predicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sign {
+    Negative,
+    Zero,
+    Positive,
+}
+
+impl Sign {
+    pub fn of(value: f64) -> Self {
+        if value > 0.0 {
+            Sign::Positive
+        } else if value < 0.0 {
+            Sign::Negative
+        } else {
+            Sign::Zero
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Sign::Zero)
+    }
+
+    pub fn as_i8(&self) -> i8 {
+        match self {
+            Sign::Negative => -1,
+            Sign::Zero => 0,
+            Sign::Positive => 1,
+        }
+    }
+}
+
+impl Neg for Sign {
+    type Output = Sign;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Sign::Negative => Sign::Positive,
+            Sign::Zero => Sign::Zero,
+            Sign::Positive => Sign::Negative,
+        }
+    }
+}
+
+fn c2(v: &Vertex2) -> Coord<f64> {
+    Coord {
+        x: v.x as f64,
+        y: v.y as f64,
+    }
+}
+
+fn c3(v: &Vertex) -> Coord3D<f64> {
+    Coord3D {
+        x: v.x as f64,
+        y: v.y as f64,
+        z: v.z as f64,
+    }
+}
+
+/// Positive if `a`, `b`, `c` go counterclockwise, negative if clockwise and zero if collinear.
+/// The value is twice the signed area of the triangle (approximately, the sign is exact).
+pub fn orient2d(a: &Vertex2, b: &Vertex2, c: &Vertex2) -> f64 {
+    robust::orient2d(c2(a), c2(b), c2(c))
+}
+
+/// Positive if `d` lies below the plane passing through `a`, `b` and `c`,
+/// where below means `a`, `b`, `c` go counterclockwise looking from above.
+/// Negative if `d` lies above the plane and zero if the points are coplanar.
+/// The value is six times the signed volume of the tetrahedron (approximately, the sign is exact).
+pub fn orient3d(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex) -> f64 {
+    robust::orient3d(c3(a), c3(b), c3(c), c3(d))
+}
+
+/// Positive if `d` lies inside the circle passing through `a`, `b` and `c`
+/// (the points go counterclockwise), negative if outside and zero if the points are cocircular.
+pub fn incircle(a: &Vertex2, b: &Vertex2, c: &Vertex2, d: &Vertex2) -> f64 {
+    robust::incircle(c2(a), c2(b), c2(c), c2(d))
+}
+
+/// Positive if `e` lies inside the sphere passing through `a`, `b`, `c` and `d`
+/// (the points are oriented so that `orient3d(a, b, c, d)` is positive),
+/// negative if outside and zero if the points are cospherical.
+pub fn insphere(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex, e: &Vertex) -> f64 {
+    robust::insphere(c3(a), c3(b), c3(c), c3(d), c3(e))
+}
+
+/// The sign of `orient2d` with the degenerate cases resolved by Simulation of Simplicity.
+/// Every point comes with a unique index (e.g. the index of the vertex in the mesh)
+/// that defines the symbolic perturbation of the point.
+/// Zero is returned only if the indexes are not unique.
+pub fn orient2d_sos(points: [(usize, &Vertex2); 3]) -> Sign {
+    let rows = points.map(|(idx, v)| (idx, [v.x as f64, v.y as f64]));
+    sos(&rows)
+}
+
+/// The sign of `orient3d` with the degenerate cases resolved by Simulation of Simplicity.
+/// See `orient2d_sos` for the meaning of the indexes.
+pub fn orient3d_sos(points: [(usize, &Vertex); 4]) -> Sign {
+    let rows = points.map(|(idx, v)| (idx, [v.x as f64, v.y as f64, v.z as f64]));
+    sos(&rows)
+}
+
+/// Evaluates the sign of the determinant
+/// | p0 1 |
+/// | .. 1 |
+/// | pd 1 |
+/// where every coordinate `j` of the point with the index `i` is perturbed by `ε^(2^(i*D + j))`.
+///
+/// The perturbed determinant is a polynomial in ε. Its terms are products of the perturbations
+/// taken from distinct rows and columns multiplied by the complementary minors of the original matrix.
+/// The sign is given by the first term (in the order of the growing power of ε)
+/// with the non-zero minor.
+fn sos<const D: usize>(rows: &[(usize, [f64; D])]) -> Sign {
+    let exact = minor(rows, &(0..rows.len()).collect::<Vec<_>>(), &(0..D).collect::<Vec<_>>());
+    if !exact.is_zero() {
+        return exact;
+    }
+    let mut indexes: Vec<usize> = rows.iter().map(|(i, _)| *i).collect();
+    indexes.sort();
+    indexes.dedup();
+    if indexes.len() != rows.len() {
+        return Sign::Zero;
+    }
+
+    let mut terms = vec![];
+    matchings(rows.len(), D, 0, &mut vec![], &mut vec![false; D], &mut terms);
+    // the power of ε is the sum of 2^rank so comparing the ranks in the descending order
+    // lexicographically gives the order of the terms
+    let mut terms: Vec<(Vec<usize>, Term)> = terms
+        .into_iter()
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let mut ranks: Vec<usize> = t.iter().map(|(r, c)| rows[*r].0 * D + c).collect();
+            ranks.sort_by(|a, b| b.cmp(a));
+            (ranks, t)
+        })
+        .collect();
+    terms.sort();
+
+    for (_, term) in terms {
+        let mut rows_left: Vec<usize> = (0..rows.len()).collect();
+        let mut cols_left: Vec<usize> = (0..=D).collect();
+        let mut sign = Sign::Positive;
+        for (r, c) in term {
+            let pr = rows_left.iter().position(|x| *x == r).unwrap_or_default();
+            let pc = cols_left.iter().position(|x| *x == c).unwrap_or_default();
+            if (pr + pc) % 2 == 1 {
+                sign = -sign;
+            }
+            rows_left.remove(pr);
+            cols_left.remove(pc);
+        }
+        cols_left.pop();
+        let m = minor(rows, &rows_left, &cols_left);
+        if !m.is_zero() {
+            return if sign == Sign::Positive { m } else { -m };
+        }
+    }
+    Sign::Zero
+}
+
+/// A product of the perturbations given as the pairs (row, column).
+type Term = Vec<(usize, usize)>;
+
+/// Collects all partial matchings between the rows and the coordinate columns.
+fn matchings(
+    rows: usize,
+    cols: usize,
+    row: usize,
+    current: &mut Vec<(usize, usize)>,
+    used: &mut Vec<bool>,
+    res: &mut Vec<Term>,
+) {
+    if row == rows {
+        res.push(current.clone());
+        return;
+    }
+    matchings(rows, cols, row + 1, current, used, res);
+    for c in 0..cols {
+        if !used[c] {
+            used[c] = true;
+            current.push((row, c));
+            matchings(rows, cols, row + 1, current, used, res);
+            current.pop();
+            used[c] = false;
+        }
+    }
+}
+
+/// The exact sign of the determinant built from the given rows and coordinate columns
+/// extended with the column of ones.
+fn minor<const D: usize>(rows: &[(usize, [f64; D])], rs: &[usize], cs: &[usize]) -> Sign {
+    let p = |r: usize, c: usize| rows[rs[r]].1[cs[c]];
+    match cs.len() {
+        0 => Sign::Positive,
+        1 => Sign::of(p(0, 0) - p(1, 0)),
+        2 => {
+            let coord = |r| Coord { x: p(r, 0), y: p(r, 1) };
+            Sign::of(robust::orient2d(coord(0), coord(1), coord(2)))
+        }
+        _ => {
+            let coord = |r| Coord3D {
+                x: p(r, 0),
+                y: p(r, 1),
+                z: p(r, 2),
+            };
+            Sign::of(robust::orient3d(coord(0), coord(1), coord(2), coord(3)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v;
+
+    #[test]
+    fn orient3d_nearly_coplanar() {
+        let a = v!(0.1, 0.7, 0.0);
+        let b = v!(1.3, 0.1, 0.0);
+        let c = v!(0.3, 1.9, 0.0);
+        assert_eq!(orient3d(&a, &b, &c, &v!(0.7, 0.9, 0.0)), 0.0);
+        assert!(orient3d(&a, &b, &c, &Vertex::new(0.7, 0.9, f32::MIN_POSITIVE)) < 0.0);
+        assert!(orient3d(&a, &b, &c, &Vertex::new(0.7, 0.9, -f32::MIN_POSITIVE)) > 0.0);
+    }
+
+    #[test]
+    fn orient2d_collinear() {
+        let a = Vertex2::new(0.1, 0.1);
+        let b = Vertex2::new(0.2, 0.2);
+        let c = Vertex2::new(0.3, 0.3);
+        assert_eq!(orient2d(&a, &b, &c), 0.0);
+        assert!(orient2d(&a, &b, &Vertex2::new(0.2, 0.3)) > 0.0);
+    }
+
+    #[test]
+    fn sos_is_consistent() {
+        let a = v!(0.0, 0.0, 0.0);
+        let b = v!(1.0, 0.0, 0.0);
+        let c = v!(0.0, 1.0, 0.0);
+        let d = v!(1.0, 1.0, 0.0);
+
+        let s = orient3d_sos([(0, &a), (1, &b), (2, &c), (3, &d)]);
+        assert!(!s.is_zero());
+        assert_eq!(orient3d_sos([(1, &b), (0, &a), (2, &c), (3, &d)]), -s);
+        assert_eq!(orient3d_sos([(0, &a), (1, &b), (3, &d), (2, &c)]), -s);
+        assert_eq!(orient3d_sos([(3, &d), (0, &a), (1, &b), (2, &c)]), -s);
+
+        let up = v!(0.0, 0.0, 1.0);
+        assert_eq!(
+            orient3d_sos([(0, &a), (1, &b), (2, &c), (3, &up)]),
+            Sign::of(orient3d(&a, &b, &c, &up))
+        );
+    }
+
+    #[test]
+    fn sos_2d() {
+        let a = Vertex2::new(0.0, 0.0);
+        let b = Vertex2::new(1.0, 1.0);
+        let c = Vertex2::new(2.0, 2.0);
+        let s = orient2d_sos([(0, &a), (1, &b), (2, &c)]);
+        assert!(!s.is_zero());
+        assert_eq!(orient2d_sos([(1, &b), (0, &a), (2, &c)]), -s);
+        assert_eq!(orient2d_sos([(0, &a), (1, &b), (1, &c)]), Sign::Zero);
+    }
+
+    #[test]
+    fn in_circle_and_sphere() {
+        let a = Vertex2::new(0.0, 0.0);
+        let b = Vertex2::new(1.0, 0.0);
+        let c = Vertex2::new(0.0, 1.0);
+        assert!(incircle(&a, &b, &c, &Vertex2::new(0.5, 0.5)) > 0.0);
+        assert_eq!(incircle(&a, &b, &c, &Vertex2::new(1.0, 1.0)), 0.0);
+        assert!(incircle(&a, &b, &c, &Vertex2::new(2.0, 2.0)) < 0.0);
+
+        let a = v!(0.0, 0.0, 0.0);
+        let b = v!(1.0, 0.0, 0.0);
+        let c = v!(0.0, 1.0, 0.0);
+        let d = v!(0.0, 0.0, 1.0);
+        let (b, c) = if orient3d(&a, &b, &c, &d) > 0.0 { (b, c) } else { (c, b) };
+        assert!(insphere(&a, &b, &c, &d, &v!(0.2, 0.2, 0.2)) > 0.0);
+        assert!(insphere(&a, &b, &c, &d, &v!(2.0, 2.0, 2.0)) < 0.0);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::mesh::material::Color;
+use crate::mesh::parts::bbox::BoundingBox;
 use crate::mesh::parts::polygon::Triangle;
-use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::parts::vertex::{Axis, Vertex};
 use crate::mesh::shape::beam::Beam;
 use rand::Rng;
 
@@ -47,6 +48,68 @@ impl Ray {
         }
     }
 
+    /// The point of the ray at the distance `t`, measured in the lengths of the direction.
+    pub fn at(&self, t: f32) -> Vertex {
+        self.origin + self.direction * t
+    }
+
+    /// Finds the distance along the ray to the triangle (Möller–Trumbore),
+    /// hitting the triangle from both sides.
+    /// Returns None if the ray misses the triangle or the triangle is behind the origin.
+    pub fn cast(&self, triangle: &Triangle) -> Option<f32> {
+        let edge1 = triangle.v1 - triangle.v0;
+        let edge2 = triangle.v2 - triangle.v0;
+        let p = self.direction.cross(&edge2);
+        let det = edge1.dot(&p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = self.origin - triangle.v0;
+        let u = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(&q) * inv_det;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// Finds the distance along the ray to the entry into the box (the slab test).
+    /// The origin inside the box gives zero.
+    pub fn cast_bbox(&self, bb: &BoundingBox) -> Option<f32> {
+        let (mut t_min, mut t_max) = (0.0f32, f32::INFINITY);
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let origin = self.origin.get(&axis);
+            let direction = self.direction.get(&axis);
+            let (min, max) = (bb.min().get(&axis), bb.max().get(&axis));
+            if direction.abs() < f32::EPSILON {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min - origin) / direction, (max - origin) / direction);
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+
+    /// The distance along the ray to the point closest to `point`
+    /// and the distance between the ray and the point.
+    pub fn closest(&self, point: &Vertex) -> (f32, f32) {
+        let t = ((*point - self.origin).dot(&self.direction) / self.direction.length_squared()).max(0.0);
+        (t, self.at(t).distance(point))
+    }
+
     pub fn new_rand(origin: Vertex) -> Self {
         let mut rng = rand::thread_rng();
         Self {
@@ -75,3 +138,32 @@ fn det(a: &Vertex, b: &Vertex, c: &Vertex) -> f32 {
     a.y * (b.x * c.z - b.z * c.x) +
     a.z * (b.x * c.y - b.y * c.x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cast_triangle_and_box() {
+        let ray = Ray::new(Vertex::new(0.2, 0.2, 5.0), Vertex::new(0.0, 0.0, -1.0));
+        let triangle = Triangle::new(
+            Vertex::new(0.0, 0.0, 1.0),
+            Vertex::new(1.0, 0.0, 1.0),
+            Vertex::new(0.0, 1.0, 1.0),
+        );
+        assert_eq!(ray.cast(&triangle), Some(4.0));
+        assert_eq!(ray.at(4.0), Vertex::new(0.2, 0.2, 1.0));
+        let back = Ray::new(ray.origin, Vertex::new(0.0, 0.0, 1.0));
+        assert_eq!(back.cast(&triangle), None);
+        let aside = Ray::new(Vertex::new(0.8, 0.8, 5.0), ray.direction);
+        assert_eq!(aside.cast(&triangle), None);
+
+        let bb = BoundingBox::new(Vertex::new(-1.0, -1.0, -1.0), Vertex::new(1.0, 1.0, 1.0));
+        assert_eq!(ray.cast_bbox(&bb), Some(4.0));
+        assert_eq!(back.cast_bbox(&bb), None);
+        assert_eq!(Ray::new(Vertex::default(), ray.direction).cast_bbox(&bb), Some(0.0));
+
+        let (t, dist) = ray.closest(&Vertex::new(1.2, 0.2, 0.0));
+        assert_eq!((t, dist), (5.0, 1.0));
+    }
+}
@@ -1,8 +1,22 @@
-use crate::mesh::parts::edge::MeshEdge;
+mod self_intersection;
+
+use crate::mesh::parts::edge::{Edge, MeshEdge};
 use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::properties::self_intersection::find_self_intersections;
 use crate::mesh::{Mesh, MeshResult};
 use std::collections::{HashMap, HashSet};
 
+/// A pair of faces of a mesh intersecting each other.
+#[derive(Debug, Clone, Copy)]
+pub struct FaceIntersection {
+    /// The indexes of the faces, the lower one goes first.
+    /// The quad faces are checked as two triangles, so the same pair can appear more than once.
+    pub faces: (usize, usize),
+    /// The segment where the faces intersect.
+    /// For the coplanar faces it is the longest chord of the overlapping area.
+    pub segment: Edge,
+}
+
 /// This struct provides various methods to analyze and query the properties
 /// of a mesh, such as checking if it is manifold, watertight, or solid.
 ///
@@ -62,8 +76,19 @@ impl<'a> MeshProperties<'a> {
             && self.is_non_self_intersecting()
     }
 
+    /// Checks that no two faces of the mesh intersect each other
+    /// apart from sharing an edge or a vertex.
+    /// The mesh with invalid faces is considered self-intersecting.
     pub fn is_non_self_intersecting(&self) -> bool {
-        unimplemented!()
+        find_self_intersections(self.mesh, true)
+            .map(|res| res.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Finds all pairs of faces intersecting each other
+    /// along with the segments where they intersect.
+    pub fn self_intersections(&self) -> MeshResult<Vec<FaceIntersection>> {
+        find_self_intersections(self.mesh, false)
     }
 
     pub fn boundary_loops(&self) -> Vec<Vec<MeshEdge>> {
@@ -91,23 +116,19 @@ impl<'a> MeshProperties<'a> {
 
             let mut loop_edges = Vec::new();
             let mut current_edge = start_edge;
+            let mut next_vertex = start_edge.1;
 
             loop {
                 loop_edges.push(MeshEdge(current_edge.0, current_edge.1));
                 visited.insert(current_edge);
 
-                let next_vertex =
-                    if current_edge.0 == start_edge.0 || current_edge.0 == start_edge.1 {
-                        current_edge.1
-                    } else {
-                        current_edge.0
-                    };
-
                 let next_edge = boundary_edges.iter().find(|&&edge| {
                     (edge.0 == next_vertex || edge.1 == next_vertex) && !visited.contains(&edge)
                 });
 
                 if let Some(&next_edge) = next_edge {
+                    // the edges are stored by the lower index first, so the loop goes either way
+                    next_vertex = if next_edge.0 == next_vertex { next_edge.1 } else { next_edge.0 };
                     current_edge = next_edge;
                 } else {
                     break;
@@ -165,4 +186,127 @@ mod tests {
         let properties = MeshProperties::new(&mesh);
         assert!(properties.is_manifold());
     }
+
+    #[test]
+    fn closed_shapes_are_solid() {
+        use crate::mesh::parts::face::FaceType;
+        use crate::mesh::shape::cuboid::cube::Cube;
+        use crate::mesh::shape::sphere::Sphere;
+
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default());
+        assert!(cube.properties().is_solid());
+        let cube = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Quad, Color::default());
+        assert!(cube.properties().is_non_self_intersecting());
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Color::default().into()).unwrap();
+        assert!(sphere.properties().is_solid());
+    }
+
+    #[test]
+    fn overlapping_cubes_intersect() {
+        use crate::mesh::parts::face::FaceType;
+        use crate::mesh::shape::cuboid::cube::Cube;
+        use crate::mesh::Mesh;
+
+        let lhs = Cube::create([0.0, 0.0, 0.0], 1.0, FaceType::Triangle, Color::default());
+        let rhs = Cube::create([0.5, 0.5, 0.5], 1.0, FaceType::Triangle, Color::default());
+        let mut polygons = lhs.try_polygons().unwrap();
+        polygons.extend(rhs.try_polygons().unwrap());
+        let mesh = Mesh::from_polygons(polygons, Color::default().into());
+
+        let props = MeshProperties::new(&mesh);
+        assert!(!props.is_non_self_intersecting());
+        let res = props.self_intersections().unwrap();
+        assert!(!res.is_empty());
+        for r in res {
+            assert!(r.faces.0 < 12 && r.faces.1 >= 12);
+            for v in [r.segment.a, r.segment.b] {
+                assert!(v.x >= -1e-6 && v.x <= 0.5 + 1e-6);
+                assert!(v.y >= -1e-6 && v.y <= 0.5 + 1e-6);
+                assert!(v.z >= -1e-6 && v.z <= 0.5 + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn crossing_triangles() {
+        use crate::mesh::parts::face::Face;
+        use crate::mesh::parts::vertex::Vertex;
+        use crate::mesh::Mesh;
+
+        let vertices = vec![
+            Vertex::new(0.0, 0.0, 0.0),
+            Vertex::new(2.0, 0.0, 0.0),
+            Vertex::new(0.0, 2.0, 0.0),
+            Vertex::new(0.25, 0.5, -1.0),
+            Vertex::new(0.25, 0.5, 1.0),
+            Vertex::new(1.0, 0.5, 0.0),
+        ];
+        let faces = vec![Face::from((0, 1, 2)), Face::from((3, 4, 5))];
+        let mesh = Mesh::from_vertices(vertices, faces, Color::default().into());
+        let res = MeshProperties::new(&mesh).self_intersections().unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].faces, (0, 1));
+        let (a, b) = (res[0].segment.a, res[0].segment.b);
+        let (a, b) = if a.x < b.x { (a, b) } else { (b, a) };
+        assert_eq!(a, Vertex::new(0.25, 0.5, 0.0));
+        assert_eq!(b, Vertex::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn folded_and_adjacent_triangles() {
+        use crate::mesh::parts::face::Face;
+        use crate::mesh::parts::vertex::Vertex;
+        use crate::mesh::Mesh;
+
+        let vertices = vec![
+            Vertex::new(0.0, 0.0, 0.0),
+            Vertex::new(1.0, 0.0, 0.0),
+            Vertex::new(0.0, 1.0, 0.0),
+            Vertex::new(0.5, 1.0, 0.0),
+            Vertex::new(0.0, -1.0, 1.0),
+        ];
+        let adjacent = vec![Face::from((0, 1, 2)), Face::from((1, 0, 4))];
+        let mesh = Mesh::from_vertices(vertices.clone(), adjacent, Color::default().into());
+        assert!(MeshProperties::new(&mesh).is_non_self_intersecting());
+
+        let folded = vec![Face::from((0, 1, 2)), Face::from((1, 0, 3))];
+        let mesh = Mesh::from_vertices(vertices, folded, Color::default().into());
+        assert!(!MeshProperties::new(&mesh).is_non_self_intersecting());
+    }
+
+    #[test]
+    fn large_triangles_across_octants() {
+        use crate::mesh::parts::face::Face;
+        use crate::mesh::parts::vertex::Vertex;
+        use crate::mesh::Mesh;
+
+        // the crossing triangles have their vertices in different octants
+        let mut vertices = vec![
+            Vertex::new(1.0, 1.0, 5.5),
+            Vertex::new(9.0, 1.0, 5.5),
+            Vertex::new(1.0, 9.0, 5.5),
+            Vertex::new(3.0, 3.0, 1.0),
+            Vertex::new(9.0, 9.0, 1.0),
+            Vertex::new(6.0, 6.0, 9.0),
+        ];
+        let mut faces = vec![Face::from((0, 1, 2)), Face::from((3, 4, 5))];
+        let mesh = Mesh::from_vertices(vertices.clone(), faces.clone(), Color::default().into());
+        assert!(!MeshProperties::new(&mesh).is_non_self_intersecting());
+
+        // the small triangles make the octree split
+        for i in 0..60 {
+            let x = i as f32 * 0.1;
+            let start = vertices.len();
+            vertices.extend([
+                Vertex::new(x, 9.5, 0.2),
+                Vertex::new(x + 0.05, 9.5, 0.2),
+                Vertex::new(x, 9.55, 0.2),
+            ]);
+            faces.push(Face::from((start, start + 1, start + 2)));
+        }
+        let mesh = Mesh::from_vertices(vertices, faces, Color::default().into());
+        let res = MeshProperties::new(&mesh).self_intersections().unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].faces, (0, 1));
+    }
 }
@@ -0,0 +1,269 @@
+use crate::mesh::parts::edge::Edge;
+use crate::mesh::parts::polygon::intersection::triangle_is_colinear;
+use crate::mesh::parts::polygon::Polygon;
+use crate::mesh::parts::predicates::{orient2d, orient3d, Sign};
+use crate::mesh::parts::vertex::{Vertex, Vertex2};
+use crate::mesh::properties::FaceIntersection;
+use crate::mesh::query::octree::build::try_build_octree;
+use crate::mesh::{Mesh, MeshResult};
+use glam::DVec3;
+use std::collections::{HashMap, HashSet};
+
+/// A triangle of a (possibly triangulated) face.
+struct Tri {
+    face: usize,
+    /// Indexes of the vertices, the vertices with the same coordinates share the index.
+    idx: [usize; 3],
+    v: [Vertex; 3],
+}
+
+impl Tri {
+    fn min(&self) -> [f32; 3] {
+        let [a, b, c] = &self.v;
+        [a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y), a.z.min(b.z).min(c.z)]
+    }
+    fn max(&self) -> [f32; 3] {
+        let [a, b, c] = &self.v;
+        [a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y), a.z.max(b.z).max(c.z)]
+    }
+    fn overlaps(&self, other: &Tri) -> bool {
+        let (min_l, max_l, min_r, max_r) = (self.min(), self.max(), other.min(), other.max());
+        (0..3).all(|i| min_l[i] <= max_r[i] && min_r[i] <= max_l[i])
+    }
+}
+
+/// Finds the pairs of faces intersecting each other.
+///
+/// The candidate pairs are the triangles sharing a leaf of the octree built over the mesh,
+/// every candidate pair is checked with the exact predicates.
+/// The faces sharing an edge or a vertex are not considered intersecting
+/// unless they overlap beyond the shared part.
+///
+/// # Arguments
+/// * `first_only` - stop after the first intersection is found
+pub(crate) fn find_self_intersections(
+    mesh: &Mesh,
+    first_only: bool,
+) -> MeshResult<Vec<FaceIntersection>> {
+    let mut canonical = HashMap::new();
+    let ids: Vec<usize> = mesh
+        .vertices()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| *canonical.entry([v.x.to_bits(), v.y.to_bits(), v.z.to_bits()]).or_insert(i))
+        .collect();
+
+    let mut tris = vec![];
+    for (face, f) in mesh.faces().iter().enumerate() {
+        for t in f.triangulate() {
+            let [a, b, c] = t.flatten()[..] else { continue };
+            let v = [*mesh.get(a)?, *mesh.get(b)?, *mesh.get(c)?];
+            let idx = [ids[a], ids[b], ids[c]];
+            if idx[0] == idx[1] || idx[1] == idx[2] || idx[2] == idx[0] {
+                continue;
+            }
+            if triangle_is_colinear(&v[0], &v[1], &v[2]) {
+                continue;
+            }
+            tris.push(Tri { face, idx, v });
+        }
+    }
+    if tris.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let polygons: Vec<Polygon> = tris.iter().map(|t| Polygon::new(t.v.to_vec())).collect();
+    let mut lookup: HashMap<&Polygon, Vec<usize>> = HashMap::new();
+    for (i, p) in polygons.iter().enumerate() {
+        lookup.entry(p).or_default().push(i);
+    }
+
+    let octree = try_build_octree(&polygons, None)?;
+    let mut pairs = HashSet::new();
+    for leaf in octree.iter_leafs() {
+        let mut members: Vec<usize> = leaf
+            .polygons()
+            .iter()
+            .flat_map(|p| lookup.get(p).into_iter().flatten().copied())
+            .collect();
+        members.sort();
+        members.dedup();
+        for (k, &i) in members.iter().enumerate() {
+            for &j in members[k + 1..].iter() {
+                if tris[i].face != tris[j].face && tris[i].overlaps(&tris[j]) {
+                    pairs.insert((i, j));
+                }
+            }
+        }
+    }
+    let mut pairs: Vec<_> = pairs.into_iter().collect();
+    pairs.sort();
+
+    let mut res = vec![];
+    for (i, j) in pairs {
+        if let Some(segment) = intersect(&tris[i], &tris[j]) {
+            let (lhs, rhs) = (tris[i].face, tris[j].face);
+            res.push(FaceIntersection {
+                faces: (lhs.min(rhs), lhs.max(rhs)),
+                segment,
+            });
+            if first_only {
+                break;
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Checks whether two triangles intersect and returns the segment of the intersection.
+fn intersect(a: &Tri, b: &Tri) -> Option<Edge> {
+    let shared: Vec<(usize, usize)> = (0..3)
+        .flat_map(|i| (0..3).map(move |j| (i, j)))
+        .filter(|&(i, j)| a.idx[i] == b.idx[j])
+        .collect();
+
+    let intersects = match shared[..] {
+        [_, _, _] => true,
+        [(ia0, _), (ia1, _)] => {
+            let (u, w) = (&a.v[ia0], &a.v[ia1]);
+            let oa = &a.v[3 - ia0 - ia1];
+            let ob = &b.v[3 - shared[0].1 - shared[1].1];
+            // the faces folded onto each other
+            if orient3d(u, w, oa, ob) == 0.0 {
+                let proj = projection(&a.v);
+                Sign::of(orient2d(&proj(u), &proj(w), &proj(oa)))
+                    == Sign::of(orient2d(&proj(u), &proj(w), &proj(ob)))
+            } else {
+                false
+            }
+        }
+        [(ia, ib)] => {
+            let (a1, a2) = (&a.v[(ia + 1) % 3], &a.v[(ia + 2) % 3]);
+            let (b1, b2) = (&b.v[(ib + 1) % 3], &b.v[(ib + 2) % 3]);
+            segment_hits_triangle(a1, a2, &b.v) || segment_hits_triangle(b1, b2, &a.v)
+        }
+        _ => {
+            (0..3).any(|i| segment_hits_triangle(&a.v[i], &a.v[(i + 1) % 3], &b.v))
+                || (0..3).any(|i| segment_hits_triangle(&b.v[i], &b.v[(i + 1) % 3], &a.v))
+        }
+    };
+    if !intersects {
+        return None;
+    }
+
+    let mut points = vec![];
+    for i in 0..3 {
+        clip_segment(&a.v[i], &a.v[(i + 1) % 3], &b.v, &mut points);
+        clip_segment(&b.v[i], &b.v[(i + 1) % 3], &a.v, &mut points);
+    }
+    let mut segment = match points.first() {
+        Some(p) => (*p, *p),
+        None => {
+            let c = (dvec(&a.v[0]) + dvec(&a.v[1]) + dvec(&a.v[2])) / 3.0;
+            (c, c)
+        }
+    };
+    for (k, p) in points.iter().enumerate() {
+        for q in points[k + 1..].iter() {
+            if p.distance_squared(*q) > segment.0.distance_squared(segment.1) {
+                segment = (*p, *q);
+            }
+        }
+    }
+    Some(Edge::new(vertex(segment.0), vertex(segment.1)))
+}
+
+/// The projection onto the coordinate plane where the triangle has the largest area.
+fn projection(t: &[Vertex; 3]) -> fn(&Vertex) -> Vertex2 {
+    let n = (dvec(&t[1]) - dvec(&t[0])).cross(dvec(&t[2]) - dvec(&t[0])).abs();
+    if n.x >= n.y && n.x >= n.z {
+        Vertex2::yz
+    } else if n.y >= n.z {
+        Vertex2::xz
+    } else {
+        Vertex2::xy
+    }
+}
+
+/// Whether the closed segment and the closed triangle have a common point.
+fn segment_hits_triangle(s0: &Vertex, s1: &Vertex, t: &[Vertex; 3]) -> bool {
+    let o0 = Sign::of(orient3d(&t[0], &t[1], &t[2], s0));
+    let o1 = Sign::of(orient3d(&t[0], &t[1], &t[2], s1));
+    if o0 == o1 && !o0.is_zero() {
+        return false;
+    }
+    if o0.is_zero() && o1.is_zero() {
+        let proj = projection(t);
+        let (p0, p1) = (proj(s0), proj(s1));
+        let tp = [proj(&t[0]), proj(&t[1]), proj(&t[2])];
+        return point_in_triangle(&p0, &tp)
+            || point_in_triangle(&p1, &tp)
+            || (0..3).any(|i| segments_touch(&p0, &p1, &tp[i], &tp[(i + 1) % 3]));
+    }
+    // the segment crosses or touches the plane, check the line passes through the triangle
+    let signs = [(0, 1), (1, 2), (2, 0)].map(|(i, j)| Sign::of(orient3d(s0, s1, &t[i], &t[j])));
+    !(signs.contains(&Sign::Positive) && signs.contains(&Sign::Negative))
+}
+
+fn point_in_triangle(p: &Vertex2, t: &[Vertex2; 3]) -> bool {
+    let signs = [(0, 1), (1, 2), (2, 0)].map(|(i, j)| Sign::of(orient2d(&t[i], &t[j], p)));
+    !(signs.contains(&Sign::Positive) && signs.contains(&Sign::Negative))
+}
+
+fn segments_touch(a: &Vertex2, b: &Vertex2, c: &Vertex2, d: &Vertex2) -> bool {
+    let d1 = Sign::of(orient2d(c, d, a)).as_i8();
+    let d2 = Sign::of(orient2d(c, d, b)).as_i8();
+    let d3 = Sign::of(orient2d(a, b, c)).as_i8();
+    let d4 = Sign::of(orient2d(a, b, d)).as_i8();
+    if d1 * d2 < 0 && d3 * d4 < 0 {
+        return true;
+    }
+    // the point `r` is collinear with `pq`, check it lies within the segment
+    let within = |p: &Vertex2, q: &Vertex2, r: &Vertex2| {
+        p.x.min(q.x) <= r.x && r.x <= p.x.max(q.x) && p.y.min(q.y) <= r.y && r.y <= p.y.max(q.y)
+    };
+    (d1 == 0 && within(c, d, a))
+        || (d2 == 0 && within(c, d, b))
+        || (d3 == 0 && within(a, b, c))
+        || (d4 == 0 && within(a, b, d))
+}
+
+/// Collects the points of the segment lying in the triangle.
+/// The points are calculated in double precision.
+fn clip_segment(s0: &Vertex, s1: &Vertex, t: &[Vertex; 3], res: &mut Vec<DVec3>) {
+    let (p0, p1) = (dvec(s0), dvec(s1));
+    let o0 = orient3d(&t[0], &t[1], &t[2], s0);
+    let o1 = orient3d(&t[0], &t[1], &t[2], s1);
+    if o0 == 0.0 && o1 == 0.0 {
+        let proj = projection(t);
+        let (q0, q1) = (proj(s0), proj(s1));
+        let tp = [proj(&t[0]), proj(&t[1]), proj(&t[2])];
+        let ccw = orient2d(&tp[0], &tp[1], &tp[2]).signum();
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            let f0 = ccw * orient2d(&tp[i], &tp[j], &q0);
+            let f1 = ccw * orient2d(&tp[i], &tp[j], &q1);
+            if f0 < 0.0 && f1 < 0.0 {
+                return;
+            } else if f0 < 0.0 {
+                lo = f64::max(lo, f0 / (f0 - f1));
+            } else if f1 < 0.0 {
+                hi = f64::min(hi, f0 / (f0 - f1));
+            }
+        }
+        if lo <= hi {
+            res.push(p0.lerp(p1, lo));
+            res.push(p0.lerp(p1, hi));
+        }
+    } else if Sign::of(o0) != Sign::of(o1) && segment_hits_triangle(s0, s1, t) {
+        res.push(p0.lerp(p1, o0 / (o0 - o1)));
+    }
+}
+
+fn dvec(v: &Vertex) -> DVec3 {
+    DVec3::new(v.x as f64, v.y as f64, v.z as f64)
+}
+
+fn vertex(v: DVec3) -> Vertex {
+    Vertex::new(v.x as f32, v.y as f32, v.z as f32)
+}
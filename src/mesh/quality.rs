@@ -0,0 +1,208 @@
+use crate::mesh::parts::face::Face;
+use crate::mesh::{Mesh, MeshResult};
+use glam::Vec3;
+use std::fmt::{Display, Formatter};
+
+/// The measure of the shape of a face, the values are computed for the triangles and the quads
+/// after the definitions of the Verdict library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityMeasure {
+    /// The area of the face.
+    Area,
+    /// The longest edge to the inscribed circle for the triangles,
+    /// the longest edge by the perimeter to the area for the quads, 1 for the regular faces.
+    AspectRatio,
+    /// The smallest angle of the face in degrees.
+    MinAngle,
+    /// The largest angle of the face in degrees.
+    MaxAngle,
+    /// The radius of the circumscribed circle to the double radius of the inscribed one,
+    /// 1 for the equilateral triangle. The quads take the worst of the triangles at their corners,
+    /// scaled to 1 for the square.
+    RadiusRatio,
+    /// The equiangle skewness, the deviation of the angles from 60 (90 for the quads) degrees,
+    /// 0 for the regular faces and 1 for the degenerated ones.
+    Skewness,
+    /// The scaled Jacobian, the smallest sine of the angles at the corners (normalized for the triangles),
+    /// 1 for the regular faces, 0 or less for the degenerated and the concave quads.
+    Jacobian,
+}
+
+impl QualityMeasure {
+    pub const ALL: [QualityMeasure; 7] = [
+        QualityMeasure::Area,
+        QualityMeasure::AspectRatio,
+        QualityMeasure::MinAngle,
+        QualityMeasure::MaxAngle,
+        QualityMeasure::RadiusRatio,
+        QualityMeasure::Skewness,
+        QualityMeasure::Jacobian,
+    ];
+
+    /// The name of the face field the measure is stored in.
+    pub fn name(&self) -> &'static str {
+        match self {
+            QualityMeasure::Area => "area",
+            QualityMeasure::AspectRatio => "aspect_ratio",
+            QualityMeasure::MinAngle => "min_angle",
+            QualityMeasure::MaxAngle => "max_angle",
+            QualityMeasure::RadiusRatio => "radius_ratio",
+            QualityMeasure::Skewness => "skewness",
+            QualityMeasure::Jacobian => "jacobian",
+        }
+    }
+}
+
+impl Display for QualityMeasure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Computes the measure for every face of the mesh.
+pub(crate) fn face_quality(mesh: &Mesh, measure: QualityMeasure) -> MeshResult<Vec<f32>> {
+    mesh.faces()
+        .iter()
+        .map(|face| {
+            let points = face
+                .flatten()
+                .into_iter()
+                .map(|idx| mesh.get(idx).map(|v| (*v).into()))
+                .collect::<MeshResult<Vec<Vec3>>>()?;
+            Ok(quality(face, &points, measure))
+        })
+        .collect()
+}
+
+fn quality(face: &Face, points: &[Vec3], measure: QualityMeasure) -> f32 {
+    let n = points.len();
+    let edge = |i: usize| points[(i + 1) % n] - points[i];
+    let lengths: Vec<f32> = (0..n).map(|i| edge(i).length()).collect();
+    let angles: Vec<f32> = (0..n)
+        .map(|i| (-edge((i + n - 1) % n)).angle_between(edge(i)).to_degrees())
+        .collect();
+    let area = area(points);
+    let min_angle = angles.iter().copied().fold(f32::MAX, f32::min);
+    let max_angle = angles.iter().copied().fold(f32::MIN, f32::max);
+    let longest = lengths.iter().copied().fold(0.0, f32::max);
+    let perimeter: f32 = lengths.iter().sum();
+    let is_triangle = matches!(face, Face::Triangle(..));
+
+    match measure {
+        QualityMeasure::Area => area,
+        QualityMeasure::MinAngle => min_angle,
+        QualityMeasure::MaxAngle => max_angle,
+        QualityMeasure::AspectRatio if is_triangle => {
+            // the inscribed radius is the area over the half perimeter
+            longest * perimeter / (4.0 * 3f32.sqrt() * area)
+        }
+        QualityMeasure::AspectRatio => longest * perimeter / (4.0 * area),
+        QualityMeasure::RadiusRatio if is_triangle => radius_ratio(points),
+        QualityMeasure::RadiusRatio => {
+            // the corner triangles of the square have the ratio (1 + sqrt(2)) / 2
+            let worst = (0..n)
+                .map(|i| radius_ratio(&[points[(i + n - 1) % n], points[i], points[(i + 1) % n]]))
+                .fold(f32::MIN, f32::max);
+            worst * 2.0 / (1.0 + 2f32.sqrt())
+        }
+        QualityMeasure::Skewness => {
+            let equal = if is_triangle { 60.0 } else { 90.0 };
+            f32::max((max_angle - equal) / (180.0 - equal), (equal - min_angle) / equal)
+        }
+        QualityMeasure::Jacobian => {
+            let normal = if is_triangle {
+                edge(0).cross(edge(1))
+            } else {
+                (points[2] - points[0]).cross(points[3] - points[1])
+            }
+            .normalize_or_zero();
+            let jacobian = (0..n)
+                .map(|i| {
+                    let (a, b) = (edge(i), -edge((i + n - 1) % n));
+                    a.cross(b).dot(normal) / (a.length() * b.length())
+                })
+                .fold(f32::MAX, f32::min);
+            // the sine of 60 degrees is the best the triangle can get
+            if is_triangle {
+                jacobian * 2.0 / 3f32.sqrt()
+            } else {
+                jacobian
+            }
+        }
+    }
+}
+
+/// The area of the face by the length of its vector area, the concave quads are fine.
+pub(crate) fn area(points: &[Vec3]) -> f32 {
+    let n = points.len();
+    0.5 * (0..n)
+        .map(|i| points[i].cross(points[(i + 1) % n]))
+        .sum::<Vec3>()
+        .length()
+}
+
+fn radius_ratio(t: &[Vec3]) -> f32 {
+    let (a, b, c) = (t[0].distance(t[1]), t[1].distance(t[2]), t[2].distance(t[0]));
+    let area = area(t);
+    // the circumscribed radius is abc / 4S, the inscribed one is 2S / (a + b + c)
+    a * b * c * (a + b + c) / (16.0 * area * area)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::parts::vertex::Vertex;
+
+    fn close(lhs: f32, rhs: f32) -> bool {
+        (lhs - rhs).abs() < 1e-4
+    }
+
+    #[test]
+    fn regular_faces() {
+        let h = 3f32.sqrt() / 2.0;
+        let vertices = vec![
+            Vertex::new(0.0, 0.0, 0.0),
+            Vertex::new(1.0, 0.0, 0.0),
+            Vertex::new(0.5, h, 0.0),
+            Vertex::new(2.0, 0.0, 0.0),
+            Vertex::new(3.0, 0.0, 0.0),
+            Vertex::new(3.0, 1.0, 0.0),
+            Vertex::new(2.0, 1.0, 0.0),
+        ];
+        let faces = vec![Face::new3(0, 1, 2), Face::new4(3, 4, 5, 6)];
+        let mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        let values = |m| face_quality(&mesh, m).unwrap();
+
+        assert!(close(values(QualityMeasure::Area)[0], h / 2.0));
+        assert!(close(values(QualityMeasure::Area)[1], 1.0));
+        for measure in [QualityMeasure::AspectRatio, QualityMeasure::RadiusRatio, QualityMeasure::Jacobian] {
+            assert!(values(measure).iter().all(|v| close(*v, 1.0)), "{}", measure);
+        }
+        assert!(values(QualityMeasure::Skewness).iter().all(|v| close(*v, 0.0)));
+        assert_eq!(values(QualityMeasure::MinAngle).iter().map(|a| a.round()).collect::<Vec<_>>(), vec![60.0, 90.0]);
+        assert_eq!(values(QualityMeasure::MaxAngle).iter().map(|a| a.round()).collect::<Vec<_>>(), vec![60.0, 90.0]);
+    }
+
+    #[test]
+    fn bad_faces() {
+        let vertices = vec![
+            Vertex::new(0.0, 0.0, 0.0),
+            Vertex::new(10.0, 0.0, 0.0),
+            Vertex::new(5.0, 0.1, 0.0),
+            // the concave quad, the dart
+            Vertex::new(0.0, 0.0, 1.0),
+            Vertex::new(2.0, 1.0, 1.0),
+            Vertex::new(0.0, 2.0, 1.0),
+            Vertex::new(0.5, 1.0, 1.0),
+        ];
+        let faces = vec![Face::new3(0, 1, 2), Face::new4(3, 4, 5, 6)];
+        let mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        let values = |m| face_quality(&mesh, m).unwrap();
+
+        assert!(values(QualityMeasure::AspectRatio)[0] > 10.0);
+        assert!(values(QualityMeasure::RadiusRatio)[0] > 10.0);
+        assert!(values(QualityMeasure::Skewness)[0] > 0.9);
+        assert!(values(QualityMeasure::MaxAngle)[0] > 170.0);
+        assert!(values(QualityMeasure::Jacobian)[1] < 0.0);
+    }
+}
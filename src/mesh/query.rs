@@ -1,15 +1,19 @@
 use super::parts::vertex::Vertex;
 use super::MeshError;
+use crate::mesh::fields::FieldData;
 use crate::mesh::parts::edge::Edge;
 use crate::mesh::query::bsp::BSPTree;
+use crate::mesh::query::bvh::BVHTree;
 use crate::mesh::query::kdtree::KDTree;
 use crate::mesh::query::octree::Octree;
 use crate::mesh::query::sskdtree::SSKDTree;
 use crate::mesh::{Mesh, MeshResult};
 
 pub mod bsp;
+pub mod bvh;
 pub mod connectivity;
 pub mod edges;
+pub mod geodesic;
 pub mod kdtree;
 pub mod octree;
 pub mod sskdtree;
@@ -64,6 +68,11 @@ impl<'a> MeshQuery<'a> {
         BSPTree::try_from_mesh(self.0, depth)
     }
 
+    /// Try to build a BVH tree over the faces of the mesh to cast rays into it
+    pub fn try_bvh_tree(&self) -> MeshResult<BVHTree> {
+        BVHTree::try_from_mesh(self.0)
+    }
+
     /// Extract the centers of the polygons
     pub fn extract_poly_centers(&self) -> MeshResult<Vec<Vertex>> {
         self.0
@@ -195,4 +204,24 @@ impl<'a> MeshQuery<'a> {
             })
             .ok_or(MeshError::Custom("No connected region found".to_string()))
     }
+
+    /// Finds the shortest path along the edges between two vertices (Dijkstra on the edge graph).
+    ///
+    /// # Returns
+    ///
+    /// * `MeshResult<Mesh>` - The path as the lines mesh to show over the mesh,
+    ///   with the vertex field `distance`, the length of the path to every point.
+    pub fn geodesic_path(&self, from_vertex: usize, to_vertex: usize) -> MeshResult<Mesh> {
+        geodesic::geodesic_path(self.0, from_vertex, to_vertex)
+    }
+
+    /// Finds the distances along the surface from the nearest of the source vertices by the heat method.
+    ///
+    /// # Returns
+    ///
+    /// * `MeshResult<FieldData>` - The scalar field with a distance for every vertex,
+    ///   `NaN` for the vertices not connected to the sources.
+    pub fn geodesic_distances(&self, sources: &[usize]) -> MeshResult<FieldData> {
+        geodesic::geodesic_distances(self.0, sources)
+    }
 }
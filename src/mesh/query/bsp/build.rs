@@ -133,6 +133,7 @@ use crate::mesh::parts::plane::Plane;
 ///     finalize the tree construction and return the root node.
 
 use crate::mesh::parts::polygon::Polygon;
+use crate::mesh::parts::predicates::{self, Sign};
 use crate::mesh::parts::vertex::Vertex;
 
 const MAX_REC_DEPTH: usize = 60;
@@ -157,11 +158,11 @@ fn build_node(polygons: &Vec<Polygon>, residual_depth: usize) -> MeshResult<BSPN
         let mut ons = vec![];
 
         for poly in polygons {
-            match classify(poly, &plane) {
+            match classify(poly, plane_polygon) {
                 PolygonToPlane::Front => fronts.push(poly.clone()),
                 PolygonToPlane::Back => backs.push(poly.clone()),
                 PolygonToPlane::Spanning => {
-                    let (front, back) = split(poly, &plane);
+                    let (front, back) = split(poly, plane_polygon, &plane);
                     fronts.push(front);
                     backs.push(back);
                 }
@@ -183,16 +184,25 @@ fn build_node(polygons: &Vec<Polygon>, residual_depth: usize) -> MeshResult<BSPN
         }
     }
 }
-fn classify(polygon: &Polygon, plane: &Plane) -> PolygonToPlane {
+/// The side of the plane passing through the first three vertices of `plane_polygon`
+/// the vertex lies on. The front is the direction of the polygon normal.
+/// The sign is exact, see [`predicates::orient3d`].
+fn side(plane_polygon: &Polygon, v: &Vertex) -> Sign {
+    match plane_polygon.vertices().as_slice() {
+        [a, b, c, ..] => -Sign::of(predicates::orient3d(a, b, c, v)),
+        _ => Sign::Zero,
+    }
+}
+
+fn classify(polygon: &Polygon, plane_polygon: &Polygon) -> PolygonToPlane {
     let mut front = 0;
     let mut back = 0;
 
     for v in polygon.vertices() {
-        let d = plane.distance(v);
-        if d > f32::EPSILON {
-            front += 1;
-        } else if d < -f32::EPSILON {
-            back += 1;
+        match side(plane_polygon, v) {
+            Sign::Positive => front += 1,
+            Sign::Negative => back += 1,
+            Sign::Zero => {}
         }
     }
 
@@ -211,7 +221,7 @@ fn classify(polygon: &Polygon, plane: &Plane) -> PolygonToPlane {
 /// If the polygon is coplanar, it will be added to both sides.
 /// The split operation is done by iterating over the polygon's vertices
 /// and determining their position relative to the plane.
-fn split(polygon: &Polygon, plane: &Plane) -> (Polygon, Polygon) {
+fn split(polygon: &Polygon, plane_polygon: &Polygon, plane: &Plane) -> (Polygon, Polygon) {
     let mut front_vs = vec![];
     let mut back_vs = vec![];
     let len = polygon.vertices().len();
@@ -220,18 +230,26 @@ fn split(polygon: &Polygon, plane: &Plane) -> (Polygon, Polygon) {
         let current = polygon.vertices()[i];
         let next = polygon.vertices()[(i + 1) % len];
 
-        let curr_d = plane.distance(current);
-        let next_d = plane.distance(next);
+        let curr_s = side(plane_polygon, &current);
+        let next_s = side(plane_polygon, &next);
 
-        if curr_d >= 0.0 {
+        if curr_s != Sign::Negative {
             front_vs.push(current);
         }
-        if curr_d <= 0.0 {
+        if curr_s != Sign::Positive {
             back_vs.push(current);
         }
 
-        if curr_d > 0.0 && next_d < 0.0 || curr_d < 0.0 && next_d > 0.0 {
-            let t = curr_d / (curr_d - next_d);
+        if curr_s.as_i8() * next_s.as_i8() < 0 {
+            // the sides are exact, the distances are used only to find the crossing point
+            let curr_d = plane.distance(current);
+            let next_d = plane.distance(next);
+            let denom = curr_d - next_d;
+            let t = if denom == 0.0 {
+                0.5
+            } else {
+                (curr_d / denom).clamp(0.0, 1.0)
+            };
             let v = interpolate(current, next, t);
             front_vs.push(v);
             back_vs.push(v);
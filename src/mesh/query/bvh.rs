@@ -0,0 +1,224 @@
+use crate::mesh::parts::bbox::BoundingBox;
+use crate::mesh::parts::polygon::Triangle;
+use crate::mesh::parts::ray::Ray;
+use crate::mesh::parts::vertex::{Axis, Vertex};
+use crate::mesh::query::MeshQuery;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+
+const MAX_TRIANGLES: usize = 4;
+
+/// The triangle of a face, quads are split into two triangles of the same face.
+#[derive(Debug, Clone)]
+struct FaceTriangle {
+    face: usize,
+    triangle: Triangle,
+    bb: BoundingBox,
+    center: Vertex,
+}
+
+#[derive(Debug)]
+enum BVHNode {
+    Leaf {
+        bb: BoundingBox,
+        triangles: Vec<FaceTriangle>,
+    },
+    Node {
+        bb: BoundingBox,
+        left: Box<BVHNode>,
+        right: Box<BVHNode>,
+    },
+}
+
+impl BVHNode {
+    fn aabb(&self) -> &BoundingBox {
+        match self {
+            BVHNode::Leaf { bb, .. } => bb,
+            BVHNode::Node { bb, .. } => bb,
+        }
+    }
+
+    fn cast(&self, ray: &Ray, best: &mut Option<RayHit>) {
+        match ray.cast_bbox(self.aabb()) {
+            None => return,
+            Some(t) if best.as_ref().is_some_and(|hit| hit.distance < t) => return,
+            _ => {}
+        }
+        match self {
+            BVHNode::Leaf { triangles, .. } => {
+                for ft in triangles {
+                    if let Some(t) = ray.cast(&ft.triangle) {
+                        if best.as_ref().is_none_or(|hit| t < hit.distance) {
+                            *best = Some(RayHit {
+                                face: ft.face,
+                                distance: t,
+                                point: ray.at(t),
+                            });
+                        }
+                    }
+                }
+            }
+            BVHNode::Node { left, right, .. } => {
+                left.cast(ray, best);
+                right.cast(ray, best);
+            }
+        }
+    }
+}
+
+/// The nearest intersection of a ray with the faces of a mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit {
+    /// The index of the hit face in the mesh.
+    pub face: usize,
+    /// The distance along the ray, measured in the lengths of its direction.
+    pub distance: f32,
+    pub point: Vertex,
+}
+
+/// A bounding volume hierarchy (BVH) over the faces of a mesh.
+/// Every node keeps the bounding box of its triangles,
+/// the triangles are split in two halves along the longest axis of their centers.
+/// The tree is used to cast rays into the mesh, e.g. to pick the faces with the mouse.
+///
+/// # Example
+/// ```
+/// use tessellate::mesh::material::Color;
+/// use tessellate::mesh::parts::face::FaceType;
+/// use tessellate::mesh::parts::ray::Ray;
+/// use tessellate::mesh::parts::vertex::Vertex;
+/// use tessellate::mesh::query::bvh::BVHTree;
+/// use tessellate::mesh::shape::cuboid::cube::Cube;
+/// use tessellate::mesh::HasMesh;
+///
+/// let cube = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Triangle, Color::default());
+/// let tree = BVHTree::try_from_mesh(cube.mesh()).unwrap();
+/// let ray = Ray::new(Vertex::new(0.5, 0.5, 5.0), Vertex::new(0.0, 0.0, -1.0));
+/// let hit = tree.cast(&ray).unwrap();
+/// assert_eq!(hit.point, Vertex::new(0.5, 0.5, 1.0));
+/// ```
+pub struct BVHTree {
+    root: Box<BVHNode>,
+}
+
+impl BVHTree {
+    pub fn try_from_mesh(mesh: &Mesh) -> MeshResult<Self> {
+        if !mesh.is_polygons() {
+            return Err(MeshError::Custom(
+                "The BVH tree is built only for the meshes of polygons".to_string(),
+            ));
+        }
+        let mut triangles = Vec::new();
+        for (idx, face) in mesh.faces().iter().enumerate() {
+            for tri in face.triangulate() {
+                let [a, b, c] = tri.flatten()[..] else {
+                    continue;
+                };
+                let triangle = Triangle::new(*mesh.get(a)?, *mesh.get(b)?, *mesh.get(c)?);
+                triangles.push(FaceTriangle {
+                    face: idx,
+                    bb: bounds([triangle.v0, triangle.v1, triangle.v2].iter()),
+                    center: (triangle.v0 + triangle.v1 + triangle.v2) / 3.0,
+                    triangle,
+                });
+            }
+        }
+        Ok(BVHTree {
+            root: build_node(triangles),
+        })
+    }
+
+    /// Finds the nearest face hit by the ray.
+    pub fn cast(&self, ray: &Ray) -> Option<RayHit> {
+        let mut best = None;
+        self.root.cast(ray, &mut best);
+        best
+    }
+
+    pub fn aabb(&self) -> &BoundingBox {
+        self.root.aabb()
+    }
+}
+
+impl<'a> TryFrom<MeshQuery<'a>> for BVHTree {
+    type Error = MeshError;
+    fn try_from(q: MeshQuery<'a>) -> MeshResult<Self> {
+        q.try_bvh_tree()
+    }
+}
+
+fn build_node(mut triangles: Vec<FaceTriangle>) -> Box<BVHNode> {
+    let bb = triangles
+        .iter()
+        .map(|t| t.bb)
+        .reduce(BoundingBox::merge)
+        .unwrap_or_default();
+    if triangles.len() <= MAX_TRIANGLES {
+        return Box::new(BVHNode::Leaf { bb, triangles });
+    }
+    let centers = bounds(triangles.iter().map(|t| &t.center));
+    let extent = *centers.max() - *centers.min();
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        Axis::X
+    } else if extent.y >= extent.z {
+        Axis::Y
+    } else {
+        Axis::Z
+    };
+    triangles.sort_by(|a, b| a.center.get(&axis).total_cmp(&b.center.get(&axis)));
+    let right = triangles.split_off(triangles.len() / 2);
+    Box::new(BVHNode::Node {
+        bb,
+        left: build_node(triangles),
+        right: build_node(right),
+    })
+}
+
+fn bounds<'a, I: Iterator<Item = &'a Vertex>>(vertices: I) -> BoundingBox {
+    let mut min_v = Vertex::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max_v = Vertex::new(f32::MIN, f32::MIN, f32::MIN);
+    for v in vertices {
+        min_v = Vertex::new(min_v.x.min(v.x), min_v.y.min(v.y), min_v.z.min(v.z));
+        max_v = Vertex::new(max_v.x.max(v.x), max_v.y.max(v.y), max_v.z.max(v.z));
+    }
+    BoundingBox::new(min_v, max_v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::shape::cuboid::cube::Cube;
+
+    #[test]
+    fn cast_nearest_face() {
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Quad, Color::default()).into();
+        let tree = BVHTree::try_from_mesh(&cube).unwrap();
+
+        for origin in [[0.3, 0.2, 5.0], [0.3, 0.2, -5.0], [5.0, -0.4, 0.1]] {
+            let origin = Vertex::from(origin);
+            let ray = Ray::new(origin, Vertex::default() - origin);
+            let hit = tree.cast(&ray).unwrap();
+            let brute = cube
+                .faces()
+                .iter()
+                .enumerate()
+                .flat_map(|(idx, f)| f.triangulate().into_iter().map(move |t| (idx, t)))
+                .filter_map(|(idx, t)| {
+                    let vs = t.flatten();
+                    let tri = Triangle::new(cube.vertices()[vs[0]], cube.vertices()[vs[1]], cube.vertices()[vs[2]]);
+                    ray.cast(&tri).map(|d| (idx, d))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            assert_eq!(hit.face, brute.0);
+            assert!((hit.distance - brute.1).abs() < 1e-5);
+            let p = hit.point;
+            assert!((p.x.abs().max(p.y.abs()).max(p.z.abs()) - 1.0).abs() < 1e-5);
+        }
+
+        let miss = Ray::new(Vertex::new(5.0, 5.0, 5.0), Vertex::new(1.0, 0.0, 0.0));
+        assert!(tree.cast(&miss).is_none());
+        assert!(BVHTree::try_from_mesh(&Mesh::cloud(vec![[0, 0, 0]], 1, Default::default())).is_err());
+    }
+}
@@ -1,8 +1,14 @@
+use crate::mesh::material::Color;
 use crate::mesh::parts::edge::MeshEdge;
+use crate::mesh::parts::face::Face;
 use crate::mesh::{Mesh, MeshResult};
-use petgraph::algo::{kosaraju_scc, tarjan_scc};
+use petgraph::algo::tarjan_scc;
 use petgraph::graph::UnGraph;
+use std::collections::{BTreeSet, HashMap};
 
+/// Splits the mesh into the connected regions.
+/// Every region keeps the faces of the mesh as they are with the slices of the fields
+/// and the colors of the vertices or the faces.
 pub fn connected_regions(mesh: &Mesh) -> MeshResult<Vec<Mesh>> {
     let mut graph = UnGraph::new_undirected();
     let mut graph_indexes = vec![];
@@ -12,23 +18,54 @@ pub fn connected_regions(mesh: &Mesh) -> MeshResult<Vec<Mesh>> {
     for MeshEdge(a, b) in mesh.edges() {
         let lhs = graph_indexes.get(*a).ok_or("Invalid edge index")?;
         let rhs = graph_indexes.get(*b).ok_or("Invalid edge index")?;
-        graph.add_edge(lhs.clone(), rhs.clone(), ());
+        graph.add_edge(*lhs, *rhs, ());
     }
 
     let mut meshes = vec![];
     let tables = mesh.try_tables()?;
 
     for v_indexes in tarjan_scc(&graph) {
-        let mut polygons = vec![];
-        for v in v_indexes {
-            let faces = tables
-                .vertex_faces_idx(v.index())
-                .ok_or("Invalid vertex index")?;
-            for f in faces {
-                polygons.push(mesh.face_idx_to_polygon(*f)?);
+        let mut vertices: Vec<usize> = v_indexes.iter().map(|v| v.index()).collect();
+        vertices.sort();
+        let mut faces = BTreeSet::new();
+        for v in vertices.iter() {
+            // the isolated vertices have no faces
+            if let Some(v_faces) = tables.vertex_faces_idx(*v) {
+                faces.extend(v_faces.iter().copied());
             }
         }
-        meshes.push(Mesh::from_polygons(polygons, mesh.attributes.clone()))
+        let faces: Vec<usize> = faces.into_iter().collect();
+
+        let new_idx: HashMap<usize, usize> =
+            vertices.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+        let mut region_faces = vec![];
+        for f in faces.iter() {
+            let face = mesh.faces().get(*f).ok_or("Invalid face index")?;
+            let idx: Vec<usize> = face
+                .flatten()
+                .iter()
+                .map(|v| new_idx.get(v).copied().ok_or("Invalid vertex index"))
+                .collect::<Result<_, _>>()?;
+            region_faces.extend(Face::new(idx)?);
+        }
+        let region_vertices = vertices
+            .iter()
+            .map(|v| mesh.get(*v).copied())
+            .collect::<MeshResult<Vec<_>>>()?;
+
+        let mut attributes = mesh.attributes.clone();
+        match mesh.attributes.color() {
+            Color::Vertex(colors) if colors.len() == mesh.vertices().len() => {
+                attributes.set_color(Color::Vertex(vertices.iter().map(|v| colors[*v].clone()).collect()))
+            }
+            Color::Face(colors) if colors.len() == mesh.faces().len() => {
+                attributes.set_color(Color::Face(faces.iter().map(|f| colors[*f].clone()).collect()))
+            }
+            _ => {}
+        }
+        let mut region = Mesh::from_vertices(region_vertices, region_faces, attributes);
+        region.fields = mesh.fields.select(&vertices, &faces);
+        meshes.push(region)
     }
     Ok(meshes)
 }
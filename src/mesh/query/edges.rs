@@ -2,13 +2,15 @@ use crate::mesh::parts::edge::{Edge, MeshEdge};
 use crate::mesh::parts::face::Face;
 use crate::mesh::{Mesh, MeshResult};
 use glam::Vec3;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f32::consts::PI;
 
 fn edges_map(mesh: &Mesh) -> HashMap<MeshEdge, Vec<&Face>> {
     let mut edges_faces: HashMap<MeshEdge, Vec<&Face>> = HashMap::new();
     for face in mesh.faces() {
-        for edge in face.edges() {
+        for MeshEdge(a, b) in face.edges() {
+            // the faces go around the shared edge in the opposite directions
+            let edge = MeshEdge::new(a.min(b), a.max(b));
             edges_faces.entry(edge).or_insert_with(Vec::new).push(face);
         }
     }
@@ -39,6 +41,21 @@ pub(crate) fn extract_non_manifold_edges(mesh: &Mesh) -> MeshResult<Vec<Edge>> {
     extract_edges(mesh, |len| len > 2)
 }
 pub(crate) fn extract_feature_edges(mesh: &Mesh, feature_angle: f32) -> MeshResult<Vec<Edge>> {
+    feature_mesh_edges(mesh, feature_angle)?
+        .into_iter()
+        .map(|edge| Ok(Edge::new(*mesh.get(edge.0)?, *mesh.get(edge.1)?)))
+        .collect()
+}
+
+/// The indexes of the vertices of the feature edges.
+pub(crate) fn feature_vertices(mesh: &Mesh, feature_angle: f32) -> MeshResult<HashSet<usize>> {
+    Ok(feature_mesh_edges(mesh, feature_angle)?
+        .into_iter()
+        .flat_map(|MeshEdge(a, b)| [a, b])
+        .collect())
+}
+
+fn feature_mesh_edges(mesh: &Mesh, feature_angle: f32) -> MeshResult<Vec<MeshEdge>> {
     let mut edges = Vec::new();
     let edges_faces = edges_map(mesh);
     let normals = mesh.try_normals()?;
@@ -48,7 +65,7 @@ pub(crate) fn extract_feature_edges(mesh: &Mesh, feature_angle: f32) -> MeshResu
             let lhs_n = normals.get_face_normal(faces[0])?;
             let rhs_n = normals.get_face_normal(faces[1])?;
             if dihedral_angle(lhs_n, rhs_n) > feature_angle * (PI / 180.0) {
-                edges.push(Edge::new(*mesh.get(edge.0)?, *mesh.get(edge.1)?));
+                edges.push(*edge);
             }
         }
     }
@@ -0,0 +1,272 @@
+use crate::mesh::attributes::{Attributes, MeshType};
+use crate::mesh::fields::FieldData;
+use crate::mesh::parts::edge::MeshEdge;
+use crate::mesh::parts::face::Face;
+use crate::mesh::linalg::{cot, points, Sparse};
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::DVec3;
+use petgraph::algo::astar;
+use petgraph::graph::{NodeIndex, UnGraph};
+use std::collections::VecDeque;
+
+/// The relative residual the linear systems of the heat method are solved to.
+const SOLVER_EPS: f64 = 1e-12;
+
+/// Finds the shortest path along the edges of the mesh between two vertices.
+/// The path is the lines mesh with the vertex field `distance`, the length of the path to every point.
+pub fn geodesic_path(mesh: &Mesh, from: usize, to: usize) -> MeshResult<Mesh> {
+    let points = points(mesh);
+    for idx in [from, to] {
+        mesh.get(idx)?;
+    }
+    let mut graph = UnGraph::new_undirected();
+    let nodes: Vec<NodeIndex> = (0..points.len()).map(|i| graph.add_node(i)).collect();
+    for MeshEdge(a, b) in mesh.edges() {
+        let (lhs, rhs) = (*a, *b);
+        if lhs >= points.len() || rhs >= points.len() {
+            return Err(MeshError::idx_edge(lhs, rhs));
+        }
+        graph.add_edge(nodes[lhs], nodes[rhs], points[lhs].distance(points[rhs]));
+    }
+
+    // the straight distance to the target never overestimates the rest of the path,
+    // so the search finds the same path as Dijkstra visiting fewer vertices
+    let target = points[to];
+    let (_, path) = astar(
+        &graph,
+        nodes[from],
+        |n| n == nodes[to],
+        |e| *e.weight(),
+        |n| points[n.index()].distance(target),
+    )
+    .ok_or(MeshError::Custom(format!("The vertices {} and {} are not connected", from, to)))?;
+
+    let vertices: Vec<_> = path.iter().map(|n| mesh.vertices()[n.index()]).collect();
+    let edges = (1..vertices.len()).map(|i| MeshEdge::new(i - 1, i)).collect();
+    let mut distance = vec![0.0];
+    for pair in path.windows(2) {
+        let step = points[pair[0].index()].distance(points[pair[1].index()]);
+        distance.push(distance[distance.len() - 1] + step as f32);
+    }
+
+    let mut attributes = Attributes::new(MeshType::Lines);
+    attributes.with_affected_by_light(false);
+    let mut path = Mesh::from_edges(vertices, edges, attributes)?;
+    path.add_vertex_field("distance", FieldData::Scalar(distance))?;
+    Ok(path)
+}
+
+/// Finds the distances along the surface from the nearest source to every vertex by the heat method
+/// (Crane, Weischedel and Wardetzky): the heat spreads from the sources for a short time,
+/// the distance is the function which gradient follows the direction of the heat flow.
+///
+/// The vertices not connected to the sources get `NaN`.
+pub fn geodesic_distances(mesh: &Mesh, sources: &[usize]) -> MeshResult<FieldData> {
+    if sources.is_empty() {
+        return Err(MeshError::Custom("The geodesic distances need a source".to_string()));
+    }
+    for idx in sources {
+        mesh.get(*idx)?;
+    }
+    let points = points(mesh);
+    let mut triangles = Vec::new();
+    for face in mesh.faces() {
+        for t in face.triangulate() {
+            if let Face::Triangle(a, b, c) = t {
+                let t = [a, b, c];
+                if let Some(idx) = t.iter().find(|i| **i >= points.len()) {
+                    return Err(MeshError::idx_vertex(*idx));
+                }
+                triangles.push(t);
+            }
+        }
+    }
+    if triangles.is_empty() {
+        return Err(MeshError::Custom("The geodesic distances need the faces".to_string()));
+    }
+    let heat = HeatMethod::new(&points, triangles);
+
+    let mut delta = vec![0.0; points.len()];
+    for idx in sources {
+        delta[*idx] = 1.0;
+    }
+    // the heat spreads for the time of a few squared edges, the shorter time gives the sharper distances
+    // but the heat far from the sources fades below the precision of the solver
+    let time = 4.0 * heat.mean_edge.powi(2);
+    let mut lhs = heat.stiffness.scaled(time);
+    lhs.add_diagonal(&heat.mass);
+    let u = lhs.solve(&delta, SOLVER_EPS);
+
+    let divergence = heat.divergence(&points, &u);
+    let phi = heat.stiffness.solve(&divergence.iter().map(|d| -d).collect::<Vec<_>>(), SOLVER_EPS);
+
+    let reached = reached(points.len(), &heat.triangles, sources);
+    let min = sources.iter().map(|i| phi[*i]).fold(f64::MAX, f64::min);
+    let distances = phi
+        .iter()
+        .zip(reached)
+        .map(|(d, r)| if r { (d - min).max(0.0) as f32 } else { f32::NAN })
+        .collect();
+    Ok(FieldData::Scalar(distances))
+}
+
+struct HeatMethod {
+    triangles: Vec<[usize; 3]>,
+    stiffness: Sparse,
+    mass: Vec<f64>,
+    mean_edge: f64,
+}
+
+impl HeatMethod {
+    /// Builds the cotangent stiffness matrix and the lumped masses (a third of the adjacent areas).
+    fn new(points: &[DVec3], triangles: Vec<[usize; 3]>) -> Self {
+        let mut stiffness = Sparse::new(points.len());
+        let mut mass = vec![0.0; points.len()];
+        let mut edges = 0.0;
+        for t in &triangles {
+            let area = 0.5 * (points[t[1]] - points[t[0]]).cross(points[t[2]] - points[t[0]]).length();
+            for i in 0..3 {
+                let (at, a, b) = (t[i], t[(i + 1) % 3], t[(i + 2) % 3]);
+                let w = 0.5 * cot(points[at], points[a], points[b]);
+                stiffness.add(a, b, -w);
+                stiffness.add(b, a, -w);
+                stiffness.add(a, a, w);
+                stiffness.add(b, b, w);
+                mass[at] += area / 3.0;
+                edges += points[a].distance(points[b]);
+            }
+        }
+        let mean_edge = edges / (3 * triangles.len()) as f64;
+        HeatMethod {
+            triangles,
+            stiffness,
+            mass,
+            mean_edge,
+        }
+    }
+
+    /// The integrated divergence of the normalized negative gradient of the heat.
+    fn divergence(&self, points: &[DVec3], u: &[f64]) -> Vec<f64> {
+        let mut divergence = vec![0.0; points.len()];
+        for t in &self.triangles {
+            let p = t.map(|i| points[i]);
+            let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            let double_area = normal.length();
+            if double_area <= f64::EPSILON {
+                continue;
+            }
+            let n = normal / double_area;
+            let grad = (0..3)
+                .map(|i| u[t[i]] * n.cross(p[(i + 2) % 3] - p[(i + 1) % 3]))
+                .sum::<DVec3>()
+                / double_area;
+            let x = -grad.normalize_or_zero();
+            for i in 0..3 {
+                let (a, b, c) = (p[i], p[(i + 1) % 3], p[(i + 2) % 3]);
+                divergence[t[i]] += 0.5 * (cot(c, a, b) * (b - a).dot(x) + cot(b, c, a) * (c - a).dot(x));
+            }
+        }
+        divergence
+    }
+}
+
+/// The vertices connected to the sources by the triangles.
+fn reached(size: usize, triangles: &[[usize; 3]], sources: &[usize]) -> Vec<bool> {
+    let mut neighbors = vec![vec![]; size];
+    for t in triangles {
+        for i in 0..3 {
+            neighbors[t[i]].push(t[(i + 1) % 3]);
+            neighbors[t[(i + 1) % 3]].push(t[i]);
+        }
+    }
+    let mut reached = vec![false; size];
+    let mut queue: VecDeque<usize> = sources.iter().copied().collect();
+    for idx in sources {
+        reached[*idx] = true;
+    }
+    while let Some(idx) = queue.pop_front() {
+        for n in &neighbors[idx] {
+            if !reached[*n] {
+                reached[*n] = true;
+                queue.push_back(*n);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::fixtures;
+    use crate::mesh::parts::vertex::Vertex;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+    use glam::Vec3;
+
+    fn plane(n: usize) -> Mesh {
+        fixtures::grid(n, |_, _| 0.0)
+    }
+
+    #[test]
+    fn path_along_edges() {
+        let n = 6;
+        let mesh = plane(n);
+        let last = (n + 1) * (n + 1) - 1;
+        let path = mesh.query().geodesic_path(0, last).unwrap();
+        assert!(path.is_lines());
+        assert_eq!(path.vertices()[0], mesh.vertices()[0]);
+        assert_eq!(path.vertices()[path.vertices().len() - 1], mesh.vertices()[last]);
+        assert_eq!(path.edges().len(), path.vertices().len() - 1);
+        let distance = path.fields().vertex("distance").and_then(|f| f.as_scalar()).unwrap();
+        // the quads have no diagonal edges
+        assert_eq!(distance[distance.len() - 1], 2.0 * n as f32);
+
+        let isolated = Mesh::from_vertices(
+            vec![
+                Vertex::new(0.0, 0.0, 0.0),
+                Vertex::new(1.0, 0.0, 0.0),
+                Vertex::new(0.0, 1.0, 0.0),
+                Vertex::new(5.0, 5.0, 5.0),
+            ],
+            vec![Face::new3(0, 1, 2)],
+            Default::default(),
+        );
+        assert!(isolated.query().geodesic_path(0, 3).is_err());
+    }
+
+    #[test]
+    fn heat_distances_on_plane() {
+        let n = 10;
+        let mesh = plane(n);
+        let center = (n / 2) * (n + 1) + n / 2;
+        let distances = mesh.query().geodesic_distances(&[center]).unwrap();
+        let distances = distances.as_scalar().unwrap();
+        assert_eq!(distances[center], 0.0);
+        let origin = mesh.vertices()[center];
+        for (v, d) in mesh.vertices().iter().zip(distances) {
+            let exact = v.distance(&origin);
+            // the quads are split by the diagonals of the same direction, the heat spreads unevenly
+            assert!((d - exact).abs() < 0.2 * exact + 0.1, "{} {}", d, exact);
+        }
+    }
+
+    #[test]
+    fn heat_distances_on_sphere() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Default::default()).unwrap();
+        let vertices = sphere.mesh().vertices().iter().map(|v| *v * (1.0 / v.magnitude())).collect();
+        let mut mesh = Mesh::from_vertices(vertices, sphere.mesh().faces().clone(), Default::default());
+        // the point far away is not connected to the sphere
+        mesh.vertices.push(Vertex::new(5.0, 5.0, 5.0));
+
+        let distances = mesh.query().geodesic_distances(&[0]).unwrap();
+        let distances = distances.as_scalar().unwrap();
+        let source: Vec3 = mesh.vertices()[0].into();
+        for (v, d) in mesh.vertices().iter().zip(distances).take(mesh.vertices().len() - 1) {
+            let v: Vec3 = (*v).into();
+            let exact = v.normalize().dot(source.normalize()).clamp(-1.0, 1.0).acos();
+            assert!((d - exact).abs() < 0.03, "{} {}", d, exact);
+        }
+        assert!(distances[distances.len() - 1].is_nan());
+    }
+}
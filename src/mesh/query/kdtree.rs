@@ -20,6 +20,11 @@ impl KDTree {
         build::try_build_kd_tree(&mesh.try_polygons()?, depth)
     }
 
+    /// Builds the tree over the points themselves, e.g. the vertices of a cloud.
+    pub fn try_from_points(points: &[Vertex], depth: Option<usize>) -> MeshResult<Self> {
+        build::try_build_kd_tree_from_points(points.to_vec(), depth)
+    }
+
     pub fn root(&self) -> &Box<KDNode> {
         &self.root
     }
@@ -109,6 +114,7 @@ mod tests {
     use crate::mesh::query::kdtree::KDTree;
     use crate::mesh::query::MeshQuery;
     use crate::mesh::shape::cone::Cone;
+    use crate::mesh::shape::sphere::Sphere;
     use crate::mesh::HasMesh;
 
     #[test]
@@ -125,4 +131,42 @@ mod tests {
         assert_eq!(full_len, 62);
         assert_eq!(part_len, 14);
     }
+
+    #[test]
+    fn mesh_centroids_nearest_first() {
+        let fig = Sphere::default();
+        let mesh = fig.mesh();
+        let kdtree = KDTree::try_from_mesh(mesh, None).unwrap();
+        let target = Vertex::new(0.3, -0.2, 0.4);
+
+        let mut expected: Vec<f32> = mesh
+            .try_polygons()
+            .unwrap()
+            .iter()
+            .map(|p| p.centroid().unwrap().distance(&target))
+            .collect();
+        expected.sort_by(f32::total_cmp);
+
+        let distances: Vec<f32> = kdtree.nearest_neighbors(&target, None).map(|n| n.distance).collect();
+        assert_eq!(distances, expected);
+
+        let near: Vec<f32> = kdtree.nearest_neighbors(&target, Some(0.5)).map(|n| n.distance).collect();
+        let expected: Vec<f32> = expected.into_iter().filter(|d| *d <= 0.5).collect();
+        assert!(!expected.is_empty());
+        assert_eq!(near, expected);
+    }
+
+    #[test]
+    fn nearest_first() {
+        let points: Vec<Vertex> = (0..200)
+            .map(|i| Vertex::new((i * 7 % 13) as f32, (i * 5 % 11) as f32, (i % 7) as f32 * 0.5))
+            .collect();
+        let kdtree = KDTree::try_from_points(&points, None).unwrap();
+        let target = Vertex::new(4.2, 3.7, 1.1);
+
+        let distances: Vec<f32> = kdtree.nearest_neighbors(&target, None).map(|n| n.distance).collect();
+        let mut expected: Vec<f32> = points.iter().map(|p| p.distance(&target)).collect();
+        expected.sort_by(f32::total_cmp);
+        assert_eq!(distances, expected);
+    }
 }
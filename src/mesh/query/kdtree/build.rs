@@ -7,49 +7,41 @@ use crate::mesh::parts::vertex::Vertex;
 const MAX_REC_DEPTH: usize = 90;
 
 pub fn try_build_kd_tree(polygons: &Vec<Polygon>, depth: Option<usize>) -> MeshResult<KDTree> {
+    let points = polygons
+        .iter()
+        .map(|p| p.centroid())
+        .collect::<Result<Vec<_>, _>>()?;
+    try_build_kd_tree_from_points(points, depth)
+}
+
+pub fn try_build_kd_tree_from_points(points: Vec<Vertex>, depth: Option<usize>) -> MeshResult<KDTree> {
     let max_depth = depth.unwrap_or(MAX_REC_DEPTH);
     let error = MeshError::Custom("empty tree".to_string());
-    let root = build_node(polygons, 0, max_depth).and_then(|v| v.ok_or(error))?;
+    let root = build_node(points, 0, max_depth).ok_or(error)?;
     Ok(KDTree { root })
 }
 
-fn build_node(
-    polygons: &Vec<Polygon>,
-    depth: usize,
-    max_depth: usize,
-) -> MeshResult<Option<Box<KDNode>>> {
+/// Splits the points at the median along the axis.
+/// All the points end up in the leafs, the node keeps the median as the splitting plane,
+/// so the left subtree is not above it and the right one is not below it.
+fn build_node(mut points: Vec<Vertex>, depth: usize, max_depth: usize) -> Option<Box<KDNode>> {
     let axis = depth % 3;
-    let by_axis = |(_, p1): &(_, Vertex), (_, p2): &(_, Vertex)| sort_by_axis(&p1, &p2, axis);
-
-    if polygons.is_empty() || depth >= max_depth {
-        Ok(None)
-    } else if polygons.len() == 1 {
-        Ok(Some(Box::new(KDNode::Leaf { point: polygons[0].centroid()?, axis })))
+    if points.is_empty() || depth >= max_depth {
+        None
+    } else if points.len() == 1 {
+        Some(Box::new(KDNode::Leaf { point: points[0], axis }))
     } else {
-        let mut points = polygons
-            .iter()
-            .enumerate()
-            .map(|(idx, p)| p.centroid().map(|v| (idx, v)))
-            .collect::<Result<Vec<_>, _>>()?;
-        points.sort_by(by_axis);
-
+        points.sort_by(|lhs, rhs| sort_by_axis(lhs, rhs, axis));
         let median = points.len() / 2;
-        let (idx, point) = points[median].clone();
-        let (left, right) = polygons.split_at(idx);
-
-        let left = build_node(&left.to_vec(), depth + 1, max_depth)?;
-        let right = build_node(&right.to_vec(), depth + 1, max_depth)?;
-
-        if left.is_none() && right.is_none() {
-            Ok(Some(Box::new(KDNode::Leaf { point, axis })))
-        } else {
-            Ok(Some(Box::new(KDNode::Node {
-                point,
-                left,
-                right,
-                axis,
-            })))
-        }
+        let point = points[median];
+        let right = points.split_off(median);
+
+        Some(Box::new(KDNode::Node {
+            point,
+            left: build_node(points, depth + 1, max_depth),
+            right: build_node(right, depth + 1, max_depth),
+            axis,
+        }))
     }
 }
 
@@ -37,20 +37,59 @@ impl Ord for Neighbour {
     }
 }
 
+/// Visits the subtrees by the lower bound of the distance to them,
+/// so the leafs come out from the nearest one.
 pub struct KDTreeNearestNeighborIter<'a> {
     target: &'a Vertex,
     max_dist: Option<f32>,
-    heap: BinaryHeap<Neighbour>,
+    heap: BinaryHeap<Candidate<'a>>,
+}
+
+/// The subtree and the lower bound of the distance to its points,
+/// the exact distance for a leaf. The heap pops the smallest bound first.
+struct Candidate<'a> {
+    node: &'a KDNode,
+    bound: f32,
+}
+
+impl Eq for Candidate<'_> {}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bound.total_cmp(&self.bound)
+    }
 }
 
 impl<'a> KDTreeNearestNeighborIter<'a> {
     pub fn new(root: &'a KDNode, target: &'a Vertex, max_dist: Option<f32>) -> Self {
-        let mut heap = BinaryHeap::new();
-        heap.push(Neighbour::new(Box::new(root.clone()), root.point().distance(target)));
-        KDTreeNearestNeighborIter {
+        let mut iter = KDTreeNearestNeighborIter {
             target,
-            heap,
+            heap: BinaryHeap::new(),
             max_dist,
+        };
+        iter.push(root, 0.0);
+        iter
+    }
+
+    fn push(&mut self, node: &'a KDNode, bound: f32) {
+        let bound = match node {
+            KDNode::Leaf { point, .. } => self.target.distance(point),
+            KDNode::Node { .. } => bound,
+        };
+        if self.max_dist.is_none_or(|max_dist| bound <= max_dist) {
+            self.heap.push(Candidate { node, bound });
         }
     }
 }
@@ -59,26 +98,25 @@ impl<'a> Iterator for KDTreeNearestNeighborIter<'a> {
     type Item = Neighbour;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(Neighbour { node, .. }) = self.heap.pop() {
-            match *node {
-                KDNode::Leaf { point, .. } => {
-                    let dist = self.target.distance(&point);
-                    if self.max_dist.map_or(true, |max_dist| dist <= max_dist) {
-                        return Some(Neighbour::new(node, dist));
-                    }
+        while let Some(Candidate { node, bound }) = self.heap.pop() {
+            match node {
+                KDNode::Leaf { .. } => {
+                    return Some(Neighbour::new(Box::new(node.clone()), bound));
                 }
                 KDNode::Node {
                     point,
-                    ref left,
-                    ref right,
-                    ..
+                    left,
+                    right,
+                    axis,
                 } => {
-                    let dist = self.target.distance(&point);
-                    if let Some(left_node) = left {
-                        self.heap.push(Neighbour::new(left_node.clone(), dist));
+                    // the far side is at least as far as the splitting plane
+                    let diff = self.target.flatten()[*axis] - point.flatten()[*axis];
+                    let (near, far) = if diff < 0.0 { (left, right) } else { (right, left) };
+                    if let Some(near) = near {
+                        self.push(near, bound);
                     }
-                    if let Some(right_node) = right {
-                        self.heap.push(Neighbour::new(right_node.clone(), dist));
+                    if let Some(far) = far {
+                        self.push(far, bound.max(diff.abs()));
                     }
                 }
             }
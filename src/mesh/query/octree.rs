@@ -67,6 +67,23 @@ impl OctNode {
         }
     }
 
+    /// Collect the polygons of all leafs whose bounding box overlaps the given one.
+    /// Unlike `find_polygons` it keeps the polygons without a vertex in the box,
+    /// so the result is the set of candidates for an exact test.
+    /// Every polygon is stored in all the octants its box overlaps,
+    /// so no polygon overlapping the given box is missed.
+    /// A polygon lying in several leafs appears several times.
+    pub fn find_leaf_polygons(&self, bb: &BoundingBox) -> Vec<Polygon> {
+        match self {
+            OctNode::Leaf { ref polygons, .. } => polygons.clone(),
+            OctNode::Node { ref children, .. } => children
+                .iter()
+                .filter(|child| child.aabb().intersects(bb))
+                .flat_map(|child| child.find_leaf_polygons(bb))
+                .collect(),
+        }
+    }
+
     pub fn is_overlapping(&self, node: &OctNode) -> bool {
         self.aabb().intersects(node.aabb())
     }
@@ -89,6 +106,14 @@ impl Octree {
         self.root.find_polygons(bb)
     }
 
+    pub fn find_leaf_polygons(&self, bb: &BoundingBox) -> Vec<Polygon> {
+        if self.root.aabb().intersects(bb) {
+            self.root.find_leaf_polygons(bb)
+        } else {
+            vec![]
+        }
+    }
+
     pub fn iter(&self) -> query::OctreeIterator {
         query::OctreeIterator::new(&self.root, false)
     }
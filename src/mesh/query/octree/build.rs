@@ -26,14 +26,27 @@ fn build_node(polygons: &Vec<Polygon>, depth: usize, max_depth: usize) -> MeshRe
 
         let child_bbs = subdivide(&bb);
 
+        // a polygon goes to every child its box overlaps,
+        // so a large polygon is found in all the octants it crosses
+        // even if it has no vertex there
         for polygon in polygons {
+            let polygon_bb = BoundingBox::from_polygon(polygon);
             for (i, child_bb) in child_bbs.iter().enumerate() {
-                if child_bb.intersects_polygon(polygon) {
+                if child_bb.intersects(&polygon_bb) {
                     children_polygons[i].push(polygon.clone());
                 }
             }
         }
 
+        // the polygons spanning the whole box can not be separated any further
+        if children_polygons.iter().any(|p| p.len() == polygons.len()) {
+            return Ok(Box::new(OctNode::Leaf {
+                bb,
+                polygons: polygons.clone(),
+                depth,
+            }));
+        }
+
         let mut children = Vec::with_capacity(8);
         for child_polygons in children_polygons {
             children.push(build_node(&child_polygons, depth + 1, max_depth)?);
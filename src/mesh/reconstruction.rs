@@ -0,0 +1,394 @@
+use crate::mesh::isosurface::{IsosurfaceOptions, ScalarGrid};
+use crate::mesh::linalg::conjugate_gradients;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::query::kdtree::KDTree;
+use crate::mesh::volume::symmetric_eigen;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::Vec3;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+const SOLVER_EPS: f64 = 1e-7;
+
+/// The settings of the surface reconstruction from the points.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::Mesh;
+/// use tessellate::mesh::parts::vertex::Vertex;
+/// use tessellate::mesh::reconstruction::ReconstructionOptions;
+///
+/// // the points on the unit sphere
+/// let golden = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+/// let points: Vec<Vertex> = (0..500)
+///     .map(|i| {
+///         let z = 1.0 - 2.0 * (i as f32 + 0.5) / 500.0;
+///         let r = (1.0 - z * z).sqrt();
+///         let phi = golden * i as f32;
+///         Vertex::new(r * phi.cos(), r * phi.sin(), z)
+///     })
+///     .collect();
+/// let cloud = Mesh::cloud(points, 2, Default::default());
+/// let surface = cloud.reconstruct(ReconstructionOptions::new(20).with_neighbours(8)).unwrap();
+/// assert!(surface.properties().is_watertight());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconstructionOptions {
+    resolution: usize,
+    neighbours: usize,
+    screening: f32,
+}
+
+impl Default for ReconstructionOptions {
+    fn default() -> Self {
+        ReconstructionOptions::new(64)
+    }
+}
+
+impl From<usize> for ReconstructionOptions {
+    fn from(resolution: usize) -> Self {
+        ReconstructionOptions::new(resolution)
+    }
+}
+
+impl ReconstructionOptions {
+    /// The grid of the given number of the cells along the longest side of the cloud,
+    /// 10 neighbours for the normals and the screening 4.
+    pub fn new(resolution: usize) -> Self {
+        ReconstructionOptions {
+            resolution,
+            neighbours: 10,
+            screening: 4.0,
+        }
+    }
+
+    /// The number of the nearest points the normals are estimated from,
+    /// if the cloud has no normals.
+    pub fn with_neighbours(self, neighbours: usize) -> Self {
+        ReconstructionOptions { neighbours, ..self }
+    }
+
+    /// How strongly the surface is pulled to the points, 0 gives the plain Poisson reconstruction
+    /// that smooths the details away.
+    pub fn with_screening(self, screening: f32) -> Self {
+        ReconstructionOptions { screening, ..self }
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    pub fn neighbours(&self) -> usize {
+        self.neighbours
+    }
+
+    pub fn screening(&self) -> f32 {
+        self.screening
+    }
+}
+
+/// Estimates the unit normals of the points by the plane fitted to the nearest neighbours
+/// (the eigenvector of the smallest eigenvalue of their covariance).
+///
+/// The normals are oriented consistently by walking the minimum spanning tree of the neighbours
+/// with the weight `1 - |n1 · n2|`, so the orientation goes over the flat places first.
+/// Every connected piece starts from its highest point with the normal looking up,
+/// so the normals of a closed surface look outside.
+pub fn estimate_normals(points: &[Vertex], neighbours: usize) -> MeshResult<Vec<Vec3>> {
+    if points.len() < 3 {
+        return Err(MeshError::Custom(format!("At least 3 points are needed, got {}", points.len())));
+    }
+    let k = neighbours.clamp(2, points.len() - 1);
+    let tree = KDTree::try_from_points(points, None)?;
+    // the duplicates of a point share its index
+    let index: HashMap<Vertex, usize> = points.iter().enumerate().rev().map(|(i, p)| (*p, i)).collect();
+
+    let mut graph: Vec<HashSet<usize>> = vec![HashSet::new(); points.len()];
+    let mut normals = Vec::with_capacity(points.len());
+    for (idx, point) in points.iter().enumerate() {
+        let near: Vec<Vertex> = tree
+            .nearest_neighbors(point, None)
+            .take(k + 1)
+            .map(|n| *n.node.point())
+            .collect();
+        for other in near.iter().filter_map(|p| index.get(p)).filter(|other| **other != idx) {
+            graph[idx].insert(*other);
+            graph[*other].insert(idx);
+        }
+        normals.push(fit_normal(&near));
+    }
+
+    let mut oriented = vec![false; points.len()];
+    while let Some(root) = (0..points.len())
+        .filter(|i| !oriented[*i])
+        .max_by(|a, b| points[*a].flatten()[2].total_cmp(&points[*b].flatten()[2]))
+    {
+        if normals[root].z < 0.0 {
+            normals[root] = -normals[root];
+        }
+        oriented[root] = true;
+        // the weights are not negative, so the order of their bits is the order of the values
+        let mut heap = BinaryHeap::new();
+        let push = |heap: &mut BinaryHeap<_>, from: usize, normals: &[Vec3]| {
+            for to in &graph[from] {
+                let weight = 1.0 - normals[from].dot(normals[*to]).abs().min(1.0);
+                heap.push(Reverse((weight.to_bits(), from, *to)));
+            }
+        };
+        push(&mut heap, root, &normals);
+        while let Some(Reverse((_, from, to))) = heap.pop() {
+            if oriented[to] {
+                continue;
+            }
+            if normals[from].dot(normals[to]) < 0.0 {
+                normals[to] = -normals[to];
+            }
+            oriented[to] = true;
+            push(&mut heap, to, &normals);
+        }
+    }
+    Ok(normals)
+}
+
+fn fit_normal(points: &[Vertex]) -> Vec3 {
+    let points: Vec<Vec3> = points.iter().map(|p| p.into()).collect();
+    let mean = points.iter().sum::<Vec3>() / points.len() as f32;
+    let mut covariance = [[0.0f64; 3]; 3];
+    for p in &points {
+        let d = (*p - mean).as_dvec3();
+        for (r, row) in covariance.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value += d[r] * d[c];
+            }
+        }
+    }
+    let (values, vectors) = symmetric_eigen(covariance);
+    let smallest = (0..3).min_by(|a, b| values[*a].total_cmp(&values[*b])).unwrap_or(0);
+    vectors[smallest].as_vec3().normalize_or_zero()
+}
+
+/// Reconstructs the closed surface from the oriented points by the screened Poisson method
+/// (Kazhdan and Hoppe) on a regular grid.
+///
+/// The normals are spread over the grid as a vector field and the indicator function
+/// whose gradient fits the field best is found by solving the Poisson equation.
+/// The screening adds the term pulling the indicator at the points to its mean value there,
+/// so the surface follows the points closer. The surface is extracted at that value by the marching cubes.
+pub(crate) fn reconstruct(points: &[Vertex], normals: &[Vec3], options: ReconstructionOptions) -> MeshResult<Mesh> {
+    if points.len() != normals.len() || points.is_empty() {
+        return Err(MeshError::Custom(format!(
+            "Every point needs a normal, got {} points and {} normals",
+            points.len(),
+            normals.len()
+        )));
+    }
+    let points: Vec<Vec3> = points.iter().map(|p| p.into()).collect();
+    let min = points.iter().copied().fold(Vec3::MAX, Vec3::min);
+    let max = points.iter().copied().fold(Vec3::MIN, Vec3::max);
+    let longest = (max - min).max_element().max(f32::EPSILON);
+    let cell = longest / options.resolution.max(2) as f32;
+    let margin = Vec3::splat(0.1 * longest + 2.0 * cell);
+    let origin = min - margin;
+    let dims = ((max + margin - origin) / cell).ceil().to_array().map(|d| d as usize + 1);
+    let grid = Grid::new(dims);
+
+    // the trilinear weights of the grid points around every sample
+    let stencils: Vec<Vec<(usize, f64)>> = points.iter().map(|p| grid.stencil((*p - origin) / cell)).collect();
+
+    let mut field = vec![Vec3::ZERO; grid.len()];
+    for (stencil, normal) in stencils.iter().zip(normals) {
+        for (idx, weight) in stencil {
+            field[*idx] += *normal * *weight as f32;
+        }
+    }
+    // the right side is minus the divergence of the field, by the central differences in the units of the cells
+    let rhs: Vec<f64> = (0..grid.len())
+        .map(|idx| {
+            let divergence: f32 = (0..3)
+                .map(|axis| {
+                    let upper = grid.neighbour(idx, axis, true).map_or(0.0, |n| field[n][axis]);
+                    let lower = grid.neighbour(idx, axis, false).map_or(0.0, |n| field[n][axis]);
+                    (upper - lower) / 2.0
+                })
+                .sum();
+            -divergence as f64
+        })
+        .collect();
+
+    let at_points = |solution: &[f64]| {
+        stencils
+            .iter()
+            .map(|stencil| stencil.iter().map(|(idx, w)| solution[*idx] * w).sum::<f64>())
+            .sum::<f64>()
+            / stencils.len() as f64
+    };
+    let steps = grid.len().min(5000);
+    let solve = |rhs: &[f64], alpha: f64, guess: Vec<f64>| {
+        conjugate_gradients(|x| grid.apply(x, &stencils, alpha), rhs, guess, SOLVER_EPS, steps)
+    };
+    let mut solution = solve(&rhs, 0.0, vec![0.0; grid.len()]);
+    let mut iso = at_points(&solution);
+
+    if options.screening > 0.0 {
+        // the weight is given per cell, so it does not depend on the density of the points
+        let cells: HashSet<[usize; 3]> = points
+            .iter()
+            .map(|p| ((*p - origin) / cell).floor().to_array().map(|v| v as usize))
+            .collect();
+        let alpha = options.screening as f64 * cells.len() as f64 / points.len() as f64;
+        let mut screened = rhs;
+        for stencil in &stencils {
+            for (idx, weight) in stencil {
+                screened[*idx] += alpha * iso * weight;
+            }
+        }
+        solution = solve(&screened, alpha, solution);
+        iso = at_points(&solution);
+    }
+
+    let values = solution.into_iter().map(|v| v as f32).collect();
+    let scalars = ScalarGrid::new(origin.into(), Vertex::new(cell, cell, cell), dims, values)?;
+    scalars.isosurface(IsosurfaceOptions::new(iso as f32))
+}
+
+/// The regular grid the Poisson equation is solved on, `i + nx * (j + ny * k)`.
+struct Grid {
+    dims: [usize; 3],
+}
+
+impl Grid {
+    fn new(dims: [usize; 3]) -> Self {
+        Grid { dims }
+    }
+
+    fn len(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    fn index(&self, [i, j, k]: [usize; 3]) -> usize {
+        i + self.dims[0] * (j + self.dims[1] * k)
+    }
+
+    fn coords(&self, idx: usize) -> [usize; 3] {
+        let [nx, ny, _] = self.dims;
+        [idx % nx, idx / nx % ny, idx / (nx * ny)]
+    }
+
+    fn neighbour(&self, idx: usize, axis: usize, upper: bool) -> Option<usize> {
+        let mut coords = self.coords(idx);
+        if upper && coords[axis] + 1 < self.dims[axis] {
+            coords[axis] += 1;
+        } else if !upper && coords[axis] > 0 {
+            coords[axis] -= 1;
+        } else {
+            return None;
+        }
+        Some(self.index(coords))
+    }
+
+    fn stencil(&self, local: Vec3) -> Vec<(usize, f64)> {
+        let base = local.floor();
+        let frac = (local - base).as_dvec3();
+        let base = base.to_array().map(|v| v as usize);
+        (0..8)
+            .map(|corner| {
+                let mut coords = base;
+                let mut weight = 1.0;
+                for axis in 0..3 {
+                    if corner >> axis & 1 == 1 {
+                        coords[axis] += 1;
+                        weight *= frac[axis];
+                    } else {
+                        weight *= 1.0 - frac[axis];
+                    }
+                }
+                (self.index(coords), weight)
+            })
+            .collect()
+    }
+
+    /// Applies minus the Laplacian, zero outside of the grid,
+    /// and the screening of the values interpolated at the points.
+    fn apply(&self, x: &[f64], stencils: &[Vec<(usize, f64)>], alpha: f64) -> Vec<f64> {
+        let [nx, ny, nz] = self.dims;
+        let strides = [1, nx, nx * ny];
+        let mut result = vec![0.0; x.len()];
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let idx = self.index([i, j, k]);
+                    let mut value = 6.0 * x[idx];
+                    for (axis, coord) in [i, j, k].into_iter().enumerate() {
+                        if coord > 0 {
+                            value -= x[idx - strides[axis]];
+                        }
+                        if coord + 1 < self.dims[axis] {
+                            value -= x[idx + strides[axis]];
+                        }
+                    }
+                    result[idx] = value;
+                }
+            }
+        }
+        if alpha > 0.0 {
+            for stencil in stencils {
+                let value: f64 = stencil.iter().map(|(idx, w)| x[*idx] * w).sum();
+                for (idx, w) in stencil {
+                    result[*idx] += alpha * w * value;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere_points(count: usize) -> Vec<Vertex> {
+        let golden = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+        (0..count)
+            .map(|i| {
+                let z = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                let r = (1.0 - z * z).sqrt();
+                let phi = golden * i as f32;
+                Vertex::new(r * phi.cos(), r * phi.sin(), z)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn normals_look_outside() {
+        let points = sphere_points(400);
+        let normals = estimate_normals(&points, 8).unwrap();
+        for (p, n) in points.iter().zip(&normals) {
+            let radial: Vec3 = p.into();
+            assert!(radial.dot(*n) > 0.95, "{:?} {:?}", p, n);
+        }
+
+        // the wavy sheet looks up as a whole
+        let sheet: Vec<Vertex> = (0..400)
+            .map(|i| {
+                let (x, y) = ((i % 20) as f32 * 0.1, (i / 20) as f32 * 0.1);
+                Vertex::new(x, y, (x * 3.0).sin() * 0.2)
+            })
+            .collect();
+        let normals = estimate_normals(&sheet, 8).unwrap();
+        assert!(normals.iter().all(|n| n.z > 0.5));
+    }
+
+    #[test]
+    fn sphere_from_cloud() {
+        let mut cloud = Mesh::cloud(sphere_points(800), 2, Default::default());
+        cloud.estimate_normals(10).unwrap();
+        let surface = cloud.reconstruct(ReconstructionOptions::new(24)).unwrap();
+
+        let stats = surface.statistics().unwrap();
+        assert!(stats.is_watertight);
+        assert_eq!(stats.genus, Some(0));
+        let radii: Vec<f32> = surface.vertices().iter().map(|v| v.distance(&Vertex::default())).collect();
+        assert!(radii.iter().all(|r| (r - 1.0).abs() < 0.1), "{:?}", radii);
+        assert!(surface.mass_properties().unwrap().volume > 0.0);
+    }
+}
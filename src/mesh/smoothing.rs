@@ -0,0 +1,387 @@
+use crate::mesh::fields::NORMALS;
+use crate::mesh::linalg::cot;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::query::edges::feature_vertices;
+use crate::mesh::tables::MeshTables;
+use crate::mesh::{Mesh, MeshResult};
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// The way the vertices are moved at every iteration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmoothingMethod {
+    /// Moves the vertex to the mean of its neighbors by the factor from 0 to 1.
+    Laplacian { factor: f32 },
+    /// Weights the neighbors by the cotangents of the opposite angles,
+    /// the vertices slide less along the surface than with the mean.
+    Cotangent { factor: f32 },
+    /// Weights the neighbors by the gaussian of the distance to them.
+    Gaussian { factor: f32, sigma: f32 },
+    /// Shrinks by `lambda` and inflates back by the negative `mu`, `|mu| > lambda`.
+    Taubin { lambda: f32, mu: f32 },
+    /// Pushes the Laplacian result back towards the original (`alpha`)
+    /// and the previous (`beta`) positions (Vollmer, Mencl and Mueller).
+    Hc { alpha: f32, beta: f32 },
+}
+
+/// The settings of the smoothing.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::smoothing::SmoothingOptions;
+/// use tessellate::mesh::shape::sphere::Sphere;
+/// use tessellate::mesh::HasMesh;
+///
+/// let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Default::default()).unwrap();
+/// let smooth = sphere.mesh().smooth(10).unwrap();
+/// assert_eq!(smooth.vertices().len(), sphere.mesh().vertices().len());
+///
+/// let options = SmoothingOptions::taubin(20).with_lock_boundary(true);
+/// let smooth = sphere.mesh().smooth(options).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothingOptions {
+    method: SmoothingMethod,
+    iterations: usize,
+    lock_boundary: bool,
+    feature_angle: Option<f32>,
+}
+
+impl From<usize> for SmoothingOptions {
+    fn from(iterations: usize) -> Self {
+        SmoothingOptions::laplacian(iterations)
+    }
+}
+
+impl SmoothingOptions {
+    /// The options moving all the vertices.
+    pub fn new(method: SmoothingMethod, iterations: usize) -> Self {
+        SmoothingOptions {
+            method,
+            iterations,
+            lock_boundary: false,
+            feature_angle: None,
+        }
+    }
+
+    /// The uniform Laplacian with the factor 0.5.
+    pub fn laplacian(iterations: usize) -> Self {
+        Self::new(SmoothingMethod::Laplacian { factor: 0.5 }, iterations)
+    }
+
+    /// The cotangent Laplacian with the factor 0.5.
+    pub fn cotangent(iterations: usize) -> Self {
+        Self::new(SmoothingMethod::Cotangent { factor: 0.5 }, iterations)
+    }
+
+    /// The gaussian weighted Laplacian with the factor 0.5.
+    pub fn gaussian(iterations: usize, sigma: f32) -> Self {
+        Self::new(SmoothingMethod::Gaussian { factor: 0.5, sigma }, iterations)
+    }
+
+    /// The Taubin smoothing with `lambda` 0.5 and `mu` -0.53.
+    pub fn taubin(iterations: usize) -> Self {
+        Self::new(SmoothingMethod::Taubin { lambda: 0.5, mu: -0.53 }, iterations)
+    }
+
+    /// The HC-Laplacian with `alpha` 0.0 and `beta` 0.5.
+    pub fn hc(iterations: usize) -> Self {
+        Self::new(SmoothingMethod::Hc { alpha: 0.0, beta: 0.5 }, iterations)
+    }
+
+    pub fn with_method(mut self, method: SmoothingMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Keeps the vertices of the boundary edges and the ends of the lines in place.
+    pub fn with_lock_boundary(mut self, lock_boundary: bool) -> Self {
+        self.lock_boundary = lock_boundary;
+        self
+    }
+
+    /// Keeps the vertices of the edges with the dihedral angle greater than the given one in degrees.
+    pub fn with_feature_angle(mut self, feature_angle: Option<f32>) -> Self {
+        self.feature_angle = feature_angle;
+        self
+    }
+
+    pub fn method(&self) -> SmoothingMethod {
+        self.method
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn lock_boundary(&self) -> bool {
+        self.lock_boundary
+    }
+
+    pub fn feature_angle(&self) -> Option<f32> {
+        self.feature_angle
+    }
+}
+
+/// How the neighbors of a vertex are weighted.
+#[derive(Clone, Copy)]
+enum Weights {
+    Uniform,
+    Cotangent,
+    Gaussian(f32),
+}
+
+/// Moves the vertices of the mesh, the faces, the edges, the fields and the colors are kept.
+/// The `normals` vertex field is dropped, it does not fit the smoothed surface.
+/// The vertices without the neighbors (the points of a cloud) stay in place.
+pub(crate) fn smooth(mesh: &Mesh, options: SmoothingOptions) -> MeshResult<Mesh> {
+    let smoother = Smoother::new(mesh, &options)?;
+    let mut points: Vec<Vec3> = mesh.vertices().iter().map(|v| (*v).into()).collect();
+
+    match options.method {
+        SmoothingMethod::Laplacian { factor } => {
+            for _ in 0..options.iterations {
+                smoother.step(&mut points, Weights::Uniform, factor);
+            }
+        }
+        SmoothingMethod::Cotangent { factor } => {
+            for _ in 0..options.iterations {
+                smoother.step(&mut points, Weights::Cotangent, factor);
+            }
+        }
+        SmoothingMethod::Gaussian { factor, sigma } => {
+            for _ in 0..options.iterations {
+                smoother.step(&mut points, Weights::Gaussian(sigma), factor);
+            }
+        }
+        SmoothingMethod::Taubin { lambda, mu } => {
+            for _ in 0..options.iterations {
+                smoother.step(&mut points, Weights::Uniform, lambda);
+                smoother.step(&mut points, Weights::Uniform, mu);
+            }
+        }
+        SmoothingMethod::Hc { alpha, beta } => {
+            let original = points.clone();
+            for _ in 0..options.iterations {
+                smoother.hc_step(&mut points, &original, alpha, beta);
+            }
+        }
+    }
+
+    let mut smoothed = mesh.clone();
+    smoothed.vertices = points.into_iter().map(Vertex::from).collect();
+    smoothed.fields.remove_vertex(NORMALS);
+    Ok(smoothed)
+}
+
+struct Smoother {
+    neighbors: Vec<Vec<usize>>,
+    triangles: Vec<Vec<usize>>,
+    locked: Vec<bool>,
+}
+
+impl Smoother {
+    fn new(mesh: &Mesh, options: &SmoothingOptions) -> MeshResult<Self> {
+        let tables = MeshTables::try_from(mesh)?;
+        let neighbors = (0..mesh.vertices().len())
+            .map(|idx| tables.vertex_neighbors(idx))
+            .collect::<MeshResult<Vec<_>>>()?;
+        let triangles = mesh
+            .faces()
+            .iter()
+            .flat_map(Face::triangulate)
+            .map(|t| t.flatten())
+            .collect();
+
+        let mut locked = vec![false; neighbors.len()];
+        if options.lock_boundary {
+            for (idx, vs) in neighbors.iter().enumerate() {
+                if vs.len() == 1 && mesh.faces().is_empty() {
+                    locked[idx] = true;
+                }
+                for v in vs {
+                    if tables.edge_faces(idx, *v)?.len() == 1 {
+                        locked[idx] = true;
+                    }
+                }
+            }
+        }
+        if let Some(angle) = options.feature_angle.filter(|_| !mesh.faces().is_empty()) {
+            for idx in feature_vertices(mesh, angle)? {
+                locked[idx] = true;
+            }
+        }
+        Ok(Smoother {
+            neighbors,
+            triangles,
+            locked,
+        })
+    }
+
+    /// Moves the free vertices towards the weighted mean of their neighbors.
+    fn step(&self, points: &mut [Vec3], weights: Weights, factor: f32) {
+        let means = self.means(points, weights);
+        for (idx, (p, mean)) in points.iter_mut().zip(means).enumerate() {
+            if !self.locked[idx] {
+                *p += factor * (mean - *p);
+            }
+        }
+    }
+
+    fn hc_step(&self, points: &mut [Vec3], original: &[Vec3], alpha: f32, beta: f32) {
+        let previous = points.to_vec();
+        let means = self.means(&previous, Weights::Uniform);
+        // the difference of the mean to the original and the previous positions
+        let diffs: Vec<Vec3> = means
+            .iter()
+            .zip(original.iter().zip(&previous))
+            .map(|(m, (o, q))| *m - (alpha * *o + (1.0 - alpha) * *q))
+            .collect();
+        let diff_means = self.means(&diffs, Weights::Uniform);
+        for idx in 0..points.len() {
+            if !self.locked[idx] {
+                points[idx] = means[idx] - (beta * diffs[idx] + (1.0 - beta) * diff_means[idx]);
+            }
+        }
+    }
+
+    /// The weighted means of the neighbors, the vertex itself if it has no neighbors.
+    fn means(&self, points: &[Vec3], weights: Weights) -> Vec<Vec3> {
+        let cotangents = match weights {
+            Weights::Cotangent => self.cotangents(points),
+            _ => HashMap::new(),
+        };
+        self.neighbors
+            .iter()
+            .enumerate()
+            .map(|(idx, vs)| {
+                let p = points[idx];
+                let mut sum = Vec3::ZERO;
+                let mut total = 0.0;
+                for v in vs {
+                    let w = match weights {
+                        Weights::Uniform => 1.0,
+                        Weights::Cotangent => cotangents.get(&key(idx, *v)).copied().unwrap_or(0.0),
+                        Weights::Gaussian(sigma) => {
+                            (-p.distance_squared(points[*v]) / (2.0 * sigma * sigma)).exp()
+                        }
+                    };
+                    sum += w * points[*v];
+                    total += w;
+                }
+                if total > f32::EPSILON {
+                    sum / total
+                } else if vs.is_empty() {
+                    p
+                } else {
+                    // the lines and the degenerated faces have no angles to weight by
+                    vs.iter().map(|v| points[*v]).sum::<Vec3>() / vs.len() as f32
+                }
+            })
+            .collect()
+    }
+
+    /// The half sums of the cotangents of the angles opposite to the edges.
+    /// The negative weights of the obtuse triangles are dropped to keep the mean inside the ring.
+    fn cotangents(&self, points: &[Vec3]) -> HashMap<(usize, usize), f32> {
+        let mut weights = HashMap::new();
+        for t in &self.triangles {
+            for i in 0..3 {
+                let (a, b, c) = (t[i], t[(i + 1) % 3], t[(i + 2) % 3]);
+                let [a_p, b_p, c_p] = [a, b, c].map(|v| points[v].as_dvec3());
+                *weights.entry(key(a, b)).or_insert(0.0) += 0.5 * cot(c_p, a_p, b_p) as f32;
+            }
+        }
+        weights.values_mut().for_each(|w| *w = f32::max(*w, 0.0));
+        weights
+    }
+}
+
+fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::fixtures;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+    use crate::mesh::parts::edge::MeshEdge;
+
+    fn noisy_grid(n: usize) -> Mesh {
+        fixtures::grid(n, |i, j| ((i * 7 + j * 13) % 5) as f32 * 0.1 - 0.2)
+    }
+
+    fn roughness(mesh: &Mesh) -> f32 {
+        mesh.vertices().iter().map(|v| v.z.abs()).sum::<f32>() / mesh.vertices().len() as f32
+    }
+
+    #[test]
+    fn all_methods_flatten_noise() {
+        let grid = noisy_grid(8);
+        for options in [
+            SmoothingOptions::laplacian(10),
+            SmoothingOptions::cotangent(10),
+            SmoothingOptions::gaussian(10, 1.0),
+            SmoothingOptions::taubin(10),
+            SmoothingOptions::hc(10),
+        ] {
+            let smooth = grid.smooth(options.with_lock_boundary(true)).unwrap();
+            assert_eq!(smooth.faces(), grid.faces());
+            assert!(roughness(&smooth) < roughness(&grid), "{:?}", options.method());
+            // the boundary is locked
+            for (lhs, rhs) in grid.vertices().iter().zip(smooth.vertices()) {
+                if lhs.x == 0.0 || lhs.y == 0.0 || lhs.x == 8.0 || lhs.y == 8.0 {
+                    assert_eq!(lhs, rhs);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn taubin_and_hc_shrink_less() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Default::default()).unwrap();
+        let mean_radius = |m: &Mesh| {
+            m.vertices().iter().map(|v| v.magnitude()).sum::<f32>() / m.vertices().len() as f32
+        };
+        let radius = mean_radius(sphere.mesh());
+        let laplacian = mean_radius(&sphere.mesh().smooth(20).unwrap());
+        let taubin = mean_radius(&sphere.mesh().smooth(SmoothingOptions::taubin(20)).unwrap());
+        let hc = mean_radius(&sphere.mesh().smooth(SmoothingOptions::hc(20)).unwrap());
+        assert!(laplacian < radius * 0.9);
+        assert!((taubin - radius).abs() < (laplacian - radius).abs());
+        assert!((hc - radius).abs() < (laplacian - radius).abs());
+    }
+
+    #[test]
+    fn features_and_lines() {
+        let (n, roof) = (6, fixtures::roof());
+        let ridge = fixtures::ridge;
+        let options = SmoothingOptions::laplacian(5);
+        assert_eq!(ridge(&roof.smooth(options.with_feature_angle(Some(30.0))).unwrap()), n + 1);
+        assert!(ridge(&roof.smooth(options).unwrap()) < n + 1);
+
+        // the polyline keeps its ends
+        let line = Mesh::from_edges(
+            vec![
+                Vertex::new(0.0, 0.0, 0.0),
+                Vertex::new(1.0, 1.0, 0.0),
+                Vertex::new(2.0, 0.0, 0.0),
+            ],
+            vec![MeshEdge(0, 1), MeshEdge(1, 2)],
+            Default::default(),
+        )
+        .unwrap();
+        let smooth = line.smooth(options.with_lock_boundary(true)).unwrap();
+        assert_eq!(smooth.vertices()[0], line.vertices()[0]);
+        assert_eq!(smooth.vertices()[2], line.vertices()[2]);
+        assert!(smooth.vertices()[1].y < 1.0);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::mesh::parts::face::Face;
+use crate::mesh::quality::area;
+use crate::mesh::{Mesh, MeshResult};
+use glam::Vec3;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// The summary of the topology and the size of a mesh.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::shape::torus::Torus;
+///
+/// let torus = Torus::default();
+/// let stats = torus.statistics().unwrap();
+/// assert_eq!(stats.genus, Some(1));
+/// println!("{}", stats);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshStatistics {
+    pub vertices: usize,
+    /// The edges without the direction, the edge shared by two faces is counted once.
+    pub edges: usize,
+    pub faces: usize,
+    pub triangles: usize,
+    pub quads: usize,
+    /// The vertices not used by any face.
+    pub isolated_vertices: usize,
+    /// `vertices - edges + faces`
+    pub euler_characteristic: i64,
+    /// The number of the connected pieces of the surface.
+    pub components: usize,
+    /// The number of the handles of the surface summed over the components,
+    /// `None` if the surface has the edges shared by more than two faces.
+    pub genus: Option<usize>,
+    pub boundary_loops: usize,
+    pub area: f32,
+    /// The signed volume of the watertight mesh, negative if the faces look inside.
+    pub volume: Option<f32>,
+    pub is_manifold: bool,
+    pub is_watertight: bool,
+}
+
+impl MeshStatistics {
+    pub fn new(mesh: &Mesh) -> MeshResult<Self> {
+        let properties = mesh.properties();
+        let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut used = HashSet::new();
+        let mut area_sum = 0.0;
+        let mut volume = 0.0;
+        for face in mesh.faces() {
+            let vs = face.flatten();
+            for i in 0..vs.len() {
+                let (a, b) = (vs[i], vs[(i + 1) % vs.len()]);
+                *edge_faces.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+            let points = vs
+                .iter()
+                .map(|idx| mesh.get(*idx).map(|v| (*v).into()))
+                .collect::<MeshResult<Vec<Vec3>>>()?;
+            area_sum += area(&points);
+            // the tetrahedra from the origin to the triangles sum up to the enclosed volume
+            for i in 1..points.len() - 1 {
+                volume += points[0].dot(points[i].cross(points[i + 1])) / 6.0;
+            }
+            used.extend(vs);
+        }
+        let mut edges: HashSet<(usize, usize)> = edge_faces.keys().copied().collect();
+        edges.extend(mesh.edges().iter().map(|e| (e.0.min(e.1), e.0.max(e.1))));
+
+        let vertices = mesh.vertices().len();
+        let faces = mesh.faces().len();
+        let isolated_vertices = vertices - used.len();
+        let euler_characteristic = vertices as i64 - edges.len() as i64 + faces as i64;
+        let components = components(vertices, mesh.faces());
+        let boundary_loops = properties.boundary_loops().len();
+        // every closed piece of the genus g has the characteristic 2 - 2g, a hole takes one more
+        let surface = euler_characteristic - isolated_vertices as i64;
+        let handles = 2 * components as i64 - surface - boundary_loops as i64;
+        let genus = (edge_faces.values().all(|c| *c <= 2) && handles >= 0 && handles % 2 == 0)
+            .then_some(handles as usize / 2);
+        let is_watertight = properties.is_watertight();
+
+        Ok(MeshStatistics {
+            vertices,
+            edges: edges.len(),
+            faces,
+            triangles: mesh.faces().iter().filter(|f| matches!(f, Face::Triangle(..))).count(),
+            quads: mesh.faces().iter().filter(|f| matches!(f, Face::Quad(..))).count(),
+            isolated_vertices,
+            euler_characteristic,
+            components,
+            genus,
+            boundary_loops,
+            area: area_sum,
+            volume: is_watertight.then_some(volume),
+            is_manifold: properties.is_manifold(),
+            is_watertight,
+        })
+    }
+}
+
+impl Display for MeshStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let optional = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+        writeln!(f, "Vertices:              {}", self.vertices)?;
+        writeln!(f, "Edges:                 {}", self.edges)?;
+        writeln!(f, "Faces:                 {} ({} triangles, {} quads)", self.faces, self.triangles, self.quads)?;
+        writeln!(f, "Isolated vertices:     {}", self.isolated_vertices)?;
+        writeln!(f, "Euler characteristic:  {}", self.euler_characteristic)?;
+        writeln!(f, "Components:            {}", self.components)?;
+        writeln!(f, "Genus:                 {}", optional(self.genus.map(|g| g.to_string())))?;
+        writeln!(f, "Boundary loops:        {}", self.boundary_loops)?;
+        writeln!(f, "Area:                  {}", self.area)?;
+        writeln!(f, "Volume:                {}", optional(self.volume.map(|v| v.to_string())))?;
+        writeln!(f, "Manifold:              {}", self.is_manifold)?;
+        write!(f, "Watertight:            {}", self.is_watertight)
+    }
+}
+
+/// The number of the groups of the faces connected by the vertices.
+fn components(vertices: usize, faces: &[Face]) -> usize {
+    let mut parents: Vec<usize> = (0..vertices).collect();
+    fn root(parents: &mut [usize], mut idx: usize) -> usize {
+        while parents[idx] != idx {
+            parents[idx] = parents[parents[idx]];
+            idx = parents[idx];
+        }
+        idx
+    }
+    for face in faces {
+        let vs = face.flatten();
+        for v in &vs[1..] {
+            let (a, b) = (root(&mut parents, vs[0]), root(&mut parents, *v));
+            parents[a] = b;
+        }
+    }
+    let used: HashSet<usize> = faces.iter().flat_map(Face::flatten).collect();
+    used.into_iter()
+        .map(|v| root(&mut parents, v))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::parts::vertex::Vertex;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn closed_shapes() {
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Quad, Color::default()).into();
+        let stats = cube.statistics().unwrap();
+        assert_eq!((stats.vertices, stats.edges, stats.faces), (8, 12, 6));
+        assert_eq!(stats.quads, 6);
+        assert_eq!(stats.euler_characteristic, 2);
+
+        let cube: Mesh = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Triangle, Color::default()).into();
+        let stats = cube.statistics().unwrap();
+        assert_eq!((stats.vertices, stats.edges, stats.faces), (8, 18, 12));
+        assert_eq!(stats.euler_characteristic, 2);
+        assert_eq!(stats.genus, Some(0));
+        assert_eq!(stats.boundary_loops, 0);
+        assert!((stats.area - 24.0).abs() < 1e-4);
+        assert!((stats.volume.unwrap().abs() - 8.0).abs() < 1e-4);
+        assert!(stats.is_watertight);
+
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 2, Default::default()).unwrap();
+        let stats = sphere.mesh().statistics().unwrap();
+        assert_eq!(stats.euler_characteristic, 2);
+        assert_eq!(stats.components, 1);
+        assert!(stats.volume.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn open_grid_with_hole() {
+        // the 3x3 grid without the middle quad
+        let mut vertices = vec![];
+        let mut faces = vec![];
+        for i in 0..4 {
+            for j in 0..4 {
+                vertices.push(Vertex::new(i as f32, j as f32, 0.0));
+            }
+        }
+        for i in 0..3 {
+            for j in 0..3 {
+                if (i, j) != (1, 1) {
+                    let v = i * 4 + j;
+                    faces.push(Face::new4(v, v + 4, v + 5, v + 1));
+                }
+            }
+        }
+        vertices.push(Vertex::new(10.0, 10.0, 10.0));
+        let mesh = Mesh::from_vertices(vertices, faces, Default::default());
+        let stats = mesh.statistics().unwrap();
+        assert_eq!(stats.boundary_loops, 2);
+        assert_eq!(stats.isolated_vertices, 1);
+        assert_eq!(stats.euler_characteristic, 1);
+        assert_eq!(stats.genus, Some(0));
+        assert_eq!(stats.volume, None);
+        assert!((stats.area - 8.0).abs() < 1e-4);
+        assert!(!stats.is_watertight);
+        assert!(stats.to_string().contains("Boundary loops:        2"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use crate::mesh::fields::Fields;
 use crate::mesh::parts::edge::MeshEdge;
 use crate::mesh::parts::face::Face;
 use crate::mesh::{Mesh, MeshError, MeshResult};
@@ -25,6 +26,7 @@ pub fn by_butterfly(mesh: &Mesh) -> MeshResult<Mesh>{
     let mut new_vertices = trimesh.vertices.clone();
     let table = trimesh.try_tables()?;
     let mut cache = HashMap::new();
+    let mut parents = vec![];
     let add_edge = |lhs:usize,rhs:usize|{
         if let Some(idx) = cache.get(&(lhs,rhs)){
             Ok::<usize, MeshError>(*idx)
@@ -48,6 +50,7 @@ pub fn by_butterfly(mesh: &Mesh) -> MeshResult<Mesh>{
             };
             let idx = new_vertices.len();
             new_vertices.push(new_point);
+            parents.push((lhs, rhs));
             cache.insert((lhs, rhs), idx);
             cache.insert((rhs, lhs), idx);
             Ok(idx)
@@ -55,7 +58,9 @@ pub fn by_butterfly(mesh: &Mesh) -> MeshResult<Mesh>{
     };
     let  new_faces = subdivide(&trimesh, add_edge)?;
 
-    Ok(Mesh::from_vertices(new_vertices, new_faces, trimesh.attributes.clone()))
+    let mut mesh = Mesh::from_vertices(new_vertices, new_faces, trimesh.attributes.clone());
+    mesh.fields = subdivide_fields(&trimesh, &parents);
+    Ok(mesh)
 }
 
 
@@ -77,6 +82,7 @@ pub fn by_loop(mesh: &Mesh) -> MeshResult<Mesh> {
     let mut new_vertices = vec![];
 
     let mut cache = HashMap::new();
+    let mut parents = vec![];
     let add_edge  = |lhs:usize, rhs:usize|{
         if let Some(idx) = cache.get(&(lhs,rhs)){
             Ok::<usize, MeshError>(*idx)
@@ -87,6 +93,7 @@ pub fn by_loop(mesh: &Mesh) -> MeshResult<Mesh> {
 
             let next = new_vertices.len() + start_idx;
             new_vertices.push((*v1 + *v2) / 2.0);
+            parents.push((lhs, rhs));
             cache.insert((lhs,rhs),next);
             cache.insert((rhs,lhs),next);
             Ok(next)
@@ -121,7 +128,18 @@ pub fn by_loop(mesh: &Mesh) -> MeshResult<Mesh> {
         updated_vertices[*idx] = new_vertex + coef;
     }
 
-    Ok(Mesh::from_vertices([updated_vertices, new_vertices].concat(), new_faces, trimesh.attributes.clone()))
+    let mut mesh = Mesh::from_vertices([updated_vertices, new_vertices].concat(), new_faces, trimesh.attributes.clone());
+    mesh.fields = subdivide_fields(&trimesh, &parents);
+    Ok(mesh)
+}
+
+/// The fields of the subdivided mesh: the vertex fields are interpolated for the vertices
+/// added in the middle of the edges and every face passes its values to the 4 faces it is split into.
+fn subdivide_fields(trimesh: &Mesh, parents: &[(usize, usize)]) -> Fields {
+    let origins: Vec<usize> = (0..trimesh.faces().len()).flat_map(|i| [i; 4]).collect();
+    let mut fields = trimesh.fields.select_faces(&origins);
+    fields.push_vertex_midpoints(parents);
+    fields
 }
 
 fn subdivide<F>(mesh: &Mesh, mut add_edge: F) -> MeshResult<Vec<Face>>
@@ -104,6 +104,20 @@ impl<'a> MeshTables<'a> {
             .collect()
     }
 
+    /// The vertices sharing an edge with the given one, in the ascending order.
+    pub fn vertex_neighbors(&self, idx: Idx) -> MeshResult<Vec<Idx>> {
+        let mut neighbors = Vec::new();
+        for e_idx in self.vert_edges.get(&idx).into_iter().flatten() {
+            let edge = self.mesh.edges.get(*e_idx).ok_or(MeshError::idx_edge(idx, idx))?;
+            let (lhs, rhs) = edge.indexes().ok_or(MeshError::idx_edge(idx, idx))?;
+            neighbors.push(if lhs == idx { rhs } else { lhs });
+        }
+        // the edges of the faces go in both directions
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        Ok(neighbors)
+    }
+
     fn get_face(&self, idx: Idx) -> MeshResult<&Face> {
         self.mesh.faces.get(idx).ok_or(MeshError::idx_face(idx))
     }
@@ -1,6 +1,7 @@
+use crate::mesh::fields::{FieldData, NORMALS};
 use crate::mesh::parts::vertex::Vertex;
 use crate::mesh::{HasMesh, Mesh, MeshResult};
-use glam::{Mat4, Vec3};
+use glam::{Mat3, Mat4, Vec3};
 
 pub trait Transform {
     fn transform<T: Into<Mat4>>(&mut self, matrix: T) -> MeshResult<()>;
@@ -14,11 +15,26 @@ impl<HM: HasMesh> Transform for HM {
 }
 
 impl Transform for Mesh {
+    /// Moves the vertices, the `normals` vertex field is turned along with them.
     fn transform<T: Into<Mat4>>(&mut self, matrix: T) -> MeshResult<()> {
         let tm:Mat4 = matrix.into();
         for vertex in &mut self.vertices {
             vertex.transform(tm)?;
         }
+        if let Some(normals) = self.fields.vertex(NORMALS).and_then(|f| f.as_vector()) {
+            // the normals go with the inverse transpose, the degenerated matrix leaves no normals
+            let linear = Mat3::from_mat4(tm);
+            if linear.determinant() == 0.0 {
+                self.fields.remove_vertex(NORMALS);
+            } else {
+                let turn = linear.inverse().transpose();
+                let normals = normals
+                    .iter()
+                    .map(|n| (turn * Vec3::from(*n)).normalize_or_zero().to_array())
+                    .collect();
+                self.fields.insert_vertex(NORMALS, FieldData::Vector(normals));
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,259 @@
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::quality::area;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::{DVec3, Mat3, Vec3};
+
+/// The volume, the center of mass and the inertia of the solid bounded by a closed mesh,
+/// the density is 1 so the mass equals the volume.
+///
+/// The values are integrated over the triangles by the divergence theorem
+/// (D. Eberly, Polyhedral Mass Properties), the quads are split into two triangles.
+/// The faces can look either inside or outside, the result is the same.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::parts::face::FaceType;
+/// use tessellate::mesh::shape::cuboid::cube::Cube;
+/// use tessellate::mesh::material::Color;
+/// use tessellate::mesh::parts::vertex::Vertex;
+///
+/// let cube = Cube::create([1.0, 2.0, 3.0], 2.0, FaceType::Triangle, Color::default());
+/// let props = cube.mass_properties().unwrap();
+/// assert!((props.volume - 8.0).abs() < 1e-4);
+/// assert!(props.center_of_mass.distance(&Vertex::new(1.0, 2.0, 3.0)) < 1e-4);
+/// println!("{:?} {:?}", props.principal_moments, props.principal_axes);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassProperties {
+    pub volume: f32,
+    /// The area of the surface.
+    pub area: f32,
+    /// The centroid of the solid, not of the vertices.
+    pub center_of_mass: Vertex,
+    /// The inertia tensor relative to the center of mass.
+    pub inertia: Mat3,
+    /// The eigenvalues of the inertia tensor from the smallest one.
+    pub principal_moments: Vec3,
+    /// The unit axes of the principal moments, in the same order.
+    pub principal_axes: [Vec3; 3],
+}
+
+impl MassProperties {
+    /// Computes the properties, fails if the mesh is not closed
+    /// i.e. some edge is not shared by exactly two faces, or if it encloses no volume.
+    pub fn new(mesh: &Mesh) -> MeshResult<Self> {
+        if mesh.faces().is_empty() || !mesh.properties().is_manifold() {
+            return Err(MeshError::WrongMesh(
+                "The mesh is not closed, every edge should be shared by two faces".to_string(),
+            ));
+        }
+
+        // 1, x, y, z, x^2, y^2, z^2, xy, yz, zx integrated over the volume
+        let mut integrals = [0.0f64; 10];
+        let mut area_sum = 0.0;
+        for face in mesh.faces() {
+            let points = face
+                .flatten()
+                .into_iter()
+                .map(|idx| mesh.get(idx).map(|v| (*v).into()))
+                .collect::<MeshResult<Vec<Vec3>>>()?;
+            area_sum += area(&points);
+            let points: Vec<DVec3> = points.iter().map(|p| p.as_dvec3()).collect();
+            // the quads are split the way `Face::triangulate` does
+            for i in 1..points.len() - 1 {
+                add_triangle(&mut integrals, points[0], points[i], points[i + 1]);
+            }
+        }
+        let weights = [
+            1.0 / 6.0,
+            1.0 / 24.0,
+            1.0 / 24.0,
+            1.0 / 24.0,
+            1.0 / 60.0,
+            1.0 / 60.0,
+            1.0 / 60.0,
+            1.0 / 120.0,
+            1.0 / 120.0,
+            1.0 / 120.0,
+        ];
+        // the faces looking inside turn every integral over
+        let sign = integrals[0].signum();
+        for (value, weight) in integrals.iter_mut().zip(weights) {
+            *value *= weight * sign;
+        }
+
+        let volume = integrals[0];
+        if volume <= f64::EPSILON {
+            return Err(MeshError::WrongMesh("The mesh does not enclose any volume".to_string()));
+        }
+        let c = DVec3::new(integrals[1], integrals[2], integrals[3]) / volume;
+        let xx = integrals[5] + integrals[6] - volume * (c.y * c.y + c.z * c.z);
+        let yy = integrals[4] + integrals[6] - volume * (c.z * c.z + c.x * c.x);
+        let zz = integrals[4] + integrals[5] - volume * (c.x * c.x + c.y * c.y);
+        let xy = -(integrals[7] - volume * c.x * c.y);
+        let yz = -(integrals[8] - volume * c.y * c.z);
+        let zx = -(integrals[9] - volume * c.z * c.x);
+        let inertia = [[xx, xy, zx], [xy, yy, yz], [zx, yz, zz]];
+
+        let (moments, axes) = symmetric_eigen(inertia);
+        let mut order = [0, 1, 2];
+        order.sort_by(|a, b| moments[*a].total_cmp(&moments[*b]));
+
+        Ok(MassProperties {
+            volume: volume as f32,
+            area: area_sum,
+            center_of_mass: Vertex::new(c.x as f32, c.y as f32, c.z as f32),
+            inertia: Mat3::from_cols_array_2d(&inertia.map(|row| row.map(|v| v as f32))),
+            principal_moments: Vec3::from_array(order.map(|i| moments[i] as f32)),
+            principal_axes: order.map(|i| axes[i].as_vec3().normalize()),
+        })
+    }
+}
+
+/// Adds the terms of the tetrahedron from the origin to the triangle.
+fn add_triangle(integrals: &mut [f64; 10], p0: DVec3, p1: DVec3, p2: DVec3) {
+    let d = (p1 - p0).cross(p2 - p0);
+    let [fx, fy, fz] = [0, 1, 2].map(|i| Terms::new(p0[i], p1[i], p2[i]));
+    integrals[0] += d.x * fx.f1;
+    integrals[1] += d.x * fx.f2;
+    integrals[2] += d.y * fy.f2;
+    integrals[3] += d.z * fz.f2;
+    integrals[4] += d.x * fx.f3;
+    integrals[5] += d.y * fy.f3;
+    integrals[6] += d.z * fz.f3;
+    integrals[7] += d.x * (p0.y * fx.g0 + p1.y * fx.g1 + p2.y * fx.g2);
+    integrals[8] += d.y * (p0.z * fy.g0 + p1.z * fy.g1 + p2.z * fy.g2);
+    integrals[9] += d.z * (p0.x * fz.g0 + p1.x * fz.g1 + p2.x * fz.g2);
+}
+
+/// The polynomials of one coordinate of the triangle vertices.
+struct Terms {
+    f1: f64,
+    f2: f64,
+    f3: f64,
+    g0: f64,
+    g1: f64,
+    g2: f64,
+}
+
+impl Terms {
+    fn new(w0: f64, w1: f64, w2: f64) -> Self {
+        let sum = w0 + w1;
+        let f1 = sum + w2;
+        let square = w0 * w0;
+        let f2_part = square + w1 * sum;
+        let f2 = f2_part + w2 * f1;
+        let f3 = w0 * square + w1 * f2_part + w2 * f2;
+        Terms {
+            f1,
+            f2,
+            f3,
+            g0: f2 + w0 * (f1 + w0),
+            g1: f2 + w1 * (f1 + w1),
+            g2: f2 + w2 * (f1 + w2),
+        }
+    }
+}
+
+/// The eigenvalues and the eigenvectors of the symmetric matrix by the Jacobi rotations.
+pub(crate) fn symmetric_eigen(matrix: [[f64; 3]; 3]) -> ([f64; 3], [DVec3; 3]) {
+    let mut a = matrix;
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let scale = a.iter().flatten().map(|x| x.abs()).fold(0.0, f64::max);
+    for _ in 0..50 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)]
+            .into_iter()
+            .max_by(|l, r| a[l.0][l.1].abs().total_cmp(&a[r.0][r.1].abs()))
+            .unwrap();
+        if a[p][q].abs() <= scale * 1e-15 {
+            break;
+        }
+        // the rotation zeroing a[p][q]
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let cos = 1.0 / (t * t + 1.0).sqrt();
+        let sin = t * cos;
+        for row in a.iter_mut() {
+            let (akp, akq) = (row[p], row[q]);
+            row[p] = cos * akp - sin * akq;
+            row[q] = sin * akp + cos * akq;
+        }
+        let (row_p, row_q) = (a[p], a[q]);
+        a[p] = std::array::from_fn(|k| cos * row_p[k] - sin * row_q[k]);
+        a[q] = std::array::from_fn(|k| sin * row_p[k] + cos * row_q[k]);
+        for row in v.iter_mut() {
+            let (vp, vq) = (row[p], row[q]);
+            row[p] = cos * vp - sin * vq;
+            row[q] = sin * vp + cos * vq;
+        }
+    }
+    let values = [a[0][0], a[1][1], a[2][2]];
+    let vectors = [0, 1, 2].map(|i| DVec3::new(v[0][i], v[1][i], v[2][i]));
+    (values, vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::{Face, FaceType};
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::shape::cuboid::rect_cuboid::RectCuboid;
+    use crate::mesh::shape::sphere::Sphere;
+    use crate::mesh::transform::Transform;
+    use crate::mesh::HasMesh;
+    use glam::{Mat4, Quat};
+
+    fn close(lhs: f32, rhs: f32, eps: f32) -> bool {
+        (lhs - rhs).abs() < eps
+    }
+
+    #[test]
+    fn cube() {
+        let cube = Cube::create([1.0, 2.0, 3.0], 2.0, FaceType::Triangle, Color::default());
+        let props = cube.mass_properties().unwrap();
+        assert!(close(props.volume, 8.0, 1e-4));
+        assert!(close(props.area, 24.0, 1e-4));
+        assert!(props.center_of_mass.distance(&Vertex::new(1.0, 2.0, 3.0)) < 1e-4);
+        // m(a^2 + b^2) / 12 on the diagonal
+        let expected = Mat3::from_diagonal(Vec3::splat(8.0 * 8.0 / 12.0));
+        assert!(props.inertia.abs_diff_eq(expected, 1e-3), "{:?}", props.inertia);
+
+        // the faces looking inside give the same values
+        let mut mesh = cube.mesh().clone();
+        let faces = mesh.faces().iter().map(|f| f.flatten()).map(|f| Face::new3(f[0], f[2], f[1])).collect();
+        mesh = Mesh::from_vertices(mesh.vertices().to_vec(), faces, Color::default().into());
+        assert_eq!(mesh.mass_properties().unwrap(), props);
+    }
+
+    #[test]
+    fn rotated_box_axes() {
+        let mut cuboid = RectCuboid::create([0.0, 0.0, 0.0], 4.0, 2.0, 1.0, FaceType::Triangle, Color::default());
+        let rotation = Quat::from_rotation_z(0.5);
+        cuboid.transform(Mat4::from_rotation_translation(rotation, Vec3::new(5.0, 0.0, 0.0))).unwrap();
+        let props = cuboid.mass_properties().unwrap();
+        assert!(close(props.volume, 8.0, 1e-3));
+        assert!(props.center_of_mass.distance(&Vertex::new(5.0, 0.0, 0.0)) < 1e-4);
+        // the longest side spins the easiest
+        let m = 8.0 / 12.0;
+        let expected = Vec3::new(m * (4.0 + 1.0), m * (16.0 + 1.0), m * (16.0 + 4.0));
+        assert!(props.principal_moments.abs_diff_eq(expected, 1e-3), "{:?}", props.principal_moments);
+        for (axis, local) in props.principal_axes.iter().zip([Vec3::X, Vec3::Y, Vec3::Z]) {
+            assert!(close(axis.dot(rotation * local).abs(), 1.0, 1e-4));
+        }
+    }
+
+    #[test]
+    fn sphere_and_open_mesh() {
+        let sphere = Sphere::create_ico([0.0, 0.0, 0.0], 1.0, 3, Color::default().into()).unwrap();
+        let props = sphere.mass_properties().unwrap();
+        let moments = props.principal_moments;
+        assert!(close(moments.x, moments.z, moments.z * 0.01));
+        assert!(props.center_of_mass.distance(&Vertex::default()) < 1e-4);
+
+        let mut mesh = sphere.mesh().clone();
+        let faces = mesh.faces()[1..].to_vec();
+        mesh = Mesh::from_vertices(mesh.vertices().to_vec(), faces, Color::default().into());
+        assert!(matches!(mesh.mass_properties(), Err(MeshError::WrongMesh(_))));
+    }
+}
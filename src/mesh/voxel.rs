@@ -0,0 +1,394 @@
+use crate::mesh::attributes::Attributes;
+use crate::mesh::parts::bbox::BoundingBox;
+use crate::mesh::parts::face::Face;
+use crate::mesh::parts::polygon::Triangle;
+use crate::mesh::parts::ray::Ray;
+use crate::mesh::parts::vertex::Vertex;
+use crate::mesh::query::octree::Octree;
+use crate::mesh::{Mesh, MeshError, MeshResult};
+use glam::Vec3;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::f32::consts::PI;
+
+/// The way the voxels are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoxelMode {
+    /// Only the voxels the faces pass through.
+    #[default]
+    Surface,
+    /// The surface voxels and the voxels whose centers are inside the mesh
+    /// by the parity of the crossings of a ray going up.
+    /// Fast, but needs a closed mesh.
+    Parity,
+    /// The surface voxels and the voxels whose centers are inside the mesh
+    /// by the generalized winding number. Slower, but tolerates small holes and
+    /// the faces looking inside.
+    Winding,
+}
+
+/// The sparse grid of the cubic voxels, only the filled voxels are stored.
+/// The voxel `[i, j, k]` spans from `origin + [i, j, k] * voxel_size` to `origin + [i + 1, j + 1, k + 1] * voxel_size`.
+///
+/// # Example
+/// ```rust
+/// use tessellate::mesh::parts::face::FaceType;
+/// use tessellate::mesh::shape::cuboid::cube::Cube;
+/// use tessellate::mesh::material::Color;
+/// use tessellate::mesh::voxel::VoxelMode;
+///
+/// let cube = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Triangle, Color::default());
+/// let grid = cube.voxelize(0.5, VoxelMode::Parity).unwrap();
+/// assert_eq!(grid.dims(), [5, 5, 5]);
+/// assert_eq!(grid.len(), 125);
+/// let blocks = grid.to_mesh(Color::default());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelGrid {
+    origin: Vertex,
+    voxel_size: f32,
+    dims: [usize; 3],
+    voxels: BTreeSet<[usize; 3]>,
+}
+
+impl VoxelGrid {
+    pub fn new(origin: Vertex, voxel_size: f32, dims: [usize; 3]) -> Self {
+        VoxelGrid {
+            origin,
+            voxel_size,
+            dims,
+            voxels: BTreeSet::new(),
+        }
+    }
+
+    pub fn origin(&self) -> &Vertex {
+        &self.origin
+    }
+
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    /// The number of the voxels along x, y and z.
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    /// The number of the filled voxels.
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    pub fn contains(&self, idx: [usize; 3]) -> bool {
+        self.voxels.contains(&idx)
+    }
+
+    /// Fills the voxel, returns false if it is outside the grid or already filled.
+    pub fn insert(&mut self, idx: [usize; 3]) -> bool {
+        (0..3).all(|a| idx[a] < self.dims[a]) && self.voxels.insert(idx)
+    }
+
+    /// The filled voxels ordered by x, y and z.
+    pub fn iter(&self) -> impl Iterator<Item = &[usize; 3]> {
+        self.voxels.iter()
+    }
+
+    /// The volume of the filled voxels.
+    pub fn volume(&self) -> f32 {
+        self.voxels.len() as f32 * self.voxel_size.powi(3)
+    }
+
+    pub fn center(&self, idx: [usize; 3]) -> Vertex {
+        self.corner(idx) + Vertex::new(0.5, 0.5, 0.5) * self.voxel_size
+    }
+
+    pub fn bbox(&self, idx: [usize; 3]) -> BoundingBox {
+        BoundingBox::new(self.corner(idx), self.corner(idx.map(|i| i + 1)))
+    }
+
+    /// The mesh of the outer faces of the voxels, the faces between two filled voxels are skipped
+    /// and the neighbouring voxels share the vertices, so every piece of the mesh is watertight.
+    pub fn to_mesh<C>(&self, attributes: C) -> Mesh
+    where
+        C: Into<Attributes>,
+    {
+        let mut corners: HashMap<[usize; 3], usize> = HashMap::new();
+        let mut vertices = vec![];
+        let mut faces = vec![];
+        let mut vertex = |idx: [usize; 3]| {
+            *corners.entry(idx).or_insert_with(|| {
+                vertices.push(self.corner(idx));
+                vertices.len() - 1
+            })
+        };
+
+        for idx in &self.voxels {
+            for axis in 0..3 {
+                let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+                for high in [false, true] {
+                    let mut next = *idx;
+                    if high {
+                        next[axis] += 1;
+                    } else if next[axis] == 0 {
+                        next[axis] = usize::MAX;
+                    } else {
+                        next[axis] -= 1;
+                    }
+                    if self.voxels.contains(&next) {
+                        continue;
+                    }
+                    let mut base = *idx;
+                    base[axis] += high as usize;
+                    let shift = |mut corner: [usize; 3], along: &[usize]| {
+                        along.iter().for_each(|a| corner[*a] += 1);
+                        corner
+                    };
+                    // the corners go around the axis counterclockwise, so the face looks outside
+                    let mut quad = [
+                        vertex(base),
+                        vertex(shift(base, &[b])),
+                        vertex(shift(base, &[b, c])),
+                        vertex(shift(base, &[c])),
+                    ];
+                    if !high {
+                        quad.reverse();
+                    }
+                    faces.push(Face::new4(quad[0], quad[1], quad[2], quad[3]));
+                }
+            }
+        }
+        Mesh::from_vertices(vertices, faces, attributes.into())
+    }
+
+    /// The flat array of the grid with 1 for the filled voxels and 0 for the empty ones,
+    /// x changes the fastest: the voxel `[i, j, k]` is at `i + nx * (j + ny * k)`.
+    pub fn occupancy(&self) -> Vec<u8> {
+        let [nx, ny, nz] = self.dims;
+        let mut result = vec![0; nx * ny * nz];
+        for [i, j, k] in &self.voxels {
+            result[i + nx * (j + ny * k)] = 1;
+        }
+        result
+    }
+
+    fn corner(&self, idx: [usize; 3]) -> Vertex {
+        self.origin + Vertex::new(idx[0] as f32, idx[1] as f32, idx[2] as f32) * self.voxel_size
+    }
+
+    /// The voxel containing the point, clamped to the grid.
+    fn index(&self, point: &Vertex) -> [usize; 3] {
+        let local = (*point - self.origin) * (1.0 / self.voxel_size);
+        let at = [local.x, local.y, local.z];
+        [0, 1, 2].map(|a| (at[a].floor().max(0.0) as usize).min(self.dims[a] - 1))
+    }
+}
+
+/// Voxelizes the mesh, the grid covers the bounding box of the mesh
+/// with the margin of half a voxel, so the faces on the box lie in the middle of the voxels.
+pub(crate) fn voxelize(mesh: &Mesh, voxel_size: f32, mode: VoxelMode) -> MeshResult<VoxelGrid> {
+    if voxel_size <= 0.0 || !voxel_size.is_finite() {
+        return Err(MeshError::Custom(format!("The voxel size should be positive, got {}", voxel_size)));
+    }
+    if mesh.faces().is_empty() {
+        return Err(MeshError::WrongMesh("The mesh has no faces to voxelize".to_string()));
+    }
+    let bb = BoundingBox::from(mesh);
+    let extent = *bb.max() - *bb.min();
+    let dims = [extent.x, extent.y, extent.z].map(|e| ((e / voxel_size + 0.5).ceil() as usize).max(1));
+    let origin = *bb.min() - Vertex::new(0.5, 0.5, 0.5) * voxel_size;
+    let mut grid = VoxelGrid::new(origin, voxel_size, dims);
+
+    let triangles = mesh
+        .try_polygons()?
+        .into_iter()
+        .flat_map(|p| p.triangulate())
+        .map(Triangle::try_from)
+        .collect::<MeshResult<Vec<_>>>()?;
+
+    for triangle in &triangles {
+        let (v0, v1, v2) = triangle.vertices();
+        let [v0, v1, v2]: [Vec3; 3] = [v0.into(), v1.into(), v2.into()];
+        let [min, max] = [v0.min(v1).min(v2), v0.max(v1).max(v2)].map(|v| grid.index(&v.into()));
+        for i in min[0]..=max[0] {
+            for j in min[1]..=max[1] {
+                for k in min[2]..=max[2] {
+                    if grid.bbox([i, j, k]).intersects_triangle(triangle) {
+                        grid.insert([i, j, k]);
+                    }
+                }
+            }
+        }
+    }
+
+    match mode {
+        VoxelMode::Surface => {}
+        VoxelMode::Parity => fill_by_parity(mesh, &mut grid)?,
+        VoxelMode::Winding => {
+            let [nx, ny, nz] = dims;
+            for idx in (0..nx).flat_map(|i| (0..ny).flat_map(move |j| (0..nz).map(move |k| [i, j, k]))) {
+                if !grid.contains(idx) && winding_number(&triangles, &grid.center(idx)).abs() > 0.5 {
+                    grid.insert(idx);
+                }
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Casts a ray up from every voxel center, the triangles of the column of voxels
+/// are taken from the octree. The ray is a bit tilted to miss the edges of the grid-aligned meshes.
+fn fill_by_parity(mesh: &Mesh, grid: &mut VoxelGrid) -> MeshResult<()> {
+    let octree = Octree::try_from_mesh(mesh, None)?;
+    let direction = Vertex::new(0.00123, 0.00071, 1.0);
+    let [nx, ny, nz] = grid.dims();
+    let height = nz as f32 * grid.voxel_size();
+    for i in 0..nx {
+        for j in 0..ny {
+            let bottom = grid.center([i, j, 0]);
+            let column = BoundingBox::new(bottom, bottom + direction * height);
+            let triangles: HashSet<Triangle> = octree
+                .find_leaf_polygons(&column)
+                .into_iter()
+                .filter(|p| BoundingBox::from_polygon(p).intersects(&column))
+                .flat_map(|p| p.triangulate())
+                .map(Triangle::try_from)
+                .collect::<MeshResult<_>>()?;
+            for k in 0..nz {
+                if grid.contains([i, j, k]) {
+                    continue;
+                }
+                let ray = Ray::new(grid.center([i, j, k]), direction);
+                let crossings = triangles.iter().filter(|t| ray.intersects((*t).clone())).count();
+                if crossings % 2 == 1 {
+                    grid.insert([i, j, k]);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The sum of the solid angles of the triangles seen from the point over the full sphere,
+/// 1 inside the closed mesh looking outside and 0 outside of it.
+fn winding_number(triangles: &[Triangle], point: &Vertex) -> f32 {
+    let p: Vec3 = point.into();
+    let angles: f32 = triangles
+        .iter()
+        .map(|t| {
+            let (v0, v1, v2) = t.vertices();
+            let [a, b, c] = [v0, v1, v2].map(|v| Into::<Vec3>::into(v) - p);
+            let (la, lb, lc) = (a.length(), b.length(), c.length());
+            // Van Oosterom and Strackee
+            let numerator = a.dot(b.cross(c));
+            let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+            2.0 * numerator.atan2(denominator)
+        })
+        .sum();
+    angles / (4.0 * PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::material::Color;
+    use crate::mesh::parts::face::FaceType;
+    use crate::mesh::parts::polygon::Polygon;
+    use crate::mesh::shape::cuboid::cube::Cube;
+    use crate::mesh::shape::torus::Torus;
+    use crate::mesh::HasMesh;
+
+    #[test]
+    fn cube_modes() {
+        let cube = Cube::create([0.0, 0.0, 0.0], 2.0, FaceType::Triangle, Color::default());
+        let surface = cube.voxelize(0.5, VoxelMode::Surface).unwrap();
+        assert_eq!(surface.dims(), [5, 5, 5]);
+        assert_eq!(surface.len(), 125 - 27);
+        assert!(!surface.contains([2, 2, 2]));
+        assert_eq!(surface.center([0, 0, 0]), Vertex::new(-1.0, -1.0, -1.0));
+
+        for mode in [VoxelMode::Parity, VoxelMode::Winding] {
+            let solid = cube.voxelize(0.5, mode).unwrap();
+            assert_eq!(solid.len(), 125, "{:?}", mode);
+        }
+        assert!(cube.voxelize(0.0, VoxelMode::Surface).is_err());
+    }
+
+    #[test]
+    fn torus_hole_stays_empty() {
+        let torus = Torus::create(Vertex::default(), 1.0, 0.4, 32, 16, Color::default());
+        let parity = torus.voxelize(0.1, VoxelMode::Parity).unwrap();
+        let winding = torus.voxelize(0.1, VoxelMode::Winding).unwrap();
+        let center = parity.index(&Vertex::default());
+        assert!(!parity.contains(center) && !winding.contains(center));
+        let tube = parity.index(&Vertex::new(1.0, 0.0, 0.0));
+        assert!(parity.contains(tube) && winding.contains(tube));
+        // 2 pi^2 R r^2 and a layer of the surface voxels
+        let volume = 2.0 * PI * PI * 0.16;
+        assert!(parity.volume() > volume && parity.volume() < volume * 1.5);
+        assert!(parity.len().abs_diff(winding.len()) < parity.len() / 100);
+    }
+
+    #[test]
+    fn fine_bottom_and_large_top() {
+        // the box [1, 5]^3 with the bottom split into small triangles and
+        // the rest made of the large ones, so the octree splits and the top triangles
+        // cross the octants without a vertex in them
+        let quad = |c: [[f32; 3]; 4], outward: Vec3| {
+            let [a, b, c, d] = c.map(Vec3::from_array);
+            let (b, d) = if (b - a).cross(c - a).dot(outward) > 0.0 { (b, d) } else { (d, b) };
+            [[a, b, c], [a, c, d]].map(|t| Polygon::new(t.map(Vertex::from).to_vec()))
+        };
+        let mut polygons = vec![];
+        let n = 8;
+        let step = 4.0 / n as f32;
+        for i in 0..n {
+            for j in 0..n {
+                let (x, y) = (1.0 + i as f32 * step, 1.0 + j as f32 * step);
+                let corners = [[x, y, 1.0], [x + step, y, 1.0], [x + step, y + step, 1.0], [x, y + step, 1.0]];
+                polygons.extend(quad(corners, Vec3::NEG_Z));
+            }
+        }
+        // the top is split along x + y = 6
+        polygons.extend(quad([[5.0, 1.0, 5.0], [5.0, 5.0, 5.0], [1.0, 5.0, 5.0], [1.0, 1.0, 5.0]], Vec3::Z));
+        for (axis, value) in [(0, 1.0), (0, 5.0), (1, 1.0), (1, 5.0)] {
+            let corners = [[1.0, 1.0], [5.0, 1.0], [5.0, 5.0], [1.0, 5.0]].map(|[u, z]| {
+                let mut c = [u, u, z];
+                c[axis] = value;
+                c
+            });
+            let mut outward = Vec3::ZERO;
+            outward[axis] = if value > 3.0 { 1.0 } else { -1.0 };
+            polygons.extend(quad(corners, outward));
+        }
+        assert!(polygons.len() > 50);
+        let mesh = Mesh::from_polygons(polygons, Color::default().into());
+
+        for mode in [VoxelMode::Parity, VoxelMode::Winding] {
+            let solid = mesh.voxelize(0.5, mode).unwrap();
+            assert_eq!(solid.dims(), [9, 9, 9]);
+            assert_eq!(solid.len(), 729, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn mesh_and_occupancy() {
+        let mut grid = VoxelGrid::new(Vertex::default(), 0.5, [3, 2, 2]);
+        assert!(grid.insert([0, 0, 0]));
+        assert!(grid.insert([1, 0, 0]));
+        assert!(!grid.insert([1, 0, 0]));
+        assert!(!grid.insert([3, 0, 0]));
+
+        let mesh = grid.to_mesh(Color::default());
+        assert_eq!(mesh.vertices().len(), 12);
+        assert_eq!(mesh.faces().len(), 10);
+        assert!(mesh.properties().is_watertight());
+        let props = mesh.mass_properties().unwrap();
+        assert!((props.volume - grid.volume()).abs() < 1e-5);
+        assert_eq!(mesh.statistics().unwrap().volume.map(|v| v > 0.0), Some(true));
+
+        assert_eq!(grid.occupancy(), vec![1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}